@@ -0,0 +1,122 @@
+use chrono::{DateTime, Local};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// Oldest entries are dropped past this, so a long session can't grow without bound
+const MAX_ENTRIES: usize = 5000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Level {
+    Info,   // what the user asked for: listings, transfers, renames, deletes
+    Detail, // protocol-level steps, only shown in verbose mode
+    Error,
+}
+
+/// One line of the session log under Config > Activity Log
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub at: DateTime<Local>,
+    pub level: Level,
+    pub text: String,
+}
+
+impl Entry {
+    pub fn line(&self) -> String {
+        let tag = match self.level {
+            Level::Info => "Status",
+            Level::Detail => "Command",
+            Level::Error => "Error",
+        };
+        format!("{} {:<8}{}", self.at.format("%H:%M:%S"), tag, self.text)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Log {
+    entries: VecDeque<Entry>,
+}
+
+impl Log {
+    fn push(&mut self, level: Level, text: String) {
+        if self.entries.len() == MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(Entry {
+            at: Local::now(),
+            level,
+            text,
+        });
+    }
+
+    fn entries(&self, verbose: bool) -> Vec<Entry> {
+        self.entries
+            .iter()
+            .filter(|e| verbose || e.level != Level::Detail)
+            .cloned()
+            .collect()
+    }
+}
+
+// One log for the whole app: the browser, transfer workers and probes all record
+// into it from their own threads
+static LOG: Mutex<Log> = Mutex::new(Log {
+    entries: VecDeque::new(),
+});
+
+fn record(level: Level, text: String) {
+    if let Ok(mut log) = LOG.lock() {
+        log.push(level, text);
+    }
+}
+
+/// Start a fresh log for a new connection to `server`
+pub fn start_session(server: &str) {
+    if let Ok(mut log) = LOG.lock() {
+        log.entries.clear();
+        log.push(Level::Info, format!("Connecting to {}", server));
+    }
+}
+
+pub fn info(text: impl Into<String>) {
+    record(Level::Info, text.into());
+}
+
+pub fn detail(text: impl Into<String>) {
+    record(Level::Detail, text.into());
+}
+
+pub fn error(text: impl Into<String>) {
+    record(Level::Error, text.into());
+}
+
+/// The session so far; protocol details only when `verbose`
+pub fn entries(verbose: bool) -> Vec<Entry> {
+    LOG.lock()
+        .map(|log| log.entries(verbose))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_filters_details_and_caps_size() {
+        let mut log = Log::default();
+        log.push(Level::Info, "Listing /srv".into());
+        log.push(Level::Detail, "realpath /srv".into());
+        log.push(Level::Error, "Listing failed".into());
+        assert_eq!(log.entries(true).len(), 3);
+        let terse: Vec<String> = log.entries(false).into_iter().map(|e| e.text).collect();
+        assert_eq!(terse, ["Listing /srv", "Listing failed"]);
+        assert!(log.entries(false)[1]
+            .line()
+            .contains("Error   Listing failed"));
+
+        for n in 0..MAX_ENTRIES {
+            log.push(Level::Info, n.to_string());
+        }
+        assert_eq!(log.entries.len(), MAX_ENTRIES);
+        assert_eq!(log.entries[0].text, "0");
+    }
+}
@@ -0,0 +1,749 @@
+use super::{remote_scroll_id, AppState, FolderSize, Message, RemoteOp, SftpApp, Thumbnail};
+use crate::download_manager::DownloadCommand;
+use crate::sftp_client::{OwnerNames, SftpClient};
+use crate::types::{FileType, RemoteFile};
+use crate::{activity, names, preview, settings, thumbnails, types};
+use iced::futures::SinkExt;
+use iced::widget::scrollable;
+use iced::Task;
+use std::sync::Arc;
+use std::time::Instant;
+
+impl SftpApp {
+    /// The remote browser: listing, navigation, the folder tree and file operations
+    pub fn update_browser(&mut self, message: Message) -> Result<Task<Message>, Message> {
+        match message {
+            Message::SortBy(column) => {
+                if self.session.sort_column == column {
+                    self.session.sort_ascending = !self.session.sort_ascending;
+                } else {
+                    self.session.sort_column = column;
+                    self.session.sort_ascending = true;
+                }
+            }
+            Message::RemoteFilterChanged(val) => self.session.filter = val,
+            Message::ShowHiddenToggled(show) => self.session.show_hidden = show,
+            Message::ShowOwnerToggled(show) => {
+                self.session.show_owner = show;
+                return Ok(self.load_owner_names());
+            }
+            Message::OwnerNamesLoaded(names) => self.owner_names = Some(names),
+            Message::ColorSizesToggled(on) => {
+                self.config.color_sizes = on;
+                let _ = self.config.save();
+            }
+            Message::RelativeTimesToggled(on) => {
+                self.config.relative_times = on;
+                let _ = self.config.save();
+            }
+            Message::ServerTimesToggled(on) => {
+                self.config.server_times = on;
+                let _ = self.config.save();
+            }
+            Message::HighContrastToggled(on) => self.config.high_contrast = on,
+            Message::ReduceMotionToggled(on) => self.config.reduce_motion = on,
+            Message::RemoteScrolled(viewport) => {
+                self.session.scroll_offset = viewport.absolute_offset().y;
+            }
+            Message::ToggleTree => {
+                self.session.show_tree = !self.session.show_tree;
+                if self.session.show_tree {
+                    return Ok(self.reveal_in_tree(&self.current_remote_path.clone()));
+                }
+            }
+            Message::ToggleGridView => {
+                self.session.grid_view = !self.session.grid_view;
+                return Ok(self.load_thumbnails());
+            }
+            Message::ThumbnailLoaded(path, png) => {
+                let thumb = match png {
+                    Some(png) => Thumbnail::Ready(iced::widget::image::Handle::from_bytes(png)),
+                    None => Thumbnail::Unavailable,
+                };
+                self.thumbnails.insert(path, thumb);
+            }
+            Message::TreeToggleExpand(path) if self.tree_expanded.contains(&path) => {
+                self.tree_expanded.remove(&path);
+            }
+            Message::TreeToggleExpand(path) => {
+                self.tree_expanded.insert(path.clone());
+                if !self.tree_children.contains_key(&path) {
+                    return Ok(self.load_tree_children(path));
+                }
+            }
+            Message::TreeChildrenLoaded(path, result) => match result {
+                Ok((_, files)) => self.set_tree_children(&path, &files),
+                Err(e) => {
+                    self.tree_expanded.remove(&path);
+                    self.app_error = Some(format!("Error loading {}: {}", path, e));
+                }
+            },
+            Message::TreeSelect(path) => {
+                if let Some(client) = &self.sftp_client {
+                    let client = client.clone();
+                    return Ok(Task::future(async move {
+                        let path_clone = path.clone();
+                        let res = tokio::task::spawn_blocking(move || {
+                            let c = client.lock().unwrap();
+                            c.list_dir(std::path::Path::new(&path_clone))
+                        })
+                        .await
+                        .unwrap_or_else(|e| Err(e.into()));
+                        Message::RemoteFilesLoaded(path, res)
+                    }));
+                }
+            }
+            Message::RemoteFilesLoaded(req_path, result) => match result {
+                Ok((resolved_path, files)) => {
+                    // The listing doubles as a tree load for this folder
+                    self.set_tree_children(&resolved_path, &files);
+                    self.note_visit(&resolved_path);
+                    self.remote_files = files;
+                    self.current_remote_path = resolved_path.clone();
+                    self.selected_file = None;
+                    self.app_error = None;
+                    self.listing_error = None;
+                    let reveal = if self.session.show_tree {
+                        self.reveal_in_tree(&resolved_path)
+                    } else {
+                        Task::none()
+                    };
+                    let reveal =
+                        Task::batch([reveal, self.load_thumbnails(), self.load_owner_names()]);
+                    if let Some(y) = self.pending_scroll.take() {
+                        return Ok(Task::batch([
+                            reveal,
+                            scrollable::scroll_to(
+                                remote_scroll_id(),
+                                scrollable::AbsoluteOffset { x: 0.0, y },
+                            ),
+                        ]));
+                    }
+                    return Ok(reveal);
+                }
+                Err(e) => {
+                    activity::error(format!(
+                        "Listing {} failed: {}",
+                        names::display(&req_path),
+                        e
+                    ));
+                    // The previous folder stays on screen; its scroll position
+                    // was meant for the one that failed
+                    self.pending_scroll = None;
+                    self.listing_error = Some((req_path, e));
+                }
+            },
+            Message::ListingGoUp => {
+                if let Some((path, _)) = self.listing_error.take() {
+                    let parent = std::path::Path::new(&path)
+                        .parent()
+                        .map(|p| p.to_string_lossy().to_string())
+                        .filter(|p| !p.is_empty())
+                        .unwrap_or_else(|| "/".to_string());
+                    return Ok(self.update(Message::TreeSelect(parent)));
+                }
+            }
+            Message::ListingRetryAsUser => {
+                if let Some((path, _)) = self.listing_error.take() {
+                    self.retry_path = Some(path);
+                    self.open_dialog(AppState::SettingsView);
+                }
+            }
+            Message::RemoteFileClicked(file) => {
+                self.selected_file = Some(file.name.clone());
+
+                let now = Instant::now();
+                let mut navigate = false;
+
+                if let Some((last_name, last_time)) = &self.last_click {
+                    if *last_name == file.name && now.duration_since(*last_time).as_millis() < 500 {
+                        navigate = true;
+                    }
+                }
+                self.last_click = Some((file.name.clone(), now));
+
+                if navigate {
+                    self.last_click = None;
+                    return Ok(self.open_remote_file(file));
+                }
+            }
+            Message::CopyRemotePath(path) => {
+                self.status_message = format!("Copied {}", path);
+                return Ok(iced::clipboard::write(path));
+            }
+            Message::GoToClipboardPath => {
+                return Ok(iced::clipboard::read().map(Message::ClipboardPathRead));
+            }
+            Message::ClipboardPathRead(text) => {
+                match text.as_deref().and_then(pasted_remote_path) {
+                    Some(path) => return Ok(self.update(Message::TreeSelect(path))),
+                    None => {
+                        self.status_message =
+                            "The clipboard doesn't hold a remote path (/...)".to_string()
+                    }
+                }
+            }
+            Message::DoubleClickChanged(action) => {
+                self.config.double_click = action;
+            }
+            Message::PreviewFile(file) => {
+                if file.size_bytes > preview::PREVIEW_LIMIT {
+                    self.status_message = format!(
+                        "{} is too big to preview ({}); queue it instead",
+                        file.name,
+                        self.format_bytes(&file.size_bytes.to_string())
+                    );
+                    return Ok(Task::none());
+                }
+                if self.is_connected {
+                    // Own session, like thumbnails, so browsing isn't held up
+                    let config = self.config.sftp_config.clone();
+                    self.status_message = format!("Fetching {} for preview...", file.name);
+                    return Ok(Task::future(async move {
+                        let res = tokio::task::spawn_blocking(move || {
+                            let client = SftpClient::connect(&config)?;
+                            preview::fetch(&client, &file.path, &preview::preview_dir())
+                        })
+                        .await
+                        .unwrap_or_else(|e| Err(e.into()));
+                        Message::PreviewReady(res)
+                    }));
+                }
+            }
+            Message::PreviewReady(Ok(path)) => {
+                self.status_message = String::new();
+                if let Err(e) = preview::open(&path) {
+                    self.app_error = Some(format!("Couldn't open {}: {}", path.display(), e));
+                }
+            }
+            Message::PreviewReady(Err(e)) => {
+                self.status_message = String::new();
+                self.app_error = Some(format!("Preview failed: {}", e));
+            }
+            Message::HoverFile(filename) => {
+                self.hovered_file = Some(filename);
+            }
+            Message::UnhoverFile => {
+                self.hovered_file = None;
+            }
+            Message::StartRemoteOp(op, file) => {
+                self.remote_op_destination = self.current_remote_path.clone();
+                self.remote_op = Some((op, file));
+                if op == RemoteOp::Relay {
+                    if let Some(tab) = self.server_tabs.first() {
+                        return Ok(
+                            self.update(Message::RelayServerPicked(tab.sftp_config.profile_key()))
+                        );
+                    }
+                }
+            }
+            Message::RelayServerPicked(profile) => {
+                // Default to the folder that server was last showing
+                self.remote_op_destination = self
+                    .config
+                    .sessions
+                    .get(&profile)
+                    .map(|s| s.remote_path.clone())
+                    .filter(|p| !p.is_empty())
+                    .unwrap_or_else(|| "/".into());
+                self.relay_server = profile;
+            }
+            Message::RemoteOpDestinationChanged(val) => self.remote_op_destination = val,
+            Message::CancelRemoteOp => self.remote_op = None,
+            Message::DeleteRemote(file) => {
+                self.pending_delete = Some(file.clone());
+                self.delete_preview = None;
+                // A folder's contents are listed so the prompt shows everything that goes
+                let whole = file.file_type != FileType::Folder
+                    || self.config.sftp_config.trash_folder.is_some();
+                let scan = match &self.sftp_client {
+                    Some(client) if !whole => client.clone(),
+                    _ => {
+                        self.delete_preview = Some(self.delete_plan(&file, &[]));
+                        return Ok(Task::none());
+                    }
+                };
+                return Ok(Task::future(async move {
+                    let path = file.path.clone();
+                    let res = tokio::task::spawn_blocking(move || {
+                        let c = scan.lock().unwrap();
+                        c.recursive_scan(std::path::Path::new(&file.path))
+                    })
+                    .await
+                    .unwrap_or_else(|e| Err(e.into()));
+                    Message::DeletePreviewListed(path, res)
+                }));
+            }
+            Message::DeletePreviewListed(path, res) => {
+                let Some(file) = self.pending_delete.as_ref().filter(|f| f.path == path) else {
+                    return Ok(Task::none());
+                };
+                self.delete_preview = Some(match res {
+                    Ok(contents) => self.delete_plan(file, &contents),
+                    Err(e) => vec![format!("Couldn't list the folder's contents: {}", e)],
+                });
+            }
+            Message::CancelDelete => {
+                self.pending_delete = None;
+                self.delete_preview = None;
+            }
+            Message::DryRunToggled(on) => {
+                self.config.dry_run = on;
+                if let Some(tx) = &self.download_tx {
+                    let _ = tx.try_send(DownloadCommand::SetDryRun(on));
+                }
+                let _ = self.config.save();
+            }
+            Message::ConfirmDelete if self.config.dry_run => {
+                let plan = self.delete_preview.take().unwrap_or_default();
+                if let Some(file) = self.pending_delete.take() {
+                    self.log_dry_run(&plan, &file.name);
+                }
+            }
+            Message::ConfirmDelete => {
+                self.delete_preview = None;
+                let (Some(file), Some(client)) =
+                    (self.pending_delete.take(), self.sftp_client.clone())
+                else {
+                    return Ok(Task::none());
+                };
+                let trash = self.config.sftp_config.trash_folder.clone();
+                self.status_message = format!("Deleting {}...", file.name);
+                return Ok(Task::future(async move {
+                    let res = tokio::task::spawn_blocking(move || {
+                        let c = client.lock().unwrap();
+                        let path = std::path::Path::new(&file.path);
+                        match trash {
+                            Some(trash) => c
+                                .move_to_trash(path, std::path::Path::new(&trash))
+                                .map(|_| format!("Moved {} to {}", file.name, trash)),
+                            None => c.remove(path).map(|_| format!("Deleted {}", file.name)),
+                        }
+                    })
+                    .await
+                    .unwrap_or_else(|e| Err(e.into()));
+                    Message::RemoteOpFinished(res)
+                }));
+            }
+            Message::ConfirmRemoteOp => {
+                let Some((op, file)) = self.remote_op.take() else {
+                    return Ok(Task::none());
+                };
+                let target = format!(
+                    "{}/{}",
+                    self.remote_op_destination.trim_end_matches('/'),
+                    file.name
+                );
+                // The same path on another server is a different file
+                if op == RemoteOp::Relay {
+                    return Ok(self.queue_relay(file, target));
+                }
+                if target == file.path {
+                    return Ok(Task::none());
+                }
+                if self.config.dry_run {
+                    let verb = if op == RemoteOp::Move { "Move" } else { "Copy" };
+                    let plan = [format!("{} {} to {}", verb, file.path, target)];
+                    self.log_dry_run(&plan, &file.name);
+                    return Ok(Task::none());
+                }
+
+                match op {
+                    RemoteOp::Move => {
+                        let Some(client) = self.sftp_client.clone() else {
+                            return Ok(Task::none());
+                        };
+                        self.status_message = format!("Moving {}...", file.name);
+                        return Ok(Task::future(async move {
+                            let res = tokio::task::spawn_blocking(move || {
+                                let c = client.lock().unwrap();
+                                c.rename(
+                                    std::path::Path::new(&file.path),
+                                    std::path::Path::new(&target),
+                                )
+                                .map(|_| format!("Moved {}", file.name))
+                            })
+                            .await
+                            .unwrap_or_else(|e| Err(e.into()));
+                            Message::RemoteOpFinished(res)
+                        }));
+                    }
+                    RemoteOp::Copy => {
+                        // Copies can take a while, so use a separate session and leave
+                        // the browser's connection free
+                        let config = self.config.sftp_config.clone();
+                        self.status_message = format!("Copying {}...", file.name);
+                        return Ok(Task::stream(iced::stream::channel(
+                            100,
+                            move |mut output| async move {
+                                let mut progress_tx = output.clone();
+                                let res = tokio::task::spawn_blocking(move || {
+                                    let client = SftpClient::connect(&config)?;
+                                    client
+                                        .copy(
+                                            std::path::Path::new(&file.path),
+                                            std::path::Path::new(&target),
+                                            &mut |done, total| {
+                                                let _ = progress_tx.try_send(
+                                                    Message::RemoteOpProgress { done, total },
+                                                );
+                                            },
+                                        )
+                                        .map(|_| format!("Copied {}", file.name))
+                                })
+                                .await
+                                .unwrap_or_else(|e| Err(e.into()));
+                                let _ = output.send(Message::RemoteOpFinished(res)).await;
+                            },
+                        )));
+                    }
+                    RemoteOp::Relay => {}
+                }
+            }
+            Message::RemoteOpProgress { done, total } if total > 0 => {
+                self.status_message = format!(
+                    "Copying... {} / {} ({}%)",
+                    self.format_bytes(&done.to_string()),
+                    self.format_bytes(&total.to_string()),
+                    done * 100 / total
+                );
+            }
+            Message::RemoteOpFinished(result) => match result {
+                Ok(msg) => {
+                    self.status_message = msg;
+                    return Ok(self.update(Message::RefreshRemote));
+                }
+                Err(e) => {
+                    self.status_message.clear();
+                    activity::error(e.to_string());
+                    self.app_error = Some(e.to_string());
+                }
+            },
+            Message::CalculateFolderSize(file) => {
+                let Some(client) = self.sftp_client.clone() else {
+                    return Ok(Task::none());
+                };
+                self.folder_sizes
+                    .insert(file.path.clone(), FolderSize::Scanning);
+                let path = file.path;
+                return Ok(Task::future(async move {
+                    let scan_path = path.clone();
+                    let res = tokio::task::spawn_blocking(move || {
+                        let c = client.lock().unwrap();
+                        c.recursive_scan(std::path::Path::new(&scan_path))
+                    })
+                    .await
+                    .unwrap_or_else(|e| Err(e.into()))
+                    .map(|files| (files.iter().map(|f| f.size_bytes).sum(), files.len()));
+                    Message::FolderSizeCalculated(path, res)
+                }));
+            }
+            Message::FolderSizeCalculated(path, result) => match result {
+                Ok((bytes, files)) => {
+                    self.folder_sizes
+                        .insert(path, FolderSize::Done { bytes, files });
+                }
+                Err(e) => {
+                    self.folder_sizes.remove(&path);
+                    self.app_error = Some(format!("Size calculation failed: {}", e));
+                }
+            },
+            Message::RefreshRemote => {
+                if let Some(client) = &self.sftp_client {
+                    let client = client.clone();
+                    // Reload current path
+                    let path = self.current_remote_path.clone();
+
+                    return Ok(Task::future(async move {
+                        let path_clone = path.clone();
+                        let res = tokio::task::spawn_blocking(move || {
+                            let c = client.lock().unwrap();
+                            c.list_dir(std::path::Path::new(&path_clone))
+                        })
+                        .await
+                        .unwrap_or_else(|e| Err(e.into()));
+                        Message::RemoteFilesLoaded(path, res)
+                    }));
+                }
+            }
+            Message::GoToParent => {
+                if let Some(client) = &self.sftp_client {
+                    let client = client.clone();
+                    // Calculate parent path
+                    let parent = std::path::Path::new(&self.current_remote_path)
+                        .parent()
+                        .unwrap_or(std::path::Path::new("/"))
+                        .to_string_lossy()
+                        .to_string();
+
+                    let parent = if parent.is_empty() {
+                        "/".to_string()
+                    } else {
+                        parent
+                    };
+
+                    return Ok(Task::future(async move {
+                        let path_clone = parent.clone();
+                        let res = tokio::task::spawn_blocking(move || {
+                            let c = client.lock().unwrap();
+                            c.list_dir(std::path::Path::new(&path_clone))
+                        })
+                        .await
+                        .unwrap_or_else(|e| Err(e.into()));
+                        Message::RemoteFilesLoaded(parent, res)
+                    }));
+                }
+            }
+            other => return Err(other),
+        }
+        Ok(Task::none())
+    }
+
+    /// What a double click or Enter does: enter a folder, or the configured
+    /// double-click action for a file
+    pub fn open_remote_file(&mut self, file: RemoteFile) -> Task<Message> {
+        match file.file_type {
+            FileType::File => {
+                let message = match self.config.double_click {
+                    settings::DoubleClick::Nothing => return Task::none(),
+                    settings::DoubleClick::Queue => Message::QueueFile(file),
+                    settings::DoubleClick::Download => Message::DownloadFile(file),
+                    settings::DoubleClick::Preview => Message::PreviewFile(file),
+                };
+                self.update(message)
+            }
+            FileType::Folder if file.name == ".." => self.update(Message::GoToParent),
+            FileType::Folder => {
+                let Some(client) = self.sftp_client.clone() else {
+                    return Task::none();
+                };
+                // Calculate target path, but don't set it yet
+                let new_path = if self.current_remote_path.ends_with('/') {
+                    format!("{}{}", self.current_remote_path, file.name)
+                } else {
+                    format!("{}/{}", self.current_remote_path, file.name)
+                };
+                Task::future(async move {
+                    let path_clone = new_path.clone();
+                    let res = tokio::task::spawn_blocking(move || {
+                        let c = client.lock().unwrap();
+                        c.list_dir(std::path::Path::new(&path_clone))
+                    })
+                    .await
+                    .unwrap_or_else(|e| Err(e.into()));
+                    Message::RemoteFilesLoaded(new_path, res)
+                })
+            }
+        }
+    }
+
+    /// Start fetching thumbnails for the images in the current folder that don't
+    /// have one yet. Runs on its own session so browsing stays responsive.
+    pub fn load_thumbnails(&mut self) -> Task<Message> {
+        if !self.session.grid_view || !self.is_connected {
+            return Task::none();
+        }
+        let wanted: Vec<RemoteFile> = self
+            .remote_files
+            .iter()
+            .filter(|f| f.kind() == types::FileKind::Image)
+            .filter(|f| !self.thumbnails.contains_key(&f.path))
+            .cloned()
+            .collect();
+        if wanted.is_empty() {
+            return Task::none();
+        }
+        for file in &wanted {
+            self.thumbnails
+                .insert(file.path.clone(), Thumbnail::Loading);
+        }
+
+        let config = self.config.sftp_config.clone();
+        Task::stream(iced::stream::channel(100, move |mut output| async move {
+            let cache = Arc::new(thumbnails::ThumbnailCache::new(&config.profile_key()));
+            let mut client: Option<SftpClient> = None;
+            for file in wanted {
+                let cache = cache.clone();
+                let config = config.clone();
+                let mut client = client.take();
+                let path = file.path.clone();
+                let res = tokio::task::spawn_blocking(move || {
+                    // A changed mtime means a changed file, so a new thumbnail
+                    let stamp = file
+                        .modified
+                        .map(|m| m.timestamp().to_string())
+                        .unwrap_or_default();
+                    if let Some(png) = cache.get(&file.path, &stamp) {
+                        return (client, Some(png));
+                    }
+                    // Only connect once something actually needs fetching
+                    if client.is_none() {
+                        client = SftpClient::connect(&config).ok();
+                    }
+                    let png = client.as_ref().and_then(|c| {
+                        thumbnails::fetch_thumbnail(c, &file.path, file.size_bytes)
+                            .ok()
+                            .flatten()
+                    });
+                    if let Some(png) = &png {
+                        cache.put(&file.path, &stamp, png);
+                    }
+                    (client, png)
+                })
+                .await;
+                let png = match res {
+                    Ok((c, png)) => {
+                        client = c;
+                        png
+                    }
+                    Err(_) => None,
+                };
+                let _ = output.send(Message::ThumbnailLoaded(path, png)).await;
+            }
+        }))
+    }
+
+    /// Store the current folder and remote pane layout for this profile (saved with the config)
+    pub fn remember_session(&mut self) {
+        self.config.last_remote_path = self.current_remote_path.clone();
+        self.session.remote_path = self.current_remote_path.clone();
+        let key = self.config.profile_key();
+        self.config.sessions.insert(key, self.session.clone());
+    }
+
+    /// Record listing `path` now. Arriving from another folder takes the
+    /// previous visit as the baseline for "new"; refreshing keeps the old one.
+    fn note_visit(&mut self, path: &str) {
+        let now = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let previous = self.session.visits.insert(path.to_string(), now);
+        if self.new_since.0 != path {
+            self.new_since = (path.to_string(), previous);
+        }
+    }
+
+    /// Look up user and group names once per connection, when the Owner
+    /// column is showing
+    fn load_owner_names(&mut self) -> Task<Message> {
+        if !self.session.show_owner || self.owner_names.is_some() {
+            return Task::none();
+        }
+        let Some(client) = self.sftp_client.clone() else {
+            return Task::none();
+        };
+        // Numbers until the names arrive
+        self.owner_names = Some(OwnerNames::default());
+        Task::future(async move {
+            let names = tokio::task::spawn_blocking(move || client.lock().unwrap().owner_names())
+                .await
+                .unwrap_or_default();
+            Message::OwnerNamesLoaded(names)
+        })
+    }
+
+    pub fn new_since_summary(&self) -> String {
+        let count = self
+            .remote_files
+            .iter()
+            .filter(|f| self.is_new_since_visit(f))
+            .count();
+        match count {
+            0 => String::new(),
+            1 => "1 new since last visit".into(),
+            n => format!("{} new since last visit", n),
+        }
+    }
+
+    /// Changed on the server since the previous visit to the current folder
+    pub fn is_new_since_visit(&self, file: &RemoteFile) -> bool {
+        let since = self.new_since.1.as_ref().and_then(|since| {
+            chrono::NaiveDateTime::parse_from_str(since, "%Y-%m-%d %H:%M:%S").ok()
+        });
+        file.name != ".."
+            && file
+                .modified
+                .zip(since)
+                .is_some_and(|(modified, since)| modified > since.and_utc())
+    }
+
+    pub fn set_tree_children(&mut self, path: &str, files: &[RemoteFile]) {
+        let folders = files
+            .iter()
+            .filter(|f| f.file_type == FileType::Folder && f.name != "..")
+            .map(|f| f.path.clone())
+            .collect();
+        self.tree_children.insert(path.to_string(), folders);
+    }
+
+    pub fn load_tree_children(&self, path: String) -> Task<Message> {
+        let Some(client) = self.sftp_client.clone() else {
+            return Task::none();
+        };
+        Task::future(async move {
+            let path_clone = path.clone();
+            let res = tokio::task::spawn_blocking(move || {
+                let c = client.lock().unwrap();
+                c.list_dir(std::path::Path::new(&path_clone))
+            })
+            .await
+            .unwrap_or_else(|e| Err(e.into()));
+            Message::TreeChildrenLoaded(path, res)
+        })
+    }
+
+    /// Expand every ancestor of `path` so the listed folder is visible in the tree,
+    /// loading any ancestor that hasn't been listed yet
+    pub fn reveal_in_tree(&mut self, path: &str) -> Task<Message> {
+        let mut tasks = Vec::new();
+        for ancestor in std::path::Path::new(path).ancestors().skip(1) {
+            let ancestor = ancestor.to_string_lossy().to_string();
+            if ancestor.is_empty() {
+                continue;
+            }
+            self.tree_expanded.insert(ancestor.clone());
+            if !self.tree_children.contains_key(&ancestor) {
+                tasks.push(self.load_tree_children(ancestor));
+            }
+        }
+        Task::batch(tasks)
+    }
+
+    /// Each change deleting `file` makes, files first; `contents` is what a
+    /// folder holds (empty for a file, or when it goes to the trash folder whole)
+    pub fn delete_plan(&self, file: &RemoteFile, contents: &[RemoteFile]) -> Vec<String> {
+        if let Some(trash) = &self.config.sftp_config.trash_folder {
+            return vec![format!("Move {} to {}", file.path, trash)];
+        }
+        let mut plan: Vec<String> = contents
+            .iter()
+            .filter(|f| f.file_type != FileType::Folder)
+            .map(|f| format!("Delete {}", f.path))
+            .collect();
+        plan.push(match file.file_type {
+            FileType::Folder => format!("Delete folder {}", file.path),
+            _ => format!("Delete {}", file.path),
+        });
+        plan
+    }
+
+    /// Dry run: write what would have happened to the Activity Log instead
+    pub fn log_dry_run(&mut self, plan: &[String], name: &str) {
+        for change in plan {
+            activity::info(format!("Dry run, not done: {}", change));
+        }
+        self.status_message = format!(
+            "Dry run: {} change(s) for {} logged, nothing was touched",
+            plan.len(),
+            name
+        );
+    }
+}
+
+/// A remote folder path from pasted text: trimmed, unquoted, without a trailing slash
+pub fn pasted_remote_path(text: &str) -> Option<String> {
+    let path = text.trim().trim_matches(|c| c == '"' || c == '\'');
+    if !path.starts_with('/') || path.contains('\n') {
+        return None;
+    }
+    let path = path.trim_end_matches('/');
+    Some(if path.is_empty() { "/" } else { path }.to_string())
+}
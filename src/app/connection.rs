@@ -0,0 +1,572 @@
+use super::{AppState, Message, ServerTab, SftpApp};
+use crate::download_manager::DownloadCommand;
+use crate::error::{FailureKind, SftpError};
+use crate::settings::SftpConfig;
+use crate::sftp_client::SftpClient;
+use crate::sftp_url::SftpUrl;
+use crate::{activity, probe, settings, sftp_client, url_handler};
+use iced::Task;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+impl SftpApp {
+    /// Connecting, testing and pinging the server, and the connection settings form
+    pub fn update_connection(&mut self, message: Message) -> Result<Task<Message>, Message> {
+        match message {
+            Message::TestConnection => {
+                self.is_probing = true;
+                self.probe_result = None;
+                let config = self.config.sftp_config.clone();
+                let dir = self.current_remote_path.clone();
+                return Ok(Task::future(async move {
+                    let res = tokio::task::spawn_blocking(move || probe::run(&config, &dir))
+                        .await
+                        .unwrap_or_else(|e| Err(e.into()));
+                    Message::TestConnectionResult(res)
+                }));
+            }
+            Message::TestConnectionResult(result) => {
+                self.is_probing = false;
+                self.probe_result = Some(match result {
+                    Ok(report) => {
+                        let burst = match &report.burst {
+                            Some(burst) => format!(
+                                "\nThroughput: {}/s ({} of {})",
+                                self.format_bytes(&burst.bytes_per_sec().to_string()),
+                                self.format_bytes(&burst.bytes.to_string()),
+                                burst.file
+                            ),
+                            None => String::new(),
+                        };
+                        format!(
+                            "Connect + login: {} ms\nLatency: {} ms{}\n{}",
+                            report.connect.as_millis(),
+                            report.latency.as_millis(),
+                            burst,
+                            report.hint()
+                        )
+                    }
+                    Err(e) => format!("Test failed: {}", e),
+                });
+            }
+            Message::SaveSettings => {
+                if !settings::validate(&self.config, &self.port_input).is_empty() {
+                    return Ok(Task::none());
+                }
+                self.settings_error = None;
+                // Still works from the config file, just not kept as safely
+                let mut unprotected = Vec::new();
+                if let Err(e) = self.config.sftp_config.store_totp_secret() {
+                    activity::error(format!(
+                        "Couldn't keep the one-time code secret in the keyring: {}",
+                        e
+                    ));
+                    unprotected.push("the one-time code secret");
+                }
+                if let Err(e) = self.config.report.store_smtp_password() {
+                    activity::error(format!(
+                        "Couldn't keep the report mail password in the keyring: {}",
+                        e
+                    ));
+                    unprotected.push("the report mail password");
+                }
+                if let Err(e) = self.config.save() {
+                    self.settings_error = Some(format!("Couldn't save settings: {}", e));
+                    return Ok(Task::none());
+                }
+                if !unprotected.is_empty() {
+                    self.settings_error = Some(format!(
+                        "Saved, but the keyring refused {}, so it's in config.json as plain text. \
+                         See the Activity Log for why.",
+                        unprotected.join(" and ")
+                    ));
+                }
+                if self.connect_after_save {
+                    // Saved already, so the dialog only stays up to show the attempt
+                    self.editing_from = Some(self.config.clone());
+                    return Ok(self.connect());
+                }
+                if self.settings_error.is_some() {
+                    // Saved, but the dialog stays up so the warning isn't missed
+                    self.editing_from = Some(self.config.clone());
+                    return Ok(Task::none());
+                }
+                self.close_dialog();
+                self.status_message = "Settings saved.".into();
+            }
+            Message::ConnectAfterSaveToggled(on) => self.connect_after_save = on,
+            Message::CancelConnect => {
+                if let Some(handle) = self.connect_handle.take() {
+                    handle.abort();
+                }
+                self.is_checking_connection = false;
+                self.status_message = "Connection cancelled.".into();
+            }
+            Message::ConnectionResult(result) => {
+                self.is_checking_connection = false;
+                self.connect_handle = None;
+                match result {
+                    Ok(client) => {
+                        let _ = self.config.save();
+                        self.is_connected = true;
+                        self.config.auto_connect = true;
+                        self.session_info = client.lock().ok().map(|c| c.info().clone());
+                        self.owner_names = None;
+                        self.connected_at = Some(Instant::now());
+                        self.latency = None;
+                        self.sftp_client = Some(client.clone());
+                        self.app_error = None; // clear error
+                        self.close_dialog();
+                        self.status_message = "Connected. Restoring session...".into();
+                        self.apply_speed_limit();
+                        // Save config immediately to persist connection state
+                        let _ = self.config.save();
+
+                        println!(
+                            "DEBUG: ConnectionResult - Last Path: '{}'",
+                            self.config.last_remote_path
+                        );
+                        // Reconnecting a server that's open in another tab replaces it
+                        let key = self.config.profile_key();
+                        self.server_tabs
+                            .retain(|tab| tab.sftp_config.profile_key() != key);
+                        self.register_profile(&self.config.sftp_config);
+
+                        let listing_task = self.restore_session(client);
+
+                        // Trigger Queue Resume Check
+                        let resume_task = Task::done(Message::ResumeQueue);
+
+                        let link_task = match self.pending_url.take() {
+                            Some(url) => Task::done(Message::QueueRemotePath(url.path)),
+                            None => Task::none(),
+                        };
+                        let job_tasks = self.cli_jobs.drain(..).map(|name| {
+                            Task::done(Message::RunJobNamed(
+                                name,
+                                settings::JobTrigger::CommandLine,
+                            ))
+                        });
+                        return Ok(Task::batch(
+                            [
+                                listing_task,
+                                resume_task,
+                                link_task,
+                                Task::done(Message::CheckWatchedFolders),
+                            ]
+                            .into_iter()
+                            .chain(job_tasks),
+                        ));
+                    }
+                    Err(e) => {
+                        // Bad credentials: bring up Settings so the user can re-enter them
+                        if e.is_auth() {
+                            self.open_dialog(AppState::SettingsView);
+                        }
+                        self.status_message = "Connection failed.".into();
+                        activity::error(format!("Connection failed: {}", e));
+                        self.settings_error = Some(e.to_string());
+                    }
+                }
+            }
+            Message::TrashFolderChanged(val) => {
+                self.config.sftp_config.trash_folder = (!val.trim().is_empty()).then_some(val)
+            }
+            Message::CancelSettings => self.cancel_dialog(),
+            Message::HostChanged(val) => self.config.sftp_config.host = val,
+            Message::PortChanged(val) => {
+                if let Ok(p) = val.trim().parse::<u16>() {
+                    self.config.sftp_config.port = p;
+                }
+                self.port_input = val;
+            }
+            Message::UsernameChanged(val) => self.config.sftp_config.username = val,
+            Message::PasswordChanged(val) => self.config.sftp_config.password = Some(val),
+            Message::CompressionToggled(val) => self.config.sftp_config.compression = val,
+            Message::ReadOnlyToggled(val) => self.config.sftp_config.read_only = val,
+            Message::StrictAlgorithmsToggled(val) => {
+                self.config.sftp_config.algorithms.strict = val
+            }
+            Message::KexChanged(val) => self.config.sftp_config.algorithms.kex = val,
+            Message::CiphersChanged(val) => self.config.sftp_config.algorithms.ciphers = val,
+            Message::MacsChanged(val) => self.config.sftp_config.algorithms.macs = val,
+            Message::StartDirectoryChanged(val) => {
+                self.config.sftp_config.start_directory = (!val.trim().is_empty()).then_some(val)
+            }
+            Message::PreConnectChanged(val) => {
+                self.config.sftp_config.pre_connect_command =
+                    (!val.trim().is_empty()).then_some(val)
+            }
+            Message::ServerTimezoneChanged(val) => {
+                self.config.sftp_config.server_timezone = (!val.trim().is_empty()).then_some(val)
+            }
+            Message::TotpSecretChanged(val) => {
+                self.config.sftp_config.totp_secret = (!val.trim().is_empty()).then_some(val)
+            }
+
+            Message::ShowAdvancedToggled(show) => self.show_advanced = show,
+            Message::ProtocolTraceToggled(on) => {
+                self.config.protocol_trace = on;
+                sftp_client::set_protocol_trace(on);
+            }
+            Message::PingConnection => {
+                if let Some(client) = self.sftp_client.clone() {
+                    return Ok(Task::future(async move {
+                        let res =
+                            tokio::task::spawn_blocking(move || client.lock().unwrap().ping())
+                                .await
+                                .unwrap_or_else(|e| Err(e.into()));
+                        Message::PingResult(res)
+                    }));
+                }
+            }
+            Message::PingResult(result) => {
+                self.latency = result.ok();
+            }
+            Message::OpenSftpUrl(url) => {
+                activity::info(format!("Opening sftp://{}{}", url.host, url.path));
+                if url.matches(&self.config.sftp_config) {
+                    if self.is_connected {
+                        return Ok(self.update(Message::QueueRemotePath(url.path)));
+                    }
+                    self.pending_url = Some(url);
+                    return Ok(self.connect());
+                }
+                if let Some(index) = self
+                    .server_tabs
+                    .iter()
+                    .position(|tab| url.matches(&tab.sftp_config))
+                {
+                    let switch = self.update(Message::SwitchServerTab(index));
+                    return Ok(Task::batch([
+                        switch,
+                        self.update(Message::QueueRemotePath(url.path)),
+                    ]));
+                }
+                // A server we don't know yet: fill in what the link gives and
+                // let the user add the password or key before connecting
+                if let Some(tab) = self.take_connection() {
+                    self.server_tabs.push(tab);
+                }
+                self.open_dialog(AppState::SettingsView);
+                self.config.sftp_config = settings::SftpConfig {
+                    host: url.host.clone(),
+                    port: url.port,
+                    username: url.username.clone().unwrap_or_default(),
+                    ..Default::default()
+                };
+                self.port_input = url.port.to_string();
+                self.settings_error = None;
+                self.connect_after_save = true;
+                self.status_message = format!("Add your login for {} to open the link", url.host);
+                self.pending_url = Some(url);
+            }
+            Message::RegisterUrlHandler => {
+                return Ok(Task::future(async move {
+                    let res = tokio::task::spawn_blocking(|| {
+                        url_handler::register()
+                            .map_err(|e| SftpError::local("Couldn't register for sftp:// links", e))
+                    })
+                    .await
+                    .unwrap_or_else(|e| Err(e.into()));
+                    Message::UrlHandlerRegistered(res)
+                }));
+            }
+            Message::UrlHandlerRegistered(Ok(())) => {
+                self.status_message = "sftp:// links now open in SimpleSFTP.".into();
+                activity::info("Registered as the sftp:// link handler");
+            }
+            Message::UrlHandlerRegistered(Err(e)) => {
+                activity::error(e.to_string());
+                self.settings_error = Some(e.to_string());
+            }
+            Message::BackupSettings => {
+                let text = self
+                    .config
+                    .backup(&chrono::Local::now().format("%Y-%m-%d %H:%M").to_string());
+                return Ok(Task::future(async move {
+                    let res = tokio::task::spawn_blocking(move || {
+                        let Some(path) = rfd::FileDialog::new()
+                            .set_file_name("simplesftp-backup.json")
+                            .save_file()
+                        else {
+                            return Ok(None);
+                        };
+                        std::fs::write(&path, text)
+                            .map(|_| Some(path))
+                            .map_err(|e| SftpError::local("Failed to write backup", e))
+                    })
+                    .await
+                    .unwrap_or_else(|e| Err(e.into()));
+                    Message::SettingsBackedUp(res)
+                }));
+            }
+            Message::SettingsBackedUp(Ok(Some(path))) => {
+                self.status_message = format!(
+                    "Settings backed up to {} (without the password)",
+                    path.display()
+                );
+            }
+            Message::SettingsBackedUp(Ok(None)) => {}
+            Message::SettingsBackedUp(Err(e)) | Message::SettingsBackupRead(Err(e)) => {
+                self.settings_error = Some(e.to_string());
+            }
+            Message::RestoreSettings => {
+                return Ok(Task::future(async move {
+                    let res = tokio::task::spawn_blocking(|| {
+                        let Some(path) = rfd::FileDialog::new()
+                            .add_filter("Settings backup", &["json"])
+                            .pick_file()
+                        else {
+                            return Ok(None);
+                        };
+                        std::fs::read_to_string(&path)
+                            .map(Some)
+                            .map_err(|e| SftpError::local("Failed to read backup", e))
+                    })
+                    .await
+                    .unwrap_or_else(|e| Err(e.into()));
+                    Message::SettingsBackupRead(res)
+                }));
+            }
+            Message::SettingsBackupRead(Ok(None)) => {}
+            Message::SettingsBackupRead(Ok(Some(text))) => {
+                match self.config.restored_from(&text) {
+                    Ok(config) => {
+                        self.config = config;
+                        let _ = self.config.save();
+                        // Restored settings are saved, not pending edits to discard
+                        if self.editing_from.is_some() {
+                            self.editing_from = Some(self.config.clone());
+                        }
+                        self.sync_inputs();
+                        self.push_transfer_settings();
+                        sftp_client::set_protocol_trace(self.config.protocol_trace);
+                        self.settings_error = None;
+                        self.status_message = "Settings restored from backup.".into();
+                        activity::info("Restored settings from a backup");
+                        return Ok(Task::batch([
+                            Task::done(Message::CheckNetwork),
+                            Task::done(Message::CheckPower),
+                        ]));
+                    }
+                    Err(e) => self.settings_error = Some(e),
+                }
+            }
+            Message::ShowUrlPrompt => self.url_input = Some(String::new()),
+            Message::UrlInputChanged(text) => self.url_input = Some(text),
+            Message::CancelUrlPrompt => self.url_input = None,
+            Message::SubmitUrl => {
+                let text = self.url_input.clone().unwrap_or_default();
+                match SftpUrl::parse(&text) {
+                    Some(url) => {
+                        self.url_input = None;
+                        return Ok(self.update(Message::OpenSftpUrl(url)));
+                    }
+                    None => {
+                        self.status_message =
+                            "Not an sftp:// link (sftp://user@host:port/path)".into()
+                    }
+                }
+            }
+            Message::NewServerTab => {
+                if let Some(tab) = self.take_connection() {
+                    self.server_tabs.push(tab);
+                }
+                self.settings_error = None;
+                self.connect_after_save = true;
+                self.open_dialog(AppState::SettingsView);
+            }
+            Message::SwitchServerTab(index) if index < self.server_tabs.len() => {
+                let tab = self.server_tabs.remove(index);
+                if let Some(current) = self.take_connection() {
+                    self.server_tabs.insert(index, current);
+                }
+                self.config.sftp_config = tab.sftp_config;
+                self.sync_inputs();
+                self.sftp_client = Some(tab.client.clone());
+                self.session_info = tab.session_info;
+                self.owner_names = None;
+                self.connected_at = tab.connected_at;
+                self.is_connected = true;
+                self.listing_error = None;
+                let _ = self.config.save();
+                return Ok(self.restore_session(tab.client));
+            }
+            Message::CloseServerTab(index) if index < self.server_tabs.len() => {
+                // Its queued items wait until the server is connected again
+                self.server_tabs.remove(index);
+            }
+            Message::ReauthPasswordChanged(val) => {
+                if let Some(reauth) = &mut self.reauth {
+                    reauth.password = val;
+                }
+            }
+            Message::ReauthKeyChanged(val) => {
+                if let Some(reauth) = &mut self.reauth {
+                    reauth.key_path = val;
+                }
+            }
+            Message::SubmitReauth => {
+                let Some(config) = self.reauth_config() else {
+                    return Ok(Task::none());
+                };
+                if let Some(reauth) = &mut self.reauth {
+                    reauth.checking = true;
+                }
+                // Try the new details before the queue leans on them again
+                return Ok(Task::future(async move {
+                    let res =
+                        tokio::task::spawn_blocking(move || SftpClient::connect(&config).map(drop))
+                            .await
+                            .unwrap_or_else(|e| Err(e.into()));
+                    Message::ReauthChecked(res)
+                }));
+            }
+            Message::ReauthChecked(Err(e)) => {
+                if let Some(reauth) = &mut self.reauth {
+                    reauth.checking = false;
+                    reauth.error = e.to_string();
+                }
+            }
+            Message::ReauthChecked(Ok(())) => {
+                let Some(config) = self.reauth_config() else {
+                    return Ok(Task::none());
+                };
+                let Some(reauth) = self.reauth.take() else {
+                    return Ok(Task::none());
+                };
+                self.state = AppState::MainView;
+                if self.config.sftp_config.profile_key() == reauth.profile {
+                    self.config.sftp_config = config.clone();
+                    let _ = self.config.save();
+                }
+                for tab in &mut self.server_tabs {
+                    if tab.sftp_config.profile_key() == reauth.profile {
+                        tab.sftp_config = config.clone();
+                    }
+                }
+                activity::info(format!("Signed in to {} again", reauth.profile));
+                self.register_profile(&config);
+                let manager_profile = self.manager_profile.clone();
+                let task = self.retry_failed(|item| {
+                    item.failure == Some(FailureKind::Login)
+                        && (item.profile == reauth.profile
+                            || item.profile.is_empty() && manager_profile == reauth.profile)
+                });
+                self.resume_after_reauth();
+                return Ok(task);
+            }
+            Message::CancelReauth => {
+                self.reauth = None;
+                self.state = AppState::MainView;
+                // The refused items stay failed, for Retry once the login is sorted
+                self.resume_after_reauth();
+            }
+            other => return Err(other),
+        }
+        Ok(Task::none())
+    }
+
+    /// Bring back the on-screen profile's layout and list the folder it was in
+    fn restore_session(&mut self, client: Arc<Mutex<SftpClient>>) -> Task<Message> {
+        // Restore this profile's layout and folder if we've seen it before
+        let saved = self
+            .config
+            .sessions
+            .get(&self.config.profile_key())
+            .cloned();
+        self.new_since = (String::new(), None);
+        // Names picked on the last server mean nothing on this one
+        self.selected_file = None;
+        self.last_click = None;
+        // A profile seen for the first time starts from the defaults, not
+        // from whatever the last one had
+        self.session = saved.unwrap_or_default();
+        self.panes.resize(self.pane_split, self.session.pane_ratio);
+        self.pending_scroll = Some(self.session.scroll_offset);
+
+        // A folder retried under another login wins, then the profile's
+        // start directory, then wherever we left off
+        let start = self.config.sftp_config.start_directory.clone();
+        let path = if let Some(retry) = self.retry_path.take() {
+            retry
+        } else if let Some(start) = start.filter(|s| !s.trim().is_empty()) {
+            start.trim().to_string()
+        } else {
+            self.session.remote_path.clone()
+        };
+        println!("DEBUG: ConnectionResult - Using Path: '{}'", path);
+
+        Task::future(async move {
+            let path_clone = path.clone();
+            let res = tokio::task::spawn_blocking(move || {
+                let c = client.lock().unwrap();
+                c.list_dir(std::path::Path::new(&path_clone))
+            })
+            .await
+            .unwrap_or_else(|e| Err(e.into()));
+
+            Message::RemoteFilesLoaded(path, res)
+        })
+    }
+
+    /// Move the on-screen server into a tab, leaving the remote pane unconnected
+    fn take_connection(&mut self) -> Option<ServerTab> {
+        let client = self.sftp_client.clone()?;
+        self.remember_session();
+        let tab = ServerTab {
+            sftp_config: self.config.sftp_config.clone(),
+            client,
+            session_info: self.session_info.take(),
+            connected_at: self.connected_at.take(),
+        };
+        self.clear_connection();
+        Some(tab)
+    }
+
+    /// Start a connection attempt unless one is already running. The attempt can
+    /// be abandoned with `Message::CancelConnect`; the blocking connect keeps going
+    /// in the background but its result is dropped.
+    pub fn connect(&mut self) -> Task<Message> {
+        if self.is_checking_connection {
+            return Task::none();
+        }
+        self.is_checking_connection = true;
+        activity::start_session(&self.config.profile_key());
+        let config = self.config.sftp_config.clone();
+
+        let (task, handle) = Task::future(async move {
+            let res = tokio::task::spawn_blocking(move || SftpClient::connect(&config))
+                .await
+                .unwrap_or_else(|e| Err(e.into()));
+
+            Message::ConnectionResult(res.map(|c| Arc::new(Mutex::new(c))))
+        })
+        .abortable();
+        self.connect_handle = Some(handle);
+        task
+    }
+
+    /// The reauth server's settings with the details typed into the prompt
+    fn reauth_config(&self) -> Option<SftpConfig> {
+        let reauth = self.reauth.as_ref()?;
+        let mut config = self.profile_config(&reauth.profile)?.clone();
+        if !reauth.password.is_empty() {
+            config.password = Some(reauth.password.clone());
+        }
+        config.private_key_path =
+            (!reauth.key_path.trim().is_empty()).then(|| reauth.key_path.trim().to_string());
+        Some(config)
+    }
+
+    /// Let the queue go again after the sign-in prompt, unless the schedule or
+    /// a network/battery hold says otherwise
+    fn resume_after_reauth(&self) {
+        if let Some(tx) = &self.download_tx {
+            if self.transfers_allowed {
+                let _ = tx.try_send(DownloadCommand::ResumeAll);
+            }
+        }
+    }
+}
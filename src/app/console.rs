@@ -0,0 +1,220 @@
+use super::{Message, SftpApp};
+use crate::sftp_client::{extract_command, shell_quote, SftpClient};
+use crate::{activity, names};
+use iced::Task;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+const CONSOLE_LINES: usize = 2000; // scrollback kept, oldest dropped first
+const HISTORY_LEN: usize = 50;
+const EXTRACT_POLL: Duration = Duration::from_secs(3);
+
+impl SftpApp {
+    /// The remote console: one-off commands over an exec channel, run in the
+    /// folder on screen, on servers that allow it
+    pub fn update_console(&mut self, message: Message) -> Result<Task<Message>, Message> {
+        match message {
+            Message::ConsoleInputChanged(val) => self.console_input = val,
+            Message::ConsoleHistoryPicked(command) => self.console_input = command,
+            Message::ClearConsole => self.console_output.clear(),
+            Message::ConsoleSubmit => {
+                let command = self.console_input.trim().to_string();
+                if command.is_empty() || self.console_running {
+                    return Ok(Task::none());
+                }
+                if let Some(reason) = self.console_blocked() {
+                    self.console_print(&format!("{}\n", reason));
+                    return Ok(Task::none());
+                }
+                if !self.is_connected {
+                    return Ok(Task::none());
+                }
+                self.console_input.clear();
+                self.console_history.retain(|c| *c != command);
+                self.console_history.push(command.clone());
+                if self.console_history.len() > HISTORY_LEN {
+                    self.console_history.remove(0);
+                }
+                self.console_running = true;
+                let cancel = Arc::new(AtomicBool::new(false));
+                self.console_cancel = Some(cancel.clone());
+                self.console_print(&format!("{}$ {}\n", self.current_remote_path, command));
+                activity::info(format!("Console: {}", command));
+                let line = format!(
+                    "cd {} && {}",
+                    shell_quote(&self.current_remote_path),
+                    command
+                );
+                // A command can run for minutes, so it gets a login of its own
+                // rather than holding the browser's session
+                let config = self.config.sftp_config.clone();
+                let session = self.console_session.clone();
+                return Ok(Task::future(async move {
+                    let res = tokio::task::spawn_blocking(move || {
+                        let mut session = session.lock().unwrap();
+                        let profile = config.profile_key();
+                        if session.as_ref().is_none_or(|(p, _)| *p != profile) {
+                            *session = Some((profile, SftpClient::connect(&config)?));
+                        }
+                        let (_, client) = session.as_ref().unwrap();
+                        let res = client.exec_output_with(&line, &cancel);
+                        if res.as_ref().is_err_and(|e| e.is_retryable()) {
+                            *session = None; // log in again next time
+                        }
+                        res
+                    })
+                    .await
+                    .unwrap_or_else(|e| Err(e.into()));
+                    Message::ConsoleOutput(res)
+                }));
+            }
+            Message::StopConsoleCommand => {
+                if let Some(cancel) = &self.console_cancel {
+                    cancel.store(true, Ordering::Relaxed);
+                }
+            }
+            Message::ConsoleOutput(res) => {
+                self.console_running = false;
+                self.console_cancel = None;
+                match res {
+                    Ok((status, output)) => {
+                        self.console_print(&output);
+                        if !output.is_empty() && !output.ends_with('\n') {
+                            self.console_print("\n");
+                        }
+                        if status != 0 {
+                            self.console_print(&format!("[exit {}]\n", status));
+                        }
+                    }
+                    Err(e) => self.console_print(&format!("{}\n", e)),
+                }
+            }
+            Message::AllowShellToggled(on) => self.config.sftp_config.allow_shell = on,
+            Message::ExtractOnServer(file) => {
+                if let Some(reason) = self.console_blocked() {
+                    self.app_error = Some(reason.to_string());
+                    return Ok(Task::none());
+                }
+                let Some(client) = self.sftp_client.clone() else {
+                    return Ok(Task::none());
+                };
+                if self.extracting.contains(&file.path) {
+                    return Ok(Task::none());
+                }
+                if self.config.dry_run {
+                    // Extracting overwrites files of the same name in the folder
+                    let folder = Path::new(&file.path).parent().unwrap_or(Path::new("/"));
+                    let command = extract_command(&file.name).unwrap_or_default();
+                    let plan = [format!("Run {} in {}", command, folder.display())];
+                    self.log_dry_run(&plan, &file.name);
+                    return Ok(Task::none());
+                }
+                self.extracting.push(file.path.clone());
+                self.status_message =
+                    format!("Extracting {} on the server...", names::display(&file.name));
+                activity::info(format!("Extracting {} on the server", file.path));
+                let path = file.path;
+                return Ok(Task::future(async move {
+                    let archive = path.clone();
+                    let res = tokio::task::spawn_blocking(move || {
+                        let c = client.lock().unwrap();
+                        c.start_extract(Path::new(&archive))
+                    })
+                    .await
+                    .unwrap_or_else(|e| Err(e.into()));
+                    Message::ExtractStarted(path, res)
+                }));
+            }
+            Message::ExtractStarted(path, Ok(())) => return Ok(poll_extract_later(path)),
+            Message::ExtractStarted(path, Err(e)) => {
+                self.extracting.retain(|p| *p != path);
+                self.app_error = Some(format!("Extraction failed to start: {}", e));
+                activity::error(format!("Extracting {} failed to start: {}", path, e));
+            }
+            Message::PollExtract(path) => {
+                let Some(client) = self.sftp_client.clone() else {
+                    // Disconnected; the server finishes on its own
+                    self.extracting.retain(|p| *p != path);
+                    return Ok(Task::none());
+                };
+                return Ok(Task::future(async move {
+                    let archive = path.clone();
+                    let res = tokio::task::spawn_blocking(move || {
+                        let c = client.lock().unwrap();
+                        c.extract_status(Path::new(&archive))
+                    })
+                    .await
+                    .unwrap_or_else(|e| Err(e.into()));
+                    Message::ExtractPolled(path, res)
+                }));
+            }
+            Message::ExtractPolled(path, result) => {
+                let Some(result) = result.transpose() else {
+                    return Ok(poll_extract_later(path));
+                };
+                self.extracting.retain(|p| *p != path);
+                let name = names::display(path.rsplit('/').next().unwrap_or(&path));
+                match result {
+                    Ok((0, _)) => {
+                        self.status_message = format!("Extracted {}", name);
+                        activity::info(format!("Extracted {} on the server", path));
+                    }
+                    Ok((status, output)) => {
+                        self.app_error = Some(format!(
+                            "Extracting {} failed (exit {}):\n{}",
+                            name, status, output
+                        ));
+                        activity::error(format!("Extracting {} failed (exit {})", path, status));
+                    }
+                    Err(e) => {
+                        self.app_error = Some(format!("Lost track of extracting {}: {}", name, e));
+                    }
+                }
+                let folder = Path::new(&path).parent().map(names::path_to_string);
+                if folder.as_deref() == Some(self.current_remote_path.as_str()) {
+                    return Ok(self.update(Message::RefreshRemote));
+                }
+            }
+            other => return Err(other),
+        }
+        Ok(Task::none())
+    }
+
+    /// Why the console can't run commands on this server, if it can't
+    pub fn console_blocked(&self) -> Option<&'static str> {
+        if !self.config.sftp_config.allow_shell {
+            Some(
+                "Commands are off for this server; turn on \"Allow console commands\" in Settings.",
+            )
+        } else if self.config.sftp_config.read_only {
+            Some("This server is read-only in SimpleSFTP, so the console is off.")
+        } else if !self.is_connected {
+            Some("Not connected.")
+        } else {
+            None
+        }
+    }
+
+    fn console_print(&mut self, text: &str) {
+        self.console_output.push_str(text);
+        let lines = self.console_output.lines().count();
+        if lines > CONSOLE_LINES {
+            let cut = self
+                .console_output
+                .match_indices('\n')
+                .nth(lines - CONSOLE_LINES - 1)
+                .map_or(0, |(i, _)| i + 1);
+            self.console_output.drain(..cut);
+        }
+    }
+}
+
+/// Check on a running extraction again after a pause
+fn poll_extract_later(path: String) -> Task<Message> {
+    Task::future(async move {
+        tokio::time::sleep(EXTRACT_POLL).await;
+        Message::PollExtract(path)
+    })
+}
@@ -0,0 +1,708 @@
+use super::{AppState, Message, Removed, SftpApp};
+use crate::download_manager::{DownloadCommand, DownloadEvent, ProfileConnector};
+use crate::persistence::save_queue;
+use crate::settings::SftpConfig;
+use crate::types::{Direction, ItemKey, TimelineEvent, TransferStatus};
+use crate::{activity, download_manager, journal, names, notify, remote_fs, settings, taskbar};
+use iced::Task;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+impl SftpApp {
+    /// Running transfers: manager events, per-item controls and transfer settings
+    pub fn update_downloads(&mut self, message: Message) -> Result<Task<Message>, Message> {
+        match message {
+            Message::StartDownloads => {
+                return Ok(self.start_manager());
+            }
+            Message::PollDownloadEvents => {
+                if let Some(rx) = &self.download_rx {
+                    let rx = rx.clone();
+                    return Ok(Task::future(async move {
+                        let mut guard = rx.lock().await;
+                        match guard.recv().await {
+                            Some(DownloadEvent::Progress {
+                                key,
+                                bytes_downloaded,
+                            }) => Message::DownloadProgress {
+                                key,
+                                bytes_downloaded,
+                            },
+                            Some(DownloadEvent::Completed { key }) => {
+                                Message::DownloadCompleted(key)
+                            }
+                            Some(DownloadEvent::Failed { key, error }) => {
+                                Message::DownloadFailed { key, error }
+                            }
+                            Some(DownloadEvent::Started { key }) => Message::DownloadStarted(key),
+                            Some(DownloadEvent::Verifying { key }) => {
+                                Message::DownloadVerifying(key)
+                            }
+                            Some(DownloadEvent::Retrying { key, attempt }) => {
+                                Message::DownloadRetrying { key, attempt }
+                            }
+                            Some(DownloadEvent::Stalled { key, attempt }) => {
+                                Message::DownloadStalled { key, attempt }
+                            }
+                            Some(DownloadEvent::Skipped { key, reason }) => {
+                                Message::DownloadSkipped { key, reason }
+                            }
+                            Some(DownloadEvent::LowDiskSpace { free, required }) => {
+                                Message::LowDiskSpace { free, required }
+                            }
+                            Some(DownloadEvent::DiskSpaceRecovered) => Message::DiskSpaceRecovered,
+                            Some(DownloadEvent::Stats(stats)) => Message::TransferStats(stats),
+                            Some(DownloadEvent::Paused { key, resume_check }) => {
+                                Message::DownloadPaused { key, resume_check }
+                            }
+                            Some(DownloadEvent::PartialChanged { key }) => {
+                                Message::PartialFileChanged(key)
+                            }
+                            Some(DownloadEvent::SourceChanged { key }) => {
+                                Message::SourceFileChanged(key)
+                            }
+                            None => Message::NoOp,
+                        }
+                    }));
+                }
+            }
+            Message::PauseDownload(path) => {
+                if let Some(tx) = &self.download_tx {
+                    let _ = tx.try_send(DownloadCommand::Pause(path.clone()));
+                }
+                if let Some(item) = self.queue_items.iter_mut().find(|i| i.key() == path) {
+                    item.status = TransferStatus::Paused;
+                    save_queue(&self.queue_items);
+                }
+            }
+            Message::ResumeDownload(path) => {
+                if let Some(tx) = &self.download_tx {
+                    let _ = tx.try_send(DownloadCommand::Resume(path.clone()));
+                }
+                if let Some(item) = self.queue_items.iter_mut().find(|i| i.key() == path) {
+                    item.status = TransferStatus::Connecting;
+                    save_queue(&self.queue_items);
+                }
+            }
+            Message::CancelDownload(path) => {
+                // First press cancels an unfinished item, second press removes it from the list
+                if let Some(item) = self.queue_items.iter_mut().find(|i| i.key() == path) {
+                    let verb = if item.status.is_finished() {
+                        "Removed"
+                    } else {
+                        "Cancelled"
+                    };
+                    self.removed = Some(Removed {
+                        label: format!("{} {}", verb, names::display(&item.filename)),
+                        items: vec![item.clone()],
+                        at: Instant::now(),
+                    });
+                    if item.status.is_finished() {
+                        self.queue_items.retain(|i| i.key() != path);
+                    } else {
+                        if let Some(tx) = &self.download_tx {
+                            let _ = tx.try_send(DownloadCommand::Cancel(path.clone()));
+                        }
+                        item.status = TransferStatus::Cancelled;
+                    }
+                }
+                save_queue(&self.queue_items);
+                return Ok(self.check_batch_finished(&path));
+            }
+            Message::RemoveQueueItem(path) => {
+                let Some(index) = self.queue_items.iter().position(|i| i.key() == path) else {
+                    return Ok(Task::none());
+                };
+                let item = self.queue_items.remove(index);
+                let batch = item.batch.clone();
+                if !item.status.is_finished() {
+                    if let Some(tx) = &self.download_tx {
+                        let _ = tx.try_send(DownloadCommand::Cancel(path.clone()));
+                    }
+                }
+                if self.selected_queue_item.as_ref() == Some(&path) {
+                    self.selected_queue_item = None;
+                }
+                self.removed = Some(Removed {
+                    label: format!("Removed {}", names::display(&item.filename)),
+                    items: vec![item],
+                    at: Instant::now(),
+                });
+                save_queue(&self.queue_items);
+                // The item is gone, so its batch is looked up by id
+                return Ok(batch.map_or_else(Task::none, |b| self.check_batch_done(b)));
+            }
+            Message::Redownload(path) => {
+                return Ok(self.redownload(&path));
+            }
+            Message::ExistingCopyChanged(policy) => {
+                self.config.existing_copy = policy;
+            }
+            Message::DownloadProgress {
+                key,
+                bytes_downloaded,
+            } => {
+                if let Some(item) = self.queue_items.iter_mut().find(|i| i.key() == key) {
+                    // Calculate delta
+                    if bytes_downloaded > item.bytes_transferred {
+                        let delta = bytes_downloaded - item.bytes_transferred;
+                        self.config.add_daily_stat(delta, 0);
+                        let profile = if item.profile.is_empty() {
+                            &self.manager_profile
+                        } else {
+                            &item.profile
+                        };
+                        self.config.add_usage(profile, delta);
+                    }
+                    item.bytes_transferred = bytes_downloaded;
+                    // Progress below the checked length means it started over
+                    if item
+                        .resume_check
+                        .as_ref()
+                        .is_some_and(|c| c.length > bytes_downloaded)
+                    {
+                        item.resume_check = None;
+                    }
+                    // A followed file keeps growing past the size it was queued with
+                    item.size_bytes = item.size_bytes.max(bytes_downloaded);
+                    item.status = TransferStatus::Downloading;
+                }
+                // Continue polling for more events
+                return Ok(self.update(Message::PollDownloadEvents));
+            }
+            Message::DownloadCompleted(key) => {
+                activity::info(format!(
+                    "Transfer finished: {}",
+                    names::display(&key.remote_file)
+                ));
+                let mut uploaded_here = false;
+                if let Some(item) = self.queue_items.iter_mut().find(|i| i.key() == key) {
+                    item.status = TransferStatus::Completed;
+                    item.bytes_transferred = item.size_bytes;
+                    item.note(now(), TimelineEvent::Finished(item.status.clone()));
+                    if item.direction == Direction::Download {
+                        let profile = match item.profile.as_str() {
+                            "" => self.config.profile_key(),
+                            profile => profile.to_string(),
+                        };
+                        let name = key
+                            .remote_file
+                            .rsplit('/')
+                            .next()
+                            .unwrap_or(&key.remote_file);
+                        self.config.remember_download(&profile, name);
+                        let _ = self.config.save();
+                    }
+                    uploaded_here = item.direction == Direction::Upload
+                        && std::path::Path::new(&key.remote_file).parent()
+                            == Some(std::path::Path::new(
+                                self.current_remote_path.trim_end_matches('/'),
+                            ));
+                }
+                self.record_transfer(&key);
+                save_queue(&self.queue_items);
+                let manifest = self.check_batch_finished(&key);
+                // Show the new file if the user is looking at the folder it went to
+                if uploaded_here {
+                    return Ok(Task::batch([
+                        manifest,
+                        self.update(Message::RefreshRemote),
+                        self.update(Message::PollDownloadEvents),
+                    ]));
+                }
+                // Continue polling for more events
+                return Ok(Task::batch([
+                    manifest,
+                    self.update(Message::PollDownloadEvents),
+                ]));
+            }
+            Message::DownloadFailed { key, error } => {
+                activity::error(format!(
+                    "Transfer of {} failed: {}",
+                    names::display(&key.remote_file),
+                    error
+                ));
+                if let Some(item) = self.queue_items.iter_mut().find(|i| i.key() == key) {
+                    item.status = TransferStatus::Failed(error.to_string());
+                    item.failure = Some(error.kind());
+                    item.note(now(), TimelineEvent::Finished(item.status.clone()));
+                }
+                if error.is_auth() && self.reauth.is_none() {
+                    let profile = self
+                        .queue_items
+                        .iter()
+                        .find(|i| i.key() == key)
+                        .map(|i| i.profile.clone())
+                        .unwrap_or_default();
+                    self.ask_reauth(profile, error.to_string());
+                }
+                self.record_transfer(&key);
+                save_queue(&self.queue_items);
+                let manifest = self.check_batch_finished(&key);
+                // Continue polling for more events
+                return Ok(Task::batch([
+                    manifest,
+                    self.update(Message::PollDownloadEvents),
+                ]));
+            }
+            Message::DownloadStarted(key) => {
+                activity::info(format!(
+                    "Transfer started: {}",
+                    names::display(&key.remote_file)
+                ));
+                if let Some(item) = self.queue_items.iter_mut().find(|i| i.key() == key) {
+                    item.status = TransferStatus::Connecting;
+                    item.note(now(), TimelineEvent::Started);
+                    if let Some(batch) = &item.batch {
+                        self.batch_started
+                            .entry(batch.id)
+                            .or_insert_with(Instant::now);
+                    }
+                }
+                save_queue(&self.queue_items);
+                // Continue polling for more events
+                return Ok(self.update(Message::PollDownloadEvents));
+            }
+            Message::DownloadVerifying(key) => {
+                if let Some(item) = self.queue_items.iter_mut().find(|i| i.key() == key) {
+                    item.status = TransferStatus::Verifying;
+                }
+                return Ok(self.update(Message::PollDownloadEvents));
+            }
+            Message::DownloadRetrying { key, attempt } => {
+                if let Some(item) = self.queue_items.iter_mut().find(|i| i.key() == key) {
+                    item.status = TransferStatus::Retrying(attempt);
+                    item.note(now(), TimelineEvent::Retried);
+                }
+                return Ok(self.update(Message::PollDownloadEvents));
+            }
+            Message::DownloadStalled { key, attempt } => {
+                activity::error(format!(
+                    "Transfer of {} stalled, restarting",
+                    names::display(&key.remote_file)
+                ));
+                if let Some(item) = self.queue_items.iter_mut().find(|i| i.key() == key) {
+                    item.status = TransferStatus::Stalled(attempt);
+                    item.note(now(), TimelineEvent::Retried);
+                }
+                return Ok(self.update(Message::PollDownloadEvents));
+            }
+            Message::DownloadPaused { key, resume_check } => {
+                let reason = self.pause_reason(&key);
+                if let Some(item) = self.queue_items.iter_mut().find(|i| i.key() == key) {
+                    item.resume_check = resume_check;
+                    item.note(now(), TimelineEvent::Paused(reason));
+                    save_queue(&self.queue_items);
+                }
+                return Ok(self.update(Message::PollDownloadEvents));
+            }
+            Message::PartialFileChanged(key) => {
+                let name = names::display(&key.remote_file);
+                activity::error(format!(
+                    "The partial copy of {} was changed on disk; downloading it again from the start",
+                    name
+                ));
+                self.status_message = format!("{} was modified locally, restarted from zero", name);
+                if let Some(item) = self.queue_items.iter_mut().find(|i| i.key() == key) {
+                    item.resume_check = None;
+                }
+                return Ok(self.update(Message::PollDownloadEvents));
+            }
+            Message::SourceFileChanged(key) => {
+                let name = names::display(&key.remote_file);
+                activity::error(format!(
+                    "{} on the server no longer matches the partial copy (replaced since?); downloading it again from the start",
+                    name
+                ));
+                self.status_message =
+                    format!("{} changed on the server, restarted from zero", name);
+                if let Some(item) = self.queue_items.iter_mut().find(|i| i.key() == key) {
+                    item.resume_check = None;
+                }
+                return Ok(self.update(Message::PollDownloadEvents));
+            }
+            Message::DownloadSkipped { key, reason } => {
+                if let Some(item) = self.queue_items.iter_mut().find(|i| i.key() == key) {
+                    item.bytes_transferred = item.size_bytes;
+                    item.status = TransferStatus::Skipped(reason);
+                    item.note(now(), TimelineEvent::Finished(item.status.clone()));
+                }
+                save_queue(&self.queue_items);
+                let manifest = self.check_batch_finished(&key);
+                return Ok(Task::batch([
+                    manifest,
+                    self.update(Message::PollDownloadEvents),
+                ]));
+            }
+            Message::LowDiskSpace { free, required } => {
+                self.disk_warning = Some(format!(
+                    "Low disk space: {} free, keeping at least {}. Downloads are on hold and will continue once space is freed.",
+                    self.format_bytes(&free.to_string()),
+                    self.format_bytes(&required.to_string())
+                ));
+                notify::desktop(
+                    "Downloads on hold",
+                    "The download folder is running out of space",
+                );
+                return Ok(self.update(Message::PollDownloadEvents));
+            }
+            Message::TransferStats(stats) => {
+                self.session_stats = stats;
+                return Ok(self.update(Message::PollDownloadEvents));
+            }
+            Message::WindowRawId(id) => {
+                self.window_raw_id = Some(id);
+                self.taskbar_percent = None;
+                return Ok(self.update_taskbar());
+            }
+            Message::DiskSpaceRecovered => {
+                self.disk_warning = None;
+                self.status_message = "Disk space freed, downloads continuing.".into();
+                return Ok(self.update(Message::PollDownloadEvents));
+            }
+            Message::SpeedLimitChanged(val) => {
+                // Allow empty string for backspace
+                if val.is_empty() {
+                    self.config.max_download_speed = 0;
+                } else if let Ok(speed) = val.parse::<u64>() {
+                    self.config.max_download_speed = speed;
+                }
+
+                // Update active manager if running
+                self.apply_speed_limit();
+                // Auto-save config on change? Maybe too frequent.
+                // Let's save on exit or explicit save.
+                // But for "Speed Limit" it feels like a live toggle.
+                // Let's save config roughly.
+                let _ = self.config.save();
+            }
+            Message::ProfileSpeedLimitChanged(val) => {
+                // Blank falls back to the default limit
+                let key = self.config.profile_key();
+                if val.is_empty() {
+                    self.config.profile_speed_limits.remove(&key);
+                } else if let Ok(speed) = val.parse::<u64>() {
+                    self.config.profile_speed_limits.insert(key, speed);
+                }
+                self.apply_speed_limit();
+                let _ = self.config.save();
+            }
+            Message::FsyncIntervalChanged(val) => {
+                if val.is_empty() {
+                    self.config.fsync_interval_mb = 0;
+                } else if let Ok(mb) = val.parse::<u64>() {
+                    self.config.fsync_interval_mb = mb;
+                }
+
+                if let Some(tx) = &self.download_tx {
+                    let _ = tx.try_send(DownloadCommand::SetFsyncInterval(
+                        self.config.fsync_interval_mb,
+                    ));
+                }
+                let _ = self.config.save();
+            }
+            Message::MinFreeSpaceChanged(val) => {
+                if val.is_empty() {
+                    self.config.min_free_space_gb = 0;
+                } else if let Ok(gb) = val.parse::<u64>() {
+                    self.config.min_free_space_gb = gb;
+                }
+
+                if let Some(tx) = &self.download_tx {
+                    let _ = tx.try_send(DownloadCommand::SetMinFreeSpace(
+                        self.config.min_free_space_gb,
+                    ));
+                }
+                let _ = self.config.save();
+            }
+            Message::FollowStableChanged(val) => {
+                if let Ok(mins) = val.parse::<u64>() {
+                    self.config.follow_stable_mins = mins.max(1);
+                    if let Some(tx) = &self.download_tx {
+                        let _ = tx.try_send(DownloadCommand::SetFollowStable(
+                            std::time::Duration::from_secs(mins.max(1) * 60),
+                        ));
+                    }
+                    let _ = self.config.save();
+                }
+            }
+            Message::FastLaneChanged(val) => {
+                if let Ok(mb) = val.parse::<u64>() {
+                    self.config.fast_lane_mb = mb;
+                    if let Some(tx) = &self.download_tx {
+                        let _ = tx.try_send(DownloadCommand::SetFastLane(mb * 1024 * 1024));
+                    }
+                    let _ = self.config.save();
+                }
+            }
+            Message::ItemSpeedLimitChanged(val) => {
+                let Some(path) = self.selected_queue_item.clone() else {
+                    return Ok(Task::none());
+                };
+                let limit = if val.is_empty() {
+                    0
+                } else if let Ok(kb) = val.parse::<u64>() {
+                    kb
+                } else {
+                    return Ok(Task::none());
+                };
+                if let Some(item) = self.queue_items.iter_mut().find(|i| i.key() == path) {
+                    item.speed_limit = limit;
+                    if let Some(tx) = &self.download_tx {
+                        let _ = tx.try_send(DownloadCommand::SetItemSpeedLimit(path, limit));
+                    }
+                    save_queue(&self.queue_items);
+                }
+            }
+            Message::ItemNoteChanged(note) => {
+                let Some(path) = self.selected_queue_item.clone() else {
+                    return Ok(Task::none());
+                };
+                if let Some(item) = self.queue_items.iter_mut().find(|i| i.key() == path) {
+                    item.note = note;
+                    save_queue(&self.queue_items);
+                }
+            }
+            Message::ItemLabelPicked(label) => {
+                let Some(path) = self.selected_queue_item.clone() else {
+                    return Ok(Task::none());
+                };
+                if let Some(item) = self.queue_items.iter_mut().find(|i| i.key() == path) {
+                    item.label = label;
+                    save_queue(&self.queue_items);
+                }
+            }
+            Message::ToggleFollow(path) => {
+                if let Some(item) = self.queue_items.iter_mut().find(|i| i.key() == path) {
+                    item.follow = !item.follow;
+                    if let Some(tx) = &self.download_tx {
+                        let _ = tx.try_send(DownloadCommand::SetFollow(path, item.follow));
+                    }
+                    save_queue(&self.queue_items);
+                }
+            }
+            other => return Err(other),
+        }
+        Ok(Task::none())
+    }
+
+    /// The default speed cap, for servers without one of their own
+    pub fn speed_limit(&self) -> u64 {
+        self.battery_limited(self.config.max_download_speed)
+    }
+
+    /// The caps of servers with a limit of their own, by profile_key(). Items
+    /// that don't name their server go by the "" entry: the manager's server
+    pub fn profile_speed_limits(&self) -> HashMap<String, u64> {
+        let mut limits: HashMap<String, u64> = self
+            .config
+            .profile_speed_limits
+            .iter()
+            .map(|(profile, &limit)| (profile.clone(), self.battery_limited(limit)))
+            .collect();
+        if let Some(&limit) = limits.get(&self.manager_profile) {
+            limits.insert(String::new(), limit);
+        }
+        limits
+    }
+
+    /// `limit` lowered to the battery limit when that applies
+    fn battery_limited(&self, limit: u64) -> u64 {
+        if !self.on_battery || self.config.battery_mode != settings::BatteryMode::Throttle {
+            return limit;
+        }
+        download_manager::tighter_limit(limit, self.config.battery_speed_limit)
+    }
+
+    /// How far the queue has got, from what moved this run and what's left of
+    /// the unfinished items. None while nothing is transferring
+    pub fn queue_progress(&self) -> Option<f32> {
+        let running = |s: &TransferStatus| {
+            matches!(
+                s,
+                TransferStatus::Connecting
+                    | TransferStatus::Downloading
+                    | TransferStatus::Verifying
+                    | TransferStatus::Retrying(_)
+                    | TransferStatus::Stalled(_)
+            )
+        };
+        if !self.queue_items.iter().any(|i| running(&i.status)) {
+            return None;
+        }
+        let left: u64 = self
+            .queue_items
+            .iter()
+            .filter(|i| running(&i.status) || i.status == TransferStatus::Queued)
+            .map(|i| i.size_bytes.saturating_sub(i.bytes_transferred))
+            .sum();
+        let done = self.session_stats.bytes;
+        Some(done as f32 / (done + left).max(1) as f32)
+    }
+
+    /// Show the queue's progress on the taskbar button or dock icon when the
+    /// whole percent changes. Windows needs the native window handle first
+    pub fn update_taskbar(&mut self) -> Task<Message> {
+        let progress = self.queue_progress();
+        let percent = progress.map(|p| (p * 100.0) as u8);
+        if percent == self.taskbar_percent {
+            return Task::none();
+        }
+        if cfg!(target_os = "windows") && self.window_raw_id.is_none() {
+            return iced::window::get_latest()
+                .and_then(iced::window::get_raw_id::<Message>)
+                .map(Message::WindowRawId);
+        }
+        self.taskbar_percent = percent;
+        taskbar::set_progress(self.window_raw_id, progress);
+        Task::none()
+    }
+
+    /// Push the current speed cap to the running manager
+    pub fn apply_speed_limit(&self) {
+        if let Some(tx) = &self.download_tx {
+            let _ = tx.try_send(DownloadCommand::SetSpeedLimit(self.speed_limit()));
+            let _ = tx.try_send(DownloadCommand::SetProfileSpeedLimits(
+                self.profile_speed_limits(),
+            ));
+        }
+    }
+
+    /// Send the running manager every transfer setting it keeps its own copy of
+    pub fn push_transfer_settings(&self) {
+        let Some(tx) = &self.download_tx else {
+            return;
+        };
+        let _ = tx.try_send(DownloadCommand::SetSpeedLimit(self.speed_limit()));
+        let _ = tx.try_send(DownloadCommand::SetProfileSpeedLimits(
+            self.profile_speed_limits(),
+        ));
+        let _ = tx.try_send(DownloadCommand::SetScheduleHolds(
+            self.schedule_holds(chrono::Local::now()),
+        ));
+        let _ = tx.try_send(DownloadCommand::SetDryRun(self.config.dry_run));
+        let _ = tx.try_send(DownloadCommand::SetFsyncInterval(
+            self.config.fsync_interval_mb,
+        ));
+        let _ = tx.try_send(DownloadCommand::SetMinFreeSpace(
+            self.config.min_free_space_gb,
+        ));
+        let _ = tx.try_send(DownloadCommand::SetQueueOrder(self.config.queue_order));
+        let _ = tx.try_send(DownloadCommand::SetFairness(self.config.fair_batches));
+        let _ = tx.try_send(DownloadCommand::SetFastLane(
+            self.config.fast_lane_mb * 1024 * 1024,
+        ));
+        let _ = tx.try_send(DownloadCommand::SetFollowStable(
+            std::time::Duration::from_secs(self.config.follow_stable_mins * 60),
+        ));
+    }
+
+    /// Why `key` is paused right now, for the pause entries in item timelines
+    fn pause_reason(&self, key: &ItemKey) -> String {
+        let reason = if self.disk_warning.is_some() {
+            "low disk space"
+        } else if self.metered_hold() {
+            "metered network"
+        } else if self.battery_hold() {
+            "on battery"
+        } else if self.schedule_holds.holds(&key.profile) {
+            "schedule"
+        } else {
+            "by you"
+        };
+        reason.to_string()
+    }
+
+    pub fn start_manager(&mut self) -> Task<Message> {
+        if self.download_tx.is_none() {
+            // Tests must not leave a journal behind for the next start to replay
+            let journal = (!cfg!(test)).then(journal::default_path);
+            let (tx, rx) = download_manager::create_download_manager(
+                self.config.sftp_config.clone(),
+                self.speed_limit(),
+                journal.as_deref(),
+            );
+            self.download_tx = Some(tx.clone());
+            // The manager keeps the server it was started with
+            self.manager_profile = self.config.profile_key();
+            self.register_profile(&self.config.sftp_config);
+            for tab in &self.server_tabs {
+                self.register_profile(&tab.sftp_config);
+            }
+            self.download_rx = Some(Arc::new(tokio::sync::Mutex::new(rx)));
+            self.is_downloading = true;
+            self.push_transfer_settings();
+
+            // Send all pending items to the download manager
+            for item in &self.queue_items {
+                if item.status == TransferStatus::Queued {
+                    let _ = tx.try_send(DownloadCommand::AddItem(Box::new(item.clone())));
+                }
+            }
+            // Removed: If schedule is NOT allowed, we used to pause info.
+            // But now we allow manual override, so if start_manager is called (manually or auto),
+            // we assume we WANT to download.
+            // Tick will handle pausing if schedule changes state.
+
+            let _ = tx.try_send(DownloadCommand::StartAll);
+
+            // Start polling for events
+            return self.update(Message::PollDownloadEvents);
+        }
+        Task::none()
+    }
+
+    /// The server refused a transfer's login (changed password, rotated key):
+    /// hold the queue and ask for new details instead of failing item after item
+    fn ask_reauth(&mut self, profile: String, error: String) {
+        let profile = if profile.is_empty() {
+            self.manager_profile.clone()
+        } else {
+            profile
+        };
+        let key_path = self
+            .profile_config(&profile)
+            .and_then(|c| c.private_key_path.clone())
+            .unwrap_or_default();
+        activity::error(format!(
+            "{} refused the login; transfers paused until you sign in again",
+            profile
+        ));
+        notify::desktop("Sign-in needed", &format!("{} refused the login", profile));
+        if let Some(tx) = &self.download_tx {
+            let _ = tx.try_send(DownloadCommand::PauseAll);
+        }
+        self.reauth = Some(super::Reauth {
+            profile,
+            error,
+            key_path,
+            ..Default::default()
+        });
+        self.state = AppState::ReauthView;
+    }
+
+    /// The connection settings of the server `profile` names: the one in
+    /// Settings or one open in a tab
+    pub fn profile_config(&self, profile: &str) -> Option<&SftpConfig> {
+        std::iter::once(&self.config.sftp_config)
+            .chain(self.server_tabs.iter().map(|t| &t.sftp_config))
+            .find(|c| c.profile_key() == profile)
+    }
+
+    /// Let the running manager open sessions to `sftp_config`'s server for the
+    /// items queued from it
+    pub fn register_profile(&self, sftp_config: &SftpConfig) {
+        if let Some(tx) = &self.download_tx {
+            let connector = ProfileConnector(remote_fs::sftp_connector(sftp_config.clone()));
+            let _ = tx.try_send(DownloadCommand::AddProfile(
+                sftp_config.profile_key(),
+                connector,
+            ));
+        }
+    }
+}
+
+fn now() -> i64 {
+    chrono::Local::now().timestamp()
+}
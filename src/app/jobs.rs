@@ -0,0 +1,239 @@
+use super::{AppState, Message, SftpApp};
+use crate::settings::{Job, JobRun, JobTrigger, TimeOfDay};
+use crate::sftp_client::SftpClient;
+use crate::types::{FileType, RemoteFile};
+use crate::{activity, filter, names};
+use chrono::{DateTime, Local};
+use iced::Task;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+impl SftpApp {
+    /// Named jobs: a server folder, filters and a destination, run on demand
+    pub fn update_jobs(&mut self, message: Message) -> Result<Task<Message>, Message> {
+        match message {
+            Message::SaveAsJob(folder) => {
+                let mut name = names::display(&folder.name).to_string();
+                let mut n = 2;
+                while self.config.jobs.iter().any(|j| j.name == name) {
+                    name = format!("{} {}", names::display(&folder.name), n);
+                    n += 1;
+                }
+                self.config.jobs.push(Job {
+                    name,
+                    profile: self.config.profile_key(),
+                    remote_path: folder.path,
+                    patterns: Vec::new(),
+                    destination: String::new(),
+                    run_at: None,
+                    last_scheduled: String::new(),
+                    history: Vec::new(),
+                });
+                self.job_inputs.push(Default::default());
+                let _ = self.config.save();
+                self.state = AppState::JobsView;
+            }
+            Message::DeleteJob(index) if index < self.config.jobs.len() => {
+                self.config.jobs.remove(index);
+                self.job_inputs.remove(index);
+                let _ = self.config.save();
+            }
+            Message::JobNameChanged(index, val) => {
+                if let Some(job) = self.config.jobs.get_mut(index) {
+                    job.name = val;
+                    let _ = self.config.save();
+                }
+            }
+            Message::JobPatternsChanged(index, val) => {
+                if let Some(job) = self.config.jobs.get_mut(index) {
+                    job.patterns = filter::parse_patterns(&val);
+                    self.job_inputs[index].0 = val;
+                    let _ = self.config.save();
+                }
+            }
+            Message::JobDestinationChanged(index, val) => {
+                if let Some(job) = self.config.jobs.get_mut(index) {
+                    job.destination = val;
+                    let _ = self.config.save();
+                }
+            }
+            Message::PickJobDestination(index) => {
+                return Ok(Task::future(async move {
+                    let path = tokio::task::spawn_blocking(|| rfd::FileDialog::new().pick_folder())
+                        .await
+                        .unwrap_or(None);
+                    Message::JobDestinationPicked(index, path)
+                }));
+            }
+            Message::JobDestinationPicked(index, Some(path)) => {
+                if let Some(job) = self.config.jobs.get_mut(index) {
+                    job.destination = names::path_to_string(&path);
+                    let _ = self.config.save();
+                }
+            }
+            Message::JobRunAtChanged(index, val) => {
+                if let Some(job) = self.config.jobs.get_mut(index) {
+                    // Blank runs by hand only; a half-typed time keeps the last good one
+                    if val.trim().is_empty() {
+                        job.run_at = None;
+                    } else if let Some(at) = TimeOfDay::parse(&val) {
+                        job.run_at = Some(at);
+                    }
+                    self.job_inputs[index].1 = val;
+                    let _ = self.config.save();
+                }
+            }
+            Message::RunJob(index) => {
+                if let Some(job) = self.config.jobs.get(index) {
+                    return Ok(self.run_job(job.name.clone(), JobTrigger::Manual));
+                }
+            }
+            Message::RunJobNamed(name, trigger) => return Ok(self.run_job(name, trigger)),
+            Message::JobScanned(name, trigger, result) => {
+                let Some(index) = self.config.jobs.iter().position(|j| j.name == name) else {
+                    return Ok(Task::none());
+                };
+                let job = self.config.jobs[index].clone();
+                let files = match result {
+                    Ok(files) => self.job_candidates(&job, files),
+                    Err(e) => {
+                        activity::error(format!("Job {} failed: {}", job.name, e));
+                        self.finish_job_run(index, trigger, 0, Some(e.to_string()));
+                        return Ok(Task::none());
+                    }
+                };
+                let destination = match job.destination.trim() {
+                    "" => self
+                        .config
+                        .profile_download_paths
+                        .get(&job.profile)
+                        .unwrap_or(&self.config.local_download_path)
+                        .clone(),
+                    folder => folder.to_string(),
+                };
+                let before = self.queue_items.len();
+                let task = self.enqueue_scanned_to(
+                    files,
+                    true,
+                    Some(job.remote_path.clone()),
+                    destination,
+                    job.profile.clone(),
+                );
+                let queued = self.queue_items.len() - before;
+                activity::info(format!("Job {}: queued {} files", job.name, queued));
+                self.finish_job_run(index, trigger, queued, None);
+                return Ok(task);
+            }
+            Message::CloseJobs => self.state = AppState::MainView,
+            other => return Err(other),
+        }
+        Ok(Task::none())
+    }
+
+    /// Scan the job's folder on its server, if that server is connected
+    pub fn run_job(&mut self, name: String, trigger: JobTrigger) -> Task<Message> {
+        let Some(index) = self.config.jobs.iter().position(|j| j.name == name) else {
+            self.status_message = format!("No job named {}", name);
+            return Task::none();
+        };
+        let job = &self.config.jobs[index];
+        let Some(client) = self.client_for(&job.profile) else {
+            let error = format!("Not connected to {}", job.profile);
+            activity::error(format!("Job {} didn't run: {}", job.name, error));
+            self.finish_job_run(index, trigger, 0, Some(error));
+            return Task::none();
+        };
+        self.status_message = format!("Running job {}...", job.name);
+        let path = job.remote_path.clone();
+        Task::future(async move {
+            let res = tokio::task::spawn_blocking(move || {
+                let c = client.lock().unwrap();
+                c.recursive_scan(Path::new(&path))
+            })
+            .await
+            .unwrap_or_else(|e| Err(e.into()));
+            Message::JobScanned(name, trigger, res)
+        })
+    }
+
+    /// Jobs whose daily time has come, each started once a day
+    pub fn run_due_jobs(&mut self, now: DateTime<Local>) -> Task<Message> {
+        let today = now.format("%Y-%m-%d").to_string();
+        let mut due = Vec::new();
+        for job in self.config.jobs.iter_mut().filter(|j| j.due(now)) {
+            job.last_scheduled = today.clone();
+            due.push(job.name.clone());
+        }
+        if due.is_empty() {
+            return Task::none();
+        }
+        let _ = self.config.save();
+        Task::batch(
+            due.into_iter()
+                .map(|name| self.run_job(name, JobTrigger::Schedule))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// Files from a job's scan to queue: matching its patterns and not in the
+    /// queue already
+    pub fn job_candidates(&self, job: &Job, files: Vec<RemoteFile>) -> Vec<RemoteFile> {
+        files
+            .into_iter()
+            .filter(|f| f.file_type == FileType::File)
+            .filter(|f| job.patterns.is_empty() || filter::is_excluded(&job.patterns, &f.name))
+            .filter(|f| {
+                !self
+                    .queue_items
+                    .iter()
+                    .any(|i| i.remote_file == f.path && i.profile == job.profile)
+            })
+            .collect()
+    }
+
+    /// The open session to `profile`'s server: the one on screen or a tab
+    fn client_for(&self, profile: &str) -> Option<Arc<Mutex<SftpClient>>> {
+        if self.is_connected && self.config.profile_key() == profile {
+            return self.sftp_client.clone();
+        }
+        self.server_tabs
+            .iter()
+            .find(|t| t.sftp_config.profile_key() == profile)
+            .map(|t| t.client.clone())
+    }
+
+    fn finish_job_run(
+        &mut self,
+        index: usize,
+        trigger: JobTrigger,
+        queued: usize,
+        error: Option<String>,
+    ) {
+        let job = &mut self.config.jobs[index];
+        self.status_message = match &error {
+            Some(e) => format!("Job {} failed: {}", job.name, e),
+            None => format!("Job {}: queued {} files", job.name, queued),
+        };
+        job.record(JobRun {
+            at: Local::now().timestamp(),
+            trigger,
+            queued,
+            error,
+        });
+        let _ = self.config.save();
+    }
+}
+
+/// Job names given as `--job <name>` or `--job=<name>` on the command line
+pub fn job_args(args: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut args = args.into_iter();
+    let mut jobs = Vec::new();
+    while let Some(arg) = args.next() {
+        if let Some(name) = arg.strip_prefix("--job=") {
+            jobs.push(name.to_string());
+        } else if arg == "--job" {
+            jobs.extend(args.next());
+        }
+    }
+    jobs
+}
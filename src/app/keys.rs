@@ -0,0 +1,183 @@
+use super::{remote_scroll_id, AppState, ConfigOption, Message, QueueRecovery, RemoteOp, SftpApp};
+use crate::types::{RemoteFile, TransferStatus};
+use iced::keyboard::key::Named;
+use iced::keyboard::{Key, Modifiers};
+use iced::widget::scrollable;
+use iced::Task;
+
+/// Something a key press asks for; see `key_action` for the bindings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyAction {
+    FocusNext,
+    FocusPrevious,
+    SelectPrevious,
+    SelectNext,
+    SelectFirst,
+    SelectLast,
+    Open,
+    Parent,
+    Refresh,
+    Queue,
+    Download,
+    Move,
+    Copy,
+    Delete,
+    TogglePause, // the selected queue item
+    Menu,
+    Show(ConfigOption),
+    Escape,
+}
+
+/// Keyboard bindings. Only keys no widget took get here, so typing in a text
+/// box never triggers them.
+pub fn key_action(key: Key, modifiers: Modifiers) -> Option<KeyAction> {
+    let action = match key.as_ref() {
+        Key::Named(Named::Tab) if modifiers.shift() => KeyAction::FocusPrevious,
+        Key::Named(Named::Tab) => KeyAction::FocusNext,
+        Key::Named(Named::ArrowUp) => KeyAction::SelectPrevious,
+        Key::Named(Named::ArrowDown) => KeyAction::SelectNext,
+        Key::Named(Named::Home) => KeyAction::SelectFirst,
+        Key::Named(Named::End) => KeyAction::SelectLast,
+        Key::Named(Named::Enter) => KeyAction::Open,
+        Key::Named(Named::Backspace) => KeyAction::Parent,
+        Key::Named(Named::F5) => KeyAction::Refresh,
+        Key::Named(Named::Delete) => KeyAction::Delete,
+        Key::Named(Named::Space) => KeyAction::TogglePause,
+        Key::Named(Named::F10) => KeyAction::Menu,
+        Key::Named(Named::Escape) => KeyAction::Escape,
+        Key::Character(c) if modifiers.command() => match c {
+            "," => KeyAction::Show(ConfigOption::Settings),
+            "h" => KeyAction::Show(ConfigOption::History),
+            "l" => KeyAction::Show(ConfigOption::ActivityLog),
+            "r" => KeyAction::Refresh,
+            _ => return None,
+        },
+        Key::Character(c) if modifiers.is_empty() || modifiers == Modifiers::SHIFT => {
+            match c.to_lowercase().as_str() {
+                "q" => KeyAction::Queue,
+                "d" => KeyAction::Download,
+                "m" => KeyAction::Move,
+                "c" => KeyAction::Copy,
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+    Some(action)
+}
+
+impl SftpApp {
+    /// Keyboard control: dialogs take Tab and Escape, the main view drives the
+    /// remote listing with the same actions its hover buttons offer
+    pub fn update_keys(&mut self, message: Message) -> Result<Task<Message>, Message> {
+        let Message::KeyPressed(action) = message else {
+            return Err(message);
+        };
+        let task = match action {
+            KeyAction::FocusNext => iced::widget::focus_next(),
+            KeyAction::FocusPrevious => iced::widget::focus_previous(),
+            KeyAction::Escape => match self.escape_message() {
+                Some(message) => self.update(message),
+                None => Task::none(),
+            },
+            _ if self.state != AppState::MainView => Task::none(),
+            KeyAction::Menu => self.update(Message::ToggleConfigMenu),
+            KeyAction::Show(option) => self.update(Message::ConfigOptionSelected(option)),
+            KeyAction::SelectPrevious => self.move_selection(-1),
+            KeyAction::SelectNext => self.move_selection(1),
+            KeyAction::SelectFirst => self.move_selection(isize::MIN),
+            KeyAction::SelectLast => self.move_selection(isize::MAX),
+            KeyAction::Parent => self.update(Message::GoToParent),
+            KeyAction::Refresh => self.update(Message::RefreshRemote),
+            KeyAction::TogglePause => {
+                let Some(item) = self
+                    .selected_queue_item
+                    .as_ref()
+                    .and_then(|key| self.queue_items.iter().find(|i| &i.key() == key))
+                else {
+                    return Ok(Task::none());
+                };
+                let key = item.key();
+                let message = match item.status {
+                    TransferStatus::Paused => Message::ResumeDownload(key),
+                    _ if item.status.is_finished() => return Ok(Task::none()),
+                    _ => Message::PauseDownload(key),
+                };
+                self.update(message)
+            }
+            _ => {
+                let Some(file) = self.selected_remote_file() else {
+                    return Ok(Task::none());
+                };
+                let read_only = self.config.sftp_config.read_only;
+                let message = match action {
+                    KeyAction::Open => return Ok(self.open_remote_file(file)),
+                    KeyAction::Queue => Message::QueueFile(file),
+                    KeyAction::Download => Message::DownloadFile(file),
+                    KeyAction::Move if !read_only => Message::StartRemoteOp(RemoteOp::Move, file),
+                    KeyAction::Copy if !read_only => Message::StartRemoteOp(RemoteOp::Copy, file),
+                    KeyAction::Delete if !read_only => Message::DeleteRemote(file),
+                    _ => return Ok(Task::none()),
+                };
+                self.update(message)
+            }
+        };
+        Ok(task)
+    }
+
+    /// What Escape closes: the open dialog, else the prompt or menu on top
+    fn escape_message(&self) -> Option<Message> {
+        let message = match self.state {
+            AppState::SettingsView => Message::CancelSettings,
+            AppState::ScheduleView => Message::CancelSchedule,
+            AppState::JobsView => Message::CloseJobs,
+            AppState::HistoryView => Message::CloseHistory,
+            AppState::FailureReportView => Message::CloseFailureReport,
+            AppState::ConnectionInfoView => Message::CloseConnectionInfo,
+            AppState::ActivityLogView => Message::CloseActivityLog,
+            AppState::ConsoleView => Message::CloseConsole,
+            AppState::ReauthView => Message::CancelReauth,
+            AppState::QueueRecoveryView => Message::RecoverQueue(QueueRecovery::Salvaged),
+            AppState::MissingFoldersView => Message::DismissMissingFolders,
+            AppState::MainView if self.pending_delete.is_some() => Message::CancelDelete,
+            AppState::MainView if self.remote_op.is_some() => Message::CancelRemoteOp,
+            AppState::MainView if self.url_input.is_some() => Message::CancelUrlPrompt,
+            AppState::MainView if self.is_config_menu_open => Message::ToggleConfigMenu,
+            AppState::MainView => return None,
+        };
+        Some(message)
+    }
+
+    /// The selected entry of the remote listing, as shown (filtered and sorted)
+    pub fn selected_remote_file(&self) -> Option<RemoteFile> {
+        let name = self.selected_file.as_ref()?;
+        self.visible_remote_files()
+            .into_iter()
+            .find(|f| &f.name == name)
+            .cloned()
+    }
+
+    /// Select the entry `step` rows from the current one, clamped to the listing
+    fn move_selection(&mut self, step: isize) -> Task<Message> {
+        let files = self.visible_remote_files();
+        if files.is_empty() {
+            return Task::none();
+        }
+        let current = self
+            .selected_file
+            .as_ref()
+            .and_then(|name| files.iter().position(|f| &f.name == name));
+        let index = match current {
+            Some(i) => i.saturating_add_signed(step).min(files.len() - 1),
+            None if step < 0 => files.len() - 1,
+            None => 0,
+        };
+        // Keep the selection in view
+        let offset = index as f32 / (files.len() - 1).max(1) as f32;
+        self.selected_file = Some(files[index].name.clone());
+        scrollable::snap_to(
+            remote_scroll_id(),
+            scrollable::RelativeOffset { x: 0.0, y: offset },
+        )
+    }
+}
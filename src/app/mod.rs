@@ -0,0 +1,2294 @@
+mod browser;
+mod connection;
+mod console;
+mod downloads;
+mod jobs;
+mod keys;
+mod queue;
+mod report;
+mod schedule;
+mod watch;
+
+pub use keys::KeyAction;
+
+use crate::checksum::ResumeCheck;
+use crate::download_manager::{DownloadCommand, DownloadEvent, ScheduleHolds, TransferStats};
+use crate::error::{DownloadError, SftpError};
+use crate::persistence::{self, save_queue, QueueProblem};
+use crate::settings::{AppConfig, SessionState, SftpConfig, SortColumn};
+use crate::sftp_client::{OwnerNames, SessionInfo, SftpClient};
+use crate::sftp_url::SftpUrl;
+use crate::tray::{TrayAction, TrayManager};
+use crate::types::{
+    Batch, ItemKey, ItemLabel, QueueFilter, QueueOrder, RemoteFile, ScanProgress, TransferItem,
+};
+use crate::{instance, network, probe, rules, settings, sftp_client, style};
+use chrono::{DateTime, FixedOffset, Local, Utc};
+use iced::widget::{pane_grid, scrollable, text_input};
+use iced::Task;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::mpsc;
+
+pub fn remote_scroll_id() -> scrollable::Id {
+    scrollable::Id::new("remote-listing")
+}
+
+pub fn console_input_id() -> text_input::Id {
+    text_input::Id::new("console-input")
+}
+
+impl SftpApp {
+    pub fn new() -> (Self, Task<Message>) {
+        let mut app = Self::default();
+        match persistence::load_queue_checked() {
+            Ok(items) => app.queue_items = items,
+            Err(problem) => {
+                app.queue_problem = Some(problem);
+                app.state = AppState::QueueRecoveryView;
+            }
+        }
+        if app.queue_problem.is_none() {
+            app.check_download_folders();
+        }
+        // Know the network before the first transfer starts, not 30s later
+        let network_check = Task::batch([
+            if app.config.pause_on_metered {
+                Task::done(Message::CheckNetwork)
+            } else {
+                Task::none()
+            },
+            if app.config.battery_mode != settings::BatteryMode::Ignore {
+                Task::done(Message::CheckPower)
+            } else {
+                Task::none()
+            },
+        ]);
+        println!(
+            "DEBUG: SftpApp::new - Auto Connect: {}, Last Path: {}",
+            app.config.auto_connect, app.config.last_remote_path
+        );
+        // Jobs named with --job run as soon as there's a connection
+        app.cli_jobs = jobs::job_args(std::env::args().skip(1));
+        // sftp:// links on the command line ("open with") connect on their own
+        let links: Vec<Task<Message>> = std::env::args()
+            .skip(1)
+            .filter_map(|arg| SftpUrl::parse(&arg))
+            .map(|url| Task::done(Message::OpenSftpUrl(url)))
+            .collect();
+        if !links.is_empty() {
+            return (app, Task::batch([network_check, Task::batch(links)]));
+        }
+        if app.config.auto_connect && !app.config.sftp_config.host.is_empty() {
+            app.status_message = format!("Auto-connecting to {}...", app.config.sftp_config.host);
+            println!("DEBUG: Triggering Auto-Connect Task");
+            return (
+                app,
+                Task::batch([
+                    network_check,
+                    Task::done(Message::ConfigOptionSelected(ConfigOption::Connect)),
+                ]),
+            );
+        }
+        (app, network_check)
+    }
+
+    /// sftp:// links and --job names from later launches, which hand them to
+    /// this copy instead of starting another
+    fn open_forwarded(&mut self) -> Task<Message> {
+        let mut tasks = Vec::new();
+        for args in instance::take_forwarded() {
+            for name in jobs::job_args(args.clone()) {
+                if self.is_connected {
+                    let trigger = settings::JobTrigger::CommandLine;
+                    tasks.push(Task::done(Message::RunJobNamed(name, trigger)));
+                } else {
+                    self.cli_jobs.push(name);
+                }
+            }
+            tasks.extend(
+                args.iter()
+                    .filter_map(|arg| SftpUrl::parse(arg))
+                    .map(|url| Task::done(Message::OpenSftpUrl(url))),
+            );
+        }
+        Task::batch(tasks)
+    }
+}
+
+pub const NETWORK_CHECK_SECS: u64 = 30;
+
+pub const POWER_CHECK_SECS: u64 = 30;
+
+pub const PING_INTERVAL_SECS: u64 = 5;
+
+pub const WATCH_INTERVAL_SECS: u64 = 5 * 60;
+
+pub const SPINNER: [&str; 4] = ["◐", "◓", "◑", "◒"];
+
+/// Folder scans with more files than this ask for confirmation before queueing
+pub const CONFIRM_BATCH_FILES: usize = 20;
+
+/// How long "Removed X — Undo" stays up in the queue pane
+pub const UNDO_SECS: u64 = 10;
+
+/// 1284 -> "1,284"
+pub fn format_count(n: usize) -> String {
+    let digits = n.to_string();
+    let mut out = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn watch_inputs(config: &AppConfig) -> Vec<String> {
+    config
+        .watched_folders
+        .iter()
+        .map(|w| w.patterns.join(", "))
+        .collect()
+}
+
+fn job_inputs(config: &AppConfig) -> Vec<(String, String)> {
+    config
+        .jobs
+        .iter()
+        .map(|j| {
+            let at = j
+                .run_at
+                .map(|t| format!("{:02}:{:02}", t.hour, t.minute))
+                .unwrap_or_default();
+            (j.patterns.join(", "), at)
+        })
+        .collect()
+}
+
+/// 3725 -> "1h 02m", 185 -> "3m 05s"
+pub fn format_duration(secs: u64) -> String {
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m {:02}s", secs / 60, secs % 60),
+        _ => format!("{}h {:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+/// A remote timestamp in the local timezone (or the server's, given `zone`),
+/// or how long ago it was relative to `now` ("3 hours ago"); blank when the
+/// server didn't give one
+pub fn format_modified(
+    modified: Option<DateTime<Utc>>,
+    relative: bool,
+    zone: Option<FixedOffset>,
+    now: DateTime<Utc>,
+) -> String {
+    let Some(modified) = modified else {
+        return String::new();
+    };
+    let date = |format: &str| match zone {
+        Some(zone) => modified.with_timezone(&zone).format(format).to_string(),
+        None => modified.with_timezone(&Local).format(format).to_string(),
+    };
+    let secs = (now - modified).num_seconds();
+    let plural = |n: i64, unit: &str| {
+        if n == 1 {
+            format!("1 {} ago", unit)
+        } else {
+            format!("{} {}s ago", n, unit)
+        }
+    };
+    match secs {
+        _ if !relative => date("%Y-%m-%d %H:%M:%S"),
+        0..=59 => "just now".into(),
+        60..=3599 => plural(secs / 60, "minute"),
+        3600..=86_399 => plural(secs / 3600, "hour"),
+        86_400..=2_591_999 => plural(secs / 86_400, "day"),
+        // Far back, or ahead of this clock: the date says more
+        _ => date("%Y-%m-%d"),
+    }
+}
+
+pub struct SftpApp {
+    pub config: AppConfig,
+    pub state: AppState,
+    pub is_config_menu_open: bool,
+    pub panes: pane_grid::State<PaneState>,
+    pub pane_split: pane_grid::Split,
+    // Sort/filter/layout of the remote pane, saved per profile
+    pub session: SessionState,
+    pub pending_scroll: Option<f32>,
+    pub listing_error: Option<(String, SftpError)>, // folder that wouldn't open, and why
+    pub retry_path: Option<String>, // folder to open after the next connect instead of the usual one
+    // Folder tree sidebar: loaded child folders per path, and which nodes are open
+    pub tree_children: std::collections::HashMap<String, Vec<String>>,
+    pub tree_expanded: std::collections::HashSet<String>,
+    // Grid view thumbnails by remote path
+    pub thumbnails: std::collections::HashMap<String, Thumbnail>,
+    // State
+    pub is_connected: bool,
+    pub is_checking_connection: bool,
+    pub connect_handle: Option<iced::task::Handle>, // aborts the pending connect attempt
+    pub settings_error: Option<String>,
+    pub probe_result: Option<String>, // "Test connection" report
+    pub is_probing: bool,
+    pub show_advanced: bool, // Settings' advanced section is folded away by default
+    pub port_input: String,  // Settings' port field as typed, validated before saving
+    pub editing_from: Option<AppConfig>, // config as it was when Settings or Schedule opened
+    pub confirm_discard: bool, // Cancel asked whether to drop the open dialog's edits
+    pub connect_after_save: bool, // Settings' Save also connects with the new details
+    pub pending_url: Option<SftpUrl>, // link to queue once its server is connected
+    pub url_input: Option<String>, // "Open link" prompt, while shown
+    pub app_error: Option<String>,
+    pub sftp_client: Option<Arc<Mutex<SftpClient>>>,
+    pub session_info: Option<SessionInfo>,
+    pub connected_at: Option<Instant>,
+    pub server_tabs: Vec<ServerTab>, // other connected servers, behind their tabs
+    pub latency: Option<std::time::Duration>, // last no-op stat round trip
+    // Selection & Navigation
+    pub selected_file: Option<String>,
+    pub last_click: Option<(String, Instant)>,
+    // Mock Data
+    pub queue_items: Vec<TransferItem>,
+    pub remote_files: Vec<RemoteFile>,
+    pub current_remote_path: String,
+    // Current folder and when it was listed before this visit; newer entries are highlighted
+    pub new_since: (String, Option<String>),
+    // Context Menu / Hover
+    pub hovered_file: Option<String>,
+    pub is_scanning_queue: bool,
+    pub scan_progress: Option<ScanProgress>,
+    pub scan_cancel: Option<Arc<AtomicBool>>,
+    // Checking the restored queue against the server: (checked, total), and skipping it
+    pub verify_progress: Option<(usize, usize)>,
+    pub verify_cancel: Option<Arc<AtomicBool>>,
+    // Move/Copy on the server: the pending action and its destination folder
+    pub remote_op: Option<(RemoteOp, RemoteFile)>,
+    pub remote_op_destination: String,
+    pub relay_server: String, // destination profile for RemoteOp::Relay
+    // "Calculate size" results by folder path
+    pub folder_sizes: std::collections::HashMap<String, FolderSize>,
+    // Last "Verify" outcome per checksum manifest path, shown in History
+    pub manifest_checks: std::collections::HashMap<String, Result<String, SftpError>>,
+    pub spinner_frame: usize,
+    // Folder scan waiting for the user to confirm it
+    pub pending_batch: Option<PendingBatch>,
+    // Items whose remote file is already queued, waiting on "Requeue anyway?"
+    pub pending_requeue: Vec<TransferItem>,
+    pub removed: Option<Removed>,
+    // Remote file waiting on the delete confirmation
+    pub pending_delete: Option<RemoteFile>,
+    pub delete_preview: Option<Vec<String>>, // what the pending delete would do; None while listing
+    pub reauth: Option<Reauth>,              // the queue is paused until this is answered
+    pub queue_problem: Option<QueueProblem>, // queue.json was damaged; asks what to restore
+    pub owner_names: Option<OwnerNames>,     // for this connection, once the Owner column asked
+    pub relocate: Option<(String, String)>,  // pending downloads' folder, and where it moved to
+    pub missing_folders: Vec<String>, // download folders gone since last run, asked about at startup
+    pub exclude_input: String,
+    pub rules_input: String, // Settings text for config.priority_rules
+    // Download Manager
+    pub download_tx: Option<mpsc::Sender<DownloadCommand>>,
+    pub manager_profile: String, // server the running manager transfers with, for usage
+    pub download_rx: Option<Arc<tokio::sync::Mutex<mpsc::Receiver<DownloadEvent>>>>,
+    pub is_downloading: bool,
+    pub selected_queue_item: Option<ItemKey>,
+    pub collapsed_batches: std::collections::HashSet<u64>,
+    pub failure_report: Option<Batch>, // batch shown in FailureReportView
+    pub queue_filter: QueueFilter,
+    pub queue_search: String,         // filename filter for the queue list
+    pub history_search: String,       // filter for Config > History
+    pub stale_dismissed: usize,       // stale item count when the alert was last dismissed
+    pub disk_warning: Option<String>, // shown while downloads are held for disk space
+    pub batch_started: std::collections::HashMap<u64, Instant>, // first transfer of each batch
+    // Tray Icon
+    pub tray_manager: Option<TrayManager>,
+    pub window_hidden: bool, // hidden to the tray
+    pub last_schedule_allowed: bool,
+    pub schedule_resumes_at: Option<chrono::DateTime<chrono::Local>>, // while schedule-paused
+    pub transfers_allowed: bool, // no metered network or battery hold on any transfers
+    pub schedule_holds: ScheduleHolds, // servers outside their schedule, as last sent
+    // Metered network detection
+    pub network: network::NetworkStatus,
+    pub on_metered: bool,
+    pub metered_override: bool, // "Download anyway" until the network changes
+    pub schedule_override: bool, // "Force downloads" until the queue drains
+    pub metered_ssids_input: String,
+    pub watch_inputs: Vec<String>, // Settings pattern text per watched folder
+    pub job_inputs: Vec<(String, String)>, // patterns and run time as typed, per job
+    pub cli_jobs: Vec<String>,     // --job names, run once connected
+    pub ignore_input: String,      // name to add to the downloaded list by hand
+    // Remote console
+    pub console_input: String,
+    pub console_output: String,       // scrollback
+    pub console_history: Vec<String>, // this run's commands, oldest first
+    pub console_running: bool,
+    pub console_cancel: Option<Arc<AtomicBool>>, // set to stop the running command
+    pub console_session: Arc<Mutex<Option<(String, SftpClient)>>>, // its own login, by profile_key()
+    pub extracting: Vec<String>, // archives being unpacked on the server
+    pub schedule_time_input: Option<(bool, String)>, // start (true) or end time as being typed
+    pub schedule_suggestion: Option<settings::ScheduleSuggestion>, // shown in the Schedule dialog
+    pub on_battery: bool,
+    pub status_message: String,
+    pub session_stats: TransferStats, // from the manager, since it started this run
+    pub window_raw_id: Option<u64>,   // native handle, for the taskbar progress on Windows
+    pub taskbar_percent: Option<u8>,  // last progress shown on the taskbar or dock
+}
+
+/// A connected server that isn't the one on screen. Picking its tab swaps it
+/// in; its browsing state comes back from the per-profile session.
+pub struct ServerTab {
+    pub sftp_config: SftpConfig,
+    pub client: Arc<Mutex<SftpClient>>,
+    pub session_info: Option<SessionInfo>,
+    pub connected_at: Option<Instant>,
+}
+
+pub enum Thumbnail {
+    Loading,
+    Ready(iced::widget::image::Handle),
+    Unavailable,
+}
+
+#[derive(Debug, Clone)]
+pub enum PaneState {
+    Queue,
+    Remote,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RemoteOp {
+    Move,
+    Copy,
+    Relay, // copy to another connected server through the queue
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FolderSize {
+    Scanning,
+    Done { bytes: u64, files: usize },
+}
+
+/// Queue items as they were before the last remove or cancel, for Undo
+#[derive(Debug, Clone)]
+pub struct Removed {
+    pub label: String,
+    pub items: Vec<TransferItem>,
+    pub at: Instant,
+}
+
+/// Sign-in details asked for again after a server refused a background transfer
+#[derive(Debug, Clone, Default)]
+pub struct Reauth {
+    pub profile: String, // profile_key() of the server
+    pub error: String,
+    pub password: String,
+    pub key_path: String,
+    pub checking: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct PendingBatch {
+    pub files: Vec<RemoteFile>,
+    pub auto_start: bool,
+    pub root_path: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AppState {
+    MainView,
+    SettingsView,
+    ScheduleView,
+    JobsView,
+    HistoryView,
+    FailureReportView,
+    ConnectionInfoView,
+    ActivityLogView,
+    ConsoleView,
+    ReauthView,
+    QueueRecoveryView,
+    MissingFoldersView,
+}
+
+/// What replaces a queue.json that couldn't be read
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QueueRecovery {
+    Salvaged,      // the items read up to the damage
+    Backup(usize), // one of QueueProblem::backups
+    Empty,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    KeyPressed(KeyAction),
+    ToggleConfigMenu,
+    ConfigOptionSelected(ConfigOption),
+    // Settings Form
+    HostChanged(String),
+    PortChanged(String),
+    UsernameChanged(String),
+    PasswordChanged(String),
+    PreConnectChanged(String),
+    TotpSecretChanged(String),
+    StartDirectoryChanged(String),
+    CompressionToggled(bool),
+    ReadOnlyToggled(bool),
+    StrictAlgorithmsToggled(bool),
+    KexChanged(String),
+    CiphersChanged(String),
+    MacsChanged(String),
+    SaveSettings,
+    ConnectAfterSaveToggled(bool),
+    TestConnection,
+    ShowAdvancedToggled(bool),
+    ProtocolTraceToggled(bool),
+    TestConnectionResult(Result<probe::ProbeReport, SftpError>),
+    CancelSettings,
+    DiscardChanges, // confirms Cancel on a Settings or Schedule dialog with edits
+    KeepEditing,
+    ConnectionResult(Result<Arc<Mutex<SftpClient>>, SftpError>),
+    CancelConnect,
+    NewServerTab,           // keep this server open in a tab and connect to another
+    SwitchServerTab(usize), // index into server_tabs
+    CloseServerTab(usize),
+    RemoteFilesLoaded(String, Result<(String, Vec<RemoteFile>), SftpError>),
+    // Remote Navigation
+    RemoteFileClicked(RemoteFile),
+    GoToParent,
+    ListingGoUp,        // leave a folder that failed to open for its parent
+    ListingRetryAsUser, // open Settings to log in as someone else, then retry the folder
+    // Local Navigation
+    SelectDownloadPath,
+    DownloadPathSelected(Option<std::path::PathBuf>),
+    SelectProfileDownloadPath, // this server's own download folder
+    ProfileDownloadPathSelected(Option<std::path::PathBuf>),
+    ClearProfileDownloadPath, // back to the default folder
+    // Uploads
+    SelectUploadFiles,
+    UploadFilesSelected(Option<Vec<std::path::PathBuf>>),
+    // RemoteFileDoubleClicked(String),
+    // Hover & Actions
+    HoverFile(String),
+    UnhoverFile,
+    QueueFile(RemoteFile),
+    DownloadFile(RemoteFile),
+    PreviewFile(RemoteFile),
+    CopyRemotePath(String),
+    OpenSftpUrl(SftpUrl),
+    RegisterUrlHandler,
+    UrlHandlerRegistered(Result<(), SftpError>),
+    BackupSettings,
+    SettingsBackedUp(Result<Option<std::path::PathBuf>, SftpError>),
+    RestoreSettings,
+    SettingsBackupRead(Result<Option<String>, SftpError>),
+    ShowUrlPrompt,
+    UrlInputChanged(String),
+    SubmitUrl,
+    CancelUrlPrompt,
+    QueueRemotePath(String), // a path typed or linked, file or folder
+    RemotePathProbed(Result<RemoteFile, SftpError>),
+    GoToClipboardPath,
+    ClipboardPathRead(Option<String>),
+    PreviewReady(Result<std::path::PathBuf, SftpError>),
+    DoubleClickChanged(settings::DoubleClick),
+    ExistingCopyChanged(settings::ExistingCopy),
+    // Move/Copy on the server
+    StartRemoteOp(RemoteOp, RemoteFile),
+    RemoteOpDestinationChanged(String),
+    RelayServerPicked(String), // profile_key() of a server tab
+    ConfirmRemoteOp,
+    CancelRemoteOp,
+    DeleteRemote(RemoteFile),
+    DeletePreviewListed(String, Result<Vec<RemoteFile>, SftpError>),
+    // Signing in again after a refused background transfer
+    ReauthPasswordChanged(String),
+    ReauthKeyChanged(String),
+    SubmitReauth,
+    ReauthChecked(Result<(), SftpError>),
+    CancelReauth,
+    RecoverQueue(QueueRecovery),
+    ShowRelocate,
+    RelocateFromChanged(String),
+    RelocateToChanged(String),
+    PickRelocateTarget,
+    RelocateTargetPicked(Option<std::path::PathBuf>),
+    ApplyRelocate,
+    CancelRelocate,
+    CreateMissingFolder(String),
+    PickMissingFolderTarget(String),
+    MissingFolderTargetPicked(String, Option<std::path::PathBuf>),
+    PauseMissingFolder(String),
+    DismissMissingFolders,
+    ConfirmDelete,
+    CancelDelete,
+    DryRunToggled(bool),
+    TrashFolderChanged(String),
+    UndoRemove,
+    DismissUndo,
+    DismissStaleAlert,
+    StaleQueueDaysChanged(String),
+    RemoteOpProgress {
+        done: u64,
+        total: u64,
+    },
+    RemoteOpFinished(Result<String, SftpError>),
+    // Folder sizes
+    CalculateFolderSize(RemoteFile),
+    // Watched folders, by index into config.watched_folders
+    WatchFolder(RemoteFile),
+    UnwatchFolder(usize),
+    WatchPatternsChanged(usize, String),
+    WatchSkipDownloadedToggled(usize, bool),
+    CheckWatchedFolders,
+    WatchedFolderListed(usize, Result<Vec<RemoteFile>, SftpError>),
+    // Jobs, by index into config.jobs; runs go by name, which outlives edits
+    SaveAsJob(RemoteFile),
+    DeleteJob(usize),
+    JobNameChanged(usize, String),
+    JobPatternsChanged(usize, String),
+    JobDestinationChanged(usize, String),
+    PickJobDestination(usize),
+    JobDestinationPicked(usize, Option<std::path::PathBuf>),
+    JobRunAtChanged(usize, String),
+    RunJob(usize),
+    RunJobNamed(String, settings::JobTrigger),
+    JobScanned(
+        String,
+        settings::JobTrigger,
+        Result<Vec<RemoteFile>, SftpError>,
+    ),
+    CloseJobs,
+    ForgetDownloaded(String),
+    IgnoreInputChanged(String),
+    AddIgnored,
+    FolderSizeCalculated(String, Result<(u64, usize), SftpError>),
+    // Remote console
+    ConsoleInputChanged(String),
+    ConsoleHistoryPicked(String),
+    ConsoleSubmit,
+    StopConsoleCommand,
+    ConsoleOutput(Result<(i32, String), SftpError>),
+    ClearConsole,
+    CloseConsole,
+    AllowShellToggled(bool),
+    ExtractOnServer(RemoteFile),
+    ExtractStarted(String, Result<(), SftpError>),
+    PollExtract(String),
+    ExtractPolled(String, Result<Option<(i32, String)>, SftpError>),
+    SpinnerTick,
+    // Batch confirmation
+    ExcludePatternsChanged(String),
+    FilenameReplacementChanged(String),
+    PriorityRulesChanged(String),
+    ConfirmBatch,
+    CancelBatch,
+    ConfirmRequeue,
+    SkipRequeue,
+    // Scan result (auto_start)
+    ScanResult(Result<Vec<RemoteFile>, SftpError>, bool, Option<String>),
+    ScanProgressed(ScanProgress),
+    CancelScan,
+    // Queue Persistence & Resume
+    ResumeQueue,
+    VerifyProgressed(usize, usize),
+    SkipVerification,
+    QueueVerificationResult(Vec<(ItemKey, bool, u64)>),
+    // Remote
+    RefreshRemote,
+    // Queue
+    RefreshQueue,
+    // Pane
+    PaneResized(pane_grid::ResizeEvent),
+    // Remote listing options
+    SortBy(SortColumn),
+    RemoteFilterChanged(String),
+    ShowHiddenToggled(bool),
+    ShowOwnerToggled(bool),
+    OwnerNamesLoaded(OwnerNames),
+    ColorSizesToggled(bool),
+    RelativeTimesToggled(bool),
+    ServerTimesToggled(bool),
+    ServerTimezoneChanged(String),
+    HighContrastToggled(bool),
+    ReduceMotionToggled(bool),
+    RemoteScrolled(scrollable::Viewport),
+    // Folder tree
+    ToggleTree,
+    TreeToggleExpand(String),
+    TreeChildrenLoaded(String, Result<(String, Vec<RemoteFile>), SftpError>),
+    TreeSelect(String),
+    // Grid view
+    ToggleGridView,
+    ThumbnailLoaded(String, Option<Vec<u8>>),
+    // Downloads
+    StartDownloads,
+    PollDownloadEvents,
+    PauseDownload(ItemKey),
+    ResumeDownload(ItemKey),
+    CancelDownload(ItemKey),
+    RemoveQueueItem(ItemKey), // cancel if need be and take off the list, with undo
+    Redownload(ItemKey),      // set the local copy aside and start from zero
+    DownloadProgress {
+        key: ItemKey,
+        bytes_downloaded: u64,
+    },
+    DownloadCompleted(ItemKey),
+    DownloadFailed {
+        key: ItemKey,
+        error: DownloadError,
+    },
+    DownloadStarted(ItemKey),
+    DownloadVerifying(ItemKey),
+    DownloadRetrying {
+        key: ItemKey,
+        attempt: u32,
+    },
+    DownloadStalled {
+        key: ItemKey,
+        attempt: u32,
+    },
+    DownloadPaused {
+        key: ItemKey,
+        resume_check: Option<ResumeCheck>,
+    },
+    PartialFileChanged(ItemKey), // restarted from zero
+    SourceFileChanged(ItemKey),  // restarted from zero
+    DownloadSkipped {
+        key: ItemKey,
+        reason: String,
+    },
+    LowDiskSpace {
+        free: u64,
+        required: u64,
+    },
+    DiskSpaceRecovered,
+    TransferStats(TransferStats),
+    WindowRawId(u64),
+    TransferItemClicked(ItemKey),
+    ItemSpeedLimitChanged(String), // KB/s cap for the selected queue item, blank = none
+    ItemNoteChanged(String),       // for the selected queue item
+    ItemLabelPicked(Option<ItemLabel>),
+    HistorySearchChanged(String),
+    ChangePriority(ItemKey, i16), // step
+    CloseHistory,
+    WriteChecksumsToggled(bool),
+    ManifestWritten(String, Result<std::path::PathBuf, SftpError>), // batch name
+    VerifyManifest(String),
+    ManifestVerified(String, Result<String, SftpError>), // manifest path, summary
+    CloseConnectionInfo,
+    CloseActivityLog,
+    ActivityVerboseToggled(bool),
+    PingConnection,
+    PingResult(Result<std::time::Duration, SftpError>),
+    // Queue groups, by batch id
+    ToggleBatchCollapsed(u64),
+    PauseBatch(u64),
+    ResumeBatch(u64),
+    RemoveBatch(u64),
+    // Tray
+    TrayEvent,
+    HideToTray,
+    ShowWindow,
+    // Schedule
+    // Metered networks
+    PauseOnMeteredToggled(bool),
+    MeteredSsidsChanged(String),
+    CheckNetwork,
+    NetworkChecked(network::NetworkStatus),
+    MeteredOverride(bool),
+    ForceDownloads(bool), // run despite the schedule until the queue drains
+    // Battery
+    BatteryModeChanged(settings::BatteryMode),
+    BatterySpeedChanged(String),
+    CheckPower,
+    PowerChecked(Option<bool>),
+    ScheduleModeChanged(settings::ScheduleMode),
+    ScheduleStartTimeChanged(u8, u8),
+    Tick(()), // Periodic check
+    ScheduleEndTimeChanged(u8, u8),
+    ScheduleTimeTyped(bool, String), // true = start; applied once it reads as a time
+    ScheduleTimeTypingDone,
+    ScheduleDayToggled(u8),            // 0=Mon, 6=Sun
+    ScheduleCellSet(usize, u32, bool), // day (0=Mon), hour, allowed; from the timeline
+    ProfileScheduleToggled(bool),      // this server gets its own schedule
+    SuggestSchedule,                   // from the per-hour speeds in the stats
+    UseScheduleSuggestion,
+    DismissScheduleSuggestion,
+    SaveSchedule,
+    CancelSchedule,
+    // Reports
+    ReportPeriodChanged(settings::ReportPeriod),
+    ReportFolderChanged(String),
+    PickReportFolder,
+    ReportFolderPicked(Option<std::path::PathBuf>),
+    ReportSmtpUrlChanged(String),
+    ReportSmtpUserChanged(String),
+    ReportSmtpPasswordChanged(String),
+    ReportEmailToChanged(String),
+    SendReportNow,
+    ReportDelivered(Result<String, String>), // where it went, or why it didn't
+    // Toolbar
+    NoOp,
+    // Window Events
+    Event(iced::Event),
+    // Speed Limit
+    SpeedLimitChanged(String),
+    ProfileSpeedLimitChanged(String),
+    FsyncIntervalChanged(String),
+    MinFreeSpaceChanged(String),
+    FollowStableChanged(String),
+    FastLaneChanged(String),
+    ToggleFollow(ItemKey),
+    QueueOrderChanged(QueueOrder),
+    FairBatchesToggled(bool),
+    QueueFilterChanged(QueueFilter),
+    RetryFailed(bool), // true = only failures a retry is likely to fix
+    RetryBatchFailed(u64),
+    ShowFailureReport(Batch),
+    CloseFailureReport,
+    ExportFailureReport,
+    FailureReportExported(Result<Option<std::path::PathBuf>, SftpError>),
+    QueueSearchChanged(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConfigOption {
+    Settings,
+    Connect,
+    Schedule,
+    Jobs,
+    History,
+    ConnectionInfo,
+    ActivityLog,
+    Console,
+    Minimize,
+    Disconnect,
+    Exit,
+}
+
+/// One area's share of `update()`: handles its own messages and hands the rest back
+pub type AreaHandler = fn(&mut SftpApp, Message) -> Result<Task<Message>, Message>;
+
+impl Default for SftpApp {
+    fn default() -> Self {
+        let (mut panes, first_pane) = pane_grid::State::new(PaneState::Queue);
+        let (_, pane_split) = panes
+            .split(pane_grid::Axis::Vertical, first_pane, PaneState::Remote)
+            .expect("Split failed");
+
+        panes.resize(pane_split, 0.4); // 40% Queue, 60% Remote
+
+        let config = AppConfig::load();
+        sftp_client::set_protocol_trace(config.protocol_trace);
+        let exclude_input = config.exclude_patterns.join(", ");
+        let rules_input = rules::format_rules(&config.priority_rules);
+        let metered_ssids_input = config.metered_ssids.join(", ");
+        let watch_inputs = watch_inputs(&config);
+        let job_inputs = job_inputs(&config);
+        let port_input = config.sftp_config.port.to_string();
+
+        Self {
+            config,
+            state: AppState::MainView,
+            is_config_menu_open: false,
+            panes,
+            pane_split,
+            session: SessionState::default(),
+            pending_scroll: None,
+            listing_error: None,
+            retry_path: None,
+            tree_children: std::collections::HashMap::new(),
+            tree_expanded: std::collections::HashSet::new(),
+            thumbnails: std::collections::HashMap::new(),
+            is_connected: false,
+            is_checking_connection: false,
+            connect_handle: None,
+            settings_error: None,
+            probe_result: None,
+            is_probing: false,
+            show_advanced: false,
+            port_input,
+            editing_from: None,
+            confirm_discard: false,
+            connect_after_save: true,
+            pending_url: None,
+            url_input: None,
+            app_error: None,
+            sftp_client: None,
+            session_info: None,
+            connected_at: None,
+            server_tabs: Vec::new(),
+            latency: None,
+            selected_file: None,
+            last_click: None,
+            queue_items: Vec::new(), // loaded by new(), which can offer recovery
+            remote_files: Vec::new(),
+            current_remote_path: ".".into(), // Start at home/current directory
+            hovered_file: None,
+            is_scanning_queue: false,
+            scan_progress: None,
+            scan_cancel: None,
+            verify_progress: None,
+            verify_cancel: None,
+            remote_op: None,
+            remote_op_destination: String::new(),
+            relay_server: String::new(),
+            folder_sizes: std::collections::HashMap::new(),
+            new_since: (String::new(), None),
+            manifest_checks: std::collections::HashMap::new(),
+            spinner_frame: 0,
+            pending_batch: None,
+            pending_requeue: Vec::new(),
+            removed: None,
+            pending_delete: None,
+            delete_preview: None,
+            reauth: None,
+            queue_problem: None,
+            owner_names: None,
+            relocate: None,
+            missing_folders: Vec::new(),
+            exclude_input,
+            rules_input,
+            download_tx: None,
+            download_rx: None,
+            manager_profile: String::new(),
+            is_downloading: false,
+            selected_queue_item: None,
+            collapsed_batches: std::collections::HashSet::new(),
+            failure_report: None,
+            queue_filter: QueueFilter::All,
+            queue_search: String::new(),
+            history_search: String::new(),
+            stale_dismissed: 0,
+            disk_warning: None,
+            batch_started: std::collections::HashMap::new(),
+            tray_manager: None,
+            window_hidden: false,
+            last_schedule_allowed: true,
+            schedule_resumes_at: None,
+            transfers_allowed: true,
+            schedule_holds: ScheduleHolds::default(),
+            network: network::NetworkStatus::default(),
+            on_metered: false,
+            metered_override: false,
+            schedule_override: false,
+            metered_ssids_input,
+            watch_inputs,
+            job_inputs,
+            cli_jobs: Vec::new(),
+            ignore_input: String::new(),
+            console_input: String::new(),
+            console_output: String::new(),
+            console_history: Vec::new(),
+            console_running: false,
+            console_cancel: None,
+            console_session: Arc::new(Mutex::new(None)),
+            extracting: Vec::new(),
+            schedule_time_input: None,
+            schedule_suggestion: None,
+            on_battery: false,
+            status_message: String::new(),
+            session_stats: TransferStats::default(),
+            window_raw_id: None,
+            taskbar_percent: None,
+        }
+    }
+}
+
+impl SftpApp {
+    /// Show Settings or Schedule, remembering the config to compare edits against
+    pub fn open_dialog(&mut self, state: AppState) {
+        if self.editing_from.is_none() {
+            self.editing_from = Some(self.config.clone());
+            self.sync_inputs();
+        }
+        self.confirm_discard = false;
+        self.state = state;
+    }
+
+    pub fn close_dialog(&mut self) {
+        self.editing_from = None;
+        self.confirm_discard = false;
+        self.schedule_suggestion = None;
+        self.state = AppState::MainView;
+    }
+
+    /// Cancel closes the dialog right away unless there are edits to confirm dropping
+    pub fn cancel_dialog(&mut self) {
+        if self.has_unsaved_changes() {
+            self.confirm_discard = true;
+        } else {
+            self.close_dialog();
+        }
+    }
+
+    pub fn has_unsaved_changes(&self) -> bool {
+        self.editing_from
+            .as_ref()
+            .is_some_and(|saved| self.config.edited_since(saved))
+    }
+
+    /// Forget the on-screen server's session and listing
+    pub fn clear_connection(&mut self) {
+        self.is_connected = false;
+        self.sftp_client = None;
+        self.session_info = None;
+        self.owner_names = None;
+        self.connected_at = None;
+        self.latency = None;
+        self.remote_files.clear();
+        self.tree_children.clear();
+        self.tree_expanded.clear();
+        self.thumbnails.clear();
+        // A running command keeps its session until it returns
+        if let Ok(mut session) = self.console_session.try_lock() {
+            *session = None;
+        }
+    }
+
+    /// Refill the free-text fields from the config they edit
+    fn sync_inputs(&mut self) {
+        self.port_input = self.config.sftp_config.port.to_string();
+        self.exclude_input = self.config.exclude_patterns.join(", ");
+        self.rules_input = rules::format_rules(&self.config.priority_rules);
+        self.metered_ssids_input = self.config.metered_ssids.join(", ");
+        self.watch_inputs = watch_inputs(&self.config);
+        self.job_inputs = job_inputs(&self.config);
+    }
+
+    /// Every message enters here: each area module takes its own, and the
+    /// app-wide ones (menu, panes, tray, window events) are handled below
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        let areas: [AreaHandler; 10] = [
+            Self::update_keys,
+            Self::update_connection,
+            Self::update_console,
+            Self::update_browser,
+            Self::update_queue,
+            Self::update_downloads,
+            Self::update_schedule,
+            Self::update_watch,
+            Self::update_jobs,
+            Self::update_report,
+        ];
+        let mut message = message;
+        for handle in areas {
+            match handle(self, message) {
+                Ok(task) => return task,
+                Err(unhandled) => message = unhandled,
+            }
+        }
+        match message {
+            Message::DiscardChanges => {
+                if let Some(saved) = self.editing_from.take() {
+                    self.config = self.config.reverted_to(&saved);
+                    let _ = self.config.save();
+                    self.sync_inputs();
+                    self.push_transfer_settings();
+                    sftp_client::set_protocol_trace(self.config.protocol_trace);
+                }
+                self.close_dialog();
+                // Metered and battery settings may have been reverted too
+                return Task::batch([
+                    Task::done(Message::CheckNetwork),
+                    Task::done(Message::CheckPower),
+                ]);
+            }
+            Message::KeepEditing => self.confirm_discard = false,
+            Message::ToggleConfigMenu => {
+                self.is_config_menu_open = !self.is_config_menu_open;
+            }
+            Message::ConfigOptionSelected(option) => {
+                self.is_config_menu_open = false;
+                match option {
+                    ConfigOption::Settings => {
+                        self.settings_error = None;
+                        self.open_dialog(AppState::SettingsView);
+                    }
+                    ConfigOption::Connect => {
+                        println!("DEBUG: ConfigOption::Connect selected");
+                        if !self.config.sftp_config.host.is_empty() {
+                            self.status_message =
+                                format!("Connecting to {}...", self.config.sftp_config.host);
+                            return self.connect();
+                        }
+                    }
+                    ConfigOption::Schedule => self.open_dialog(AppState::ScheduleView),
+                    ConfigOption::Jobs => {
+                        self.state = AppState::JobsView;
+                    }
+                    ConfigOption::History => {
+                        self.state = AppState::HistoryView;
+                    }
+                    ConfigOption::ConnectionInfo => {
+                        self.state = AppState::ConnectionInfoView;
+                        return self.update(Message::PingConnection);
+                    }
+                    ConfigOption::ActivityLog => {
+                        self.state = AppState::ActivityLogView;
+                    }
+                    ConfigOption::Console => {
+                        self.state = AppState::ConsoleView;
+                        return text_input::focus(console_input_id());
+                    }
+                    ConfigOption::Minimize => {
+                        return self.update(Message::HideToTray);
+                    }
+                    ConfigOption::Disconnect => {
+                        self.remember_session();
+                        self.config.auto_connect = false;
+                        let _ = self.config.save();
+                        self.clear_connection();
+                    }
+                    ConfigOption::Exit => {
+                        self.remember_session();
+                        let _ = self.config.save();
+                        save_queue(&self.queue_items);
+                        return iced::exit();
+                    }
+                }
+            }
+            Message::PaneResized(event) => {
+                self.panes.resize(event.split, event.ratio);
+                if event.split == self.pane_split {
+                    self.session.pane_ratio = event.ratio;
+                }
+            }
+            Message::SpinnerTick => {
+                self.spinner_frame = (self.spinner_frame + 1) % SPINNER.len();
+            }
+            // Tray Icon Events
+            Message::TrayEvent => {
+                if let Some(tray) = &self.tray_manager {
+                    tray.update(); // Pump GTK events
+                    if let Some(action) = tray.poll_events() {
+                        match action {
+                            TrayAction::Show => {
+                                return self.update(Message::ShowWindow);
+                            }
+                            TrayAction::Toggle if self.window_hidden => {
+                                return self.update(Message::ShowWindow);
+                            }
+                            TrayAction::Toggle => {
+                                return self.update(Message::HideToTray);
+                            }
+                            TrayAction::Exit => {
+                                self.remember_session();
+                                let _ = self.config.save();
+                                save_queue(&self.queue_items);
+                                return iced::exit();
+                            }
+                        }
+                    }
+                }
+            }
+            Message::HideToTray => {
+                // Create tray icon if it doesn't exist
+                if self.tray_manager.is_none() {
+                    match TrayManager::new() {
+                        Ok(tray) => {
+                            tray.update(); // Initial pump
+                            self.tray_manager = Some(tray);
+                        }
+                        Err(e) => {
+                            self.app_error = Some(format!("Failed to create tray icon: {}", e));
+                            return Task::none();
+                        }
+                    }
+                }
+                // Hidden rather than closed, so the tray icon can bring it back
+                self.window_hidden = true;
+                return iced::window::get_latest()
+                    .and_then(|id| iced::window::change_mode(id, iced::window::Mode::Hidden));
+            }
+            Message::ShowWindow => {
+                // The tray icon stays, for hiding the window again with a click
+                self.window_hidden = false;
+                return iced::window::get_latest().and_then(|id| {
+                    Task::batch([
+                        iced::window::change_mode(id, iced::window::Mode::Windowed),
+                        iced::window::gain_focus(id),
+                    ])
+                });
+            }
+
+            Message::CloseHistory
+            | Message::CloseFailureReport
+            | Message::CloseConnectionInfo
+            | Message::CloseActivityLog
+            | Message::CloseConsole => {
+                self.state = AppState::MainView;
+            }
+            Message::ActivityVerboseToggled(verbose) => {
+                self.config.activity_verbose = verbose;
+                let _ = self.config.save();
+            }
+            Message::Event(event) => {
+                if let iced::Event::Window(iced::window::Event::CloseRequested) = event {
+                    println!("DEBUG: Window Close Requested. Saving config...");
+                    self.remember_session();
+                    match self.config.save() {
+                        Ok(_) => println!(
+                            "DEBUG: Config saved successfully. Path: {}",
+                            self.config.last_remote_path
+                        ),
+                        Err(e) => println!("DEBUG: Failed to save config: {}", e),
+                    }
+                    save_queue(&self.queue_items);
+                    return iced::exit();
+                }
+            }
+            _ => {}
+        }
+        Task::none()
+    }
+
+    pub fn theme(&self) -> iced::Theme {
+        style::theme(self.config.high_contrast)
+    }
+
+    pub fn format_bytes(&self, size_str: &str) -> String {
+        let size = size_str
+            .trim()
+            .replace(" B", "")
+            .parse::<u64>()
+            .unwrap_or(0);
+        sftp_client::format_size(size)
+    }
+
+    pub fn subscription(&self) -> iced::Subscription<Message> {
+        let tray_sub = if self.tray_manager.is_some() {
+            iced::time::every(std::time::Duration::from_millis(50)).map(|_| {
+                // Pump GTK events to keep tray icon alive
+                Message::TrayEvent
+            })
+        } else {
+            iced::Subscription::none()
+        };
+
+        // Keep the latency figure fresh while the info dialog is up
+        let ping_sub = if self.state == AppState::ConnectionInfoView && self.is_connected {
+            iced::time::every(std::time::Duration::from_secs(PING_INTERVAL_SECS))
+                .map(|_| Message::PingConnection)
+        } else {
+            iced::Subscription::none()
+        };
+
+        // Look for new files in this server's watched folders
+        let profile = self.config.profile_key();
+        let watch_sub = if self.is_connected
+            && self
+                .config
+                .watched_folders
+                .iter()
+                .any(|w| w.profile == profile)
+        {
+            iced::time::every(std::time::Duration::from_secs(WATCH_INTERVAL_SECS))
+                .map(|_| Message::CheckWatchedFolders)
+        } else {
+            iced::Subscription::none()
+        };
+
+        // Tick every 1 second for scheduler and stats
+        let tick_sub =
+            iced::time::every(std::time::Duration::from_secs(1)).map(|_| Message::Tick(()));
+
+        // Listen for window events (CloseRequested)
+        let event_sub = iced::event::listen().map(Message::Event);
+
+        let key_sub = iced::keyboard::on_key_press(|key, modifiers| {
+            keys::key_action(key, modifiers).map(Message::KeyPressed)
+        });
+
+        // Animate the size spinner only while a folder is being scanned
+        let spinner_sub = if !self.config.reduce_motion
+            && self
+                .folder_sizes
+                .values()
+                .any(|s| *s == FolderSize::Scanning)
+        {
+            iced::time::every(std::time::Duration::from_millis(150)).map(|_| Message::SpinnerTick)
+        } else {
+            iced::Subscription::none()
+        };
+
+        // Check the power source only while battery mode does something with it
+        let power_sub = if self.config.battery_mode != settings::BatteryMode::Ignore {
+            iced::time::every(std::time::Duration::from_secs(POWER_CHECK_SECS))
+                .map(|_| Message::CheckPower)
+        } else {
+            iced::Subscription::none()
+        };
+
+        // Watch for metered networks only when the user asked for it
+        let network_sub = if self.config.pause_on_metered {
+            iced::time::every(std::time::Duration::from_secs(NETWORK_CHECK_SECS))
+                .map(|_| Message::CheckNetwork)
+        } else {
+            iced::Subscription::none()
+        };
+
+        iced::Subscription::batch(vec![
+            tray_sub,
+            tick_sub,
+            event_sub,
+            key_sub,
+            spinner_sub,
+            network_sub,
+            power_sub,
+            ping_sub,
+            watch_sub,
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{FileType, TransferStatus};
+
+    /// The app with an in-memory config and queue, and a channel standing in
+    /// for the download manager so tests can see what update() sends it
+    fn app_with_manager() -> (SftpApp, mpsc::Receiver<DownloadCommand>) {
+        let mut app = SftpApp::default();
+        app.config.local_download_path = std::env::temp_dir().to_string_lossy().to_string();
+        let (tx, rx) = mpsc::channel(32);
+        app.download_tx = Some(tx);
+        (app, rx)
+    }
+
+    fn remote_file(path: &str, size: u64) -> RemoteFile {
+        RemoteFile {
+            name: path.rsplit('/').next().unwrap().to_string(),
+            path: path.to_string(),
+            size: size.to_string(),
+            size_bytes: size,
+            file_type: FileType::File,
+            modified: None,
+            permissions: None,
+            uid: None,
+            gid: None,
+        }
+    }
+
+    /// The key of the queued item for `path`
+    fn key_of(app: &SftpApp, path: &str) -> ItemKey {
+        app.queue_items
+            .iter()
+            .find(|i| i.remote_file == path)
+            .map(|i| i.key())
+            .expect("item in queue")
+    }
+
+    fn status_of(app: &SftpApp, path: &str) -> TransferStatus {
+        app.queue_items
+            .iter()
+            .find(|i| i.remote_file == path)
+            .map(|i| i.status.clone())
+            .expect("item in queue")
+    }
+
+    #[test]
+    fn test_scan_queues_once_and_prompts_to_requeue_finished() {
+        let (mut app, _rx) = app_with_manager();
+        let scanned = || Message::ScanResult(Ok(vec![remote_file("/srv/a.bin", 10)]), false, None);
+
+        let _ = app.update(scanned());
+        let _ = app.update(scanned());
+        assert_eq!(app.queue_items.len(), 1);
+        assert!(app.pending_requeue.is_empty());
+
+        app.queue_items[0].status = TransferStatus::Completed;
+        let _ = app.update(scanned());
+        assert_eq!(app.pending_requeue.len(), 1);
+        let _ = app.update(Message::ConfirmRequeue);
+        assert!(app.pending_requeue.is_empty());
+        assert_eq!(app.queue_items.len(), 1);
+        assert_eq!(status_of(&app, "/srv/a.bin"), TransferStatus::Queued);
+    }
+
+    #[test]
+    fn test_cancel_then_undo_restores_and_resends() {
+        let (mut app, mut rx) = app_with_manager();
+        let _ = app.update(Message::ScanResult(
+            Ok(vec![remote_file("/srv/a.bin", 10)]),
+            false,
+            None,
+        ));
+
+        let _ = app.update(Message::CancelDownload(key_of(&app, "/srv/a.bin")));
+        assert_eq!(status_of(&app, "/srv/a.bin"), TransferStatus::Cancelled);
+        assert!(
+            matches!(rx.try_recv(), Ok(DownloadCommand::Cancel(p)) if p.remote_file == "/srv/a.bin")
+        );
+        assert!(app.removed.is_some());
+
+        let _ = app.update(Message::UndoRemove);
+        assert_eq!(status_of(&app, "/srv/a.bin"), TransferStatus::Queued);
+        assert!(
+            matches!(rx.try_recv(), Ok(DownloadCommand::AddItem(i)) if i.remote_file == "/srv/a.bin")
+        );
+        assert!(app.removed.is_none());
+
+        // Removing a finished item takes it off the list; undo puts it back
+        app.queue_items[0].status = TransferStatus::Completed;
+        let _ = app.update(Message::CancelDownload(key_of(&app, "/srv/a.bin")));
+        assert!(app.queue_items.is_empty());
+        let _ = app.update(Message::UndoRemove);
+        assert_eq!(status_of(&app, "/srv/a.bin"), TransferStatus::Completed);
+    }
+
+    #[test]
+    fn test_download_events_move_item_through_states() {
+        let (mut app, _rx) = app_with_manager();
+        let _ = app.update(Message::ScanResult(
+            Ok(vec![
+                remote_file("/srv/a.bin", 10),
+                remote_file("/srv/b.bin", 10),
+            ]),
+            false,
+            None,
+        ));
+
+        let _ = app.update(Message::DownloadStarted(key_of(&app, "/srv/a.bin")));
+        let _ = app.update(Message::DownloadProgress {
+            key: key_of(&app, "/srv/a.bin"),
+            bytes_downloaded: 4,
+        });
+        assert_eq!(status_of(&app, "/srv/a.bin"), TransferStatus::Downloading);
+        assert_eq!(app.queue_items[0].bytes_transferred, 4);
+
+        let _ = app.update(Message::DownloadCompleted(key_of(&app, "/srv/a.bin")));
+        assert_eq!(status_of(&app, "/srv/a.bin"), TransferStatus::Completed);
+        assert_eq!(app.queue_items[0].bytes_transferred, 10);
+
+        let _ = app.update(Message::DownloadFailed {
+            key: key_of(&app, "/srv/b.bin"),
+            error: DownloadError::RemoteChanged,
+        });
+        assert!(matches!(
+            status_of(&app, "/srv/b.bin"),
+            TransferStatus::Failed(_)
+        ));
+    }
+
+    #[test]
+    fn test_saved_schedule_applies_at_once_and_edits_wait_for_save() {
+        let (mut app, mut rx) = app_with_manager();
+        app.is_downloading = true;
+        app.open_dialog(AppState::ScheduleView);
+        // Edited to allow nothing at all
+        app.config.schedule.mode = settings::ScheduleMode::Custom;
+        let _ = app.update(Message::Tick(()));
+        assert!(!app.schedule_holds.holds(""));
+
+        let _ = app.update(Message::SaveSchedule);
+        assert!(app.schedule_holds.holds(""));
+        assert!(app.transfers_allowed); // other servers keep to their own schedules
+        assert!(app.schedule_resumes_at.is_none()); // never, until the grid is painted
+        let mut sent = Vec::new();
+        while let Ok(command) = rx.try_recv() {
+            sent.push(command);
+        }
+        assert!(sent
+            .iter()
+            .any(|c| matches!(c, DownloadCommand::SetScheduleHolds(holds) if holds.default)));
+    }
+
+    #[test]
+    fn test_force_downloads_overrides_schedule_until_queue_drains() {
+        let (mut app, mut rx) = app_with_manager();
+        let _ = app.update(Message::ScanResult(
+            Ok(vec![remote_file("/srv/now.bin", 10)]),
+            false,
+            None,
+        ));
+        app.is_downloading = true;
+        app.config.schedule.mode = settings::ScheduleMode::Custom; // nothing allowed
+        let _ = app.update(Message::Tick(()));
+        assert!(app.schedule_holds.holds(""));
+
+        let _ = app.update(Message::ForceDownloads(true));
+        assert_eq!(app.schedule_holds, ScheduleHolds::default());
+        let mut sent = Vec::new();
+        while let Ok(command) = rx.try_recv() {
+            sent.push(command);
+        }
+        assert!(sent
+            .iter()
+            .any(|c| matches!(c, DownloadCommand::SetScheduleHolds(holds) if !holds.holds(""))));
+
+        let _ = app.update(Message::DownloadCompleted(key_of(&app, "/srv/now.bin")));
+        let _ = app.update(Message::Tick(()));
+        assert!(!app.schedule_override);
+        assert!(app.schedule_holds.holds(""));
+    }
+
+    #[test]
+    fn test_battery_hold_pauses_and_resumes_running_manager() {
+        let (mut app, mut rx) = app_with_manager();
+        app.is_downloading = true;
+        app.config.battery_mode = settings::BatteryMode::Pause;
+
+        let _ = app.update(Message::PowerChecked(Some(true)));
+        let _ = app.update(Message::Tick(()));
+        assert!(!app.transfers_allowed);
+        let mut sent = Vec::new();
+        while let Ok(command) = rx.try_recv() {
+            sent.push(command);
+        }
+        assert!(matches!(sent.last(), Some(DownloadCommand::PauseAll)));
+
+        let _ = app.update(Message::PowerChecked(Some(false)));
+        let _ = app.update(Message::Tick(()));
+        assert!(app.transfers_allowed);
+        let mut sent = Vec::new();
+        while let Ok(command) = rx.try_recv() {
+            sent.push(command);
+        }
+        assert!(matches!(sent.last(), Some(DownloadCommand::ResumeAll)));
+    }
+
+    #[test]
+    fn test_cancel_with_edits_asks_before_discarding() {
+        let (mut app, _rx) = app_with_manager();
+        let _ = app.update(Message::ConfigOptionSelected(ConfigOption::Settings));
+        assert!(matches!(app.state, AppState::SettingsView));
+        let _ = app.update(Message::CancelSettings);
+        assert!(matches!(app.state, AppState::MainView));
+
+        let _ = app.update(Message::ConfigOptionSelected(ConfigOption::Settings));
+        let _ = app.update(Message::HostChanged("example.org".into()));
+        let _ = app.update(Message::PortChanged("abc".into()));
+        assert!(app.has_unsaved_changes());
+        let _ = app.update(Message::CancelSettings);
+        assert!(app.confirm_discard);
+        assert!(matches!(app.state, AppState::SettingsView));
+        let _ = app.update(Message::KeepEditing);
+        assert_eq!(app.config.sftp_config.host, "example.org");
+
+        let _ = app.update(Message::CancelSettings);
+        let _ = app.update(Message::DiscardChanges);
+        assert!(matches!(app.state, AppState::MainView));
+        assert_eq!(app.config.sftp_config.host, "localhost");
+        assert_eq!(app.port_input, "22");
+    }
+
+    #[test]
+    fn test_save_persists_without_connecting_and_test_stays_put() {
+        let (mut app, _rx) = app_with_manager();
+        let _ = app.update(Message::ConfigOptionSelected(ConfigOption::Settings));
+        let _ = app.update(Message::TestConnection);
+        assert!(app.is_probing);
+        assert!(matches!(app.state, AppState::SettingsView));
+
+        let _ = app.update(Message::PortChanged("0".into()));
+        let _ = app.update(Message::SaveSettings);
+        assert!(matches!(app.state, AppState::SettingsView)); // invalid port
+
+        let _ = app.update(Message::PortChanged("2222".into()));
+        let _ = app.update(Message::ConnectAfterSaveToggled(false));
+        let _ = app.update(Message::SaveSettings);
+        assert!(matches!(app.state, AppState::MainView));
+        assert!(!app.is_checking_connection);
+        assert_eq!(app.config.sftp_config.port, 2222);
+    }
+
+    #[test]
+    fn test_queue_filter_chips_and_filename_search() {
+        let (mut app, _rx) = app_with_manager();
+        let _ = app.update(Message::ScanResult(
+            Ok(vec![
+                remote_file("/srv/Holiday.mkv", 10),
+                remote_file("/srv/holiday.srt", 10),
+                remote_file("/srv/work.pdf", 10),
+            ]),
+            false,
+            None,
+        ));
+        app.queue_items[1].status = TransferStatus::Failed("gone".into());
+        app.queue_items[2].status = TransferStatus::Failed("gone".into());
+        let shown = |app: &SftpApp| {
+            app.queue_items
+                .iter()
+                .filter(|i| app.queue_shows(i))
+                .map(|i| i.filename.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let _ = app.update(Message::QueueFilterChanged(QueueFilter::Failed));
+        assert_eq!(shown(&app), ["holiday.srt", "work.pdf"]);
+        let _ = app.update(Message::QueueSearchChanged("HOLI".into()));
+        assert_eq!(shown(&app), ["holiday.srt"]);
+        let _ = app.update(Message::QueueFilterChanged(QueueFilter::All));
+        assert_eq!(shown(&app), ["Holiday.mkv", "holiday.srt"]);
+    }
+
+    #[test]
+    fn test_item_notes_and_labels_are_searchable() {
+        let (mut app, _rx) = app_with_manager();
+        let _ = app.update(Message::ScanResult(
+            Ok(vec![
+                remote_file("/srv/a.mkv", 10),
+                remote_file("/srv/b.mkv", 10),
+            ]),
+            false,
+            None,
+        ));
+        let _ = app.update(Message::TransferItemClicked(key_of(&app, "/srv/b.mkv")));
+        let _ = app.update(Message::ItemNoteChanged("for Alice".into()));
+        let _ = app.update(Message::ItemLabelPicked(Some(ItemLabel::Green)));
+        assert_eq!(app.queue_items[1].note, "for Alice");
+        assert_eq!(app.queue_items[1].label, Some(ItemLabel::Green));
+
+        let _ = app.update(Message::QueueSearchChanged("alice".into()));
+        let shown: Vec<_> = app
+            .queue_items
+            .iter()
+            .filter(|i| app.queue_shows(i))
+            .collect();
+        assert_eq!(shown.len(), 1);
+        assert_eq!(shown[0].filename, "b.mkv");
+
+        let season = settings::BatchRecord {
+            finished_at: "2026-10-01 10:00".into(),
+            name: "Season 1".into(),
+            files: 2,
+            bytes: 20,
+            elapsed_secs: 5,
+            failures: 0,
+            manifest: None,
+            notes: vec!["for Alice".into()],
+        };
+        app.config.batch_history = vec![
+            season.clone(),
+            settings::BatchRecord {
+                name: "Season 2".into(),
+                notes: Vec::new(),
+                ..season
+            },
+        ];
+        let _ = app.update(Message::HistorySearchChanged("ALICE".into()));
+        let names: Vec<_> = app.history_shown().iter().map(|r| r.name.clone()).collect();
+        assert_eq!(names, ["Season 1"]);
+    }
+
+    #[test]
+    fn test_keyboard_drives_listing_and_dialogs() {
+        use iced::keyboard::{key::Named, Key, Modifiers};
+        let press = |app: &mut SftpApp, key: Key| {
+            let action = keys::key_action(key, Modifiers::default()).unwrap();
+            let _ = app.update(Message::KeyPressed(action));
+        };
+        let (mut app, _rx) = app_with_manager();
+        app.remote_files = vec![remote_file("/srv/a.mkv", 1), remote_file("/srv/b.mkv", 2)];
+
+        press(&mut app, Key::Named(Named::ArrowDown));
+        press(&mut app, Key::Named(Named::ArrowDown));
+        press(&mut app, Key::Named(Named::ArrowDown));
+        assert_eq!(app.selected_file.as_deref(), Some("b.mkv"));
+        press(&mut app, Key::Named(Named::Home));
+        assert_eq!(app.selected_file.as_deref(), Some("a.mkv"));
+
+        press(&mut app, Key::Named(Named::Delete));
+        assert_eq!(app.pending_delete.as_ref().unwrap().name, "a.mkv");
+        press(&mut app, Key::Named(Named::Escape));
+        assert!(app.pending_delete.is_none());
+
+        app.config.sftp_config.read_only = true;
+        press(&mut app, Key::Named(Named::Delete));
+        assert!(app.pending_delete.is_none());
+
+        let _ = app.update(Message::ConfigOptionSelected(ConfigOption::ActivityLog));
+        press(&mut app, Key::Named(Named::Escape));
+        assert_eq!(app.state, AppState::MainView);
+    }
+
+    #[test]
+    fn test_reduced_motion_keeps_undo_bar_and_high_contrast_swaps_theme() {
+        let (mut app, _rx) = app_with_manager();
+        assert_eq!(app.theme(), iced::Theme::Dark);
+        let _ = app.update(Message::HighContrastToggled(true));
+        assert_eq!(app.theme().palette().background, iced::Color::BLACK);
+
+        let _ = app.update(Message::ReduceMotionToggled(true));
+        app.removed = Some(Removed {
+            label: "Removed a.mkv".into(),
+            items: Vec::new(),
+            at: Instant::now() - std::time::Duration::from_secs(UNDO_SECS + 1),
+        });
+        let _ = app.update(Message::Tick(()));
+        assert!(app.removed.is_some());
+        let _ = app.update(Message::ReduceMotionToggled(false));
+        let _ = app.update(Message::Tick(()));
+        assert!(app.removed.is_none());
+    }
+
+    #[test]
+    fn test_refused_login_pauses_queue_and_asks_to_sign_in() {
+        let (mut app, mut rx) = app_with_manager();
+        app.config.sftp_config.host = "nas".into();
+        app.config.sftp_config.username = "me".into();
+        let _ = app.update(Message::ScanResult(
+            Ok(vec![
+                remote_file("/srv/a.bin", 10),
+                remote_file("/srv/b.bin", 10),
+            ]),
+            false,
+            None,
+        ));
+        while rx.try_recv().is_ok() {}
+        for path in ["/srv/a.bin", "/srv/b.bin"] {
+            let _ = app.update(Message::DownloadFailed {
+                key: key_of(&app, path),
+                error: SftpError::Auth("password expired".into()).into(),
+            });
+        }
+        assert_eq!(app.state, AppState::ReauthView);
+        assert!(matches!(rx.try_recv(), Ok(DownloadCommand::PauseAll)));
+        assert!(rx.try_recv().is_err(), "asked once, not per item");
+
+        let _ = app.update(Message::ReauthPasswordChanged("new secret".into()));
+        let _ = app.update(Message::ReauthChecked(Ok(())));
+        assert_eq!(app.state, AppState::MainView);
+        assert_eq!(
+            app.config.sftp_config.password.as_deref(),
+            Some("new secret")
+        );
+        assert!(app
+            .queue_items
+            .iter()
+            .all(|i| i.status == TransferStatus::Queued));
+    }
+
+    #[test]
+    fn test_retry_failed_can_leave_out_permanent_errors() {
+        let (mut app, mut rx) = app_with_manager();
+        let _ = app.update(Message::ScanResult(
+            Ok(vec![
+                remote_file("/srv/a.bin", 10),
+                remote_file("/srv/b.bin", 10),
+            ]),
+            false,
+            None,
+        ));
+        while rx.try_recv().is_ok() {}
+        let _ = app.update(Message::DownloadFailed {
+            key: key_of(&app, "/srv/a.bin"),
+            error: SftpError::ConnectionLost("reset".into()).into(),
+        });
+        let _ = app.update(Message::DownloadFailed {
+            key: key_of(&app, "/srv/b.bin"),
+            error: SftpError::NotFound("/srv/b.bin".into()).into(),
+        });
+
+        let _ = app.update(Message::RetryFailed(true));
+        assert_eq!(status_of(&app, "/srv/a.bin"), TransferStatus::Queued);
+        assert!(matches!(
+            status_of(&app, "/srv/b.bin"),
+            TransferStatus::Failed(_)
+        ));
+        assert!(
+            matches!(rx.try_recv(), Ok(DownloadCommand::AddItem(i)) if i.remote_file == "/srv/a.bin")
+        );
+
+        let _ = app.update(Message::RetryFailed(false));
+        assert_eq!(status_of(&app, "/srv/b.bin"), TransferStatus::Queued);
+    }
+
+    #[test]
+    fn test_batch_with_failures_opens_triage_report() {
+        let (mut app, _rx) = app_with_manager();
+        let _ = app.update(Message::ScanResult(
+            Ok(vec![
+                remote_file("/srv/show/e1.mkv", 10),
+                remote_file("/srv/show/e2.mkv", 10),
+            ]),
+            false,
+            Some("/srv/show".into()),
+        ));
+        let _ = app.update(Message::DownloadCompleted(key_of(&app, "/srv/show/e1.mkv")));
+        assert_eq!(app.state, AppState::MainView);
+        let _ = app.update(Message::DownloadFailed {
+            key: key_of(&app, "/srv/show/e2.mkv"),
+            error: SftpError::PermissionDenied("/srv/show/e2.mkv".into()).into(),
+        });
+
+        assert_eq!(app.state, AppState::FailureReportView);
+        let batch = app.failure_report.clone().expect("report for the batch");
+        let report = app.failure_report_text(batch.id);
+        assert_eq!(report.lines().count(), 1);
+        assert!(report.starts_with("/srv/show/e2.mkv\tPermission\t"));
+        assert!(report.contains("Check the file's permissions"));
+    }
+
+    #[test]
+    fn test_pasted_remote_path_is_cleaned_up() {
+        use super::browser::pasted_remote_path;
+        assert_eq!(
+            pasted_remote_path("  '/srv/media/tv shows/'\n").as_deref(),
+            Some("/srv/media/tv shows")
+        );
+        assert_eq!(pasted_remote_path("/").as_deref(), Some("/"));
+        assert_eq!(pasted_remote_path("media/tv"), None);
+        assert_eq!(pasted_remote_path("/a\n/b"), None);
+    }
+
+    #[test]
+    fn test_link_to_unknown_server_prefills_settings_and_waits() {
+        let mut app = SftpApp::default();
+        let url = SftpUrl::parse("sftp://alice@files.example.org:2200/data/set1").unwrap();
+        let _ = app.update(Message::OpenSftpUrl(url.clone()));
+
+        assert_eq!(app.state, AppState::SettingsView);
+        assert_eq!(app.config.sftp_config.host, "files.example.org");
+        assert_eq!(app.config.sftp_config.username, "alice");
+        assert_eq!(app.port_input, "2200");
+        assert!(app.connect_after_save);
+        assert_eq!(app.pending_url, Some(url));
+    }
+
+    #[test]
+    fn test_entries_newer_than_last_visit_are_highlighted() {
+        let mut app = SftpApp::default();
+        app.session
+            .visits
+            .insert("/srv".into(), "2026-03-01 08:00:00".into());
+        let file = |name: &str, modified: &str| RemoteFile {
+            modified: chrono::NaiveDateTime::parse_from_str(modified, "%Y-%m-%d %H:%M:%S")
+                .ok()
+                .map(|t| t.and_utc()),
+            ..remote_file(&format!("/srv/{}", name), 1)
+        };
+        let listing = vec![
+            file("old.mkv", "2026-02-28 23:00:00"),
+            file("overnight.mkv", "2026-03-02 03:10:00"),
+        ];
+        let _ = app.update(Message::RemoteFilesLoaded(
+            "/srv".into(),
+            Ok(("/srv".into(), listing.clone())),
+        ));
+        assert!(!app.is_new_since_visit(&listing[0]));
+        assert!(app.is_new_since_visit(&listing[1]));
+
+        // Refreshing keeps the baseline, but it has moved on for the next visit
+        let _ = app.update(Message::RemoteFilesLoaded(
+            "/srv".into(),
+            Ok(("/srv".into(), listing.clone())),
+        ));
+        assert_eq!(app.new_since_summary(), "1 new since last visit");
+        assert!(app.session.visits["/srv"].as_str() > "2026-03-02 03:10:00");
+    }
+
+    #[test]
+    fn test_items_waiting_for_days_raise_stale_alert() {
+        let (mut app, _rx) = app_with_manager();
+        let paths = [
+            "/r/stuck.bin",
+            "/r/paused.bin",
+            "/r/done.bin",
+            "/r/fresh.bin",
+        ];
+        let _ = app.update(Message::ScanResult(
+            Ok(paths.iter().map(|p| remote_file(p, 10)).collect()),
+            false,
+            None,
+        ));
+        let now = chrono::Local::now().timestamp();
+        let day = 24 * 60 * 60;
+        for (item, (age, status)) in app.queue_items.iter_mut().zip([
+            (4 * day, TransferStatus::Queued),
+            (5 * day, TransferStatus::Paused),
+            (9 * day, TransferStatus::Completed),
+            (day, TransferStatus::Queued),
+        ]) {
+            item.status = status;
+            item.queued_at = now - age;
+        }
+        assert_eq!(app.stale_items(), 2);
+
+        let _ = app.update(Message::DismissStaleAlert);
+        assert_eq!(app.stale_dismissed, 2);
+        let _ = app.update(Message::StaleQueueDaysChanged("0".into()));
+        assert_eq!(app.stale_items(), 0);
+    }
+
+    #[test]
+    fn test_dry_run_delete_lists_changes_and_touches_nothing() {
+        let (mut app, _rx) = app_with_manager();
+        app.config.dry_run = true;
+        let folder = RemoteFile {
+            file_type: FileType::Folder,
+            ..remote_file("/srv/old", 0)
+        };
+        let _ = app.update(Message::DeleteRemote(folder));
+        let contents = vec![
+            remote_file("/srv/old/a.mkv", 10),
+            RemoteFile {
+                file_type: FileType::Folder,
+                ..remote_file("/srv/old/sub", 0)
+            },
+            remote_file("/srv/old/sub/b.nfo", 1),
+        ];
+        let _ = app.update(Message::DeletePreviewListed(
+            "/srv/old".into(),
+            Ok(contents),
+        ));
+        assert_eq!(
+            app.delete_preview.as_deref().unwrap(),
+            [
+                "Delete /srv/old/a.mkv",
+                "Delete /srv/old/sub/b.nfo",
+                "Delete folder /srv/old"
+            ]
+        );
+
+        let _ = app.update(Message::ConfirmDelete);
+        assert!(app.pending_delete.is_none());
+        assert!(app.status_message.starts_with("Dry run: 3 change(s)"));
+    }
+
+    #[test]
+    fn test_console_runs_only_where_allowed() {
+        let (mut app, _rx) = app_with_manager();
+        let _ = app.update(Message::ConsoleInputChanged("df -h".into()));
+        let _ = app.update(Message::ConsoleSubmit);
+        assert!(!app.console_running);
+        assert!(app.console_output.starts_with("Commands are off"));
+        assert_eq!(app.console_input, "df -h");
+
+        let _ = app.update(Message::AllowShellToggled(true));
+        let _ = app.update(Message::ReadOnlyToggled(true));
+        assert!(app.console_blocked().unwrap().contains("read-only"));
+
+        let _ = app.update(Message::ClearConsole);
+        let _ = app.update(Message::ConsoleOutput(Ok((1, "no such file".into()))));
+        assert_eq!(app.console_output, "no such file\n[exit 1]\n");
+    }
+
+    #[test]
+    fn test_watched_folder_queues_new_matching_files_only() {
+        let (mut app, _rx) = app_with_manager();
+        let _ = app.update(Message::WatchFolder(RemoteFile {
+            file_type: FileType::Folder,
+            ..remote_file("/srv/show", 0)
+        }));
+        let _ = app.update(Message::WatchPatternsChanged(0, "*.mkv, ".into()));
+        assert_eq!(app.config.watched_folders[0].patterns, ["*.mkv"]);
+        // Downloaded and deleted since; the history still knows it
+        let profile = app.config.profile_key();
+        app.config.remember_download(&profile, "e01.mkv");
+
+        let listing = vec![
+            remote_file("/srv/show/e01.mkv", 10),
+            remote_file("/srv/show/e02.mkv", 10),
+            remote_file("/srv/show/e02.nfo", 1),
+        ];
+        let _ = app.update(Message::WatchedFolderListed(0, Ok(listing.clone())));
+        let queued: Vec<&str> = app
+            .queue_items
+            .iter()
+            .map(|i| i.remote_file.as_str())
+            .collect();
+        assert_eq!(queued, ["/srv/show/e02.mkv"]);
+
+        let _ = app.update(Message::DownloadCompleted(key_of(
+            &app,
+            "/srv/show/e02.mkv",
+        )));
+        assert!(app.config.downloaded_names[&profile].contains(&"e02.mkv".to_string()));
+        app.queue_items.clear();
+        let _ = app.update(Message::WatchedFolderListed(0, Ok(listing.clone())));
+        assert!(app.queue_items.is_empty());
+
+        // Forgetting a name lets the next check fetch it again
+        let _ = app.update(Message::ForgetDownloaded("e01.mkv".into()));
+        let _ = app.update(Message::WatchedFolderListed(0, Ok(listing)));
+        assert_eq!(app.queue_items.len(), 1);
+    }
+
+    #[test]
+    fn test_unreadable_folder_keeps_listing_and_offers_recovery() {
+        let mut app = SftpApp::default();
+        let listing = vec![remote_file("/srv/a.bin", 10)];
+        let _ = app.update(Message::RemoteFilesLoaded(
+            "/srv".into(),
+            Ok(("/srv".into(), listing)),
+        ));
+        app.pending_scroll = Some(120.0);
+
+        let denied = SftpError::PermissionDenied("Listing failed: permission denied".into());
+        let _ = app.update(Message::RemoteFilesLoaded(
+            "/srv/private".into(),
+            Err(denied),
+        ));
+        assert_eq!(app.current_remote_path, "/srv");
+        assert_eq!(app.remote_files.len(), 1);
+        assert_eq!(app.pending_scroll, None);
+        let (path, error) = app.listing_error.as_ref().expect("listing error");
+        assert_eq!(path, "/srv/private");
+        assert!(error.folder_message().contains("permission"));
+
+        let _ = app.update(Message::ListingRetryAsUser);
+        assert!(matches!(app.state, AppState::SettingsView));
+        assert_eq!(app.retry_path.as_deref(), Some("/srv/private"));
+        assert!(app.listing_error.is_none());
+    }
+
+    #[test]
+    fn test_skipping_verification_starts_unchecked_items() {
+        let (mut app, _rx) = app_with_manager();
+        let _ = app.update(Message::ScanResult(
+            Ok(vec![
+                remote_file("/srv/a.mkv", 10),
+                remote_file("/srv/b.mkv", 10),
+            ]),
+            false,
+            None,
+        ));
+        for item in &mut app.queue_items {
+            item.status = TransferStatus::Downloading;
+        }
+        app.verify_cancel = Some(Arc::new(AtomicBool::new(false)));
+        let _ = app.update(Message::VerifyProgressed(1, 2));
+        assert_eq!(app.verify_progress, Some((1, 2)));
+
+        // Only the first was checked before the skip
+        let _ = app.update(Message::SkipVerification);
+        let verified = vec![(key_of(&app, "/srv/a.mkv"), true, 10)];
+        let _ = app.update(Message::QueueVerificationResult(verified));
+        assert_eq!(app.verify_progress, None);
+        assert!(app.verify_cancel.is_none());
+        assert_eq!(status_of(&app, "/srv/a.mkv"), TransferStatus::Queued);
+        assert_eq!(status_of(&app, "/srv/b.mkv"), TransferStatus::Queued);
+    }
+
+    #[test]
+    fn test_damaged_queue_is_restored_from_a_backup() {
+        let (mut app, _rx) = app_with_manager();
+        app.config.local_download_path = std::env::temp_dir().to_string_lossy().to_string();
+        let scan = |app: &mut SftpApp, paths: &[&str]| {
+            let files = paths.iter().map(|p| remote_file(p, 10)).collect();
+            let _ = app.update(Message::ScanResult(Ok(files), false, None));
+        };
+        scan(&mut app, &["/srv/a.mkv", "/srv/b.mkv"]);
+        let backup = std::mem::take(&mut app.queue_items);
+        // Queued while the dialog was up
+        scan(&mut app, &["/srv/c.mkv"]);
+        app.queue_problem = Some(QueueProblem {
+            error: "EOF while parsing".into(),
+            salvaged: Vec::new(),
+            backups: vec![persistence::QueueBackup {
+                modified: String::new(),
+                items: backup,
+            }],
+        });
+        app.state = AppState::QueueRecoveryView;
+
+        let _ = app.update(Message::RecoverQueue(QueueRecovery::Backup(0)));
+        let paths: Vec<_> = app
+            .queue_items
+            .iter()
+            .map(|i| i.remote_file.as_str())
+            .collect();
+        assert_eq!(paths, ["/srv/a.mkv", "/srv/b.mkv", "/srv/c.mkv"]);
+        assert_eq!(app.state, AppState::MainView);
+        assert!(app.queue_problem.is_none());
+    }
+
+    #[test]
+    fn test_relocating_pending_downloads_keeps_moved_progress() {
+        let (mut app, mut rx) = app_with_manager();
+        let _ = app.update(Message::ScanResult(
+            Ok(vec![
+                remote_file("/srv/a.mkv", 10),
+                remote_file("/srv/b.mkv", 10),
+                remote_file("/srv/c.mkv", 10),
+            ]),
+            false,
+            None,
+        ));
+        while rx.try_recv().is_ok() {}
+        let dir = std::env::temp_dir().join(format!("simplesftp-relocate-{}", std::process::id()));
+        let new_dir = dir.join("new/Shows");
+        std::fs::create_dir_all(&new_dir).unwrap();
+        // a.mkv's partial file was moved along, b.mkv's wasn't; c.mkv is done
+        std::fs::write(new_dir.join("a.mkv"), [0; 4]).unwrap();
+        for item in &mut app.queue_items {
+            item.local_location = "/mnt/old-nas/Shows".into();
+            item.bytes_transferred = 4;
+        }
+        app.queue_items[2].status = TransferStatus::Completed;
+
+        let _ = app.update(Message::ShowRelocate);
+        let _ = app.update(Message::RelocateFromChanged("/mnt/old-nas".into()));
+        let _ = app.update(Message::RelocateToChanged(
+            dir.join("new").to_string_lossy().to_string(),
+        ));
+        let _ = app.update(Message::ApplyRelocate);
+
+        let new_location = new_dir.to_string_lossy().to_string();
+        let (a, b, c) = (
+            &app.queue_items[0],
+            &app.queue_items[1],
+            &app.queue_items[2],
+        );
+        assert_eq!(
+            (a.local_location.as_str(), a.bytes_transferred),
+            (new_location.as_str(), 4)
+        );
+        assert_eq!(
+            (b.local_location.as_str(), b.bytes_transferred),
+            (new_location.as_str(), 0)
+        );
+        assert_eq!(c.local_location, "/mnt/old-nas/Shows");
+        assert!(app.relocate.is_none());
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(DownloadCommand::SetDestination(key, _, 4)) if key.remote_file == "/srv/a.mkv"
+        ));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_missing_download_folder_is_asked_about_at_startup() {
+        let (mut app, _rx) = app_with_manager();
+        let dir = std::env::temp_dir().join(format!("simplesftp-missing-{}", std::process::id()));
+        let gone = dir.join("nas").to_string_lossy().to_string();
+        app.config.local_download_path = gone.clone();
+        let _ = app.update(Message::ScanResult(
+            Ok(vec![remote_file("/srv/a.mkv", 10)]),
+            false,
+            None,
+        ));
+        assert!(app.queue_items[0].local_location.starts_with(&gone));
+
+        app.check_download_folders();
+        assert_eq!(app.state, AppState::MissingFoldersView);
+        assert_eq!(app.missing_folders, [gone.as_str()]);
+
+        let _ = app.update(Message::PauseMissingFolder(gone.clone()));
+        assert_eq!(app.queue_items[0].status, TransferStatus::Paused);
+        assert_eq!(app.state, AppState::MainView);
+
+        let _ = app.update(Message::CreateMissingFolder(gone.clone()));
+        assert!(std::path::Path::new(&gone).is_dir());
+        assert!(app.missing_download_folders().is_empty());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_modified_times_read_relative_or_local() {
+        let now = DateTime::from_timestamp(1_760_000_000, 0).unwrap();
+        let ago = |secs: i64| Some(now - chrono::Duration::seconds(secs));
+        assert_eq!(format_modified(ago(20), true, None, now), "just now");
+        assert_eq!(format_modified(ago(60), true, None, now), "1 minute ago");
+        assert_eq!(
+            format_modified(ago(2 * 3600 + 5), true, None, now),
+            "2 hours ago"
+        );
+        assert_eq!(
+            format_modified(ago(3 * 86_400), true, None, now),
+            "3 days ago"
+        );
+        assert_eq!(format_modified(None, true, None, now), "");
+
+        let local = now.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S");
+        assert_eq!(
+            format_modified(Some(now), false, None, now),
+            local.to_string()
+        );
+        // 2025-10-09 08:53:20 UTC, as a server at UTC+02:00 has it
+        let zone = settings::parse_utc_offset("UTC+2");
+        assert_eq!(
+            format_modified(Some(now), false, zone, now),
+            "2025-10-09 10:53:20"
+        );
+        assert_eq!(
+            settings::parse_utc_offset("-0530")
+                .unwrap()
+                .local_minus_utc(),
+            -19_800
+        );
+        assert!(settings::parse_utc_offset("Europe/Berlin").is_none());
+        // Newest first sorts on the timestamp itself, not its text
+        assert!(ago(3600) > ago(86_400));
+    }
+
+    #[test]
+    fn test_space_toggles_pause_and_middle_click_removes() {
+        let (mut app, mut rx) = app_with_manager();
+        let _ = app.update(Message::ScanResult(
+            Ok(vec![remote_file("/srv/a.bin", 10)]),
+            false,
+            None,
+        ));
+        while rx.try_recv().is_ok() {}
+        let space = |app: &mut SftpApp| {
+            let _ = app.update(Message::KeyPressed(KeyAction::TogglePause));
+        };
+        space(&mut app); // nothing selected
+        assert_eq!(status_of(&app, "/srv/a.bin"), TransferStatus::Queued);
+
+        let _ = app.update(Message::TransferItemClicked(key_of(&app, "/srv/a.bin")));
+        space(&mut app);
+        assert_eq!(status_of(&app, "/srv/a.bin"), TransferStatus::Paused);
+        space(&mut app);
+        assert_eq!(status_of(&app, "/srv/a.bin"), TransferStatus::Connecting);
+
+        let _ = app.update(Message::RemoveQueueItem(key_of(&app, "/srv/a.bin")));
+        assert!(app.queue_items.is_empty());
+        assert!(app.selected_queue_item.is_none());
+        let _ = app.update(Message::UndoRemove);
+        assert_eq!(status_of(&app, "/srv/a.bin"), TransferStatus::Queued);
+
+        // Removing a folder's last unfinished item still finishes the folder
+        let _ = app.update(Message::ScanResult(
+            Ok(vec![
+                remote_file("/srv/show/e01.mkv", 10),
+                remote_file("/srv/show/e02.mkv", 10),
+            ]),
+            false,
+            Some("/srv/show".into()),
+        ));
+        let done = app.queue_items.len() - 2;
+        app.queue_items[done].status = TransferStatus::Completed;
+        let _ = app.update(Message::RemoveQueueItem(key_of(&app, "/srv/show/e02.mkv")));
+        assert_eq!(app.config.batch_history.len(), 1);
+    }
+
+    #[test]
+    fn test_redownload_sets_the_old_copy_aside() {
+        let (mut app, mut rx) = app_with_manager();
+        let dir =
+            std::env::temp_dir().join(format!("simplesftp-redownload-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        app.config.local_download_path = dir.to_string_lossy().to_string();
+        let _ = app.update(Message::ScanResult(
+            Ok(vec![remote_file("/srv/a.mkv", 10)]),
+            false,
+            None,
+        ));
+        while rx.try_recv().is_ok() {}
+        std::fs::write(dir.join("a.mkv"), [1; 10]).unwrap();
+        app.queue_items[0].status = TransferStatus::Completed;
+        app.queue_items[0].bytes_transferred = 10;
+
+        let _ = app.update(Message::Redownload(key_of(&app, "/srv/a.mkv")));
+        assert_eq!(status_of(&app, "/srv/a.mkv"), TransferStatus::Queued);
+        assert_eq!(app.queue_items[0].bytes_transferred, 0);
+        assert!(!dir.join("a.mkv").exists());
+        assert_eq!(std::fs::read(dir.join("a.old.mkv")).unwrap(), [1; 10]);
+        assert!(matches!(rx.try_recv(), Ok(DownloadCommand::AddItem(_))));
+
+        // Or deleted, when Settings say so
+        std::fs::write(dir.join("a.mkv"), [2; 10]).unwrap();
+        app.queue_items[0].status = TransferStatus::Completed;
+        app.config.existing_copy = settings::ExistingCopy::Delete;
+        let _ = app.update(Message::Redownload(key_of(&app, "/srv/a.mkv")));
+        assert!(!dir.join("a.mkv").exists());
+        assert!(!dir.join("a.old2.mkv").exists());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_queue_progress_counts_this_run_and_whats_left() {
+        let (mut app, _rx) = app_with_manager();
+        let _ = app.update(Message::ScanResult(
+            Ok(vec![
+                remote_file("/srv/a.mkv", 100),
+                remote_file("/srv/b.mkv", 300),
+            ]),
+            false,
+            None,
+        ));
+        assert_eq!(app.queue_progress(), None);
+
+        // A finished, b a quarter in: 175 of 400 moved
+        app.queue_items[0].status = TransferStatus::Completed;
+        app.queue_items[0].bytes_transferred = 100;
+        app.queue_items[1].status = TransferStatus::Downloading;
+        app.queue_items[1].bytes_transferred = 75;
+        app.session_stats.bytes = 175;
+        assert_eq!(app.queue_progress(), Some(175.0 / 400.0));
+
+        // Paused items wait for the user, so they don't hold the bar back
+        app.queue_items[1].status = TransferStatus::Paused;
+        assert_eq!(app.queue_progress(), None);
+    }
+
+    #[test]
+    fn test_job_queues_matching_files_into_its_folder() {
+        let (mut app, _rx) = app_with_manager();
+        let _ = app.update(Message::SaveAsJob(RemoteFile {
+            file_type: FileType::Folder,
+            ..remote_file("/srv/done", 0)
+        }));
+        let _ = app.update(Message::JobPatternsChanged(0, "*.mkv".into()));
+        let _ = app.update(Message::JobDestinationChanged(0, "/media/films".into()));
+        let _ = app.update(Message::JobRunAtChanged(0, "2am".into()));
+        assert_eq!(app.config.jobs[0].name, "done");
+
+        // Not connected: the run is recorded as failed
+        let _ = app.update(Message::RunJob(0));
+        assert!(app.config.jobs[0].history[0].error.is_some());
+
+        let listing = vec![
+            remote_file("/srv/done/a.mkv", 10),
+            remote_file("/srv/done/a.nfo", 1),
+        ];
+        let _ = app.update(Message::JobScanned(
+            "done".into(),
+            settings::JobTrigger::CommandLine,
+            Ok(listing),
+        ));
+        assert_eq!(app.queue_items.len(), 1);
+        assert_eq!(app.queue_items[0].local_location, "/media/films/done");
+        assert_eq!(app.config.jobs[0].history[1].queued, 1);
+
+        // Due once a day from its time on
+        let at = |h| chrono::TimeZone::with_ymd_and_hms(&Local, 2026, 10, 14, h, 0, 0).unwrap();
+        assert!(!app.config.jobs[0].due(at(1)));
+        assert!(app.config.jobs[0].due(at(3)));
+        let _ = app.run_due_jobs(at(3));
+        assert!(!app.config.jobs[0].due(at(4)));
+        assert_eq!(
+            jobs::job_args(["--job".into(), "done".into(), "--job=b".into()]),
+            ["done", "b"]
+        );
+    }
+
+    #[test]
+    fn test_switching_to_an_unseen_profile_starts_from_its_defaults() {
+        let server = crate::test_server::TestServer::start();
+        let client = Arc::new(Mutex::new(SftpClient::connect(&server.config()).unwrap()));
+        let mut app = SftpApp::default();
+        app.config.sftp_config = server.config();
+        app.sftp_client = Some(client.clone());
+        app.is_connected = true;
+        app.current_remote_path = "/srv/shows".into();
+        app.session.filter = "mkv".into();
+        app.selected_file = Some("a.mkv".into());
+        app.server_tabs.push(ServerTab {
+            sftp_config: settings::SftpConfig {
+                username: "other".into(),
+                ..server.config()
+            },
+            client,
+            session_info: None,
+            connected_at: None,
+        });
+
+        let _ = app.update(Message::SwitchServerTab(0));
+        assert_eq!(app.config.sftp_config.username, "other");
+        assert_eq!(app.session, settings::SessionState::default());
+        assert_eq!(app.selected_file, None);
+
+        // Back on the first one, its folder and filter are as they were
+        let _ = app.update(Message::SwitchServerTab(0));
+        assert_eq!(app.session.remote_path, "/srv/shows");
+        assert_eq!(app.session.filter, "mkv");
+    }
+}
@@ -0,0 +1,1342 @@
+use super::{
+    format_count, format_duration, AppState, Message, PendingBatch, QueueRecovery, Removed,
+    SftpApp, CONFIRM_BATCH_FILES,
+};
+use crate::download_manager::DownloadCommand;
+use crate::error::{FailureKind, SftpError};
+use crate::persistence::{load_queue_checked, save_queue};
+use crate::settings::ExistingCopy;
+use crate::types::{
+    Batch, Collision, Direction, FileType, ItemKey, RelayTarget, RemoteFile, ScanProgress,
+    TransferItem, TransferStatus,
+};
+use crate::{activity, checksum, filter, names, notify, rules, settings, types};
+use chrono::Local;
+use iced::futures::SinkExt;
+use iced::Task;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+impl SftpApp {
+    /// Building the queue: scans, uploads, batches, priorities and removal
+    pub fn update_queue(&mut self, message: Message) -> Result<Task<Message>, Message> {
+        match message {
+            Message::ResumeQueue => {
+                if let Some(client) = self.sftp_client.clone() {
+                    // Only this server's items can be checked on its session
+                    let profile = self.config.profile_key();
+                    let items_to_check: Vec<ItemKey> = self
+                        .queue_items
+                        .iter()
+                        .filter(|i| {
+                            // Uploads don't exist remotely until they've run
+                            i.direction == Direction::Download
+                                && (i.profile.is_empty() || i.profile == profile)
+                                && (i.status == TransferStatus::Queued
+                                    || i.status == TransferStatus::Paused
+                                    || i.status.is_active())
+                        })
+                        .map(|i| i.key())
+                        .collect();
+
+                    if items_to_check.is_empty() {
+                        return Ok(Task::none());
+                    }
+
+                    let cancel = Arc::new(AtomicBool::new(false));
+                    self.verify_cancel = Some(cancel.clone());
+                    let total = items_to_check.len();
+                    self.verify_progress = Some((0, total));
+                    return Ok(Task::stream(iced::stream::channel(
+                        100,
+                        move |mut output| async move {
+                            let mut progress_tx = output.clone();
+                            let res = tokio::task::spawn_blocking(move || {
+                                // Checked on a channel of its own so browsing isn't
+                                // held up; falls back to the client's channel
+                                let channel = client.lock().unwrap().background_channel();
+                                let mut results = Vec::new();
+                                for (checked, key) in items_to_check.into_iter().enumerate() {
+                                    if cancel.load(Ordering::Relaxed) {
+                                        break;
+                                    }
+                                    let _ = progress_tx
+                                        .try_send(Message::VerifyProgressed(checked + 1, total));
+                                    // Check if file exists and get size
+                                    let path = &key.remote_file;
+                                    let size = match &channel {
+                                        Ok(channel) => channel.get_file_size(path),
+                                        Err(_) => client.lock().unwrap().get_file_size(path),
+                                    };
+                                    match size {
+                                        Ok(size) => results.push((key, true, size)),
+                                        Err(_) => results.push((key, false, 0)),
+                                    }
+                                }
+                                results
+                            })
+                            .await
+                            .unwrap_or_default();
+
+                            let _ = output.send(Message::QueueVerificationResult(res)).await;
+                        },
+                    )));
+                }
+            }
+            Message::VerifyProgressed(checked, total) if self.verify_cancel.is_some() => {
+                self.verify_progress = Some((checked, total));
+            }
+            Message::SkipVerification => {
+                if let Some(cancel) = &self.verify_cancel {
+                    cancel.store(true, Ordering::Relaxed);
+                }
+            }
+            Message::QueueVerificationResult(results) => {
+                self.verify_progress = None;
+                let skipped = self
+                    .verify_cancel
+                    .take()
+                    .is_some_and(|c| c.load(Ordering::Relaxed));
+                let mut changed = false;
+                for (key, exists, size) in results {
+                    if let Some(item) = self.queue_items.iter_mut().find(|i| i.key() == key) {
+                        if !exists {
+                            item.status = TransferStatus::Failed("Remote file missing".into());
+                            changed = true;
+                        } else {
+                            if item.size_bytes == 0 {
+                                item.size_bytes = size;
+                                changed = true;
+                            }
+                            // Reset in-flight items to 'Queued' so manager picks them up (Auto-Resume)
+                            if item.status.is_active() {
+                                item.status = TransferStatus::Queued;
+                                changed = true;
+                            }
+                        }
+                    }
+                }
+
+                if skipped {
+                    // Unchecked items start as they are; a missing file fails on its own
+                    for item in self.queue_items.iter_mut().filter(|i| i.status.is_active()) {
+                        item.status = TransferStatus::Queued;
+                        changed = true;
+                    }
+                }
+
+                if changed {
+                    save_queue(&self.queue_items);
+                }
+
+                let pending_count = self
+                    .queue_items
+                    .iter()
+                    .filter(|i| i.status == TransferStatus::Queued)
+                    .count();
+                if pending_count > 0 {
+                    self.status_message = format!("Resuming {} downloads...", pending_count);
+                } else {
+                    self.status_message = "Connected.".to_string();
+                }
+
+                // Try to start manager if we have pending items
+                return Ok(self.start_manager());
+            }
+            Message::QueueFile(file) => {
+                // Check if it's a file or folder
+                if file.file_type == FileType::File {
+                    self.is_scanning_queue = true;
+                    let file_clone = file.clone();
+                    return Ok(Task::future(async move {
+                        Message::ScanResult(Ok(vec![file_clone]), false, None)
+                    }));
+                }
+
+                // Queue only (don't auto-start)
+                return Ok(self.scan_folder(file, false));
+            }
+            Message::QueueRemotePath(path) => {
+                let Some(client) = self.sftp_client.clone() else {
+                    return Ok(Task::none());
+                };
+                return Ok(Task::future(async move {
+                    let res = tokio::task::spawn_blocking(move || {
+                        let c = client.lock().unwrap();
+                        let name = std::path::Path::new(&path)
+                            .file_name()
+                            .map(|n| n.to_string_lossy().to_string())
+                            .unwrap_or_else(|| path.clone());
+                        // Only folders list; anything else is queued as a file
+                        let (file_type, resolved, size) =
+                            match c.list_dir(std::path::Path::new(&path)) {
+                                Ok((resolved, _)) => (FileType::Folder, resolved, 0),
+                                Err(_) => (FileType::File, path.clone(), c.get_file_size(&path)?),
+                            };
+                        Ok(RemoteFile {
+                            name,
+                            path: resolved,
+                            size: size.to_string(),
+                            size_bytes: size,
+                            file_type,
+                            modified: None,
+                            permissions: None,
+                            uid: None,
+                            gid: None,
+                        })
+                    })
+                    .await
+                    .unwrap_or_else(|e| Err(e.into()));
+                    Message::RemotePathProbed(res)
+                }));
+            }
+            Message::RemotePathProbed(Ok(file)) => {
+                self.status_message = format!("Queued {}", file.path);
+                return Ok(self.update(Message::QueueFile(file)));
+            }
+            Message::RemotePathProbed(Err(e)) => {
+                self.app_error = Some(format!("Couldn't queue the link: {}", e));
+            }
+            Message::DownloadFile(file) => {
+                // Check if it's a file or folder
+                if file.file_type == FileType::File {
+                    self.is_scanning_queue = true;
+                    let file_clone = file.clone();
+                    return Ok(Task::future(async move {
+                        Message::ScanResult(Ok(vec![file_clone]), true, None)
+                    }));
+                }
+
+                // Recursively scan path
+                return Ok(self.scan_folder(file, true));
+            }
+            Message::ExcludePatternsChanged(val) => {
+                self.config.exclude_patterns = filter::parse_patterns(&val);
+                self.exclude_input = val;
+                let _ = self.config.save();
+            }
+            Message::FilenameReplacementChanged(val) => {
+                self.config.filename_replacement = val;
+            }
+            Message::PriorityRulesChanged(val) => {
+                self.config.priority_rules = rules::parse_rules(&val);
+                self.rules_input = val;
+                let _ = self.config.save();
+            }
+            Message::ConfirmBatch => {
+                if let Some(batch) = self.pending_batch.take() {
+                    return Ok(self.enqueue_scanned(
+                        batch.files,
+                        batch.auto_start,
+                        batch.root_path,
+                    ));
+                }
+            }
+            Message::CancelBatch => self.pending_batch = None,
+            Message::ConfirmRequeue => {
+                let mut busy = 0;
+                for item in std::mem::take(&mut self.pending_requeue) {
+                    if let Some(pos) = self.queue_items.iter().position(|i| i.key() == item.key()) {
+                        if self.queue_items[pos].status.is_active() {
+                            busy += 1;
+                            continue;
+                        }
+                        let old = self.queue_items.remove(pos);
+                        if let Some(tx) = &self.download_tx {
+                            // Drop the old entry from the manager, including any pause on it
+                            let _ = tx.try_send(DownloadCommand::Cancel(old.key()));
+                            let _ = tx.try_send(DownloadCommand::Resume(old.key()));
+                        }
+                    }
+                    let send = self.is_downloading;
+                    self.push_queue_item(item, send);
+                }
+                if busy > 0 {
+                    self.status_message =
+                        format!("{} still transferring, not requeued", format_count(busy));
+                }
+                save_queue(&self.queue_items);
+            }
+            Message::SkipRequeue => self.pending_requeue.clear(),
+            Message::RefreshQueue => match load_queue_checked() {
+                Ok(items) => {
+                    self.queue_items = items;
+                    return Ok(Task::done(Message::ResumeQueue));
+                }
+                Err(problem) => {
+                    self.queue_items.clear();
+                    self.queue_problem = Some(problem);
+                    self.state = AppState::QueueRecoveryView;
+                }
+            },
+            Message::RecoverQueue(choice) => {
+                let Some(problem) = self.queue_problem.take() else {
+                    return Ok(Task::none());
+                };
+                let items = match choice {
+                    QueueRecovery::Salvaged => problem.salvaged,
+                    QueueRecovery::Backup(index) => problem
+                        .backups
+                        .into_iter()
+                        .nth(index)
+                        .map(|b| b.items)
+                        .unwrap_or_default(),
+                    QueueRecovery::Empty => Vec::new(),
+                };
+                // Anything queued since startup stays, after the recovered items
+                let added = std::mem::take(&mut self.queue_items);
+                self.queue_items = items;
+                for item in added {
+                    if !self.queue_items.iter().any(|i| i.key() == item.key()) {
+                        self.queue_items.push(item);
+                    }
+                }
+                save_queue(&self.queue_items);
+                self.close_dialog();
+                self.status_message = format!(
+                    "Queue restored with {} items.",
+                    format_count(self.queue_items.len())
+                );
+                self.check_download_folders();
+                return Ok(Task::done(Message::ResumeQueue));
+            }
+            Message::ScanProgressed(progress) if self.is_scanning_queue => {
+                self.scan_progress = Some(progress);
+            }
+            Message::CancelScan => {
+                if let Some(cancel) = &self.scan_cancel {
+                    cancel.store(true, Ordering::Relaxed);
+                }
+            }
+            Message::ScanResult(result, auto_start, root_path) => {
+                self.is_scanning_queue = false;
+                let special_skipped = self.scan_progress.take().map_or(0, |p| p.special_skipped);
+                self.scan_cancel = None;
+                match result {
+                    Ok(files) => {
+                        // Big folder trees get a summary to confirm (or adjust) first
+                        if root_path.is_some() && files.len() > CONFIRM_BATCH_FILES {
+                            self.pending_batch = Some(PendingBatch {
+                                files,
+                                auto_start,
+                                root_path,
+                            });
+                            return Ok(Task::none());
+                        }
+                        let task = self.enqueue_scanned(files, auto_start, root_path);
+                        if special_skipped > 0 {
+                            self.status_message = format!(
+                                "{} Skipped {} special files (pipes, devices).",
+                                self.status_message, special_skipped
+                            );
+                        }
+                        return Ok(task);
+                    }
+                    Err(SftpError::Cancelled) => {
+                        self.status_message = "Scan cancelled.".into();
+                    }
+                    Err(e) => {
+                        println!("DEBUG: Scan failed: {}", e);
+                        activity::error(format!("Scan failed: {}", e));
+                        self.app_error = Some(format!("Scan failed: {}", e));
+                    }
+                }
+            }
+            Message::SelectDownloadPath => {
+                return Ok(Task::future(async {
+                    let path = tokio::task::spawn_blocking(|| rfd::FileDialog::new().pick_folder())
+                        .await
+                        .unwrap_or(None);
+                    Message::DownloadPathSelected(path)
+                }));
+            }
+            Message::DownloadPathSelected(path) => {
+                if let Some(p) = path {
+                    self.config.local_download_path = p.to_string_lossy().to_string();
+                    let _ = self.config.save();
+                }
+            }
+            Message::SelectProfileDownloadPath => {
+                return Ok(Task::future(async {
+                    let path = tokio::task::spawn_blocking(|| rfd::FileDialog::new().pick_folder())
+                        .await
+                        .unwrap_or(None);
+                    Message::ProfileDownloadPathSelected(path)
+                }));
+            }
+            Message::ProfileDownloadPathSelected(path) => {
+                if let Some(p) = path {
+                    let key = self.config.profile_key();
+                    let path = p.to_string_lossy().to_string();
+                    self.config.profile_download_paths.insert(key, path);
+                    let _ = self.config.save();
+                }
+            }
+            Message::ClearProfileDownloadPath => {
+                let key = self.config.profile_key();
+                self.config.profile_download_paths.remove(&key);
+                let _ = self.config.save();
+            }
+            Message::SelectUploadFiles => {
+                return Ok(Task::future(async {
+                    let paths = tokio::task::spawn_blocking(|| rfd::FileDialog::new().pick_files())
+                        .await
+                        .unwrap_or(None);
+                    Message::UploadFilesSelected(paths)
+                }));
+            }
+            Message::UploadFilesSelected(paths) => {
+                let Some(paths) = paths else {
+                    return Ok(Task::none());
+                };
+                if self.config.sftp_config.read_only {
+                    self.status_message = "This server is read-only; nothing was uploaded.".into();
+                    return Ok(Task::none());
+                }
+                let remote_dir = self.current_remote_path.trim_end_matches('/').to_string();
+                for path in paths {
+                    let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+                        continue;
+                    };
+                    let filename = names::path_to_string(std::path::Path::new(name));
+                    let remote_file = format!("{}/{}", remote_dir, filename);
+                    let size_bytes = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+                    let item = TransferItem {
+                        direction: Direction::Upload,
+                        local_location: names::path_to_string(parent),
+                        priority: rules::priority_for(
+                            &self.config.priority_rules,
+                            &filename,
+                            size_bytes,
+                        ),
+                        filename,
+                        remote_file,
+                        size_bytes,
+                        bytes_transferred: 0,
+                        status: TransferStatus::Queued,
+                        batch: None,
+                        follow: false,
+                        speed_limit: 0,
+                        profile: self.config.profile_key(),
+                        relay_to: None,
+                        resume_check: None,
+                        failure: None,
+                        queued_at: Local::now().timestamp(),
+                        timeline: Vec::new(),
+                        note: String::new(),
+                        label: None,
+                    };
+                    self.push_queue_item(item, true);
+                }
+                save_queue(&self.queue_items);
+                if !self.is_downloading {
+                    return Ok(self.start_manager());
+                }
+            }
+
+            Message::ShowRelocate => {
+                // Start from the selected item's folder, or the first pending one's
+                let pending = |i: &&TransferItem| {
+                    i.direction == Direction::Download && !i.status.is_finished()
+                };
+                let from = self
+                    .selected_queue_item
+                    .as_ref()
+                    .and_then(|key| self.queue_items.iter().find(|i| &i.key() == key))
+                    .filter(pending)
+                    .or_else(|| self.queue_items.iter().find(pending))
+                    .map(|i| i.local_location.clone())
+                    .unwrap_or_default();
+                self.relocate = Some((from, String::new()));
+            }
+            Message::RelocateFromChanged(val) => {
+                if let Some((from, _)) = &mut self.relocate {
+                    *from = val;
+                }
+            }
+            Message::RelocateToChanged(val) => {
+                if let Some((_, to)) = &mut self.relocate {
+                    *to = val;
+                }
+            }
+            Message::PickRelocateTarget => {
+                return Ok(Task::future(async {
+                    let path = tokio::task::spawn_blocking(|| rfd::FileDialog::new().pick_folder())
+                        .await
+                        .unwrap_or(None);
+                    Message::RelocateTargetPicked(path)
+                }));
+            }
+            Message::RelocateTargetPicked(Some(path)) => {
+                if let Some((_, to)) = &mut self.relocate {
+                    *to = path.to_string_lossy().to_string();
+                }
+            }
+            Message::CancelRelocate => self.relocate = None,
+            Message::ApplyRelocate => {
+                let Some((from, to)) = self.relocate.clone() else {
+                    return Ok(Task::none());
+                };
+                if !names::to_path(&to).is_dir() {
+                    self.status_message = format!("{} isn't a folder", names::display(&to));
+                    return Ok(Task::none());
+                }
+                let moved = self.relocate_pending(&from, &to);
+                if moved.is_empty() {
+                    self.status_message =
+                        format!("No pending downloads go to {}", names::display(&from));
+                    return Ok(Task::none());
+                }
+                let restarted = moved.iter().filter(|(_, _, kept)| !kept).count();
+                for (old, new, _) in &moved {
+                    if self.selected_queue_item.as_ref() == Some(old) {
+                        self.selected_queue_item = Some(new.clone());
+                    }
+                    let Some(tx) = &self.download_tx else {
+                        continue;
+                    };
+                    if let Some(item) = self.queue_items.iter().find(|i| &i.key() == new) {
+                        let _ = tx.try_send(DownloadCommand::SetDestination(
+                            old.clone(),
+                            item.local_location.clone(),
+                            item.bytes_transferred,
+                        ));
+                    }
+                }
+                save_queue(&self.queue_items);
+                self.relocate = None;
+                self.status_message = format!(
+                    "{} downloads now go to {}{}",
+                    format_count(moved.len()),
+                    names::display(&to),
+                    if restarted > 0 {
+                        format!(
+                            "; {} start over, their partial files weren't found there",
+                            format_count(restarted)
+                        )
+                    } else {
+                        String::new()
+                    }
+                );
+            }
+            Message::CreateMissingFolder(folder) => {
+                match std::fs::create_dir_all(names::long_path(&names::to_path(&folder))) {
+                    Ok(()) => self.resolve_missing_folder(&folder),
+                    Err(e) => {
+                        self.status_message =
+                            format!("Couldn't create {}: {}", names::display(&folder), e)
+                    }
+                }
+            }
+            Message::PickMissingFolderTarget(folder) => {
+                return Ok(Task::future(async move {
+                    let path = tokio::task::spawn_blocking(|| rfd::FileDialog::new().pick_folder())
+                        .await
+                        .unwrap_or(None);
+                    Message::MissingFolderTargetPicked(folder, path)
+                }));
+            }
+            Message::MissingFolderTargetPicked(folder, Some(path)) => {
+                let to = path.to_string_lossy().to_string();
+                // Download folder settings pointing there follow it too
+                if self.config.local_download_path == folder {
+                    self.config.local_download_path = to.clone();
+                }
+                for path in self.config.profile_download_paths.values_mut() {
+                    if *path == folder {
+                        *path = to.clone();
+                    }
+                }
+                let _ = self.config.save();
+                self.relocate = Some((folder.clone(), to));
+                let task = self.update(Message::ApplyRelocate);
+                self.resolve_missing_folder(&folder);
+                return Ok(task);
+            }
+            Message::PauseMissingFolder(folder) => {
+                let under = names::to_path(&folder);
+                for item in &mut self.queue_items {
+                    if item.status == TransferStatus::Queued
+                        && names::to_path(&item.local_location).starts_with(&under)
+                    {
+                        item.status = TransferStatus::Paused;
+                    }
+                }
+                save_queue(&self.queue_items);
+                self.resolve_missing_folder(&folder);
+            }
+            Message::DismissMissingFolders => {
+                self.missing_folders.clear();
+                self.close_dialog();
+            }
+            Message::TransferItemClicked(key) => {
+                self.selected_queue_item = Some(key);
+            }
+            Message::ChangePriority(key, step) => {
+                if let Some(item) = self.queue_items.iter_mut().find(|i| i.key() == key) {
+                    item.priority = (item.priority as i16 + step).clamp(0, u8::MAX as i16) as u8;
+                    if let Some(tx) = &self.download_tx {
+                        let _ = tx.try_send(DownloadCommand::SetPriority(key, item.priority));
+                    }
+                }
+                types::sort_pending(&mut self.queue_items, self.config.queue_order);
+                save_queue(&self.queue_items);
+            }
+            Message::WriteChecksumsToggled(on) => {
+                self.config.write_checksums = on;
+                let _ = self.config.save();
+            }
+            Message::ManifestWritten(name, Ok(path)) => {
+                activity::info(format!(
+                    "Checksums for {} written to {}",
+                    name,
+                    path.display()
+                ));
+            }
+            Message::ManifestWritten(name, Err(e)) => {
+                activity::error(format!("Checksums for {} not written: {}", name, e));
+                self.status_message = format!("Couldn't write checksums for {}: {}", name, e);
+            }
+            Message::VerifyManifest(path) => {
+                self.manifest_checks
+                    .insert(path.clone(), Ok("Verifying...".to_string()));
+                return Ok(Task::future(async move {
+                    let manifest = path.clone();
+                    let res = tokio::task::spawn_blocking(move || {
+                        checksum::verify_manifest(std::path::Path::new(&manifest))
+                            .map(|v| v.summary())
+                            .map_err(|e| SftpError::local("Failed to read SHA256SUMS", e))
+                    })
+                    .await
+                    .unwrap_or_else(|e| Err(e.into()));
+                    Message::ManifestVerified(path, res)
+                }));
+            }
+            Message::ManifestVerified(path, result) => {
+                self.manifest_checks.insert(path, result);
+            }
+            Message::HistorySearchChanged(search) => self.history_search = search,
+            Message::ToggleBatchCollapsed(id) if self.collapsed_batches.contains(&id) => {
+                self.collapsed_batches.remove(&id);
+            }
+            Message::ToggleBatchCollapsed(id) => {
+                self.collapsed_batches.insert(id);
+            }
+            Message::PauseBatch(id) => {
+                for key in self.batch_keys(id, |s| !s.is_finished() && *s != TransferStatus::Paused)
+                {
+                    let _ = self.update(Message::PauseDownload(key));
+                }
+            }
+            Message::ResumeBatch(id) => {
+                for key in self.batch_keys(id, |s| *s == TransferStatus::Paused) {
+                    let _ = self.update(Message::ResumeDownload(key));
+                }
+            }
+            Message::RemoveBatch(id) => {
+                let items: Vec<TransferItem> = self
+                    .queue_items
+                    .iter()
+                    .filter(|i| i.batch.as_ref().map(|b| b.id) == Some(id))
+                    .cloned()
+                    .collect();
+                if let Some(batch) = items.first().and_then(|i| i.batch.clone()) {
+                    self.removed = Some(Removed {
+                        label: format!(
+                            "Removed {} ({} files)",
+                            batch.name,
+                            format_count(items.len())
+                        ),
+                        items,
+                        at: Instant::now(),
+                    });
+                }
+                if let Some(tx) = &self.download_tx {
+                    for key in self.batch_keys(id, |s| !s.is_finished()) {
+                        let _ = tx.try_send(DownloadCommand::Cancel(key));
+                    }
+                }
+                self.queue_items
+                    .retain(|i| i.batch.as_ref().map(|b| b.id) != Some(id));
+                self.collapsed_batches.remove(&id);
+                save_queue(&self.queue_items);
+            }
+            Message::UndoRemove => {
+                let Some(removed) = self.removed.take() else {
+                    return Ok(Task::none());
+                };
+                for mut item in removed.items {
+                    // A cancelled transfer restarts from what's already on disk
+                    if item.status.is_active() {
+                        item.status = TransferStatus::Queued;
+                    }
+                    let resend = !item.status.is_finished();
+                    match self.queue_items.iter_mut().find(|i| i.key() == item.key()) {
+                        Some(existing) => *existing = item.clone(),
+                        None => self.queue_items.push(item.clone()),
+                    }
+                    if resend {
+                        if let Some(tx) = &self.download_tx {
+                            let _ = tx.try_send(DownloadCommand::AddItem(Box::new(item)));
+                        }
+                    }
+                }
+                save_queue(&self.queue_items);
+            }
+            Message::DismissUndo => self.removed = None,
+
+            Message::QueueFilterChanged(filter) => self.queue_filter = filter,
+            Message::RetryFailed(transient_only) => {
+                return Ok(self.retry_failed(|item| {
+                    !transient_only || item.failure.is_some_and(|k| k.is_transient())
+                }));
+            }
+            Message::RetryBatchFailed(id) => {
+                self.state = AppState::MainView;
+                return Ok(self.retry_failed(|item| item.batch.as_ref().map(|b| b.id) == Some(id)));
+            }
+            Message::ShowFailureReport(batch) => {
+                self.failure_report = Some(batch);
+                self.state = AppState::FailureReportView;
+            }
+            Message::ExportFailureReport => {
+                let Some(batch) = &self.failure_report else {
+                    return Ok(Task::none());
+                };
+                let report = self.failure_report_text(batch.id);
+                let file_name = format!("{} failures.txt", batch.name);
+                return Ok(Task::future(async move {
+                    let res = tokio::task::spawn_blocking(move || {
+                        let Some(path) =
+                            rfd::FileDialog::new().set_file_name(file_name).save_file()
+                        else {
+                            return Ok(None);
+                        };
+                        std::fs::write(&path, report)
+                            .map(|_| Some(path))
+                            .map_err(|e| SftpError::local("Failed to write report", e))
+                    })
+                    .await
+                    .unwrap_or_else(|e| Err(e.into()));
+                    Message::FailureReportExported(res)
+                }));
+            }
+            Message::FailureReportExported(Ok(Some(path))) => {
+                self.status_message = format!("Failure report saved to {}", path.display());
+            }
+            Message::FailureReportExported(Ok(None)) => {}
+            Message::FailureReportExported(Err(e)) => {
+                self.app_error = Some(e.to_string());
+            }
+            Message::QueueSearchChanged(search) => self.queue_search = search,
+            Message::DismissStaleAlert => self.stale_dismissed = self.stale_items(),
+            Message::StaleQueueDaysChanged(val) => {
+                if let Ok(days) = val.parse::<u64>() {
+                    self.config.stale_queue_days = days;
+                    self.stale_dismissed = 0;
+                    let _ = self.config.save();
+                }
+            }
+            Message::QueueOrderChanged(order) => {
+                self.config.queue_order = order;
+                if let Some(tx) = &self.download_tx {
+                    let _ = tx.try_send(DownloadCommand::SetQueueOrder(order));
+                }
+                types::sort_pending(&mut self.queue_items, order);
+                save_queue(&self.queue_items);
+                let _ = self.config.save();
+            }
+            Message::FairBatchesToggled(on) => {
+                self.config.fair_batches = on;
+                if let Some(tx) = &self.download_tx {
+                    let _ = tx.try_send(DownloadCommand::SetFairness(on));
+                }
+                let _ = self.config.save();
+            }
+            other => return Err(other),
+        }
+        Ok(Task::none())
+    }
+
+    /// Ask about download folders that have gone missing (a renamed folder, an
+    /// unmounted drive) before their downloads fail one by one
+    pub fn check_download_folders(&mut self) {
+        self.missing_folders = self.missing_download_folders();
+        if !self.missing_folders.is_empty() {
+            self.state = AppState::MissingFoldersView;
+        }
+    }
+
+    /// The download folders, and other folders pending downloads save to, that
+    /// aren't there. Subfolders are only made when a download runs, so items
+    /// under a download folder are covered by it, and other items only count
+    /// once they've made progress there
+    pub fn missing_download_folders(&self) -> Vec<String> {
+        let mut bases: Vec<&String> = vec![&self.config.local_download_path];
+        bases.extend(self.config.profile_download_paths.values());
+        let mut missing: Vec<String> = Vec::new();
+        let mut note = |folder: &str| {
+            if !names::to_path(folder).is_dir() && !missing.iter().any(|m| m == folder) {
+                missing.push(folder.to_string());
+            }
+        };
+        note(&self.config.local_download_path);
+        for item in &self.queue_items {
+            if item.direction != Direction::Download || item.status.is_finished() {
+                continue;
+            }
+            let location = names::to_path(&item.local_location);
+            match bases
+                .iter()
+                .find(|base| location.starts_with(names::to_path(base)))
+            {
+                Some(base) => note(base),
+                None if item.bytes_transferred > 0 => note(&item.local_location),
+                None => {}
+            }
+        }
+        missing
+    }
+
+    fn resolve_missing_folder(&mut self, folder: &str) {
+        self.missing_folders.retain(|f| f != folder);
+        if self.missing_folders.is_empty() && self.state == AppState::MissingFoldersView {
+            self.close_dialog();
+        }
+    }
+
+    /// Point unfinished downloads saving under `from` at the same place under
+    /// `to`, keeping each one's progress when its partial file is there too.
+    /// Returns the moved items' keys from before and after, and whether their
+    /// progress was kept; running ones are left alone
+    pub fn relocate_pending(&mut self, from: &str, to: &str) -> Vec<(ItemKey, ItemKey, bool)> {
+        let from = names::to_path(from);
+        let to = names::to_path(to);
+        let mut moved = Vec::new();
+        for item in &mut self.queue_items {
+            if item.direction != Direction::Download
+                || item.status.is_finished()
+                || item.status.is_active()
+            {
+                continue;
+            }
+            let Ok(rest) = names::to_path(&item.local_location)
+                .strip_prefix(&from)
+                .map(|rest| rest.to_path_buf())
+            else {
+                continue;
+            };
+            let old = item.key();
+            item.local_location = names::path_to_string(&to.join(rest));
+            let partial = std::fs::metadata(item.local_path()).map_or(0, |m| m.len());
+            let kept = item.bytes_transferred == 0 || partial >= item.bytes_transferred;
+            if !kept {
+                item.bytes_transferred = 0;
+            }
+            moved.push((old, item.key(), kept));
+        }
+        moved
+    }
+
+    /// Recursively scan `folder` in the background, streaming progress to the status bar
+    pub fn scan_folder(&mut self, folder: RemoteFile, auto_start: bool) -> Task<Message> {
+        let Some(client) = self.sftp_client.clone() else {
+            // Without a connection we can't scan, but we can still queue the entry itself
+            let root_path = Some(folder.path.clone());
+            return Task::done(Message::ScanResult(Ok(vec![folder]), auto_start, root_path));
+        };
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.scan_cancel = Some(cancel.clone());
+        self.scan_progress = Some(ScanProgress::default());
+        self.is_scanning_queue = true;
+
+        let root_path = folder.path;
+        Task::stream(iced::stream::channel(100, move |mut output| async move {
+            let mut progress_tx = output.clone();
+            let path = root_path.clone();
+            let res = tokio::task::spawn_blocking(move || {
+                let c = client.lock().unwrap();
+                c.recursive_scan_with(std::path::Path::new(&path), &cancel, &mut |p| {
+                    let _ = progress_tx.try_send(Message::ScanProgressed(p.clone()));
+                })
+            })
+            .await
+            .unwrap_or_else(|e| Err(e.into()));
+            let _ = output
+                .send(Message::ScanResult(res, auto_start, Some(root_path)))
+                .await;
+        }))
+    }
+
+    /// Once the last item of `key`'s batch is done, send one notification
+    /// for the whole folder and add it to the history
+    /// Report a batch once its last item finishes; returns the task writing its
+    /// checksum manifest when that's turned on
+    pub fn check_batch_finished(&mut self, key: &ItemKey) -> Task<Message> {
+        let Some(batch) = self
+            .queue_items
+            .iter()
+            .find(|i| &i.key() == key)
+            .and_then(|i| i.batch.clone())
+        else {
+            return Task::none();
+        };
+        self.check_batch_done(batch)
+    }
+
+    /// check_batch_finished() by the batch itself, for when the item that
+    /// finished it has left the queue
+    pub fn check_batch_done(&mut self, batch: Batch) -> Task<Message> {
+        let Some(outcome) = types::batch_outcome(&self.queue_items, batch.id) else {
+            return Task::none();
+        };
+        // Without a recorded start (everything skipped, or a retry after a
+        // restart) count from when the folder was queued
+        let elapsed_secs = match self.batch_started.remove(&batch.id) {
+            Some(started) => started.elapsed().as_secs(),
+            None => (Local::now().timestamp_millis() as u64).saturating_sub(batch.id) / 1000,
+        };
+        if outcome.files == 0 && outcome.failures == 0 {
+            return Task::none(); // cancelled outright, nothing to report
+        }
+
+        let mut summary = format!(
+            "{} files, {} in {}",
+            format_count(outcome.files),
+            self.format_bytes(&outcome.bytes.to_string()),
+            format_duration(elapsed_secs)
+        );
+        if outcome.failures > 0 {
+            summary.push_str(&format!(", {} failed", outcome.failures));
+            // Lay the failures out instead of leaving them scattered through the queue
+            if self.state == AppState::MainView {
+                self.failure_report = Some(batch.clone());
+                self.state = AppState::FailureReportView;
+            }
+        }
+        notify::desktop(&format!("{} finished", batch.name), &summary);
+        self.status_message = format!("{} finished: {}", batch.name, summary);
+
+        let completed: Vec<std::path::PathBuf> = self
+            .queue_items
+            .iter()
+            .filter(|i| {
+                i.batch.as_ref().map(|b| b.id) == Some(batch.id)
+                    && i.direction == Direction::Download
+                    && i.status == TransferStatus::Completed
+            })
+            .map(|i| i.local_path())
+            .collect();
+        let manifest_dir = if self.config.write_checksums {
+            checksum::common_folder(&completed)
+        } else {
+            None
+        };
+
+        let mut notes: Vec<String> = Vec::new();
+        for item in &self.queue_items {
+            if item.batch.as_ref().map(|b| b.id) == Some(batch.id)
+                && !item.note.is_empty()
+                && !notes.contains(&item.note)
+            {
+                notes.push(item.note.clone());
+            }
+        }
+        self.config.record_batch(settings::BatchRecord {
+            finished_at: Local::now().format("%Y-%m-%d %H:%M").to_string(),
+            name: batch.name.clone(),
+            files: outcome.files,
+            bytes: outcome.bytes,
+            elapsed_secs,
+            failures: outcome.failures,
+            manifest: manifest_dir.as_ref().map(|dir| {
+                dir.join(checksum::MANIFEST_NAME)
+                    .to_string_lossy()
+                    .to_string()
+            }),
+            notes,
+        });
+        let _ = self.config.save();
+
+        let Some(dir) = manifest_dir else {
+            return Task::none();
+        };
+        Task::future(async move {
+            let res = tokio::task::spawn_blocking(move || {
+                checksum::write_manifest(&dir, &completed)
+                    .map_err(|e| SftpError::local("Failed to write SHA256SUMS", e))
+            })
+            .await
+            .unwrap_or_else(|e| Err(e.into()));
+            Message::ManifestWritten(batch.name, res)
+        })
+    }
+
+    /// Put the failed items matching `include` back in the queue
+    pub fn retry_failed(&mut self, include: impl Fn(&TransferItem) -> bool) -> Task<Message> {
+        let mut retried = Vec::new();
+        for item in &mut self.queue_items {
+            if !matches!(item.status, TransferStatus::Failed(_)) || !include(item) {
+                continue;
+            }
+            item.status = TransferStatus::Queued;
+            item.failure = None;
+            retried.push(item.clone());
+        }
+        if retried.is_empty() {
+            return Task::none();
+        }
+        self.app_error = None;
+        self.status_message = format!("Retrying {} failed transfers", format_count(retried.len()));
+        save_queue(&self.queue_items);
+        match &self.download_tx {
+            Some(tx) => {
+                for item in retried {
+                    let _ = tx.try_send(DownloadCommand::AddItem(Box::new(item)));
+                }
+                Task::none()
+            }
+            None => self.start_manager(),
+        }
+    }
+
+    /// Start the download of `path` over from nothing. The copy already on disk
+    /// is renamed aside or deleted, as Settings say; running and paused items
+    /// are left alone
+    pub fn redownload(&mut self, key: &ItemKey) -> Task<Message> {
+        let policy = self.config.existing_copy;
+        let Some(item) = self.queue_items.iter_mut().find(|i| &i.key() == key) else {
+            return Task::none();
+        };
+        if item.direction != Direction::Download
+            || item.status.is_active()
+            || item.status == TransferStatus::Paused
+        {
+            return Task::none();
+        }
+        let local = item.local_path();
+        if self.config.dry_run && local.exists() {
+            let verb = match policy {
+                ExistingCopy::Rename => "Rename aside",
+                ExistingCopy::Delete => "Delete",
+            };
+            let plan = [format!(
+                "{} {} and download it again",
+                verb,
+                local.display()
+            )];
+            let name = item.filename.clone();
+            self.log_dry_run(&plan, &name);
+            return Task::none();
+        }
+        let set_aside = match policy {
+            _ if !local.exists() => Ok(None),
+            ExistingCopy::Rename => {
+                let old = old_copy_path(&local);
+                std::fs::rename(&local, &old).map(|_| Some(old))
+            }
+            ExistingCopy::Delete => std::fs::remove_file(&local).map(|_| None),
+        };
+        let name = names::display(&item.filename).to_string();
+        match set_aside {
+            Ok(old) => {
+                if let Some(old) = old {
+                    activity::info(format!(
+                        "Kept the old copy of {} as {}",
+                        name,
+                        old.display()
+                    ));
+                }
+            }
+            Err(e) => {
+                self.status_message = format!("Couldn't replace {}: {}", name, e);
+                activity::error(self.status_message.clone());
+                return Task::none();
+            }
+        }
+        let was_finished = item.status.is_finished();
+        item.status = TransferStatus::Queued;
+        item.bytes_transferred = 0;
+        item.resume_check = None;
+        item.failure = None;
+        let item = item.clone();
+        self.status_message = format!("Downloading {} again", name);
+        save_queue(&self.queue_items);
+        match &self.download_tx {
+            Some(tx) => {
+                let command = if was_finished {
+                    DownloadCommand::AddItem(Box::new(item))
+                } else {
+                    // Still waiting in the manager, just with the old offset
+                    DownloadCommand::SetDestination(item.key(), item.local_location, 0)
+                };
+                let _ = tx.try_send(command);
+                Task::none()
+            }
+            None if was_finished => self.start_manager(),
+            None => Task::none(),
+        }
+    }
+
+    /// Failed items of batch `id`, one line each: file, category, error and what to try
+    pub fn failure_report_text(&self, id: u64) -> String {
+        self.queue_items
+            .iter()
+            .filter(|i| i.batch.as_ref().map(|b| b.id) == Some(id))
+            .filter_map(|i| match &i.status {
+                TransferStatus::Failed(error) => {
+                    let kind = i.failure.unwrap_or(FailureKind::Other);
+                    Some(format!(
+                        "{}\t{}\t{}\t{}\n",
+                        i.remote_file,
+                        kind.label(),
+                        error,
+                        kind.suggestion()
+                    ))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Whether the queue list shows `item` under the current status chip and the
+    /// filter, which matches file names and notes
+    pub fn queue_shows(&self, item: &TransferItem) -> bool {
+        let search = self.queue_search.trim().to_lowercase();
+        self.queue_filter.matches(&item.status)
+            && (search.is_empty()
+                || item.filename.to_lowercase().contains(&search)
+                || item.note.to_lowercase().contains(&search))
+    }
+
+    /// Finished folders for the history view, newest first, narrowed by its search
+    /// over folder names and notes
+    pub fn history_shown(&self) -> Vec<&settings::BatchRecord> {
+        let search = self.history_search.trim().to_lowercase();
+        self.config
+            .batch_history
+            .iter()
+            .rev()
+            .filter(|r| {
+                search.is_empty()
+                    || r.name.to_lowercase().contains(&search)
+                    || r.notes.iter().any(|n| n.to_lowercase().contains(&search))
+            })
+            .collect()
+    }
+
+    /// Keys of the queue items in batch `id` whose status matches
+    pub fn batch_keys(&self, id: u64, matches: impl Fn(&TransferStatus) -> bool) -> Vec<ItemKey> {
+        self.queue_items
+            .iter()
+            .filter(|i| i.batch.as_ref().map(|b| b.id) == Some(id) && matches(&i.status))
+            .map(|i| i.key())
+            .collect()
+    }
+
+    /// Add scanned remote files to the queue, skipping excluded names and duplicates
+    pub fn enqueue_scanned(
+        &mut self,
+        files: Vec<RemoteFile>,
+        auto_start: bool,
+        root_path: Option<String>,
+    ) -> Task<Message> {
+        let base_local_path = self.config.download_path().to_string();
+        let profile = self.config.profile_key();
+        self.enqueue_scanned_to(files, auto_start, root_path, base_local_path, profile)
+    }
+
+    /// enqueue_scanned() into `base_local_path`, from the server `profile`
+    /// rather than the one on screen
+    pub fn enqueue_scanned_to(
+        &mut self,
+        files: Vec<RemoteFile>,
+        auto_start: bool,
+        root_path: Option<String>,
+        base_local_path: String,
+        profile: String,
+    ) -> Task<Message> {
+        // Everything from one folder scan shares a batch so the queue can group it
+        let batch = root_path.as_ref().map(|root| Batch {
+            id: Local::now().timestamp_millis() as u64,
+            name: std::path::Path::new(root)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| root.clone()),
+        });
+
+        for file in files {
+            if root_path.is_some() && filter::is_excluded(&self.config.exclude_patterns, &file.name)
+            {
+                continue;
+            }
+            let mut local_location = base_local_path.clone();
+
+            // If we have a root_path, we need to calculate the relative path
+            if let Some(root) = &root_path {
+                // root is e.g. /mnt/remote/Movies
+                // file.path is /mnt/remote/Movies/Action/DieHard.mkv
+                // we want local_location to be .../Downloads/Movies/Action/
+                // filename is DieHard.mkv
+
+                let root_path_obj = std::path::Path::new(root);
+                let file_path_obj = std::path::Path::new(&file.path);
+
+                // Get the parent of the root (so we include the root directory itself in the download)
+                // e.g. /mnt/remote/Movies -> parent is /mnt/remote.
+                // relative path of file to /mnt/remote is Movies/Action/DieHard.mkv
+                if let Some(parent) = root_path_obj.parent() {
+                    if let Ok(relative) = file_path_obj.strip_prefix(parent) {
+                        if let Some(parent_dir) = relative.parent() {
+                            // relative is Movies/Action/DieHard.mkv
+                            // parent_dir is Movies/Action
+                            // We append this to the user's local path
+                            let relative_str = parent_dir.to_string_lossy();
+                            if !relative_str.is_empty() {
+                                let new_base = std::path::Path::new(&base_local_path).join(
+                                    names::local_relative(
+                                        parent_dir,
+                                        &self.config.filename_replacement,
+                                    ),
+                                );
+                                local_location = new_base.to_string_lossy().to_string();
+                            }
+                        }
+                    }
+                }
+            }
+
+            let item = TransferItem {
+                direction: Direction::Download,
+                local_location,
+                priority: rules::priority_for(
+                    &self.config.priority_rules,
+                    &file.name,
+                    file.size_bytes,
+                ),
+                filename: names::local_name(&file.name, &self.config.filename_replacement),
+                remote_file: file.path,
+                size_bytes: file.size_bytes,
+                bytes_transferred: 0,
+                status: TransferStatus::Queued,
+                batch: batch.clone(),
+                follow: false,
+                speed_limit: 0,
+                profile: profile.clone(),
+                relay_to: None,
+                resume_check: None,
+                failure: None,
+                queued_at: Local::now().timestamp(),
+                timeline: Vec::new(),
+                note: String::new(),
+                label: None,
+            };
+            // If downloading is active, send the item to the manager immediately
+            let send = self.is_downloading;
+            self.push_queue_item(item, send);
+        }
+
+        // auto-start logic
+        if auto_start
+            && !self.is_downloading
+            && self
+                .queue_items
+                .iter()
+                .any(|i| i.status == TransferStatus::Queued)
+        {
+            println!("DEBUG: Auto-starting manager...");
+            return self.start_manager();
+        }
+        Task::none()
+    }
+
+    /// Queue `item`, or hold it for the "Requeue anyway?" prompt if its remote
+    /// file is already in the queue. `send` also hands it to a running manager.
+    /// Unfinished items that have waited longer than the configured threshold
+    pub fn stale_items(&self) -> usize {
+        if self.config.stale_queue_days == 0 {
+            return 0;
+        }
+        let now = Local::now().timestamp();
+        let limit = self.config.stale_queue_days * 24 * 60 * 60;
+        self.queue_items
+            .iter()
+            .filter(|i| i.waiting_secs(now).is_some_and(|secs| secs > limit))
+            .count()
+    }
+
+    pub fn push_queue_item(&mut self, item: TransferItem, send: bool) {
+        match types::collision(&self.queue_items, &item) {
+            Collision::Duplicate => {
+                println!("DEBUG: Item already in queue: {}", item.filename);
+                return;
+            }
+            Collision::Conflict => {
+                self.pending_requeue.push(item);
+                return;
+            }
+            Collision::None => {}
+        }
+        self.queue_items.push(item.clone());
+        if send {
+            if let Some(tx) = &self.download_tx {
+                // Always add to manager if it's active. It will handle queueing/starting.
+                let _ = tx.try_send(DownloadCommand::AddItem(Box::new(item)));
+            }
+        }
+    }
+
+    /// Queue a copy of `file` to `target` on the server picked for the relay. It
+    /// streams through the manager like any transfer, never touching the disk.
+    pub fn queue_relay(&mut self, file: RemoteFile, target: String) -> Task<Message> {
+        let item = TransferItem {
+            direction: Direction::Upload,
+            local_location: String::new(),
+            priority: rules::priority_for(&self.config.priority_rules, &file.name, file.size_bytes),
+            filename: file.name,
+            remote_file: file.path,
+            size_bytes: file.size_bytes,
+            bytes_transferred: 0,
+            status: TransferStatus::Queued,
+            batch: None,
+            follow: false,
+            speed_limit: 0,
+            profile: self.config.profile_key(),
+            relay_to: Some(RelayTarget {
+                profile: self.relay_server.clone(),
+                remote_file: target,
+            }),
+            resume_check: None,
+            failure: None,
+            queued_at: Local::now().timestamp(),
+            timeline: Vec::new(),
+            note: String::new(),
+            label: None,
+        };
+        self.push_queue_item(item, true);
+        save_queue(&self.queue_items);
+        if !self.is_downloading {
+            return self.start_manager();
+        }
+        Task::none()
+    }
+}
+
+/// Where a replaced download is kept: movie.mkv becomes movie.old.mkv, or
+/// movie.old2.mkv and so on when that's taken
+fn old_copy_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    (1..)
+        .map(|n| match n {
+            1 => path.with_file_name(format!("{}.old{}", stem, ext)),
+            n => path.with_file_name(format!("{}.old{}{}", stem, n, ext)),
+        })
+        .find(|p| !p.exists())
+        .unwrap()
+}
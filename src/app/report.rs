@@ -0,0 +1,108 @@
+use super::{Message, SftpApp};
+use crate::report::Report;
+use crate::settings::TransferRecord;
+use crate::types::{Direction, ItemKey, TimelineSpan, TransferStatus};
+use crate::{activity, names};
+use chrono::Local;
+use iced::Task;
+
+impl SftpApp {
+    /// Daily or weekly transfer reports, saved to a folder or emailed
+    pub fn update_report(&mut self, message: Message) -> Result<Task<Message>, Message> {
+        match message {
+            Message::ReportPeriodChanged(period) => self.config.report.period = period,
+            Message::ReportFolderChanged(val) => self.config.report.folder = val,
+            Message::PickReportFolder => {
+                return Ok(Task::future(async {
+                    let path = tokio::task::spawn_blocking(|| rfd::FileDialog::new().pick_folder())
+                        .await
+                        .unwrap_or(None);
+                    Message::ReportFolderPicked(path)
+                }));
+            }
+            Message::ReportFolderPicked(Some(path)) => {
+                self.config.report.folder = names::path_to_string(&path);
+            }
+            Message::ReportSmtpUrlChanged(val) => self.config.report.smtp_url = val,
+            Message::ReportSmtpUserChanged(val) => self.config.report.smtp_user = val,
+            Message::ReportSmtpPasswordChanged(val) => {
+                self.config.report.smtp_password = (!val.is_empty()).then_some(val)
+            }
+            Message::ReportEmailToChanged(val) => self.config.report.email_to = val,
+            Message::SendReportNow => {
+                // The period so far; Off still gives today's
+                let report = Report::for_day(
+                    self.config.report.period,
+                    Local::now().date_naive(),
+                    &self.config.transfer_history,
+                );
+                return Ok(self.deliver_report(report));
+            }
+            Message::ReportDelivered(Ok(done)) => {
+                activity::info(done.clone());
+                self.status_message = done;
+            }
+            Message::ReportDelivered(Err(e)) => {
+                activity::error(e.clone());
+                self.status_message = e;
+            }
+            other => return Err(other),
+        }
+        Ok(Task::none())
+    }
+
+    /// Send the report for the period just ended, once. Marked sent before it
+    /// goes so a failing mail server isn't retried every tick; the error is
+    /// in the Activity Log
+    pub fn report_due(&mut self, now: chrono::DateTime<Local>) -> Task<Message> {
+        let Some(report) = Report::due(&self.config.report, &self.config.transfer_history, now)
+        else {
+            return Task::none();
+        };
+        self.config.report.last_report = report.key.clone();
+        let _ = self.config.save();
+        self.deliver_report(report)
+    }
+
+    fn deliver_report(&self, report: Report) -> Task<Message> {
+        let config = self.config.report.clone();
+        Task::future(async move {
+            let res = tokio::task::spawn_blocking(move || report.deliver(&config))
+                .await
+                .unwrap_or_else(|e| Err(e.to_string()));
+            Message::ReportDelivered(res)
+        })
+    }
+
+    /// Add the finished item `key` to the history the reports are made from
+    pub fn record_transfer(&mut self, key: &ItemKey) {
+        let Some(item) = self.queue_items.iter().find(|i| &i.key() == key) else {
+            return;
+        };
+        let now = Local::now().timestamp();
+        let seconds = item
+            .timeline_spans(now)
+            .iter()
+            .map(|span| match span {
+                TimelineSpan::Running(secs) => *secs,
+                TimelineSpan::Paused(..) => 0,
+            })
+            .sum();
+        let record = TransferRecord {
+            finished_at: now,
+            name: item.filename.clone(),
+            profile: match item.profile.as_str() {
+                "" => self.config.profile_key(),
+                profile => profile.to_string(),
+            },
+            upload: item.direction == Direction::Upload,
+            bytes: item.bytes_transferred,
+            seconds,
+            error: match &item.status {
+                TransferStatus::Failed(e) => Some(e.clone()),
+                _ => None,
+            },
+        };
+        self.config.record_transfer(record);
+    }
+}
@@ -0,0 +1,297 @@
+use super::{Message, SftpApp, UNDO_SECS};
+use crate::download_manager::{DownloadCommand, ScheduleHolds};
+use crate::scheduler::Scheduler;
+use crate::types::TransferStatus;
+use crate::{activity, filter, network, power, settings};
+use chrono::Local;
+use iced::Task;
+
+impl SftpApp {
+    /// When transfers may run: the schedule, metered networks and battery
+    pub fn update_schedule(&mut self, message: Message) -> Result<Task<Message>, Message> {
+        match message {
+            Message::ScheduleModeChanged(mode) => {
+                let schedule = self.config.active_schedule_mut();
+                // A first switch to Custom starts from the hours the old mode allowed
+                if mode == settings::ScheduleMode::Custom && schedule.hours == [0; 7] {
+                    schedule.hours = Scheduler::hour_grid(schedule);
+                }
+                schedule.mode = mode;
+            }
+            Message::ScheduleStartTimeChanged(hour, minute) => {
+                self.schedule_time_input = None;
+                let start = &mut self.config.active_schedule_mut().start_time;
+                start.hour = hour;
+                start.minute = minute;
+            }
+            Message::ScheduleEndTimeChanged(hour, minute) => {
+                self.schedule_time_input = None;
+                let end = &mut self.config.active_schedule_mut().end_time;
+                end.hour = hour;
+                end.minute = minute;
+            }
+            Message::ScheduleTimeTyped(start, text) => {
+                if let Some(time) = settings::TimeOfDay::parse(&text) {
+                    let _ = self.update(if start {
+                        Message::ScheduleStartTimeChanged(time.hour, time.minute)
+                    } else {
+                        Message::ScheduleEndTimeChanged(time.hour, time.minute)
+                    });
+                }
+                // Set after the change, which drops any half-typed text
+                self.schedule_time_input = Some((start, text));
+            }
+            Message::ScheduleTimeTypingDone => self.schedule_time_input = None,
+            Message::ScheduleDayToggled(day_idx) => {
+                let days = &mut self.config.active_schedule_mut().days;
+                match day_idx {
+                    0 => days.mon = !days.mon,
+                    1 => days.tue = !days.tue,
+                    2 => days.wed = !days.wed,
+                    3 => days.thu = !days.thu,
+                    4 => days.fri = !days.fri,
+                    5 => days.sat = !days.sat,
+                    6 => days.sun = !days.sun,
+                    _ => {}
+                }
+            }
+            Message::ScheduleCellSet(day, hour, on) => {
+                let schedule = self.config.active_schedule_mut();
+                // Painting starts from whatever the timeline was showing
+                if schedule.mode != settings::ScheduleMode::Custom {
+                    schedule.hours = Scheduler::hour_grid(schedule);
+                    schedule.mode = settings::ScheduleMode::Custom;
+                }
+                if on {
+                    schedule.hours[day] |= 1 << hour;
+                } else {
+                    schedule.hours[day] &= !(1 << hour);
+                }
+            }
+            Message::ProfileScheduleToggled(own) => {
+                // Start the server's own schedule from the default one
+                let key = self.config.profile_key();
+                if own {
+                    let schedule = self.config.schedule.clone();
+                    self.config.profile_schedules.insert(key, schedule);
+                } else {
+                    self.config.profile_schedules.remove(&key);
+                }
+            }
+            Message::Tick(_) => {
+                let now = Local::now();
+                // With reduced motion the undo bar waits to be dismissed
+                if !self.config.reduce_motion
+                    && self
+                        .removed
+                        .as_ref()
+                        .is_some_and(|r| r.at.elapsed().as_secs() >= UNDO_SECS)
+                {
+                    self.removed = None;
+                }
+
+                // Stats: Add 1 second if we are downloading
+                if self.is_downloading
+                    && self
+                        .queue_items
+                        .iter()
+                        .any(|i| i.status == TransferStatus::Downloading)
+                {
+                    self.config.add_daily_stat(0, 1);
+                }
+
+                return Ok(Task::batch([
+                    self.apply_schedule(now),
+                    self.report_due(now),
+                    self.run_due_jobs(now),
+                    self.open_forwarded(),
+                    self.update_taskbar(),
+                ]));
+            }
+            Message::PauseOnMeteredToggled(on) => {
+                self.config.pause_on_metered = on;
+                let _ = self.config.save();
+                if on {
+                    return Ok(self.update(Message::CheckNetwork));
+                }
+            }
+            Message::MeteredSsidsChanged(val) => {
+                self.config.metered_ssids = filter::parse_patterns(&val);
+                self.metered_ssids_input = val;
+                let _ = self.config.save();
+                return Ok(self.update(Message::CheckNetwork));
+            }
+            Message::CheckNetwork => {
+                return Ok(Task::future(async {
+                    let status = tokio::task::spawn_blocking(network::detect)
+                        .await
+                        .unwrap_or_default();
+                    Message::NetworkChecked(status)
+                }));
+            }
+            Message::NetworkChecked(status) => {
+                let metered = status.metered
+                    || status
+                        .ssid
+                        .as_ref()
+                        .is_some_and(|ssid| filter::is_excluded(&self.config.metered_ssids, ssid));
+                // An override only holds for the network it was given on
+                if metered != self.on_metered || status.ssid != self.network.ssid {
+                    self.metered_override = false;
+                }
+                self.on_metered = metered;
+                self.network = status;
+            }
+            Message::MeteredOverride(on) => {
+                self.metered_override = on;
+            }
+            Message::ForceDownloads(on) => {
+                self.schedule_override = on;
+                return Ok(self.apply_schedule(Local::now()));
+            }
+            Message::BatteryModeChanged(mode) => {
+                self.config.battery_mode = mode;
+                self.apply_speed_limit();
+                if mode != settings::BatteryMode::Ignore {
+                    return Ok(self.update(Message::CheckPower));
+                }
+            }
+            Message::BatterySpeedChanged(val) => {
+                if val.is_empty() {
+                    self.config.battery_speed_limit = 0;
+                } else if let Ok(speed) = val.parse::<u64>() {
+                    self.config.battery_speed_limit = speed;
+                }
+                self.apply_speed_limit();
+            }
+            Message::CheckPower => {
+                return Ok(Task::future(async {
+                    let on_battery = tokio::task::spawn_blocking(power::on_battery)
+                        .await
+                        .unwrap_or(None);
+                    Message::PowerChecked(on_battery)
+                }));
+            }
+            Message::PowerChecked(on_battery) => {
+                let on_battery = on_battery.unwrap_or(false);
+                if on_battery != self.on_battery {
+                    self.on_battery = on_battery;
+                    self.apply_speed_limit();
+                }
+            }
+            Message::SuggestSchedule => {
+                self.schedule_suggestion = self.config.suggest_schedule();
+                if self.schedule_suggestion.is_none() {
+                    self.status_message =
+                        "No hours stand out as faster in the last month of transfers yet".into();
+                }
+            }
+            Message::UseScheduleSuggestion => {
+                if let Some(suggestion) = self.schedule_suggestion.take() {
+                    self.config.use_schedule_suggestion(&suggestion);
+                    self.apply_speed_limit();
+                }
+            }
+            Message::DismissScheduleSuggestion => self.schedule_suggestion = None,
+            Message::SaveSchedule => {
+                let _ = self.config.save();
+                self.close_dialog();
+                // Don't leave a new window waiting for the next tick
+                return Ok(self.apply_schedule(Local::now()));
+            }
+            Message::CancelSchedule => self.cancel_dialog(),
+
+            other => return Err(other),
+        }
+        Ok(Task::none())
+    }
+
+    /// Pause or resume transfers for the schedules, metered networks and battery
+    /// as of `now`, starting the queue when it may run. Each server keeps to its
+    /// own schedule; a metered network or the battery holds them all.
+    pub fn apply_schedule(&mut self, now: chrono::DateTime<Local>) -> Task<Message> {
+        let schedule = self
+            .editing_from
+            .as_ref()
+            .unwrap_or(&self.config)
+            .active_schedule();
+        self.last_schedule_allowed = Scheduler::is_allowed(schedule, now);
+        self.schedule_resumes_at = if self.last_schedule_allowed {
+            None
+        } else {
+            Scheduler::next_transition(schedule, now)
+        };
+        if self.schedule_override && self.queue_items.iter().all(|i| i.status.is_finished()) {
+            self.schedule_override = false;
+            activity::info("Queue finished, back to the schedule");
+        }
+        let holds = self.schedule_holds(now);
+        if holds != self.schedule_holds {
+            if let Some(tx) = &self.download_tx {
+                let _ = tx.try_send(DownloadCommand::SetScheduleHolds(holds.clone()));
+            }
+            self.schedule_holds = holds;
+        }
+        let allowed = !self.metered_hold() && !self.battery_hold();
+
+        if allowed != self.transfers_allowed {
+            self.transfers_allowed = allowed;
+            if let Some(tx) = &self.download_tx {
+                if self.is_downloading {
+                    if allowed {
+                        let _ = tx.try_send(DownloadCommand::ResumeAll);
+                    } else {
+                        let _ = tx.try_send(DownloadCommand::PauseAll);
+                    }
+                }
+            }
+        }
+
+        // Auto-start check
+        if allowed
+            && !self.is_downloading
+            && self.queue_items.iter().any(|i| {
+                i.status == TransferStatus::Queued && !self.schedule_holds.holds(&i.profile)
+            })
+        {
+            return self.start_manager();
+        }
+        Task::none()
+    }
+
+    /// Which servers are outside their schedule as of `now`, none while
+    /// downloads are forced. While Settings or Schedule is open the saved
+    /// schedules still rule, not the unsaved edits.
+    pub fn schedule_holds(&self, now: chrono::DateTime<Local>) -> ScheduleHolds {
+        if self.schedule_override {
+            return ScheduleHolds::default();
+        }
+        let config = self.editing_from.as_ref().unwrap_or(&self.config);
+        let mut holds = ScheduleHolds {
+            default: !Scheduler::is_allowed(&config.schedule, now),
+            profiles: config
+                .profile_schedules
+                .iter()
+                .map(|(profile, schedule)| (profile.clone(), !Scheduler::is_allowed(schedule, now)))
+                .collect(),
+        };
+        // Items that don't name their server are from the manager's, or the
+        // current one until the manager starts
+        let own = match self.manager_profile.as_str() {
+            "" => config.profile_key(),
+            profile => profile.to_string(),
+        };
+        holds.profiles.insert(String::new(), holds.holds(&own));
+        holds
+    }
+
+    /// True while transfers should wait because we're on a metered network
+    pub fn metered_hold(&self) -> bool {
+        self.config.pause_on_metered && self.on_metered && !self.metered_override
+    }
+
+    /// True while transfers should wait because we're running on battery
+    pub fn battery_hold(&self) -> bool {
+        self.on_battery && self.config.battery_mode == settings::BatteryMode::Pause
+    }
+}
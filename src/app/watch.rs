@@ -0,0 +1,146 @@
+use super::{Message, SftpApp};
+use crate::settings::WatchedFolder;
+use crate::types::{FileType, RemoteFile};
+use crate::{activity, filter, names};
+use iced::Task;
+use std::path::Path;
+
+impl SftpApp {
+    /// Watched folders: polled for new files, which are queued like any other
+    pub fn update_watch(&mut self, message: Message) -> Result<Task<Message>, Message> {
+        match message {
+            Message::WatchFolder(folder) => {
+                let profile = self.config.profile_key();
+                let known = self
+                    .config
+                    .watched_folders
+                    .iter()
+                    .any(|w| w.profile == profile && w.remote_path == folder.path);
+                if !known {
+                    self.config.watched_folders.push(WatchedFolder {
+                        profile,
+                        remote_path: folder.path.clone(),
+                        patterns: Vec::new(),
+                        skip_downloaded: true,
+                    });
+                    self.watch_inputs.push(String::new());
+                    let _ = self.config.save();
+                }
+                self.status_message = format!(
+                    "Watching {} for new files; patterns are in Settings",
+                    names::display(&folder.name)
+                );
+                return Ok(self.update(Message::CheckWatchedFolders));
+            }
+            Message::UnwatchFolder(index) if index < self.config.watched_folders.len() => {
+                self.config.watched_folders.remove(index);
+                self.watch_inputs.remove(index);
+                let _ = self.config.save();
+            }
+            Message::WatchPatternsChanged(index, val) => {
+                if let Some(watch) = self.config.watched_folders.get_mut(index) {
+                    watch.patterns = filter::parse_patterns(&val);
+                    self.watch_inputs[index] = val;
+                    let _ = self.config.save();
+                }
+            }
+            Message::WatchSkipDownloadedToggled(index, on) => {
+                if let Some(watch) = self.config.watched_folders.get_mut(index) {
+                    watch.skip_downloaded = on;
+                    let _ = self.config.save();
+                }
+            }
+            Message::CheckWatchedFolders => {
+                let Some(client) = self.sftp_client.clone() else {
+                    return Ok(Task::none());
+                };
+                let profile = self.config.profile_key();
+                let tasks = self
+                    .config
+                    .watched_folders
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, w)| w.profile == profile)
+                    .map(|(index, watch)| {
+                        let client = client.clone();
+                        let path = watch.remote_path.clone();
+                        Task::future(async move {
+                            let res = tokio::task::spawn_blocking(move || {
+                                let c = client.lock().unwrap();
+                                c.list_dir(Path::new(&path)).map(|(_, files)| files)
+                            })
+                            .await
+                            .unwrap_or_else(|e| Err(e.into()));
+                            Message::WatchedFolderListed(index, res)
+                        })
+                    });
+                return Ok(Task::batch(tasks));
+            }
+            Message::WatchedFolderListed(index, Ok(files)) => {
+                let Some(watch) = self.config.watched_folders.get(index) else {
+                    return Ok(Task::none());
+                };
+                let new = self.watch_candidates(watch, files);
+                if new.is_empty() {
+                    return Ok(Task::none());
+                }
+                activity::info(format!(
+                    "Watched folder {}: queueing {} new files",
+                    names::display(&watch.remote_path),
+                    new.len()
+                ));
+                // Queued only; the schedule decides when they run
+                return Ok(self.enqueue_scanned(new, false, None));
+            }
+            Message::WatchedFolderListed(index, Err(e)) => {
+                if let Some(watch) = self.config.watched_folders.get(index) {
+                    activity::error(format!(
+                        "Checking watched folder {} failed: {}",
+                        names::display(&watch.remote_path),
+                        e
+                    ));
+                }
+            }
+            Message::ForgetDownloaded(name) => {
+                let key = self.config.profile_key();
+                if let Some(names) = self.config.downloaded_names.get_mut(&key) {
+                    names.retain(|n| *n != name);
+                    let _ = self.config.save();
+                }
+            }
+            Message::IgnoreInputChanged(val) => self.ignore_input = val,
+            Message::AddIgnored => {
+                let name = std::mem::take(&mut self.ignore_input);
+                if !name.trim().is_empty() {
+                    let key = self.config.profile_key();
+                    self.config.remember_download(&key, name.trim());
+                    let _ = self.config.save();
+                }
+            }
+            other => return Err(other),
+        }
+        Ok(Task::none())
+    }
+
+    /// Files in a watched folder's listing that should be queued: matching its
+    /// patterns, not in the queue already and, if asked, never downloaded before
+    pub fn watch_candidates(
+        &self,
+        watch: &WatchedFolder,
+        files: Vec<RemoteFile>,
+    ) -> Vec<RemoteFile> {
+        let history = self.config.downloaded_names.get(&watch.profile);
+        files
+            .into_iter()
+            .filter(|f| f.file_type == FileType::File)
+            .filter(|f| watch.patterns.is_empty() || filter::is_excluded(&watch.patterns, &f.name))
+            .filter(|f| {
+                !self
+                    .queue_items
+                    .iter()
+                    .any(|i| i.remote_file == f.path && i.profile == watch.profile)
+            })
+            .filter(|f| !watch.skip_downloaded || !history.is_some_and(|h| h.contains(&f.name)))
+            .collect()
+    }
+}
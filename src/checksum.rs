@@ -0,0 +1,175 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+pub const MANIFEST_NAME: &str = "SHA256SUMS";
+const TAIL_BYTES: u64 = 64 * 1024; // how much of a partial download's end is hashed
+
+/// Result of checking a folder against its manifest
+#[derive(Debug, Default, PartialEq)]
+pub struct Verification {
+    pub ok: usize,
+    pub mismatched: Vec<String>, // paths as listed in the manifest
+    pub missing: Vec<String>,
+}
+
+impl Verification {
+    pub fn summary(&self) -> String {
+        if self.mismatched.is_empty() && self.missing.is_empty() {
+            return format!("All {} files match", self.ok);
+        }
+        format!(
+            "{} match, {} differ, {} missing",
+            self.ok,
+            self.mismatched.len(),
+            self.missing.len()
+        )
+    }
+}
+
+pub fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1024 * 1024];
+    loop {
+        let n = file.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(hex(hasher))
+}
+
+fn hex(hasher: Sha256) -> String {
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// What a paused download's partial file looked like, so resuming can tell
+/// whether it was edited or truncated in the meantime
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResumeCheck {
+    pub length: u64,
+    pub tail_hash: String, // SHA-256 of the last TAIL_BYTES before `length`
+}
+
+impl ResumeCheck {
+    pub fn record(path: &Path, length: u64) -> std::io::Result<Self> {
+        Ok(Self {
+            length,
+            tail_hash: tail_hash(path, length)?,
+        })
+    }
+
+    /// The file still holds at least `length` bytes and ends (at `length`) the same way
+    pub fn matches(&self, path: &Path) -> bool {
+        let on_disk = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        on_disk >= self.length
+            && tail_hash(path, self.length).is_ok_and(|hash| hash == self.tail_hash)
+    }
+}
+
+/// SHA-256 of the last TAIL_BYTES of `path` before `length`
+pub fn tail_hash(path: &Path, length: u64) -> std::io::Result<String> {
+    let start = tail_start(length);
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(start))?;
+    let mut tail = Vec::new();
+    file.take(length - start).read_to_end(&mut tail)?;
+    Ok(hash_bytes(&tail))
+}
+
+/// Where the tail hashed for a file cut at `length` begins
+pub fn tail_start(length: u64) -> u64 {
+    length.saturating_sub(TAIL_BYTES)
+}
+
+pub fn hash_bytes(data: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    hex(hasher)
+}
+
+/// Deepest folder containing all of `files`, where a batch's manifest goes
+pub fn common_folder(files: &[PathBuf]) -> Option<PathBuf> {
+    let mut dirs = files.iter().filter_map(|f| f.parent());
+    let mut common = dirs.next()?.to_path_buf();
+    for dir in dirs {
+        while !dir.starts_with(&common) {
+            common = common.parent()?.to_path_buf();
+        }
+    }
+    Some(common)
+}
+
+/// Write `dir/SHA256SUMS` for `files`, in the `sha256sum` format so
+/// `sha256sum -c` can check it too. Paths are relative to `dir`.
+pub fn write_manifest(dir: &Path, files: &[PathBuf]) -> std::io::Result<PathBuf> {
+    let mut lines = String::new();
+    for file in files {
+        let name = file.strip_prefix(dir).unwrap_or(file);
+        let name = name.to_string_lossy().replace('\\', "/");
+        lines.push_str(&format!("{}  {}\n", sha256_file(file)?, name));
+    }
+    let path = dir.join(MANIFEST_NAME);
+    File::create(&path)?.write_all(lines.as_bytes())?;
+    Ok(path)
+}
+
+/// Hash every file listed in `manifest` again and compare
+pub fn verify_manifest(manifest: &Path) -> std::io::Result<Verification> {
+    let dir = manifest.parent().unwrap_or(Path::new("."));
+    let mut result = Verification::default();
+    for line in BufReader::new(File::open(manifest)?).lines() {
+        let line = line?;
+        // "<hash>  <name>", or "<hash> *<name>" from binary mode
+        let Some((expected, name)) = line.split_once(' ') else {
+            continue;
+        };
+        let name = name.trim_start_matches([' ', '*']).to_string();
+        match sha256_file(&dir.join(&name)) {
+            Ok(actual) if actual == expected => result.ok += 1,
+            Ok(_) => result.mismatched.push(name),
+            Err(_) => result.missing.push(name),
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_round_trip_finds_changed_and_missing_files() {
+        let dir = std::env::temp_dir().join(format!("simplesftp-sums-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("season1")).unwrap();
+        let files = ["a.txt", "season1/b.txt", "season1/c.txt"].map(|f| dir.join(f));
+        for file in &files {
+            std::fs::write(file, file.to_string_lossy().as_bytes()).unwrap();
+        }
+
+        assert_eq!(common_folder(&files).as_deref(), Some(dir.as_path()));
+        let manifest = write_manifest(&dir, &files).unwrap();
+        let text = std::fs::read_to_string(&manifest).unwrap();
+        assert!(text.contains("  season1/b.txt\n"));
+        assert_eq!(
+            verify_manifest(&manifest).unwrap().summary(),
+            "All 3 files match"
+        );
+
+        std::fs::write(&files[1], b"edited").unwrap();
+        std::fs::remove_file(&files[2]).unwrap();
+        let result = verify_manifest(&manifest).unwrap();
+        assert_eq!(result.ok, 1);
+        assert_eq!(result.mismatched, ["season1/b.txt"]);
+        assert_eq!(result.missing, ["season1/c.txt"]);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -1,74 +1,314 @@
+use crate::checksum::{self, ResumeCheck};
+use crate::error::{DownloadError, SftpError};
+use crate::journal::Journal;
+use crate::names;
+use crate::remote_fs::{sftp_connector, Connector, RemoteFs, RemoteStat};
 use crate::settings::SftpConfig;
-use crate::sftp_client::SftpClient;
-use crate::types::{QueueItem, TransferStatus};
+use crate::types::{Direction, ItemKey, QueueOrder, TransferItem, TransferStatus};
+use crate::{activity, disk};
 
 use std::collections::{HashMap, HashSet};
-use std::path::Path;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::{mpsc, Mutex};
 
 const CHUNK_SIZE: usize = 65536; // 64KB chunks
+const WRITE_BUFFER_SIZE: usize = 1024 * 1024; // 1MB, flushed on pause and when the transfer ends
 const MAX_CONCURRENT: usize = 2;
+const MAX_RETRIES: u32 = 3;
+#[cfg(not(test))]
+const RETRY_BACKOFF_MS: u64 = 2000; // doubled on every attempt
+#[cfg(test)]
+const RETRY_BACKOFF_MS: u64 = 10;
+const DISK_CHECK_SECS: u64 = 5;
+const STATS_SECS: u64 = 1;
+const RESTAT_CHUNKS: u32 = 160; // re-stat the source about every 10 MB
+#[cfg(not(test))]
+const FOLLOW_POLL: Duration = Duration::from_secs(2); // checks for growth at EOF
+#[cfg(test)]
+const FOLLOW_POLL: Duration = Duration::from_millis(20);
+const DEFAULT_FOLLOW_STABLE: Duration = Duration::from_secs(120);
+#[cfg(not(test))]
+const STALL_TIMEOUT: Duration = Duration::from_secs(60); // a chunk with no bytes this long is stuck
+#[cfg(test)]
+const STALL_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// A server's connector as carried by `DownloadCommand::AddProfile`
+#[derive(Clone)]
+pub struct ProfileConnector(pub Connector);
+
+impl std::fmt::Debug for ProfileConnector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ProfileConnector")
+    }
+}
 
 #[derive(Debug, Clone)]
 pub enum DownloadCommand {
     StartAll,
     PauseAll,
     ResumeAll,
-    Pause(String), // remote_file path
-    Resume(String),
-    Cancel(String),
-    AddItem(QueueItem),
+    Pause(ItemKey),
+    Resume(ItemKey),
+    Cancel(ItemKey),
+    AddItem(Box<TransferItem>),
+    SetPriority(ItemKey, u8),
+    SetDestination(ItemKey, String, u64), // new local_location, resume offset
+    SetQueueOrder(QueueOrder),
+    SetFairness(bool), // batches take turns instead of one draining before the next starts
+    SetFastLane(u64),  // bytes; files this small get an extra slot of their own, 0 = off
+    SetFollow(ItemKey, bool), // tail-follow a file still being written
+    SetItemSpeedLimit(ItemKey, u64), // KB/s cap for one item, 0 = only the global limit
+    AddProfile(String, ProfileConnector), // sessions for items queued from that server
     // Internal commands sent by download tasks
-    TaskPaused { remote_file: String, offset: u64 },
-    TaskDone { remote_file: String },
-    SetSpeedLimit(u64), // In KB/s
+    TaskPaused {
+        key: ItemKey,
+        offset: u64,
+        check: Option<ResumeCheck>,
+    },
+    TaskDone {
+        key: ItemKey,
+    },
+    SetSpeedLimit(u64), // In KB/s, for servers without a limit of their own
+    SetProfileSpeedLimits(HashMap<String, u64>), // KB/s by profile_key(), "" = the default server
+    SetScheduleHolds(ScheduleHolds),
+    SetFsyncInterval(u64),     // In MB, 0 = only when a transfer stops
+    SetMinFreeSpace(u64),      // In GB, 0 = no guard
+    SetFollowStable(Duration), // how long a followed file must stop growing
+    SetDryRun(bool),           // skip transfers that would overwrite a file, logging them
 }
 
 #[derive(Debug, Clone)]
 pub enum DownloadEvent {
     Progress {
-        remote_file: String,
+        key: ItemKey,
         bytes_downloaded: u64,
     },
     Completed {
-        remote_file: String,
+        key: ItemKey,
     },
     Failed {
-        remote_file: String,
-        error: String,
+        key: ItemKey,
+        error: DownloadError,
     },
     Paused {
-        remote_file: String,
+        key: ItemKey,
+        resume_check: Option<ResumeCheck>, // downloads: the partial file as left
+    },
+    // The partial file no longer matches its ResumeCheck; downloading from the start
+    PartialChanged {
+        key: ItemKey,
+    },
+    // The server's copy no longer ends where the partial file does (re-uploaded
+    // since); downloading from the start
+    SourceChanged {
+        key: ItemKey,
     },
     Started {
-        remote_file: String,
+        key: ItemKey,
     },
+    Verifying {
+        key: ItemKey,
+    },
+    Retrying {
+        key: ItemKey,
+        attempt: u32,
+    },
+    // No bytes for STALL_TIMEOUT; the chunk was abandoned and the transfer reconnects
+    Stalled {
+        key: ItemKey,
+        attempt: u32,
+    },
+    Skipped {
+        key: ItemKey,
+        reason: String,
+    },
+    // Downloads are held while the destination has less than `required` bytes free
+    LowDiskSpace {
+        free: u64,
+        required: u64,
+    },
+    DiskSpaceRecovered,
+    Stats(TransferStats),
+}
+
+/// Whose items wait for their schedule: `default` for servers that follow the
+/// default schedule, `profiles` for those with their own (by profile_key(),
+/// "" for items that don't name their server)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScheduleHolds {
+    pub default: bool,
+    pub profiles: HashMap<String, bool>,
+}
+
+impl ScheduleHolds {
+    pub fn holds(&self, profile: &str) -> bool {
+        self.profiles.get(profile).copied().unwrap_or(self.default)
+    }
+}
+
+/// Running totals since the manager started, sent each STATS_SECS while they change
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TransferStats {
+    pub bytes: u64,         // moved either way this run
+    pub bytes_per_sec: u64, // over the last interval
+    pub busy_secs: u64,     // time with at least one transfer running
+    pub active: usize,
+    pub pending: usize, // queued and not started
+}
+
+impl TransferStats {
+    /// Average speed while transferring, idle time left out
+    pub fn average_speed(&self) -> u64 {
+        self.bytes / self.busy_secs.max(1)
+    }
 }
 
 pub struct DownloadManager {
-    config: SftpConfig,
+    connector: Connector,                 // for items that don't name their server
+    profiles: HashMap<String, Connector>, // one per connected server, by profile_key()
     command_tx: mpsc::Sender<DownloadCommand>, // Need this to pass to tasks
     command_rx: mpsc::Receiver<DownloadCommand>,
     event_tx: mpsc::Sender<DownloadEvent>,
-    queue: Vec<QueueItem>,
-    active_downloads: HashSet<String>,
-    paused_downloads: Arc<Mutex<HashMap<String, u64>>>, // Shared for pause checking
-    cancelled: Arc<Mutex<HashSet<String>>>,             // Shared for cancel checking
+    queue: Vec<TransferItem>,
+    active_downloads: HashSet<ItemKey>,
+    paused_downloads: Arc<Mutex<HashMap<ItemKey, u64>>>, // Shared for pause checking
+    cancelled: Arc<Mutex<HashSet<ItemKey>>>,             // Shared for cancel checking
+    following: Arc<Mutex<HashSet<ItemKey>>>,             // Shared, checked at EOF
+    follow_stable: Arc<AtomicU64>,                       // ms without growth to finish
+    limits: Limits,
     is_global_paused: bool,
-    speed_limit: Arc<std::sync::atomic::AtomicU64>, // KB/s, 0 = unlimited
+    order: QueueOrder,             // how the next waiting item is picked
+    fair: bool,                    // spread slots across batches first
+    turns: HashMap<String, u64>,   // fairness group -> when it last got a slot
+    starts: u64,                   // slots handed out so far, the clock for `turns`
+    fast_lane: u64,                // size limit for the small-file slot, 0 = no such slot
+    in_fast_lane: Option<ItemKey>, // the small file using it
+    schedule_holds: ScheduleHolds,
+    schedule_paused: HashSet<ItemKey>, // running items stopped for their schedule
+    fsync_interval: Arc<AtomicU64>,    // bytes, 0 = only when a transfer stops
+    min_free_space: u64,               // bytes, 0 = no guard
+    space_paused: Option<HashSet<ItemKey>>, // downloads held for disk space, while low
+    journal: Option<Arc<Journal>>,
+    transferred: Arc<AtomicU64>, // bytes moved by all tasks this run
+    stats: TransferStats,        // as last sent
+    dry_run: bool,
+}
+
+/// The KB/s caps, 0 = none, that running transfers look up before each chunk
+#[derive(Clone)]
+struct Limits {
+    default: Arc<AtomicU64>,
+    profiles: Arc<Mutex<HashMap<String, u64>>>, // servers with a limit of their own
+    items: Arc<Mutex<HashMap<ItemKey, u64>>>,
+}
+
+impl Limits {
+    /// `key`'s server's cap, or its own if that's tighter
+    async fn for_item(&self, key: &ItemKey) -> u64 {
+        let server = match self.profiles.lock().await.get(&key.profile) {
+            Some(&limit) => limit,
+            None => self.default.load(Ordering::Relaxed),
+        };
+        let own = self.items.lock().await.get(key).copied().unwrap_or(0);
+        tighter_limit(server, own)
+    }
+}
+
+/// The local end of a transfer, opened once per task instead of once per chunk
+enum LocalFile {
+    Writer {
+        file: BufWriter<File>,
+        unsynced: u64,
+    },
+    Reader(File),
+}
+
+impl LocalFile {
+    fn open(direction: Direction, path: &Path, offset: u64) -> std::io::Result<Self> {
+        match direction {
+            Direction::Download => {
+                let file = if offset == 0 {
+                    File::create(path)?
+                } else {
+                    // Drop anything past the resume point, e.g. writes made after
+                    // the last recorded offset before a crash
+                    let mut file = OpenOptions::new().write(true).open(path)?;
+                    file.set_len(offset)?;
+                    file.seek(SeekFrom::Start(offset))?;
+                    file
+                };
+                Ok(Self::Writer {
+                    file: BufWriter::with_capacity(WRITE_BUFFER_SIZE, file),
+                    unsynced: 0,
+                })
+            }
+            Direction::Upload => Ok(Self::Reader(File::open(path)?)),
+        }
+    }
+
+    /// Move one chunk between `client` and the local file
+    fn transfer_chunk(
+        &mut self,
+        client: &dyn RemoteFs,
+        remote_path: &Path,
+        offset: u64,
+        fsync_interval: u64,
+    ) -> Result<usize, SftpError> {
+        match self {
+            Self::Writer { file, unsynced } => {
+                let n = client.download_chunk(remote_path, file, offset, CHUNK_SIZE)?;
+                *unsynced += n as u64;
+                if fsync_interval > 0 && *unsynced >= fsync_interval {
+                    self.sync()
+                        .map_err(|e| SftpError::local("Failed to sync local file", e))?;
+                }
+                Ok(n)
+            }
+            Self::Reader(file) => {
+                // Seek every time so a retried chunk is read again rather than skipped
+                file.seek(SeekFrom::Start(offset))
+                    .map_err(|e| SftpError::local("Failed to seek in local file", e))?;
+                client.upload_chunk(file, remote_path, offset, CHUNK_SIZE)
+            }
+        }
+    }
+
+    /// Bytes written by us but still sitting in the buffer, not yet in the file
+    fn buffered(&self) -> u64 {
+        match self {
+            Self::Writer { file, .. } => file.buffer().len() as u64,
+            Self::Reader(_) => 0,
+        }
+    }
+
+    /// Flush buffered writes and fsync them; a no-op for uploads
+    fn sync(&mut self) -> std::io::Result<()> {
+        if let Self::Writer { file, unsynced } = self {
+            file.flush()?;
+            file.get_ref().sync_data()?;
+            *unsynced = 0;
+        }
+        Ok(())
+    }
 }
 
 impl DownloadManager {
     pub fn new(
-        config: SftpConfig,
+        connector: Connector,
         initial_speed_limit: u64,
         command_tx: mpsc::Sender<DownloadCommand>,
         command_rx: mpsc::Receiver<DownloadCommand>,
         event_tx: mpsc::Sender<DownloadEvent>,
+        journal: Option<Arc<Journal>>,
     ) -> Self {
         Self {
-            config,
+            connector,
+            profiles: HashMap::new(),
             command_tx,
             command_rx,
             event_tx,
@@ -76,13 +316,43 @@ impl DownloadManager {
             active_downloads: HashSet::new(),
             paused_downloads: Arc::new(Mutex::new(HashMap::new())),
             cancelled: Arc::new(Mutex::new(HashSet::new())),
+            following: Arc::new(Mutex::new(HashSet::new())),
+            limits: Limits {
+                default: Arc::new(AtomicU64::new(initial_speed_limit)),
+                profiles: Arc::new(Mutex::new(HashMap::new())),
+                items: Arc::new(Mutex::new(HashMap::new())),
+            },
+            follow_stable: Arc::new(AtomicU64::new(DEFAULT_FOLLOW_STABLE.as_millis() as u64)),
             is_global_paused: false,
-            speed_limit: Arc::new(std::sync::atomic::AtomicU64::new(initial_speed_limit)),
+            order: QueueOrder::default(),
+            fair: false,
+            turns: HashMap::new(),
+            starts: 0,
+            fast_lane: 0,
+            in_fast_lane: None,
+            schedule_holds: ScheduleHolds::default(),
+            schedule_paused: HashSet::new(),
+            fsync_interval: Arc::new(AtomicU64::new(0)),
+            min_free_space: 0,
+            space_paused: None,
+            journal,
+            transferred: Arc::new(AtomicU64::new(0)),
+            stats: TransferStats::default(),
+            dry_run: false,
         }
     }
     pub async fn run(&mut self) {
+        let mut disk_check =
+            tokio::time::interval(tokio::time::Duration::from_secs(DISK_CHECK_SECS));
+        let mut stats_tick = tokio::time::interval(tokio::time::Duration::from_secs(STATS_SECS));
         loop {
             tokio::select! {
+                _ = disk_check.tick() => {
+                    self.check_disk_space().await;
+                }
+                _ = stats_tick.tick() => {
+                    self.send_stats().await;
+                }
                 res = self.command_rx.recv() => {
                     match res {
                         Some(cmd) => {
@@ -97,6 +367,35 @@ impl DownloadManager {
         }
     }
 
+    async fn send_stats(&mut self) {
+        let bytes = self.transferred.load(Ordering::Relaxed);
+        let active = self.active_downloads.len();
+        let paused = self.paused_downloads.lock().await;
+        let stats = TransferStats {
+            bytes,
+            bytes_per_sec: (bytes - self.stats.bytes) / STATS_SECS,
+            busy_secs: self.stats.busy_secs + if active > 0 { STATS_SECS } else { 0 },
+            active,
+            pending: self
+                .queue
+                .iter()
+                .filter(|i| i.status == TransferStatus::Queued)
+                .filter(|i| !self.active_downloads.contains(&i.key()))
+                .filter(|i| !paused.contains_key(&i.key()))
+                .count(),
+        };
+        drop(paused);
+        // Nothing running and nothing new: stay quiet
+        let unchanged = TransferStats {
+            busy_secs: self.stats.busy_secs,
+            ..stats
+        } == self.stats;
+        if !unchanged {
+            let _ = self.event_tx.send(DownloadEvent::Stats(stats)).await;
+        }
+        self.stats = stats;
+    }
+
     async fn handle_command(&mut self, command: DownloadCommand) {
         // println!("DEBUG: Processing command: {:?}", command);
         match command {
@@ -107,8 +406,8 @@ impl DownloadManager {
             DownloadCommand::PauseAll => {
                 self.is_global_paused = true;
                 let mut paused = self.paused_downloads.lock().await;
-                for path in &self.active_downloads {
-                    paused.insert(path.clone(), 0);
+                for key in &self.active_downloads {
+                    paused.insert(key.clone(), 0);
                 }
             }
             DownloadCommand::ResumeAll => {
@@ -116,132 +415,447 @@ impl DownloadManager {
                 self.paused_downloads.lock().await.clear();
                 self.process_queue().await;
             }
-            DownloadCommand::Pause(path) => {
+            DownloadCommand::Pause(key) => {
                 let mut paused = self.paused_downloads.lock().await;
-                paused.insert(path.clone(), 0);
+                paused.insert(key, 0);
             }
-            DownloadCommand::Resume(path) => {
+            DownloadCommand::Resume(key) => {
                 {
                     let mut paused = self.paused_downloads.lock().await;
-                    paused.remove(&path);
+                    paused.remove(&key);
                 }
                 self.process_queue().await;
             }
-            DownloadCommand::Cancel(path) => {
+            DownloadCommand::Cancel(key) => {
                 let mut cancelled = self.cancelled.lock().await;
-                cancelled.insert(path.clone());
-                self.queue.retain(|i| i.remote_file != path);
+                cancelled.insert(key.clone());
+                self.queue.retain(|i| i.key() != key);
             }
             DownloadCommand::AddItem(item) => {
-                if !self.queue.iter().any(|i| i.remote_file == item.remote_file)
-                    && !self.active_downloads.contains(&item.remote_file)
+                let key = item.key();
+                // A re-queued item may have been cancelled earlier in this session
+                self.cancelled.lock().await.remove(&key);
+                let mut following = self.following.lock().await;
+                if item.follow {
+                    following.insert(key.clone());
+                } else {
+                    following.remove(&key);
+                }
+                drop(following);
+                self.set_item_limit(&key, item.speed_limit).await;
+                if !self.queue.iter().any(|i| i.key() == key)
+                    && !self.active_downloads.contains(&key)
                 {
-                    self.queue.push(item);
+                    self.queue.push(*item);
+                    // A new destination may already be short on space
+                    if self.min_free_space > 0 {
+                        self.check_disk_space().await;
+                    }
                     self.process_queue().await;
                 }
             }
-            DownloadCommand::TaskPaused {
-                remote_file,
-                offset,
-            } => {
-                self.active_downloads.remove(&remote_file);
-                if let Some(item) = self.queue.iter_mut().find(|i| i.remote_file == remote_file) {
-                    item.bytes_downloaded = offset;
+            DownloadCommand::SetPriority(key, priority) => {
+                // Picked up by the next process_queue, which always takes the highest
+                if let Some(item) = self.queue.iter_mut().find(|i| i.key() == key) {
+                    item.priority = priority;
+                }
+            }
+            DownloadCommand::SetDestination(key, local_location, offset) => {
+                let Some(item) = self.queue.iter_mut().find(|i| i.key() == key) else {
+                    return;
+                };
+                item.local_location = local_location;
+                item.bytes_transferred = offset;
+                // The destination is part of the key, so what was kept under the old one moves over
+                let moved = item.key();
+                let mut paused = self.paused_downloads.lock().await;
+                if paused.remove(&key).is_some() {
+                    paused.insert(moved.clone(), offset);
+                }
+                drop(paused);
+                if self.following.lock().await.remove(&key) {
+                    self.following.lock().await.insert(moved.clone());
+                }
+                let limit = self.limits.items.lock().await.remove(&key);
+                if let Some(limit) = limit {
+                    self.set_item_limit(&moved, limit).await;
+                }
+                if let Some(held) = &mut self.space_paused {
+                    if held.remove(&key) {
+                        held.insert(moved);
+                    }
+                }
+            }
+            DownloadCommand::SetQueueOrder(order) => {
+                // Picked up by the next process_queue
+                self.order = order;
+            }
+            DownloadCommand::SetFairness(on) => self.fair = on,
+            DownloadCommand::SetFastLane(bytes) => {
+                self.fast_lane = bytes;
+                self.process_queue().await;
+            }
+            DownloadCommand::AddProfile(profile, ProfileConnector(connector)) => {
+                // Items from this server may have been waiting for it
+                self.profiles.insert(profile, connector);
+                self.process_queue().await;
+            }
+            DownloadCommand::SetItemSpeedLimit(key, limit) => {
+                // A running transfer picks this up at its next chunk
+                self.set_item_limit(&key, limit).await;
+                if let Some(item) = self.queue.iter_mut().find(|i| i.key() == key) {
+                    item.speed_limit = limit;
+                }
+            }
+            DownloadCommand::SetFollow(key, follow) => {
+                // A running transfer looks this up when it reaches the end of the file
+                if follow {
+                    self.following.lock().await.insert(key.clone());
+                } else {
+                    self.following.lock().await.remove(&key);
+                }
+                if let Some(item) = self.queue.iter_mut().find(|i| i.key() == key) {
+                    item.follow = follow;
+                }
+            }
+            DownloadCommand::TaskPaused { key, offset, check } => {
+                self.release_slot(&key);
+                if let Some(item) = self.queue.iter_mut().find(|i| i.key() == key) {
+                    item.bytes_transferred = offset;
+                    item.resume_check = check;
                 }
             }
-            DownloadCommand::TaskDone { remote_file } => {
-                self.active_downloads.remove(&remote_file);
+            DownloadCommand::TaskDone { key } => {
+                self.release_slot(&key);
+                if let Some(journal) = &self.journal {
+                    journal.forget(&key.id());
+                }
+                // Finished (or failed/cancelled) items must not be picked up again
+                self.queue.retain(|i| i.key() != key);
                 self.process_queue().await;
             }
             DownloadCommand::SetSpeedLimit(limit) => {
-                self.speed_limit
-                    .store(limit, std::sync::atomic::Ordering::Relaxed);
+                self.limits.default.store(limit, Ordering::Relaxed);
+            }
+            DownloadCommand::SetProfileSpeedLimits(limits) => {
+                *self.limits.profiles.lock().await = limits;
+            }
+            DownloadCommand::SetScheduleHolds(holds) => self.set_schedule_holds(holds).await,
+            DownloadCommand::SetDryRun(on) => self.dry_run = on,
+            DownloadCommand::SetFsyncInterval(mb) => {
+                self.fsync_interval
+                    .store(mb * 1024 * 1024, Ordering::Relaxed);
+            }
+            DownloadCommand::SetFollowStable(stable) => {
+                self.follow_stable
+                    .store(stable.as_millis() as u64, Ordering::Relaxed);
+            }
+            DownloadCommand::SetMinFreeSpace(gb) => {
+                self.min_free_space = gb.saturating_mul(1024 * 1024 * 1024);
+                self.check_disk_space().await;
+            }
+        }
+    }
+
+    /// Hold downloads while any destination folder is under the free space
+    /// threshold, and let them continue once it's back above it. Uploads only
+    /// read local files, so they keep going.
+    /// Where `item`'s sessions come from; None while its server isn't connected
+    fn connector_for(&self, item: &TransferItem) -> Option<Connector> {
+        if item.profile.is_empty() {
+            return Some(self.connector.clone());
+        }
+        self.profiles.get(&item.profile).cloned()
+    }
+
+    async fn set_item_limit(&self, key: &ItemKey, limit: u64) {
+        let mut limits = self.limits.items.lock().await;
+        if limit > 0 {
+            limits.insert(key.clone(), limit);
+        } else {
+            limits.remove(key);
+        }
+    }
+
+    async fn check_disk_space(&mut self) {
+        let locations: HashSet<&str> = self
+            .queue
+            .iter()
+            .filter(|i| i.direction == Direction::Download)
+            .map(|i| i.local_location.as_str())
+            .collect();
+        let free = locations
+            .into_iter()
+            .filter_map(|dir| disk::free_space(Path::new(dir)))
+            .min();
+        let low = matches!(free, Some(free) if free < self.min_free_space);
+
+        match (low, self.space_paused.is_some()) {
+            (true, false) => {
+                let mut paused = self.paused_downloads.lock().await;
+                let held: HashSet<ItemKey> = self
+                    .queue
+                    .iter()
+                    .map(|i| (i, i.key()))
+                    .filter(|(i, key)| {
+                        i.direction == Direction::Download
+                            && self.active_downloads.contains(key)
+                            && !paused.contains_key(key)
+                    })
+                    .map(|(_, key)| key)
+                    .collect();
+                for key in &held {
+                    paused.insert(key.clone(), 0);
+                }
+                drop(paused);
+                self.space_paused = Some(held);
+                let _ = self
+                    .event_tx
+                    .send(DownloadEvent::LowDiskSpace {
+                        free: free.unwrap_or(0),
+                        required: self.min_free_space,
+                    })
+                    .await;
+            }
+            (false, true) => {
+                if let Some(held) = self.space_paused.take() {
+                    let mut paused = self.paused_downloads.lock().await;
+                    for key in held {
+                        paused.remove(&key);
+                    }
+                }
+                let _ = self.event_tx.send(DownloadEvent::DiskSpaceRecovered).await;
+                self.process_queue().await;
+            }
+            _ => {}
+        }
+    }
+
+    /// Stop running items whose server is now outside its hours, and let
+    /// those stopped earlier go again once their hours come round
+    async fn set_schedule_holds(&mut self, holds: ScheduleHolds) {
+        let mut paused = self.paused_downloads.lock().await;
+        for key in &self.active_downloads {
+            if holds.holds(&key.profile) && !paused.contains_key(key) {
+                paused.insert(key.clone(), 0);
+                self.schedule_paused.insert(key.clone());
+            }
+        }
+        self.schedule_paused.retain(|key| {
+            let held = holds.holds(&key.profile);
+            if !held {
+                paused.remove(key);
             }
+            held
+        });
+        drop(paused);
+        self.schedule_holds = holds;
+        self.process_queue().await;
+    }
+
+    fn release_slot(&mut self, key: &ItemKey) {
+        self.active_downloads.remove(key);
+        if self.in_fast_lane.as_ref() == Some(key) {
+            self.in_fast_lane = None;
         }
     }
 
     async fn process_queue(&mut self) {
         // Start downloads if we have capacity AND NOT PAUSED GLOBALLY
-        while self.active_downloads.len() < MAX_CONCURRENT && !self.is_global_paused {
+        while !self.is_global_paused {
+            // Small files may also take the fast lane once the regular slots are full
+            let regular_free = self.active_downloads.len()
+                - usize::from(self.in_fast_lane.is_some())
+                < MAX_CONCURRENT;
+            let lane_free = self.fast_lane > 0 && self.in_fast_lane.is_none();
+            if !regular_free && !lane_free {
+                break;
+            }
             // Find next pending item that's not paused or cancelled
             let paused = self.paused_downloads.lock().await;
             let cancelled = self.cancelled.lock().await;
 
-            let next_item = self.queue.iter().find(|item| {
-                item.status == TransferStatus::Pending
-                    && !self.active_downloads.contains(&item.remote_file)
-                    && !paused.contains_key(&item.remote_file)
-                    && !cancelled.contains(&item.remote_file)
-            });
+            // Fair mode: the group with the fewest running, then the one waiting
+            // longest since its last turn, goes first
+            let mut running: HashMap<String, usize> = HashMap::new();
+            for item in self.queue.iter() {
+                if self.active_downloads.contains(&item.key()) {
+                    *running.entry(item.fairness_group()).or_default() += 1;
+                }
+            }
+            let turn = |item: &TransferItem| {
+                let group = item.fairness_group();
+                let busy = running.get(&group).copied().unwrap_or(0);
+                (busy, self.turns.get(&group).copied().unwrap_or(0))
+            };
+
+            // Uploads and downloads share the slots; the chosen order first, then queue order
+            let next_item = self
+                .queue
+                .iter()
+                .filter(|item| {
+                    item.status == TransferStatus::Queued
+                        && (self.space_paused.is_none() || item.direction == Direction::Upload)
+                        && !self.active_downloads.contains(&item.key())
+                        && !paused.contains_key(&item.key())
+                        && !cancelled.contains(&item.key())
+                        && !self.schedule_holds.holds(&item.profile)
+                        && self.connector_for(item).is_some()
+                        && item
+                            .relay_to
+                            .as_ref()
+                            .is_none_or(|to| self.profiles.contains_key(&to.profile))
+                        && (regular_free || item.size_bytes <= self.fast_lane)
+                })
+                .min_by(|a, b| {
+                    let fairness = if self.fair {
+                        turn(a).cmp(&turn(b))
+                    } else {
+                        std::cmp::Ordering::Equal
+                    };
+                    fairness.then_with(|| self.order.compare(a, b))
+                });
 
             if let Some(item) = next_item {
-                let remote_file = item.remote_file.clone();
-                let local_path = format!("{}/{}", item.local_location, item.filename);
+                self.starts += 1;
+                self.turns.insert(item.fairness_group(), self.starts);
+                let key = item.key();
+                let local_path = names::long_path(&item.local_path()).into_owned();
+                let expected_size = item.size_bytes;
+                let direction = item.direction;
+
+                // Nothing to do if a complete copy is already on disk
+                if direction == Direction::Download
+                    && expected_size > 0
+                    && item.bytes_transferred == 0
+                    && !item.follow
+                {
+                    if let Ok(metadata) = std::fs::metadata(&local_path) {
+                        if metadata.len() == expected_size {
+                            drop(paused);
+                            drop(cancelled);
+                            self.queue.retain(|i| i.key() != key);
+                            let _ = self
+                                .event_tx
+                                .send(DownloadEvent::Skipped {
+                                    key,
+                                    reason: "Already downloaded".into(),
+                                })
+                                .await;
+                            continue;
+                        }
+                    }
+                }
 
                 // Ensure directory exists
-                if let Err(e) = std::fs::create_dir_all(&item.local_location) {
-                    println!(
-                        "ERROR: Failed to create directory {}: {}",
-                        item.local_location, e
-                    );
-                    // Should probably fail the item here, but for now we'll let download_file fail
+                if direction == Direction::Download {
+                    let local_dir = names::to_path(&item.local_location);
+                    if let Err(e) = std::fs::create_dir_all(names::long_path(&local_dir)) {
+                        println!(
+                            "ERROR: Failed to create directory {}: {}",
+                            item.local_location, e
+                        );
+                        // Should probably fail the item here, but for now we'll let transfer_file fail
+                    }
                 }
 
-                let config = self.config.clone();
+                let connector = self
+                    .connector_for(item)
+                    .expect("only items with a connector are picked");
                 let event_tx = self.event_tx.clone();
 
                 // Determine start offset: use stored item progress if available
-                let mut offset = match paused.get(&remote_file) {
+                let mut offset = match paused.get(&key) {
                     Some(o) => *o,
-                    None => item.bytes_downloaded,
+                    None => item.bytes_transferred,
                 };
 
-                let path_exists = std::path::Path::new(&local_path).exists();
+                let path_exists = local_path.exists();
 
-                // If file is missing locally, we MUST restart from 0, regardless of what the queue says
-                if !path_exists {
-                    offset = 0;
-                }
-                // Auto-resume logic if file exists
-                else if offset == 0 {
-                    if let Ok(metadata) = std::fs::metadata(&local_path) {
-                        let file_size = metadata.len();
-                        if file_size > 0 && file_size < item.size_bytes {
-                            offset = file_size;
+                // Uploads work out their resume point from the remote side once connected
+                if direction == Direction::Download {
+                    // If file is missing locally, we MUST restart from 0, regardless of what the queue says
+                    if !path_exists {
+                        offset = 0;
+                    }
+                    // Auto-resume logic if file exists
+                    else if offset == 0 {
+                        if let Ok(metadata) = std::fs::metadata(&local_path) {
+                            let file_size = metadata.len();
+                            if file_size > 0 && file_size < item.size_bytes {
+                                offset = file_size;
+                            }
                         }
                     }
                 }
 
                 let paused_downloads = self.paused_downloads.clone();
                 let cancelled_downloads = self.cancelled.clone();
+                let following = self.following.clone();
+                let follow_stable = self.follow_stable.clone();
+                let limits = self.limits.clone();
                 let cmd_tx = self.command_tx.clone();
-                let speed_limit = self.speed_limit.clone();
+                let fsync_interval = self.fsync_interval.clone();
+                let journal = self.journal.clone();
+                let transferred = self.transferred.clone();
+                let resume_check = item.resume_check.clone();
+                let dry_run = self.dry_run;
 
                 drop(paused);
                 drop(cancelled);
 
-                self.active_downloads.insert(remote_file.clone());
+                self.active_downloads.insert(key.clone());
+                if !regular_free {
+                    self.in_fast_lane = Some(key.clone());
+                }
 
                 let _ = self
                     .event_tx
-                    .send(DownloadEvent::Started {
-                        remote_file: remote_file.clone(),
-                    })
+                    .send(DownloadEvent::Started { key: key.clone() })
                     .await;
 
-                // Spawn download task with shared pause/cancel state
-                let remote_file_clone = remote_file.clone();
+                // Spawn transfer task with shared pause/cancel state
+                if let Some(to) = &item.relay_to {
+                    let dest = self.profiles[&to.profile].clone();
+                    let dest_path = to.remote_file.clone();
+                    tokio::spawn(async move {
+                        Self::relay_file(
+                            connector,
+                            dest,
+                            key,
+                            dest_path,
+                            event_tx,
+                            cmd_tx,
+                            paused_downloads,
+                            cancelled_downloads,
+                            limits,
+                            transferred,
+                            dry_run,
+                        )
+                        .await;
+                    });
+                    continue;
+                }
                 tokio::spawn(async move {
-                    Self::download_file(
-                        config,
-                        remote_file_clone,
+                    Self::transfer_file(
+                        connector,
+                        direction,
+                        key,
                         local_path,
                         offset,
+                        expected_size,
+                        resume_check,
                         event_tx,
                         cmd_tx,
                         paused_downloads,
                         cancelled_downloads,
-                        speed_limit,
+                        following,
+                        follow_stable,
+                        limits,
+                        fsync_interval,
+                        journal,
+                        transferred,
+                        dry_run,
                     )
                     .await;
                 });
@@ -254,21 +868,31 @@ impl DownloadManager {
     }
 
     #[allow(clippy::too_many_arguments)]
-    async fn download_file(
-        config: SftpConfig,
-        remote_file: String,
-        local_path: String,
+    async fn transfer_file(
+        connector: Connector,
+        direction: Direction,
+        key: ItemKey,
+        local_path: PathBuf,
         start_offset: u64,
+        expected_size: u64,
+        resume_check: Option<ResumeCheck>,
         event_tx: mpsc::Sender<DownloadEvent>,
         cmd_tx: mpsc::Sender<DownloadCommand>,
-        paused_downloads: Arc<Mutex<HashMap<String, u64>>>,
-        cancelled_downloads: Arc<Mutex<HashSet<String>>>,
-        speed_limit: Arc<std::sync::atomic::AtomicU64>,
+        paused_downloads: Arc<Mutex<HashMap<ItemKey, u64>>>,
+        cancelled_downloads: Arc<Mutex<HashSet<ItemKey>>>,
+        following: Arc<Mutex<HashSet<ItemKey>>>,
+        follow_stable: Arc<AtomicU64>,
+        limits: Limits,
+        fsync_interval: Arc<AtomicU64>,
+        journal: Option<Arc<Journal>>,
+        transferred: Arc<AtomicU64>,
+        dry_run: bool,
     ) {
+        let remote_file = key.remote_file.clone();
         // Connect to SFTP
         let client = match tokio::task::spawn_blocking({
-            let config = config.clone();
-            move || SftpClient::connect(&config)
+            let connector = connector.clone();
+            move || connector()
         })
         .await
         {
@@ -276,47 +900,228 @@ impl DownloadManager {
             Ok(Err(e)) => {
                 let _ = event_tx
                     .send(DownloadEvent::Failed {
-                        remote_file: remote_file.clone(),
-                        error: e,
+                        key: key.clone(),
+                        error: e.into(),
                     })
                     .await;
-                let _ = cmd_tx.send(DownloadCommand::TaskDone { remote_file }).await;
+                let _ = cmd_tx.send(DownloadCommand::TaskDone { key }).await;
                 return;
             }
             Err(e) => {
                 let _ = event_tx
                     .send(DownloadEvent::Failed {
-                        remote_file: remote_file.clone(),
-                        error: e.to_string(),
+                        key: key.clone(),
+                        error: e.into(),
                     })
                     .await;
-                let _ = cmd_tx.send(DownloadCommand::TaskDone { remote_file }).await;
+                let _ = cmd_tx.send(DownloadCommand::TaskDone { key }).await;
                 return;
             }
         };
 
-        let client = Arc::new(Mutex::new(client));
+        // Downloads note the source's size and mtime up front: items queued without
+        // a size get one, and a later change means the file was replaced under us
+        let mut client = Arc::new(Mutex::new(client));
+        let queued_size = expected_size;
+        let mut snapshot = match direction {
+            Direction::Download => Self::remote_stat(&client, &remote_file).await,
+            Direction::Upload => None,
+        };
+        let known_size = match direction {
+            Direction::Upload => std::fs::metadata(&local_path).map(|m| m.len()).ok(),
+            Direction::Download => snapshot
+                .map(|s| s.size)
+                .or((expected_size > 0).then_some(expected_size)),
+        };
+        let mut expected_size = known_size.unwrap_or(expected_size);
+
+        // An empty source reads as EOF straight away, before the chunk loop has
+        // written anything: no local file for downloads, no remote one for uploads.
+        // Create the empty copy here and finish.
+        if known_size == Some(0) && !following.lock().await.contains(&key) {
+            if dry_run {
+                let existing =
+                    Self::overwrite_target(&client, direction, &remote_file, &local_path).await;
+                if let Some(target) = existing {
+                    Self::skip_for_dry_run(key, &target, &event_tx, &cmd_tx).await;
+                    return;
+                }
+            }
+            let result = tokio::task::spawn_blocking({
+                let client = client.clone();
+                let (remote, local) = (remote_file.clone(), local_path.clone());
+                move || match direction {
+                    Direction::Download => File::create(&local)
+                        .map(drop)
+                        .map_err(|e| SftpError::local("Failed to create local file", e)),
+                    Direction::Upload => {
+                        client
+                            .blocking_lock()
+                            .write_chunk(Path::new(&remote), 0, &[])
+                    }
+                }
+            })
+            .await
+            .unwrap_or_else(|e| Err(e.into()));
+            let event = match result {
+                Ok(()) => DownloadEvent::Completed { key: key.clone() },
+                Err(e) => DownloadEvent::Failed {
+                    key: key.clone(),
+                    error: e.into(),
+                },
+            };
+            let _ = event_tx.send(event).await;
+            let _ = cmd_tx.send(DownloadCommand::TaskDone { key }).await;
+            return;
+        }
         let mut bytes_downloaded = start_offset;
+        let mut attempt = 0;
+
+        // An interrupted upload picks up at its own recorded offset, and only if
+        // the remote copy still ends with the same bytes as the local file there.
+        // Anything else at the remote path is overwritten from the start.
+        if direction == Direction::Upload && bytes_downloaded > 0 {
+            let client = client.clone();
+            let (remote, local, length) =
+                (remote_file.clone(), local_path.clone(), bytes_downloaded);
+            let same = tokio::task::spawn_blocking(move || {
+                let ours = checksum::tail_hash(&local, length).ok()?;
+                let client = client.blocking_lock();
+                if client.get_file_size(&remote).ok()? < length {
+                    return Some(false);
+                }
+                let theirs = client.tail_hash(Path::new(&remote), length).ok()?;
+                Some(ours == theirs)
+            })
+            .await
+            .ok()
+            .flatten();
+            if same != Some(true) {
+                bytes_downloaded = 0;
+            }
+        }
+
+        // For downloads the file on disk has the final say: after a crash it can
+        // be shorter than the saved offset (the write buffer was lost)
+        if direction == Direction::Download && bytes_downloaded > 0 {
+            let on_disk = std::fs::metadata(&local_path).map(|m| m.len()).unwrap_or(0);
+            bytes_downloaded = bytes_downloaded.min(on_disk);
+            // A partial copy of a file that has since changed size is worthless
+            if queued_size > 0 && snapshot.is_some_and(|s| s.size != queued_size) {
+                println!(
+                    "DEBUG: {} changed since it was queued, starting over",
+                    remote_file
+                );
+                bytes_downloaded = 0;
+            }
+            // Appending to a partial copy the user edited or truncated would
+            // corrupt it, so it has to still end the way it did at the pause
+            if let Some(check) = resume_check.filter(|_| bytes_downloaded > 0) {
+                let path = local_path.clone();
+                let intact = tokio::task::spawn_blocking(move || check.matches(&path))
+                    .await
+                    .unwrap_or(false);
+                if !intact {
+                    bytes_downloaded = 0;
+                    let _ = event_tx
+                        .send(DownloadEvent::PartialChanged { key: key.clone() })
+                        .await;
+                }
+            }
+            // A file re-uploaded at the same size slips past the snapshot, so the
+            // last block on disk is compared with the same range on the server.
+            // A read that fails here is left to the chunk loop to retry.
+            if bytes_downloaded > 0 {
+                let client = client.clone();
+                let (remote, local, length) =
+                    (remote_file.clone(), local_path.clone(), bytes_downloaded);
+                let same = tokio::task::spawn_blocking(move || {
+                    let ours = checksum::tail_hash(&local, length).ok()?;
+                    let theirs = client
+                        .blocking_lock()
+                        .tail_hash(Path::new(&remote), length)
+                        .ok()?;
+                    Some(ours == theirs)
+                })
+                .await
+                .ok()
+                .flatten();
+                if same == Some(false) {
+                    println!(
+                        "DEBUG: {} no longer matches the partial copy, starting over",
+                        remote_file
+                    );
+                    bytes_downloaded = 0;
+                    let _ = event_tx
+                        .send(DownloadEvent::SourceChanged { key: key.clone() })
+                        .await;
+                }
+            }
+        }
+        // Dry run: starting from nothing would overwrite what's at the destination
+        if dry_run && bytes_downloaded == 0 {
+            let existing =
+                Self::overwrite_target(&client, direction, &remote_file, &local_path).await;
+            if let Some(target) = existing {
+                Self::skip_for_dry_run(key, &target, &event_tx, &cmd_tx).await;
+                return;
+            }
+        }
+        let mut chunks_since_stat = 0;
+        let mut idle_since: Option<Instant> = None; // followed file at EOF since
+
+        let mut local_file = match LocalFile::open(direction, &local_path, bytes_downloaded) {
+            Ok(f) => Arc::new(Mutex::new(f)),
+            Err(e) => {
+                let _ = event_tx
+                    .send(DownloadEvent::Failed {
+                        key: key.clone(),
+                        error: SftpError::local("Failed to open local file", e).into(),
+                    })
+                    .await;
+                let _ = cmd_tx.send(DownloadCommand::TaskDone { key }).await;
+                return;
+            }
+        };
 
         loop {
             // Check if paused
             {
                 let paused = paused_downloads.lock().await;
-                if paused.contains_key(&remote_file) {
-                    // Store current progress and exit
+                if paused.contains_key(&key) {
+                    // Store current progress and exit; the offset is only valid once
+                    // buffered writes are on disk
                     drop(paused);
+                    let _ = Self::sync_local(&local_file).await;
+                    if let Some(journal) = &journal {
+                        journal.record(&key.id(), bytes_downloaded);
+                    }
+                    let check = match direction {
+                        Direction::Download if bytes_downloaded > 0 => {
+                            let (path, length) = (local_path.clone(), bytes_downloaded);
+                            tokio::task::spawn_blocking(move || {
+                                ResumeCheck::record(&path, length).ok()
+                            })
+                            .await
+                            .ok()
+                            .flatten()
+                        }
+                        _ => None,
+                    };
                     let mut paused = paused_downloads.lock().await;
-                    paused.insert(remote_file.clone(), bytes_downloaded);
+                    paused.insert(key.clone(), bytes_downloaded);
                     let _ = event_tx
                         .send(DownloadEvent::Paused {
-                            remote_file: remote_file.clone(),
+                            key: key.clone(),
+                            resume_check: check.clone(),
                         })
                         .await;
                     // Notify manager to clear active state and persist offset
                     let _ = cmd_tx
                         .send(DownloadCommand::TaskPaused {
-                            remote_file,
+                            key,
                             offset: bytes_downloaded,
+                            check,
                         })
                         .await;
                     return;
@@ -326,19 +1131,24 @@ impl DownloadManager {
             // Check if cancelled
             {
                 let cancelled = cancelled_downloads.lock().await;
-                if cancelled.contains(&remote_file) {
-                    let _ = cmd_tx.send(DownloadCommand::TaskDone { remote_file }).await;
+                if cancelled.contains(&key) {
+                    drop(cancelled);
+                    let _ = Self::sync_local(&local_file).await;
+                    let _ = cmd_tx.send(DownloadCommand::TaskDone { key }).await;
                     return;
                 }
             }
 
             let client_clone = client.clone();
+            let local_clone = local_file.clone();
             let remote_path = remote_file.clone();
-            let local = local_path.clone();
+            let journal_id = key.id();
             let offset = bytes_downloaded;
+            let sync_every = fsync_interval.load(Ordering::Relaxed);
+            let journal_clone = journal.clone();
 
             // Throttling Logic
-            let limit_kb = speed_limit.load(std::sync::atomic::Ordering::Relaxed);
+            let limit_kb = limits.default.load(Ordering::Relaxed);
             if limit_kb > 0 {
                 // NOTE: This is a simple per-task throttling. If MAX_CONCURRENT > 1,
                 // total speed = limit * active_tasks.
@@ -366,34 +1176,181 @@ impl DownloadManager {
 
                 // But we are inside the loop. Let's start timer.
             }
-            let start = std::time::Instant::now();
+            let start = tokio::time::Instant::now(); // the clock the throttling sleeps run on
 
-            let result = tokio::task::spawn_blocking(move || {
+            let chunk = tokio::task::spawn_blocking(move || {
                 let c = client_clone.blocking_lock();
-                c.download_chunk(
+                let mut local = local_clone.blocking_lock();
+                let n = local.transfer_chunk(
+                    c.as_ref(),
                     Path::new(&remote_path),
-                    Path::new(&local),
                     offset,
-                    CHUNK_SIZE,
-                )
-            })
-            .await;
+                    sync_every,
+                )?;
+                // Only what has left our buffer would survive the process dying
+                if let (Some(journal), true) = (&journal_clone, n > 0) {
+                    journal.record(&journal_id, offset + n as u64 - local.buffered());
+                }
+                Ok::<_, SftpError>(n)
+            });
+
+            let result = match tokio::time::timeout(STALL_TIMEOUT, chunk).await {
+                Ok(result) => result,
+                Err(_) => {
+                    // The stuck read keeps the old session and local handle; leave
+                    // them to it and carry on with fresh ones from the last offset
+                    let stalled = SftpError::ConnectionLost(format!(
+                        "no data for {} seconds",
+                        STALL_TIMEOUT.as_secs()
+                    ));
+                    if attempt >= MAX_RETRIES {
+                        let _ = event_tx
+                            .send(DownloadEvent::Failed {
+                                key: key.clone(),
+                                error: stalled.into(),
+                            })
+                            .await;
+                        let _ = cmd_tx.send(DownloadCommand::TaskDone { key }).await;
+                        break;
+                    }
+                    attempt += 1;
+                    let _ = event_tx
+                        .send(DownloadEvent::Stalled {
+                            key: key.clone(),
+                            attempt,
+                        })
+                        .await;
+                    if direction == Direction::Download {
+                        // Whatever sat in the abandoned write buffer never reached the file
+                        let on_disk = std::fs::metadata(&local_path).map(|m| m.len()).unwrap_or(0);
+                        bytes_downloaded = bytes_downloaded.min(on_disk);
+                    }
+                    local_file = match LocalFile::open(direction, &local_path, bytes_downloaded) {
+                        Ok(f) => Arc::new(Mutex::new(f)),
+                        Err(e) => {
+                            let _ = event_tx
+                                .send(DownloadEvent::Failed {
+                                    key: key.clone(),
+                                    error: SftpError::local("Failed to open local file", e).into(),
+                                })
+                                .await;
+                            let _ = cmd_tx.send(DownloadCommand::TaskDone { key }).await;
+                            break;
+                        }
+                    };
+                    let connector = connector.clone();
+                    if let Ok(Ok(new_client)) =
+                        tokio::task::spawn_blocking(move || connector()).await
+                    {
+                        client = Arc::new(Mutex::new(new_client));
+                    }
+                    continue;
+                }
+            };
 
             match result {
                 Ok(Ok(bytes_read)) => {
+                    chunks_since_stat += 1;
+                    let follow = following.lock().await.contains(&key);
+                    if bytes_read == 0 || chunks_since_stat >= RESTAT_CHUNKS {
+                        chunks_since_stat = 0;
+                        // A followed file is meant to grow; only shrinking means it
+                        // was replaced. Keep the snapshot current in case it's unfollowed.
+                        let changed = if follow {
+                            let now = Self::remote_stat(&client, &remote_file).await;
+                            snapshot = now.or(snapshot);
+                            now.is_some_and(|now| now.size < bytes_downloaded)
+                        } else {
+                            Self::remote_changed(&client, &remote_file, snapshot).await
+                        };
+                        if changed {
+                            // What we have mixes two versions of the file
+                            drop(local_file); // Windows won't delete an open file
+                            if dry_run {
+                                activity::info(format!(
+                                    "Dry run, not done: would delete the partial copy {}",
+                                    local_path.display()
+                                ));
+                            } else {
+                                let _ = std::fs::remove_file(&local_path);
+                            }
+                            if let Some(journal) = &journal {
+                                journal.forget(&key.id());
+                            }
+                            let _ = event_tx
+                                .send(DownloadEvent::Failed {
+                                    key: key.clone(),
+                                    error: DownloadError::RemoteChanged,
+                                })
+                                .await;
+                            let _ = cmd_tx.send(DownloadCommand::TaskDone { key }).await;
+                            break;
+                        }
+                    }
+                    if bytes_read == 0 && follow {
+                        // Keep polling until the file has stopped growing for long enough
+                        let idle = *idle_since.get_or_insert_with(Instant::now);
+                        let stable = Duration::from_millis(follow_stable.load(Ordering::Relaxed));
+                        if idle.elapsed() < stable {
+                            tokio::time::sleep(FOLLOW_POLL.min(stable - idle.elapsed())).await;
+                            continue;
+                        }
+                        expected_size = bytes_downloaded;
+                    }
+                    if bytes_read > 0 {
+                        idle_since = None;
+                    }
                     if bytes_read == 0 {
-                        // Download complete
+                        if let Err(e) = Self::sync_local(&local_file).await {
+                            let _ = event_tx
+                                .send(DownloadEvent::Failed {
+                                    key: key.clone(),
+                                    error: SftpError::local("Failed to flush local file", e).into(),
+                                })
+                                .await;
+                            let _ = cmd_tx.send(DownloadCommand::TaskDone { key }).await;
+                            break;
+                        }
+
+                        // Transfer complete, make sure the copy matches the source size
                         let _ = event_tx
-                            .send(DownloadEvent::Completed {
-                                remote_file: remote_file.clone(),
-                            })
+                            .send(DownloadEvent::Verifying { key: key.clone() })
                             .await;
-                        let _ = cmd_tx.send(DownloadCommand::TaskDone { remote_file }).await;
+                        let written_size = match direction {
+                            Direction::Download => {
+                                std::fs::metadata(&local_path).map(|m| m.len()).unwrap_or(0)
+                            }
+                            Direction::Upload => {
+                                let client = client.clone();
+                                let path = remote_file.clone();
+                                tokio::task::spawn_blocking(move || {
+                                    client.blocking_lock().get_file_size(&path)
+                                })
+                                .await
+                                .ok()
+                                .and_then(|r| r.ok())
+                                .unwrap_or(0)
+                            }
+                        };
+                        let event = if expected_size > 0 && written_size != expected_size {
+                            DownloadEvent::Failed {
+                                key: key.clone(),
+                                error: DownloadError::SizeMismatch {
+                                    expected: expected_size,
+                                    actual: written_size,
+                                },
+                            }
+                        } else {
+                            DownloadEvent::Completed { key: key.clone() }
+                        };
+                        let _ = event_tx.send(event).await;
+                        let _ = cmd_tx.send(DownloadCommand::TaskDone { key }).await;
                         break;
                     }
+                    attempt = 0;
 
                     // Apply throttling delay
-                    let limit_kb = speed_limit.load(std::sync::atomic::Ordering::Relaxed);
+                    let limit_kb = limits.for_item(&key).await;
                     if limit_kb > 0 {
                         let duration = start.elapsed();
                         let min_duration_micros =
@@ -405,58 +1362,358 @@ impl DownloadManager {
                     }
 
                     bytes_downloaded += bytes_read as u64;
+                    transferred.fetch_add(bytes_read as u64, Ordering::Relaxed);
 
                     let _ = event_tx
                         .send(DownloadEvent::Progress {
-                            remote_file: remote_file.clone(),
+                            key: key.clone(),
                             bytes_downloaded,
                         })
                         .await;
                 }
                 Ok(Err(e)) => {
+                    // Dropped sessions are worth a reconnect with backoff; auth or
+                    // missing-file errors won't fix themselves
+                    if e.is_retryable() && attempt < MAX_RETRIES {
+                        attempt += 1;
+                        let _ = event_tx
+                            .send(DownloadEvent::Retrying {
+                                key: key.clone(),
+                                attempt,
+                            })
+                            .await;
+                        let backoff = RETRY_BACKOFF_MS * 2u64.pow(attempt - 1);
+                        tokio::time::sleep(tokio::time::Duration::from_millis(backoff)).await;
+                        let connector = connector.clone();
+                        if let Ok(Ok(new_client)) =
+                            tokio::task::spawn_blocking(move || connector()).await
+                        {
+                            client = Arc::new(Mutex::new(new_client));
+                        }
+                        continue;
+                    }
+                    let _ = Self::sync_local(&local_file).await;
                     let _ = event_tx
                         .send(DownloadEvent::Failed {
-                            remote_file: remote_file.clone(),
-                            error: e,
+                            key: key.clone(),
+                            error: e.into(),
                         })
                         .await;
-                    let _ = cmd_tx.send(DownloadCommand::TaskDone { remote_file }).await;
+                    let _ = cmd_tx.send(DownloadCommand::TaskDone { key }).await;
                     break;
                 }
                 Err(e) => {
                     let _ = event_tx
                         .send(DownloadEvent::Failed {
-                            remote_file: remote_file.clone(),
-                            error: e.to_string(),
+                            key: key.clone(),
+                            error: e.into(),
                         })
                         .await;
-                    let _ = cmd_tx.send(DownloadCommand::TaskDone { remote_file }).await;
+                    let _ = cmd_tx.send(DownloadCommand::TaskDone { key }).await;
                     break;
                 }
             }
         }
     }
-}
-
-/// Creates a download manager and returns the command sender and event receiver
-pub fn create_download_manager(
-    config: SftpConfig,
-    initial_speed_limit: u64,
-) -> (mpsc::Sender<DownloadCommand>, mpsc::Receiver<DownloadEvent>) {
-    let (cmd_tx, cmd_rx) = mpsc::channel(100);
-    let (event_tx, event_rx) = mpsc::channel(100);
 
-    // Update config with speed limit
-    // Wait, manager creates its own AtomicU64 from config.max_download_speed
-    // So we don't need to do anything special here as long as config passed in has it.
+    /// Copy `remote_file` to `dest_path` on another server, one chunk in
+    /// memory at a time. Like an upload it resumes from the size of the copy
+    /// already at the destination.
+    #[allow(clippy::too_many_arguments)]
+    async fn relay_file(
+        source: Connector,
+        dest: Connector,
+        key: ItemKey,
+        dest_path: String,
+        event_tx: mpsc::Sender<DownloadEvent>,
+        cmd_tx: mpsc::Sender<DownloadCommand>,
+        paused_downloads: Arc<Mutex<HashMap<ItemKey, u64>>>,
+        cancelled_downloads: Arc<Mutex<HashSet<ItemKey>>>,
+        limits: Limits,
+        transferred: Arc<AtomicU64>,
+        dry_run: bool,
+    ) {
+        let remote_file = key.remote_file.clone();
+        let connect = |source: Connector, dest: Connector| async move {
+            let (from, to) = tokio::task::spawn_blocking(move || Ok((source()?, dest()?)))
+                .await
+                .unwrap_or_else(|e| Err(SftpError::from(e)))?;
+            Ok::<_, SftpError>((Arc::new(Mutex::new(from)), Arc::new(Mutex::new(to))))
+        };
+        let size_of = |client: &Arc<Mutex<Box<dyn RemoteFs>>>, path: &str| {
+            let (client, path) = (client.clone(), path.to_string());
+            async move {
+                tokio::task::spawn_blocking(move || client.blocking_lock().get_file_size(&path))
+                    .await
+                    .unwrap_or_else(|e| Err(e.into()))
+            }
+        };
 
-    let mut manager = DownloadManager::new(
-        config,
-        initial_speed_limit,
-        cmd_tx.clone(),
-        cmd_rx,
-        event_tx,
-    );
+        let (mut from, mut to) = match connect(source.clone(), dest.clone()).await {
+            Ok(sessions) => sessions,
+            Err(e) => {
+                let _ = event_tx
+                    .send(DownloadEvent::Failed {
+                        key: key.clone(),
+                        error: e.into(),
+                    })
+                    .await;
+                let _ = cmd_tx.send(DownloadCommand::TaskDone { key }).await;
+                return;
+            }
+        };
+        let expected_size = match size_of(&from, &remote_file).await {
+            Ok(size) => size,
+            Err(e) => {
+                let _ = event_tx
+                    .send(DownloadEvent::Failed {
+                        key: key.clone(),
+                        error: e.into(),
+                    })
+                    .await;
+                let _ = cmd_tx.send(DownloadCommand::TaskDone { key }).await;
+                return;
+            }
+        };
+        let existing = size_of(&to, &dest_path).await.ok();
+        let mut offset = match existing {
+            Some(size) if size < expected_size => size,
+            _ => 0,
+        };
+        if dry_run && existing.is_some() && offset == 0 {
+            let target = format!("{} on the other server", dest_path);
+            Self::skip_for_dry_run(key, &target, &event_tx, &cmd_tx).await;
+            return;
+        }
+        let mut attempt = 0;
+
+        loop {
+            if paused_downloads.lock().await.contains_key(&key) {
+                paused_downloads.lock().await.insert(key.clone(), offset);
+                let _ = event_tx
+                    .send(DownloadEvent::Paused {
+                        key: key.clone(),
+                        resume_check: None,
+                    })
+                    .await;
+                let _ = cmd_tx
+                    .send(DownloadCommand::TaskPaused {
+                        key,
+                        offset,
+                        check: None,
+                    })
+                    .await;
+                return;
+            }
+            if cancelled_downloads.lock().await.contains(&key) {
+                let _ = cmd_tx.send(DownloadCommand::TaskDone { key }).await;
+                return;
+            }
+
+            let start = tokio::time::Instant::now();
+            let chunk = tokio::task::spawn_blocking({
+                let (from, to) = (from.clone(), to.clone());
+                let (source_path, dest_path) = (remote_file.clone(), dest_path.clone());
+                move || {
+                    let data = from.blocking_lock().read_chunk(
+                        Path::new(&source_path),
+                        offset,
+                        CHUNK_SIZE,
+                    )?;
+                    // An empty source still needs its (empty) copy created
+                    if !data.is_empty() || offset == 0 {
+                        to.blocking_lock()
+                            .write_chunk(Path::new(&dest_path), offset, &data)?;
+                    }
+                    Ok::<_, SftpError>(data.len())
+                }
+            });
+            let result = match tokio::time::timeout(STALL_TIMEOUT, chunk).await {
+                Ok(result) => result.unwrap_or_else(|e| Err(e.into())),
+                Err(_) => Err(SftpError::ConnectionLost(format!(
+                    "no data for {} seconds",
+                    STALL_TIMEOUT.as_secs()
+                ))),
+            };
+
+            match result {
+                Ok(0) => {
+                    let written = size_of(&to, &dest_path).await.unwrap_or(0);
+                    let event = if written != expected_size {
+                        DownloadEvent::Failed {
+                            key: key.clone(),
+                            error: DownloadError::SizeMismatch {
+                                expected: expected_size,
+                                actual: written,
+                            },
+                        }
+                    } else {
+                        DownloadEvent::Completed { key: key.clone() }
+                    };
+                    let _ = event_tx.send(event).await;
+                    let _ = cmd_tx.send(DownloadCommand::TaskDone { key }).await;
+                    return;
+                }
+                Ok(n) => {
+                    attempt = 0;
+                    let limit_kb = limits.for_item(&key).await;
+                    if limit_kb > 0 {
+                        let min = Duration::from_micros(n as u64 * 1000 * 1000 / (limit_kb * 1024));
+                        if let Some(rest) = min.checked_sub(start.elapsed()) {
+                            tokio::time::sleep(rest).await;
+                        }
+                    }
+                    offset += n as u64;
+                    transferred.fetch_add(n as u64, Ordering::Relaxed);
+                    let _ = event_tx
+                        .send(DownloadEvent::Progress {
+                            key: key.clone(),
+                            bytes_downloaded: offset,
+                        })
+                        .await;
+                }
+                Err(e) if e.is_retryable() && attempt < MAX_RETRIES => {
+                    // Either end may have dropped; reconnect both and redo the chunk
+                    attempt += 1;
+                    let _ = event_tx
+                        .send(DownloadEvent::Retrying {
+                            key: key.clone(),
+                            attempt,
+                        })
+                        .await;
+                    let backoff = RETRY_BACKOFF_MS * 2u64.pow(attempt - 1);
+                    tokio::time::sleep(Duration::from_millis(backoff)).await;
+                    if let Ok(sessions) = connect(source.clone(), dest.clone()).await {
+                        (from, to) = sessions;
+                    }
+                }
+                Err(e) => {
+                    let _ = event_tx
+                        .send(DownloadEvent::Failed {
+                            key: key.clone(),
+                            error: e.into(),
+                        })
+                        .await;
+                    let _ = cmd_tx.send(DownloadCommand::TaskDone { key }).await;
+                    return;
+                }
+            }
+        }
+    }
+
+    /// What a transfer starting from nothing would overwrite: the local file
+    /// for downloads, the remote one for uploads
+    async fn overwrite_target(
+        client: &Arc<Mutex<Box<dyn RemoteFs>>>,
+        direction: Direction,
+        remote_file: &str,
+        local_path: &Path,
+    ) -> Option<String> {
+        match direction {
+            Direction::Download => local_path
+                .exists()
+                .then(|| local_path.display().to_string()),
+            Direction::Upload => Self::remote_stat(client, remote_file)
+                .await
+                .map(|_| remote_file.to_string()),
+        }
+    }
+
+    /// Dry run: log the overwrite instead of doing it and leave the item skipped
+    async fn skip_for_dry_run(
+        key: ItemKey,
+        target: &str,
+        event_tx: &mpsc::Sender<DownloadEvent>,
+        cmd_tx: &mpsc::Sender<DownloadCommand>,
+    ) {
+        activity::info(format!("Dry run, not done: would overwrite {}", target));
+        let _ = event_tx
+            .send(DownloadEvent::Skipped {
+                key: key.clone(),
+                reason: "Dry run: would overwrite the existing copy".into(),
+            })
+            .await;
+        let _ = cmd_tx.send(DownloadCommand::TaskDone { key }).await;
+    }
+
+    async fn remote_stat(
+        client: &Arc<Mutex<Box<dyn RemoteFs>>>,
+        remote_file: &str,
+    ) -> Option<RemoteStat> {
+        let client = client.clone();
+        let path = remote_file.to_string();
+        tokio::task::spawn_blocking(move || client.blocking_lock().stat(&path))
+            .await
+            .ok()
+            .and_then(|r| r.ok())
+    }
+
+    /// True if the source no longer matches `snapshot`. A failed stat says
+    /// nothing either way; the next read will report the real problem.
+    async fn remote_changed(
+        client: &Arc<Mutex<Box<dyn RemoteFs>>>,
+        remote_file: &str,
+        snapshot: Option<RemoteStat>,
+    ) -> bool {
+        let Some(before) = snapshot else {
+            return false;
+        };
+        Self::remote_stat(client, remote_file)
+            .await
+            .is_some_and(|now| now != before)
+    }
+
+    /// Flush and fsync the local file off the async runtime
+    async fn sync_local(local_file: &Arc<Mutex<LocalFile>>) -> std::io::Result<()> {
+        let local_file = local_file.clone();
+        tokio::task::spawn_blocking(move || local_file.blocking_lock().sync())
+            .await
+            .map_err(std::io::Error::other)?
+    }
+}
+
+/// The stricter of two KB/s caps, where 0 means no cap
+pub fn tighter_limit(a: u64, b: u64) -> u64 {
+    match (a, b) {
+        (0, limit) | (limit, 0) => limit,
+        (a, b) => a.min(b),
+    }
+}
+
+/// Creates a download manager and returns the command sender and event receiver.
+/// Progress is journaled to `journal_path` if given
+pub fn create_download_manager(
+    config: SftpConfig,
+    initial_speed_limit: u64,
+    journal_path: Option<&Path>,
+) -> (mpsc::Sender<DownloadCommand>, mpsc::Receiver<DownloadEvent>) {
+    let journal = journal_path.and_then(|path| match Journal::open(path) {
+        Ok(journal) => Some(Arc::new(journal)),
+        Err(e) => {
+            println!("DEBUG: Transfer journal unavailable: {}", e);
+            None
+        }
+    });
+    spawn_download_manager(sftp_connector(config), initial_speed_limit, journal)
+}
+
+/// Like `create_download_manager`, but against any remote filesystem
+pub fn spawn_download_manager(
+    connector: Connector,
+    initial_speed_limit: u64,
+    journal: Option<Arc<Journal>>,
+) -> (mpsc::Sender<DownloadCommand>, mpsc::Receiver<DownloadEvent>) {
+    let (cmd_tx, cmd_rx) = mpsc::channel(100);
+    let (event_tx, event_rx) = mpsc::channel(100);
+
+    let mut manager = DownloadManager::new(
+        connector,
+        initial_speed_limit,
+        cmd_tx.clone(),
+        cmd_rx,
+        event_tx,
+        journal,
+    );
 
     tokio::spawn(async move {
         manager.run().await;
@@ -464,3 +1721,1125 @@ pub fn create_download_manager(
 
     (cmd_tx, event_rx)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::remote_fs::MemoryFs;
+    use crate::test_server::{pattern, TestServer};
+    use crate::types::{Batch, RelayTarget};
+    use tokio::time::{timeout, Duration};
+
+    fn scratch_dir(name: &str) -> std::path::PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("simplesftp-dm-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn memory_item(local_dir: &Path, remote: &str, size: u64) -> TransferItem {
+        TransferItem {
+            direction: Direction::Download,
+            local_location: local_dir.to_string_lossy().to_string(),
+            filename: Path::new(remote)
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .to_string(),
+            remote_file: remote.into(),
+            size_bytes: size,
+            bytes_transferred: 0,
+            priority: 10,
+            status: TransferStatus::Queued,
+            batch: None,
+            follow: false,
+            speed_limit: 0,
+            profile: String::new(),
+            relay_to: None,
+            resume_check: None,
+            failure: None,
+            queued_at: 0,
+            timeline: Vec::new(),
+            note: String::new(),
+            label: None,
+        }
+    }
+
+    fn queue_item(server: &TestServer, name: &str, size: u64) -> TransferItem {
+        TransferItem {
+            direction: Direction::Download,
+            local_location: server.local_dir().to_string_lossy().to_string(),
+            filename: name.into(),
+            remote_file: server.path(name),
+            size_bytes: size,
+            bytes_transferred: 0,
+            priority: 10,
+            status: TransferStatus::Queued,
+            batch: None,
+            follow: false,
+            speed_limit: 0,
+            profile: String::new(),
+            relay_to: None,
+            resume_check: None,
+            failure: None,
+            queued_at: 0,
+            timeline: Vec::new(),
+            note: String::new(),
+            label: None,
+        }
+    }
+
+    /// Wait for a terminal event for `remote_file`, skipping progress updates
+    async fn wait_for_end(
+        rx: &mut mpsc::Receiver<DownloadEvent>,
+        remote_file: &str,
+    ) -> DownloadEvent {
+        loop {
+            let event = timeout(Duration::from_secs(20), rx.recv())
+                .await
+                .expect("timed out waiting for download")
+                .expect("manager stopped");
+            match &event {
+                DownloadEvent::Completed { key }
+                | DownloadEvent::Failed { key, .. }
+                | DownloadEvent::Skipped { key, .. }
+                    if key.remote_file == remote_file =>
+                {
+                    return event
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_downloads_queued_item() {
+        let server = TestServer::start();
+        let contents = pattern(CHUNK_SIZE * 3 + 17);
+        server.write_file("movie.mkv", &contents);
+        let (tx, mut rx) = create_download_manager(server.config(), 0, None);
+
+        let item = queue_item(&server, "movie.mkv", contents.len() as u64);
+        tx.send(DownloadCommand::AddItem(Box::new(item.clone())))
+            .await
+            .unwrap();
+        tx.send(DownloadCommand::StartAll).await.unwrap();
+
+        let event = wait_for_end(&mut rx, &item.remote_file).await;
+        assert!(
+            matches!(event, DownloadEvent::Completed { .. }),
+            "{:?}",
+            event
+        );
+        let local = server.local_dir().join("movie.mkv");
+        assert_eq!(std::fs::read(local).unwrap(), contents);
+    }
+
+    #[tokio::test]
+    async fn test_reports_session_totals() {
+        let fs = MemoryFs::new();
+        fs.add_file("/srv/a.bin", vec![7; CHUNK_SIZE * 2]);
+        let (tx, mut rx) = spawn_download_manager(fs.connector(), 0, None);
+        let dir = scratch_dir("stats");
+        let item = memory_item(&dir, "/srv/a.bin", (CHUNK_SIZE * 2) as u64);
+        tx.send(DownloadCommand::AddItem(Box::new(item.clone())))
+            .await
+            .unwrap();
+        tx.send(DownloadCommand::StartAll).await.unwrap();
+        wait_for_end(&mut rx, &item.remote_file).await;
+
+        let stats = loop {
+            let event = timeout(Duration::from_secs(5), rx.recv()).await.unwrap();
+            if let Some(DownloadEvent::Stats(stats)) = event {
+                break stats;
+            }
+        };
+        assert_eq!(stats.bytes, (CHUNK_SIZE * 2) as u64);
+        assert_eq!((stats.active, stats.pending), (0, 0));
+        assert!(stats.busy_secs >= 1 || stats.bytes_per_sec > 0);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_same_file_to_two_folders() {
+        let fs = MemoryFs::new();
+        fs.add_file("/srv/a.bin", vec![7; 100]);
+        let (tx, mut rx) = spawn_download_manager(fs.connector(), 0, None);
+        let dir = scratch_dir("two-folders");
+        let (first, second) = (dir.join("one"), dir.join("two"));
+        for folder in [&first, &second] {
+            std::fs::create_dir_all(folder).unwrap();
+            let item = memory_item(folder, "/srv/a.bin", 100);
+            tx.send(DownloadCommand::AddItem(Box::new(item)))
+                .await
+                .unwrap();
+        }
+        tx.send(DownloadCommand::StartAll).await.unwrap();
+
+        for _ in 0..2 {
+            let event = wait_for_end(&mut rx, "/srv/a.bin").await;
+            assert!(
+                matches!(event, DownloadEvent::Completed { .. }),
+                "{:?}",
+                event
+            );
+        }
+        assert_eq!(std::fs::read(first.join("a.bin")).unwrap(), vec![7; 100]);
+        assert_eq!(std::fs::read(second.join("a.bin")).unwrap(), vec![7; 100]);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_resumes_from_partial_local_file() {
+        let server = TestServer::start();
+        let contents = pattern(CHUNK_SIZE * 2);
+        server.write_file("partial.bin", &contents);
+        // Half the file is already on disk; anything re-downloaded from 0 would be appended twice
+        std::fs::write(
+            server.local_dir().join("partial.bin"),
+            &contents[..CHUNK_SIZE],
+        )
+        .unwrap();
+        let (tx, mut rx) = create_download_manager(server.config(), 0, None);
+
+        let item = queue_item(&server, "partial.bin", contents.len() as u64);
+        tx.send(DownloadCommand::AddItem(Box::new(item.clone())))
+            .await
+            .unwrap();
+        tx.send(DownloadCommand::StartAll).await.unwrap();
+
+        let event = wait_for_end(&mut rx, &item.remote_file).await;
+        assert!(
+            matches!(event, DownloadEvent::Completed { .. }),
+            "{:?}",
+            event
+        );
+        assert_eq!(
+            std::fs::read(server.local_dir().join("partial.bin")).unwrap(),
+            contents
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cancel_stops_transfer() {
+        let server = TestServer::start();
+        let contents = pattern(CHUNK_SIZE * 20);
+        server.write_file("big.bin", &contents);
+        // 64 KB/s keeps the transfer running long enough to cancel it
+        let (tx, mut rx) = create_download_manager(server.config(), 64, None);
+
+        let item = queue_item(&server, "big.bin", contents.len() as u64);
+        tx.send(DownloadCommand::AddItem(Box::new(item.clone())))
+            .await
+            .unwrap();
+        tx.send(DownloadCommand::StartAll).await.unwrap();
+
+        // Cancel once the first chunk has landed
+        loop {
+            if let Some(DownloadEvent::Progress { .. }) = rx.recv().await {
+                break;
+            }
+        }
+        tx.send(DownloadCommand::Cancel(item.key())).await.unwrap();
+
+        let finished = timeout(
+            Duration::from_secs(3),
+            wait_for_end(&mut rx, &item.remote_file),
+        )
+        .await;
+        assert!(
+            finished.is_err(),
+            "cancelled item should not finish: {:?}",
+            finished
+        );
+        let local_len = std::fs::metadata(server.local_dir().join("big.bin"))
+            .unwrap()
+            .len();
+        assert!(local_len < contents.len() as u64);
+    }
+
+    #[tokio::test]
+    async fn test_respects_concurrency_limit() {
+        let fs = MemoryFs::new();
+        let local = scratch_dir("concurrency");
+        let items: Vec<_> = (0..4)
+            .map(|i| {
+                let remote = format!("/r/file{}.bin", i);
+                fs.add_file(&remote, pattern(CHUNK_SIZE * 2));
+                memory_item(&local, &remote, (CHUNK_SIZE * 2) as u64)
+            })
+            .collect();
+        // Throttle so transfers overlap
+        let (tx, mut rx) = spawn_download_manager(fs.connector(), 2048, None);
+        for item in &items {
+            tx.send(DownloadCommand::AddItem(Box::new(item.clone())))
+                .await
+                .unwrap();
+        }
+        tx.send(DownloadCommand::StartAll).await.unwrap();
+
+        let (mut running, mut max_running, mut completed) = (0usize, 0usize, 0usize);
+        while completed < items.len() {
+            match timeout(Duration::from_secs(10), rx.recv()).await.unwrap() {
+                Some(DownloadEvent::Started { .. }) => {
+                    running += 1;
+                    max_running = max_running.max(running);
+                }
+                Some(DownloadEvent::Completed { .. }) => {
+                    running -= 1;
+                    completed += 1;
+                }
+                Some(DownloadEvent::Failed { error, .. }) => {
+                    panic!("unexpected failure: {}", error)
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(max_running, MAX_CONCURRENT);
+        let _ = std::fs::remove_dir_all(local);
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_read_failures() {
+        let fs = MemoryFs::new();
+        let local = scratch_dir("retry");
+        let contents = pattern(CHUNK_SIZE + 5);
+        fs.add_file("/r/flaky.bin", contents.clone());
+        fs.fail_next_reads(2);
+        let (tx, mut rx) = spawn_download_manager(fs.connector(), 0, None);
+
+        let item = memory_item(&local, "/r/flaky.bin", contents.len() as u64);
+        tx.send(DownloadCommand::AddItem(Box::new(item.clone())))
+            .await
+            .unwrap();
+
+        let mut attempts = Vec::new();
+        loop {
+            match timeout(Duration::from_secs(10), rx.recv()).await.unwrap() {
+                Some(DownloadEvent::Retrying { attempt, .. }) => attempts.push(attempt),
+                Some(DownloadEvent::Completed { .. }) => break,
+                Some(DownloadEvent::Failed { error, .. }) => {
+                    panic!("unexpected failure: {}", error)
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(attempts, vec![1, 2]);
+        // One initial session plus one reconnect per retry
+        assert_eq!(fs.connects.load(Ordering::SeqCst), 3);
+        assert_eq!(std::fs::read(local.join("flaky.bin")).unwrap(), contents);
+        let _ = std::fs::remove_dir_all(local);
+    }
+
+    #[tokio::test]
+    async fn test_items_wait_for_their_server_to_connect() {
+        let home = MemoryFs::new();
+        let other = MemoryFs::new();
+        let local = scratch_dir("profiles");
+        home.add_file("/r/notes.txt", pattern(10));
+        other.add_file("/r/notes.txt", pattern(20));
+        let (tx, mut rx) = spawn_download_manager(home.connector(), 0, None);
+
+        let item = TransferItem {
+            profile: "me@other:22".into(),
+            ..memory_item(&local, "/r/notes.txt", 20)
+        };
+        tx.send(DownloadCommand::AddItem(Box::new(item)))
+            .await
+            .unwrap();
+        tx.send(DownloadCommand::StartAll).await.unwrap();
+        // Nothing starts; the only news is the item counted as waiting
+        while let Ok(event) = timeout(Duration::from_millis(100), rx.recv()).await {
+            assert!(
+                matches!(event, Some(DownloadEvent::Stats(_))),
+                "{:?}",
+                event
+            );
+        }
+
+        let connector = ProfileConnector(other.connector());
+        tx.send(DownloadCommand::AddProfile("me@other:22".into(), connector))
+            .await
+            .unwrap();
+        let event = wait_for_end(&mut rx, "/r/notes.txt").await;
+        assert!(
+            matches!(event, DownloadEvent::Completed { .. }),
+            "{:?}",
+            event
+        );
+        assert_eq!(home.reads.load(Ordering::SeqCst), 0);
+        assert_eq!(std::fs::read(local.join("notes.txt")).unwrap(), pattern(20));
+        let _ = std::fs::remove_dir_all(local);
+    }
+
+    #[tokio::test]
+    async fn test_profile_speed_limit_overrides_default() {
+        let limits = Limits {
+            default: Arc::new(AtomicU64::new(500)),
+            profiles: Default::default(),
+            items: Default::default(),
+        };
+        let key = |profile: &str| ItemKey {
+            profile: profile.into(),
+            remote_file: "/r/a.bin".into(),
+            ..Default::default()
+        };
+        assert_eq!(limits.for_item(&key("pi@localhost:22")).await, 500);
+
+        limits
+            .profiles
+            .lock()
+            .await
+            .insert("pi@localhost:22".into(), 0);
+        assert_eq!(limits.for_item(&key("pi@localhost:22")).await, 0); // unlimited for this server only
+        assert_eq!(limits.for_item(&key("pi@seedbox:22")).await, 500);
+
+        limits
+            .items
+            .lock()
+            .await
+            .insert(key("pi@localhost:22"), 200);
+        assert_eq!(limits.for_item(&key("pi@localhost:22")).await, 200);
+    }
+
+    #[tokio::test]
+    async fn test_same_path_from_two_servers_keeps_to_each_schedule() {
+        let home = MemoryFs::new();
+        let other = MemoryFs::new();
+        home.add_file("/r/a.bin", pattern(10));
+        other.add_file("/r/a.bin", pattern(30));
+        let local = scratch_dir("two-servers");
+        let (home_dir, other_dir) = (local.join("home"), local.join("other"));
+        std::fs::create_dir_all(&home_dir).unwrap();
+        std::fs::create_dir_all(&other_dir).unwrap();
+        let (tx, mut rx) = spawn_download_manager(home.connector(), 0, None);
+        let connector = ProfileConnector(other.connector());
+        tx.send(DownloadCommand::AddProfile("me@other:22".into(), connector))
+            .await
+            .unwrap();
+        let mut holds = ScheduleHolds::default();
+        holds.profiles.insert("me@other:22".into(), true);
+        tx.send(DownloadCommand::SetScheduleHolds(holds))
+            .await
+            .unwrap();
+
+        let from_other = TransferItem {
+            profile: "me@other:22".into(),
+            ..memory_item(&other_dir, "/r/a.bin", 30)
+        };
+        for item in [memory_item(&home_dir, "/r/a.bin", 10), from_other] {
+            tx.send(DownloadCommand::AddItem(Box::new(item)))
+                .await
+                .unwrap();
+        }
+        let event = wait_for_end(&mut rx, "/r/a.bin").await;
+        assert!(
+            matches!(&event, DownloadEvent::Completed { key } if key.profile.is_empty()),
+            "{:?}",
+            event
+        );
+        assert_eq!(other.reads.load(Ordering::SeqCst), 0);
+
+        // Its schedule opens: the other server's copy goes too
+        tx.send(DownloadCommand::SetScheduleHolds(ScheduleHolds::default()))
+            .await
+            .unwrap();
+        let event = wait_for_end(&mut rx, "/r/a.bin").await;
+        assert!(
+            matches!(&event, DownloadEvent::Completed { key } if key.profile == "me@other:22"),
+            "{:?}",
+            event
+        );
+        assert_eq!(std::fs::read(home_dir.join("a.bin")).unwrap(), pattern(10));
+        assert_eq!(std::fs::read(other_dir.join("a.bin")).unwrap(), pattern(30));
+        let _ = std::fs::remove_dir_all(local);
+    }
+
+    #[tokio::test]
+    async fn test_relay_copies_between_servers_and_resumes() {
+        let source = MemoryFs::new();
+        let dest = MemoryFs::new();
+        let contents = pattern(CHUNK_SIZE * 2 + 3);
+        source.add_file("/r/video.mkv", contents.clone());
+        // An earlier attempt got the first chunk across
+        dest.add_file("/in/video.mkv", contents[..CHUNK_SIZE].to_vec());
+        let (tx, mut rx) = spawn_download_manager(source.connector(), 0, None);
+        tx.send(DownloadCommand::AddProfile(
+            "me@dest:22".into(),
+            ProfileConnector(dest.connector()),
+        ))
+        .await
+        .unwrap();
+
+        let item = TransferItem {
+            direction: Direction::Upload,
+            relay_to: Some(RelayTarget {
+                profile: "me@dest:22".into(),
+                remote_file: "/in/video.mkv".into(),
+            }),
+            resume_check: None,
+            failure: None,
+            ..memory_item(Path::new(""), "/r/video.mkv", contents.len() as u64)
+        };
+        tx.send(DownloadCommand::AddItem(Box::new(item)))
+            .await
+            .unwrap();
+
+        let event = wait_for_end(&mut rx, "/r/video.mkv").await;
+        assert!(
+            matches!(event, DownloadEvent::Completed { .. }),
+            "{:?}",
+            event
+        );
+        assert_eq!(dest.file("/in/video.mkv").unwrap(), contents);
+        // The rest of the file and the EOF check; the first chunk wasn't read again
+        assert_eq!(source.reads.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_stalled_read_is_abandoned_and_restarted() {
+        let fs = MemoryFs::new();
+        let local = scratch_dir("stall");
+        let contents = pattern(CHUNK_SIZE * 2 + 7);
+        fs.add_file("/r/stuck.bin", contents.clone());
+        fs.stall_next_read(STALL_TIMEOUT * 3);
+        let (tx, mut rx) = spawn_download_manager(fs.connector(), 0, None);
+
+        let item = memory_item(&local, "/r/stuck.bin", contents.len() as u64);
+        tx.send(DownloadCommand::AddItem(Box::new(item)))
+            .await
+            .unwrap();
+
+        let mut stalls = Vec::new();
+        loop {
+            match timeout(Duration::from_secs(10), rx.recv()).await.unwrap() {
+                Some(DownloadEvent::Stalled { attempt, .. }) => stalls.push(attempt),
+                Some(DownloadEvent::Completed { .. }) => break,
+                Some(DownloadEvent::Failed { error, .. }) => {
+                    panic!("unexpected failure: {}", error)
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(stalls, vec![1]);
+        assert_eq!(fs.connects.load(Ordering::SeqCst), 2);
+        assert_eq!(std::fs::read(local.join("stuck.bin")).unwrap(), contents);
+        let _ = std::fs::remove_dir_all(local);
+    }
+
+    #[tokio::test]
+    async fn test_edited_partial_file_restarts_from_zero() {
+        let fs = MemoryFs::new();
+        let local = scratch_dir("edited");
+        let contents = pattern(CHUNK_SIZE * 2);
+        fs.add_file("/r/notes.bin", contents.clone());
+        // Paused halfway, then someone overwrote the end of the partial copy
+        let partial = local.join("notes.bin");
+        std::fs::write(&partial, &contents[..CHUNK_SIZE]).unwrap();
+        let check = ResumeCheck::record(&partial, CHUNK_SIZE as u64).unwrap();
+        let mut edited = contents[..CHUNK_SIZE].to_vec();
+        edited[CHUNK_SIZE - 1] ^= 0xff;
+        std::fs::write(&partial, edited).unwrap();
+        let (tx, mut rx) = spawn_download_manager(fs.connector(), 0, None);
+
+        let mut item = memory_item(&local, "/r/notes.bin", contents.len() as u64);
+        item.bytes_transferred = CHUNK_SIZE as u64;
+        item.resume_check = Some(check);
+        tx.send(DownloadCommand::AddItem(Box::new(item)))
+            .await
+            .unwrap();
+
+        let mut warned = false;
+        loop {
+            match timeout(Duration::from_secs(10), rx.recv()).await.unwrap() {
+                Some(DownloadEvent::PartialChanged { .. }) => warned = true,
+                Some(DownloadEvent::Completed { .. }) => break,
+                Some(DownloadEvent::Failed { error, .. }) => {
+                    panic!("unexpected failure: {}", error)
+                }
+                _ => {}
+            }
+        }
+        assert!(warned);
+        assert_eq!(std::fs::read(&partial).unwrap(), contents);
+        let _ = std::fs::remove_dir_all(local);
+    }
+
+    #[tokio::test]
+    async fn test_reuploaded_source_restarts_from_zero() {
+        let fs = MemoryFs::new();
+        let local = scratch_dir("reuploaded");
+        let old = pattern(CHUNK_SIZE * 2);
+        // Same size on the server, different bytes: only the tail comparison notices
+        let new: Vec<u8> = old.iter().map(|b| b ^ 0x55).collect();
+        fs.add_file("/r/show.mkv", new.clone());
+        let partial = local.join("show.mkv");
+        std::fs::write(&partial, &old[..CHUNK_SIZE]).unwrap();
+        let (tx, mut rx) = spawn_download_manager(fs.connector(), 0, None);
+
+        let mut item = memory_item(&local, "/r/show.mkv", new.len() as u64);
+        item.bytes_transferred = CHUNK_SIZE as u64;
+        tx.send(DownloadCommand::AddItem(Box::new(item)))
+            .await
+            .unwrap();
+
+        let mut warned = false;
+        loop {
+            match timeout(Duration::from_secs(10), rx.recv()).await.unwrap() {
+                Some(DownloadEvent::SourceChanged { .. }) => warned = true,
+                Some(DownloadEvent::Completed { .. }) => break,
+                Some(DownloadEvent::Failed { error, .. }) => {
+                    panic!("unexpected failure: {}", error)
+                }
+                _ => {}
+            }
+        }
+        assert!(warned);
+        assert_eq!(std::fs::read(&partial).unwrap(), new);
+        let _ = std::fs::remove_dir_all(local);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_retries() {
+        let fs = MemoryFs::new();
+        let local = scratch_dir("giveup");
+        fs.add_file("/r/broken.bin", pattern(10));
+        fs.fail_next_reads(usize::MAX);
+        let (tx, mut rx) = spawn_download_manager(fs.connector(), 0, None);
+
+        let item = memory_item(&local, "/r/broken.bin", 10);
+        tx.send(DownloadCommand::AddItem(Box::new(item.clone())))
+            .await
+            .unwrap();
+
+        let event = wait_for_end(&mut rx, "/r/broken.bin").await;
+        assert!(matches!(event, DownloadEvent::Failed { .. }), "{:?}", event);
+        assert_eq!(fs.reads.load(Ordering::SeqCst), MAX_RETRIES as usize + 1);
+        let _ = std::fs::remove_dir_all(local);
+    }
+
+    #[tokio::test]
+    async fn test_pause_and_resume_continue_from_offset() {
+        let fs = MemoryFs::new();
+        let local = scratch_dir("pause");
+        let contents = pattern(CHUNK_SIZE * 6);
+        fs.add_file("/r/movie.mkv", contents.clone());
+        let (tx, mut rx) = spawn_download_manager(fs.connector(), 1024, None);
+
+        let item = memory_item(&local, "/r/movie.mkv", contents.len() as u64);
+        tx.send(DownloadCommand::AddItem(Box::new(item.clone())))
+            .await
+            .unwrap();
+
+        loop {
+            if let Some(DownloadEvent::Progress { .. }) = rx.recv().await {
+                break;
+            }
+        }
+        tx.send(DownloadCommand::Pause(item.key())).await.unwrap();
+        loop {
+            match timeout(Duration::from_secs(10), rx.recv()).await.unwrap() {
+                Some(DownloadEvent::Paused { .. }) => break,
+                Some(DownloadEvent::Completed { .. }) => panic!("finished before pausing"),
+                _ => {}
+            }
+        }
+        let reads_while_paused = fs.reads.load(Ordering::SeqCst);
+        // Buffered chunks must reach the disk before the pause offset is recorded
+        let on_disk = std::fs::metadata(local.join("movie.mkv")).unwrap().len();
+        assert_eq!(on_disk, (reads_while_paused * CHUNK_SIZE) as u64);
+
+        tx.send(DownloadCommand::Resume(item.key())).await.unwrap();
+        let event = wait_for_end(&mut rx, &item.remote_file).await;
+        assert!(
+            matches!(event, DownloadEvent::Completed { .. }),
+            "{:?}",
+            event
+        );
+        // Resuming must not start over: 6 data chunks, the EOF read and one
+        // read of the tail compared before resuming
+        assert!(reads_while_paused < 7);
+        assert_eq!(fs.reads.load(Ordering::SeqCst), 8);
+        assert_eq!(std::fs::read(local.join("movie.mkv")).unwrap(), contents);
+        let _ = std::fs::remove_dir_all(local);
+    }
+
+    #[tokio::test]
+    async fn test_holds_downloads_while_disk_is_low() {
+        let fs = MemoryFs::new();
+        let local = scratch_dir("diskguard");
+        fs.add_file("/r/a.bin", pattern(1000));
+        let (tx, mut rx) = spawn_download_manager(fs.connector(), 0, None);
+
+        // No disk has a petabyte free, so the guard trips straight away
+        tx.send(DownloadCommand::SetMinFreeSpace(1024 * 1024))
+            .await
+            .unwrap();
+        let item = memory_item(&local, "/r/a.bin", 1000);
+        tx.send(DownloadCommand::AddItem(Box::new(item.clone())))
+            .await
+            .unwrap();
+        let event = timeout(Duration::from_secs(5), rx.recv()).await.unwrap();
+        assert!(
+            matches!(event, Some(DownloadEvent::LowDiskSpace { .. })),
+            "{:?}",
+            event
+        );
+        assert_eq!(fs.connects.load(Ordering::SeqCst), 0);
+
+        tx.send(DownloadCommand::SetMinFreeSpace(0)).await.unwrap();
+        let event = timeout(Duration::from_secs(5), rx.recv()).await.unwrap();
+        assert!(matches!(event, Some(DownloadEvent::DiskSpaceRecovered)));
+        let event = wait_for_end(&mut rx, &item.remote_file).await;
+        assert!(
+            matches!(event, DownloadEvent::Completed { .. }),
+            "{:?}",
+            event
+        );
+        let _ = std::fs::remove_dir_all(local);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_skips_overwrites_only() {
+        let fs = MemoryFs::new();
+        let local = scratch_dir("dry-run");
+        fs.add_file("/r/old.bin", pattern(100));
+        fs.add_file("/r/new.bin", pattern(100));
+        std::fs::write(local.join("old.bin"), b"keep me").unwrap();
+        let (tx, mut rx) = spawn_download_manager(fs.connector(), 0, None);
+        tx.send(DownloadCommand::SetDryRun(true)).await.unwrap();
+
+        for name in ["old", "new"] {
+            let item = memory_item(&local, &format!("/r/{}.bin", name), 100);
+            tx.send(DownloadCommand::AddItem(Box::new(item)))
+                .await
+                .unwrap();
+            let event = wait_for_end(&mut rx, &format!("/r/{}.bin", name)).await;
+            let skipped = matches!(event, DownloadEvent::Skipped { .. });
+            assert_eq!(skipped, name == "old", "{:?}", event);
+        }
+        assert_eq!(std::fs::read(local.join("old.bin")).unwrap(), b"keep me");
+        assert_eq!(std::fs::read(local.join("new.bin")).unwrap(), pattern(100));
+        let _ = std::fs::remove_dir_all(local);
+    }
+
+    #[tokio::test]
+    async fn test_resume_discards_writes_past_journaled_offset() {
+        let fs = MemoryFs::new();
+        let local = scratch_dir("journal");
+        let contents = pattern(1000);
+        fs.add_file("/r/a.bin", contents.clone());
+        // The process died after writing 500 bytes, but only 300 were journaled
+        let mut partial = contents[..300].to_vec();
+        partial.extend_from_slice(&[0xAA; 200]);
+        std::fs::write(local.join("a.bin"), &partial).unwrap();
+
+        let mut item = memory_item(&local, "/r/a.bin", 1000);
+        item.bytes_transferred = 300;
+        let journal = Arc::new(Journal::open(local.join("transfers.journal")).unwrap());
+        journal.record(&item.key().id(), 300);
+        let (tx, mut rx) = spawn_download_manager(fs.connector(), 0, Some(journal));
+
+        tx.send(DownloadCommand::AddItem(Box::new(item.clone())))
+            .await
+            .unwrap();
+        let event = wait_for_end(&mut rx, &item.remote_file).await;
+        assert!(
+            matches!(event, DownloadEvent::Completed { .. }),
+            "{:?}",
+            event
+        );
+        assert_eq!(std::fs::read(local.join("a.bin")).unwrap(), contents);
+
+        // Finished transfers drop out of the journal
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(crate::journal::replay(&local.join("transfers.journal")).is_empty());
+        let _ = std::fs::remove_dir_all(local);
+    }
+
+    #[tokio::test]
+    async fn test_skips_already_downloaded_file() {
+        let fs = MemoryFs::new();
+        let local = scratch_dir("skip");
+        let contents = pattern(100);
+        fs.add_file("/r/done.txt", contents.clone());
+        std::fs::write(local.join("done.txt"), &contents).unwrap();
+        let (tx, mut rx) = spawn_download_manager(fs.connector(), 0, None);
+
+        let item = memory_item(&local, "/r/done.txt", 100);
+        tx.send(DownloadCommand::AddItem(Box::new(item.clone())))
+            .await
+            .unwrap();
+
+        let event = wait_for_end(&mut rx, "/r/done.txt").await;
+        assert!(
+            matches!(event, DownloadEvent::Skipped { .. }),
+            "{:?}",
+            event
+        );
+        assert_eq!(fs.connects.load(Ordering::SeqCst), 0);
+        let _ = std::fs::remove_dir_all(local);
+    }
+
+    #[tokio::test]
+    async fn test_uploads_local_file() {
+        let fs = MemoryFs::new();
+        let local = scratch_dir("upload");
+        let contents = pattern(CHUNK_SIZE * 2 + 3);
+        std::fs::write(local.join("holiday.jpg"), &contents).unwrap();
+        let (tx, mut rx) = spawn_download_manager(fs.connector(), 0, None);
+
+        let item = TransferItem {
+            direction: Direction::Upload,
+            ..memory_item(&local, "/r/holiday.jpg", contents.len() as u64)
+        };
+        tx.send(DownloadCommand::AddItem(Box::new(item)))
+            .await
+            .unwrap();
+
+        let event = wait_for_end(&mut rx, "/r/holiday.jpg").await;
+        assert!(
+            matches!(event, DownloadEvent::Completed { .. }),
+            "{:?}",
+            event
+        );
+        assert_eq!(fs.file("/r/holiday.jpg").unwrap(), contents);
+        let _ = std::fs::remove_dir_all(local);
+    }
+
+    #[tokio::test]
+    async fn test_upload_resumes_only_its_own_partial_copy() {
+        let fs = MemoryFs::new();
+        let local = scratch_dir("upload-resume");
+        let contents = pattern(CHUNK_SIZE * 2 + 3);
+        std::fs::write(local.join("stale.jpg"), &contents).unwrap();
+        std::fs::write(local.join("paused.jpg"), &contents).unwrap();
+        // Something else already sits at the remote path, shorter than ours
+        fs.add_file("/r/stale.jpg", vec![b'x'; CHUNK_SIZE]);
+        fs.add_file("/r/paused.jpg", contents[..CHUNK_SIZE].to_vec());
+        let (tx, mut rx) = spawn_download_manager(fs.connector(), 0, None);
+
+        let fresh = TransferItem {
+            direction: Direction::Upload,
+            ..memory_item(&local, "/r/stale.jpg", contents.len() as u64)
+        };
+        let resumed = TransferItem {
+            direction: Direction::Upload,
+            bytes_transferred: CHUNK_SIZE as u64,
+            ..memory_item(&local, "/r/paused.jpg", contents.len() as u64)
+        };
+        for item in [fresh, resumed] {
+            let remote = item.remote_file.clone();
+            tx.send(DownloadCommand::AddItem(Box::new(item)))
+                .await
+                .unwrap();
+            let event = wait_for_end(&mut rx, &remote).await;
+            assert!(
+                matches!(event, DownloadEvent::Completed { .. }),
+                "{:?}",
+                event
+            );
+            assert_eq!(fs.file(&remote).unwrap(), contents);
+        }
+        let _ = std::fs::remove_dir_all(local);
+    }
+
+    #[tokio::test]
+    async fn test_empty_files_complete_both_ways() {
+        let fs = MemoryFs::new();
+        let local = scratch_dir("empty");
+        fs.add_file("/r/empty.nfo", Vec::new());
+        std::fs::write(local.join("blank.txt"), b"").unwrap();
+        let (tx, mut rx) = spawn_download_manager(fs.connector(), 0, None);
+
+        tx.send(DownloadCommand::AddItem(Box::new(memory_item(
+            &local,
+            "/r/empty.nfo",
+            0,
+        ))))
+        .await
+        .unwrap();
+        let event = wait_for_end(&mut rx, "/r/empty.nfo").await;
+        assert!(
+            matches!(event, DownloadEvent::Completed { .. }),
+            "{:?}",
+            event
+        );
+        assert_eq!(std::fs::read(local.join("empty.nfo")).unwrap(), b"");
+
+        let item = TransferItem {
+            direction: Direction::Upload,
+            ..memory_item(&local, "/r/blank.txt", 0)
+        };
+        tx.send(DownloadCommand::AddItem(Box::new(item)))
+            .await
+            .unwrap();
+        let event = wait_for_end(&mut rx, "/r/blank.txt").await;
+        assert!(
+            matches!(event, DownloadEvent::Completed { .. }),
+            "{:?}",
+            event
+        );
+        assert_eq!(fs.file("/r/blank.txt"), Some(Vec::new()));
+        let _ = std::fs::remove_dir_all(local);
+    }
+
+    #[tokio::test]
+    async fn test_lower_priority_number_starts_first() {
+        let fs = MemoryFs::new();
+        let local = scratch_dir("priority");
+        let (tx, mut rx) = spawn_download_manager(fs.connector(), 0, None);
+        // Hold the queue so every item is in place before the first pick
+        tx.send(DownloadCommand::PauseAll).await.unwrap();
+        for (name, priority) in [("a", 1), ("b", 5), ("c", 5), ("d", 20)] {
+            let remote = format!("/r/{}.bin", name);
+            fs.add_file(&remote, pattern(10));
+            let item = TransferItem {
+                priority,
+                ..memory_item(&local, &remote, 10)
+            };
+            tx.send(DownloadCommand::AddItem(Box::new(item)))
+                .await
+                .unwrap();
+        }
+        tx.send(DownloadCommand::StartAll).await.unwrap();
+
+        let mut started = Vec::new();
+        while started.len() < 4 {
+            if let Some(DownloadEvent::Started { key }) =
+                timeout(Duration::from_secs(10), rx.recv()).await.unwrap()
+            {
+                started.push(key.remote_file);
+            }
+        }
+        assert_eq!(&started[..2], ["/r/a.bin", "/r/b.bin"]);
+        let _ = std::fs::remove_dir_all(local);
+    }
+
+    #[tokio::test]
+    async fn test_small_file_passes_busy_slots_in_fast_lane() {
+        let fs = MemoryFs::new();
+        let local = scratch_dir("fast-lane");
+        let (tx, mut rx) = spawn_download_manager(fs.connector(), 2048, None);
+        tx.send(DownloadCommand::SetFastLane(1024)).await.unwrap();
+        for (name, size) in [
+            ("big1", CHUNK_SIZE * 8),
+            ("big2", CHUNK_SIZE * 8),
+            ("tiny", 10),
+        ] {
+            let remote = format!("/r/{}.bin", name);
+            fs.add_file(&remote, pattern(size));
+            let item = memory_item(&local, &remote, size as u64);
+            tx.send(DownloadCommand::AddItem(Box::new(item)))
+                .await
+                .unwrap();
+        }
+        tx.send(DownloadCommand::StartAll).await.unwrap();
+
+        let mut order = Vec::new();
+        while order.len() < 4 {
+            match timeout(Duration::from_secs(10), rx.recv()).await.unwrap() {
+                Some(DownloadEvent::Started { key }) => order.push(key.remote_file),
+                Some(DownloadEvent::Completed { key }) => {
+                    order.push(format!("done {}", key.remote_file))
+                }
+                _ => {}
+            }
+        }
+        // Both big files hold the regular slots; the tiny one doesn't wait for them
+        assert_eq!(
+            order,
+            [
+                "/r/big1.bin",
+                "/r/big2.bin",
+                "/r/tiny.bin",
+                "done /r/tiny.bin"
+            ]
+        );
+        let _ = std::fs::remove_dir_all(local);
+    }
+
+    #[tokio::test]
+    async fn test_fair_mode_interleaves_batches() {
+        let fs = MemoryFs::new();
+        let local = scratch_dir("fair");
+        let (tx, mut rx) = spawn_download_manager(fs.connector(), 0, None);
+        tx.send(DownloadCommand::PauseAll).await.unwrap();
+        tx.send(DownloadCommand::SetFairness(true)).await.unwrap();
+        for (id, name) in [(1, "a1"), (1, "a2"), (1, "a3"), (2, "b1"), (2, "b2")] {
+            let remote = format!("/r/{}.bin", name);
+            fs.add_file(&remote, pattern(10));
+            let item = TransferItem {
+                batch: Some(Batch {
+                    id,
+                    name: format!("batch {}", id),
+                }),
+                ..memory_item(&local, &remote, 10)
+            };
+            tx.send(DownloadCommand::AddItem(Box::new(item)))
+                .await
+                .unwrap();
+        }
+        tx.send(DownloadCommand::StartAll).await.unwrap();
+
+        let mut started = Vec::new();
+        while started.len() < 5 {
+            if let Some(DownloadEvent::Started { key }) =
+                timeout(Duration::from_secs(10), rx.recv()).await.unwrap()
+            {
+                started.push(key.remote_file);
+            }
+        }
+        // Without turns, a1 and a2 would take both slots ahead of b1
+        assert_eq!(&started[..2], ["/r/a1.bin", "/r/b1.bin"]);
+        let _ = std::fs::remove_dir_all(local);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_speed_limit_throttles_transfer() {
+        let fs = MemoryFs::new();
+        let local = scratch_dir("throttle");
+        let contents = pattern(CHUNK_SIZE * 4);
+        fs.add_file("/r/slow.bin", contents.clone());
+        // 256 KB at 512 KB/s takes half a second, on tokio's paused clock
+        let (tx, mut rx) = spawn_download_manager(fs.connector(), 512, None);
+
+        let started = tokio::time::Instant::now();
+        let item = memory_item(&local, "/r/slow.bin", contents.len() as u64);
+        tx.send(DownloadCommand::AddItem(Box::new(item.clone())))
+            .await
+            .unwrap();
+        let event = wait_for_end(&mut rx, "/r/slow.bin").await;
+
+        assert!(
+            matches!(event, DownloadEvent::Completed { .. }),
+            "{:?}",
+            event
+        );
+        assert!(started.elapsed() >= Duration::from_millis(500));
+        let _ = std::fs::remove_dir_all(local);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_item_speed_cap_applies_under_global_limit() {
+        let fs = MemoryFs::new();
+        let local = scratch_dir("item_cap");
+        let contents = pattern(CHUNK_SIZE * 4);
+        fs.add_file("/r/capped.bin", contents.clone());
+        // The global limit alone would take 62ms; the item's 512 KB/s half a second
+        let (tx, mut rx) = spawn_download_manager(fs.connector(), 4096, None);
+
+        let started = tokio::time::Instant::now();
+        let item = TransferItem {
+            speed_limit: 512,
+            ..memory_item(&local, "/r/capped.bin", contents.len() as u64)
+        };
+        tx.send(DownloadCommand::AddItem(Box::new(item)))
+            .await
+            .unwrap();
+        let event = wait_for_end(&mut rx, "/r/capped.bin").await;
+
+        assert!(
+            matches!(event, DownloadEvent::Completed { .. }),
+            "{:?}",
+            event
+        );
+        assert!(started.elapsed() >= Duration::from_millis(500));
+        assert_eq!(tighter_limit(0, 512), 512);
+        assert_eq!(tighter_limit(300, 512), 300);
+        let _ = std::fs::remove_dir_all(local);
+    }
+
+    #[tokio::test]
+    async fn test_fails_when_remote_changes_mid_download() {
+        let fs = MemoryFs::new();
+        let local = scratch_dir("changed");
+        let contents = pattern(CHUNK_SIZE * 8);
+        fs.add_file("/r/growing.log", contents.clone());
+        // Slow enough to swap the file out while the transfer runs
+        let (tx, mut rx) = spawn_download_manager(fs.connector(), 1024, None);
+
+        let item = memory_item(&local, "/r/growing.log", contents.len() as u64);
+        tx.send(DownloadCommand::AddItem(Box::new(item.clone())))
+            .await
+            .unwrap();
+        loop {
+            if let Some(DownloadEvent::Progress { .. }) = rx.recv().await {
+                break;
+            }
+        }
+        let replaced: Vec<u8> = contents.iter().map(|b| b.wrapping_add(1)).collect();
+        fs.add_file("/r/growing.log", replaced.clone());
+
+        let event = wait_for_end(&mut rx, "/r/growing.log").await;
+        assert!(
+            matches!(
+                event,
+                DownloadEvent::Failed {
+                    error: DownloadError::RemoteChanged,
+                    ..
+                }
+            ),
+            "{:?}",
+            event
+        );
+        assert!(!local.join("growing.log").exists());
+
+        // Retrying starts over and gets the new version whole
+        tx.send(DownloadCommand::AddItem(Box::new(item)))
+            .await
+            .unwrap();
+        let event = wait_for_end(&mut rx, "/r/growing.log").await;
+        assert!(
+            matches!(event, DownloadEvent::Completed { .. }),
+            "{:?}",
+            event
+        );
+        assert_eq!(std::fs::read(local.join("growing.log")).unwrap(), replaced);
+        let _ = std::fs::remove_dir_all(local);
+    }
+
+    #[tokio::test]
+    async fn test_follow_waits_for_file_to_stop_growing() {
+        let fs = MemoryFs::new();
+        let local = scratch_dir("follow");
+        let mut contents = pattern(CHUNK_SIZE + 10);
+        fs.add_file("/r/recording.ts", contents.clone());
+        let (tx, mut rx) = spawn_download_manager(fs.connector(), 0, None);
+        tx.send(DownloadCommand::SetFollowStable(Duration::from_millis(300)))
+            .await
+            .unwrap();
+
+        let item = TransferItem {
+            follow: true,
+            ..memory_item(&local, "/r/recording.ts", contents.len() as u64)
+        };
+        tx.send(DownloadCommand::AddItem(Box::new(item)))
+            .await
+            .unwrap();
+
+        // Still being written: what arrives after the first EOF is picked up too
+        loop {
+            if let Some(DownloadEvent::Progress {
+                bytes_downloaded, ..
+            }) = rx.recv().await
+            {
+                if bytes_downloaded == contents.len() as u64 {
+                    break;
+                }
+            }
+        }
+        contents.extend_from_slice(&pattern(5000));
+        fs.add_file("/r/recording.ts", contents.clone());
+
+        let event = wait_for_end(&mut rx, "/r/recording.ts").await;
+        assert!(
+            matches!(event, DownloadEvent::Completed { .. }),
+            "{:?}",
+            event
+        );
+        assert_eq!(std::fs::read(local.join("recording.ts")).unwrap(), contents);
+        let _ = std::fs::remove_dir_all(local);
+    }
+}
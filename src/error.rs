@@ -0,0 +1,240 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+// libssh2 session error codes (LIBSSH2_ERROR_*)
+const SESSION_KEX_FAILURE: i32 = -5;
+const SESSION_SOCKET_SEND: i32 = -7;
+const SESSION_TIMEOUT: i32 = -9;
+const SESSION_SOCKET_DISCONNECT: i32 = -13;
+const SESSION_FILE: i32 = -16;
+const SESSION_AUTHENTICATION_FAILED: i32 = -18;
+const SESSION_PUBLICKEY_UNVERIFIED: i32 = -19;
+const SESSION_SOCKET_TIMEOUT: i32 = -30;
+const SESSION_SOCKET_RECV: i32 = -43;
+
+// SFTP status codes (LIBSSH2_FX_*)
+const FX_NO_SUCH_FILE: i32 = 2;
+const FX_PERMISSION_DENIED: i32 = 3;
+const FX_NO_CONNECTION: i32 = 6;
+const FX_CONNECTION_LOST: i32 = 7;
+const FX_NO_SUCH_PATH: i32 = 10;
+
+/// Errors raised while talking to the SFTP server.
+///
+/// ssh2 errors are not `Clone`, so the original message is kept as a String and
+/// the classification lives in the variant.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum SftpError {
+    #[error("Failed to connect to host: {0}")]
+    Connection(String),
+    #[error("Handshake failed: {0}")]
+    Handshake(String),
+    #[error("Authentication failed: {0}")]
+    Auth(String),
+    #[error("Not found: {0}")]
+    NotFound(String),
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+    #[error("Connection lost: {0}")]
+    ConnectionLost(String),
+    #[error("Local file error: {0}")]
+    LocalIo(String),
+    #[error("SFTP error: {0}")]
+    Protocol(String),
+    #[error("Internal error: {0}")]
+    Internal(String),
+    #[error("Cancelled")]
+    Cancelled,
+}
+
+impl SftpError {
+    /// Classify an ssh2 error using its libssh2 code, prefixing the message with `context`
+    pub fn from_ssh(context: &str, e: ssh2::Error) -> Self {
+        let msg = format!("{}: {}", context, e.message());
+        match e.code() {
+            ssh2::ErrorCode::Session(code) => match code {
+                SESSION_AUTHENTICATION_FAILED | SESSION_PUBLICKEY_UNVERIFIED => Self::Auth(msg),
+                SESSION_KEX_FAILURE => Self::Handshake(msg),
+                SESSION_SOCKET_SEND
+                | SESSION_SOCKET_RECV
+                | SESSION_SOCKET_DISCONNECT
+                | SESSION_TIMEOUT
+                | SESSION_SOCKET_TIMEOUT => Self::ConnectionLost(msg),
+                _ => Self::Protocol(msg),
+            },
+            ssh2::ErrorCode::SFTP(code) => match code {
+                FX_NO_SUCH_FILE | FX_NO_SUCH_PATH => Self::NotFound(msg),
+                FX_PERMISSION_DENIED => Self::PermissionDenied(msg),
+                FX_NO_CONNECTION | FX_CONNECTION_LOST => Self::ConnectionLost(msg),
+                _ => Self::Protocol(msg),
+            },
+        }
+    }
+
+    /// Classify a failed login attempt: only a refusal or an unreadable key
+    /// file is Auth. Anything else (a dropped socket, a timeout) is a
+    /// connection problem worth retrying.
+    pub fn from_auth(e: ssh2::Error) -> Self {
+        match e.code() {
+            ssh2::ErrorCode::Session(
+                SESSION_AUTHENTICATION_FAILED | SESSION_PUBLICKEY_UNVERIFIED | SESSION_FILE,
+            ) => Self::Auth(e.message().to_string()),
+            _ => Self::Connection(format!("Login interrupted: {}", e.message())),
+        }
+    }
+
+    /// Classify an I/O error coming from a remote file handle
+    pub fn from_remote_io(context: &str, e: std::io::Error) -> Self {
+        let msg = format!("{}: {}", context, e);
+        match e.kind() {
+            std::io::ErrorKind::NotFound => Self::NotFound(msg),
+            std::io::ErrorKind::PermissionDenied => Self::PermissionDenied(msg),
+            _ => Self::ConnectionLost(msg),
+        }
+    }
+
+    pub fn local(context: &str, e: std::io::Error) -> Self {
+        Self::LocalIo(format!("{}: {}", context, e))
+    }
+
+    /// Transient failures that are worth reconnecting and trying again
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Self::Connection(_) | Self::ConnectionLost(_) | Self::Protocol(_)
+        )
+    }
+
+    pub fn is_auth(&self) -> bool {
+        matches!(self, Self::Auth(_))
+    }
+
+    /// What to tell the user when a folder won't open; the raw server text
+    /// still goes to the Activity Log
+    pub fn folder_message(&self) -> String {
+        match self {
+            Self::NotFound(_) => "This folder doesn't exist (any more).".into(),
+            Self::PermissionDenied(_) => "You don't have permission to open this folder.".into(),
+            Self::ConnectionLost(_) => "The connection dropped while listing this folder.".into(),
+            other => other.to_string(),
+        }
+    }
+}
+
+impl From<tokio::task::JoinError> for SftpError {
+    fn from(e: tokio::task::JoinError) -> Self {
+        Self::Internal(e.to_string())
+    }
+}
+
+/// Errors that end a single queued transfer.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum DownloadError {
+    #[error(transparent)]
+    Sftp(#[from] SftpError),
+    #[error("Size mismatch: expected {expected} bytes, got {actual}")]
+    SizeMismatch { expected: u64, actual: u64 },
+    #[error("Remote file changed during download; partial copy discarded, retry to start over")]
+    RemoteChanged,
+}
+
+impl DownloadError {
+    pub fn is_auth(&self) -> bool {
+        matches!(self, Self::Sftp(e) if e.is_auth())
+    }
+
+    pub fn kind(&self) -> FailureKind {
+        match self {
+            Self::Sftp(e) => match e {
+                SftpError::Connection(_)
+                | SftpError::ConnectionLost(_)
+                | SftpError::Handshake(_)
+                | SftpError::Protocol(_) => FailureKind::Network,
+                SftpError::Auth(_) => FailureKind::Login,
+                SftpError::PermissionDenied(_) => FailureKind::Permission,
+                SftpError::NotFound(_) => FailureKind::Missing,
+                SftpError::LocalIo(_) => FailureKind::LocalDisk,
+                SftpError::Internal(_) | SftpError::Cancelled => FailureKind::Other,
+            },
+            Self::SizeMismatch { .. } | Self::RemoteChanged => FailureKind::Changed,
+        }
+    }
+}
+
+/// Broad reason a transfer failed, kept on the queue item next to the error text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FailureKind {
+    Network,
+    Login,
+    Permission,
+    Missing,
+    Changed, // the source changed while it was copied
+    LocalDisk,
+    Other,
+}
+
+impl FailureKind {
+    /// Failures a plain retry is likely to get past
+    pub fn is_transient(self) -> bool {
+        matches!(self, FailureKind::Network | FailureKind::Changed)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FailureKind::Network => "Network",
+            FailureKind::Login => "Login",
+            FailureKind::Permission => "Permission",
+            FailureKind::Missing => "Missing",
+            FailureKind::Changed => "Changed",
+            FailureKind::LocalDisk => "Local disk",
+            FailureKind::Other => "Other",
+        }
+    }
+
+    /// Next step offered in the failure report
+    pub fn suggestion(self) -> &'static str {
+        match self {
+            FailureKind::Network => "Retry; the connection dropped",
+            FailureKind::Login => "Update your credentials in Settings, then retry",
+            FailureKind::Permission => "Check the file's permissions on the server, then retry",
+            FailureKind::Missing => "The file is gone from the server; remove it from the queue",
+            FailureKind::Changed => "Retry once the file has stopped changing",
+            FailureKind::LocalDisk => "Check free space and the download folder, then retry",
+            FailureKind::Other => "See the Activity Log for details",
+        }
+    }
+}
+
+impl From<tokio::task::JoinError> for DownloadError {
+    fn from(e: tokio::task::JoinError) -> Self {
+        Self::Sftp(e.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ssh_error_classification() {
+        let missing = ssh2::Error::new(ssh2::ErrorCode::SFTP(FX_NO_SUCH_FILE), "no such file");
+        assert!(matches!(
+            SftpError::from_ssh("Stat failed", missing),
+            SftpError::NotFound(_)
+        ));
+
+        let auth = ssh2::Error::new(
+            ssh2::ErrorCode::Session(SESSION_AUTHENTICATION_FAILED),
+            "auth failed",
+        );
+        let err = SftpError::from_ssh("Login", auth);
+        assert!(err.is_auth());
+        assert!(!err.is_retryable());
+
+        let dropped = ssh2::Error::new(
+            ssh2::ErrorCode::Session(SESSION_SOCKET_DISCONNECT),
+            "disconnected",
+        );
+        assert!(SftpError::from_ssh("Read", dropped).is_retryable());
+    }
+}
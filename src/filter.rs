@@ -0,0 +1,73 @@
+/// Shell-style match of `name` against `pattern`: `*` matches any run of
+/// characters, `?` exactly one. Case-insensitive, since media extensions come
+/// in every casing (`.NFO`, `.nfo`).
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+
+    let (mut p, mut n) = (0, 0);
+    // Where the last `*` was and which name position it is currently swallowing up to
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    n = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// True if `name` matches any of the exclude `patterns`
+pub fn is_excluded(patterns: &[String], name: &str) -> bool {
+    patterns.iter().any(|p| glob_match(p, name))
+}
+
+/// Parse a comma separated pattern list as typed in the UI ("*.nfo, sample*")
+pub fn parse_patterns(input: &str) -> Vec<String> {
+    input
+        .split(',')
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.nfo", "movie.nfo"));
+        assert!(glob_match("*.nfo", "Movie.NFO"));
+        assert!(!glob_match("*.nfo", "movie.nfo.mkv"));
+        assert!(glob_match("sample*", "sample-1080p.mkv"));
+        assert!(glob_match("s??e*", "s01e02.mkv"));
+        assert!(glob_match("*a*b*", "xxaxxbxx"));
+        assert!(!glob_match("*a*b", "xxaxxbxx"));
+        assert!(glob_match("*", ""));
+    }
+
+    #[test]
+    fn test_parse_patterns() {
+        assert_eq!(
+            parse_patterns(" *.nfo, ,sample* "),
+            vec!["*.nfo".to_string(), "sample*".to_string()]
+        );
+        assert!(is_excluded(&parse_patterns("*.txt,*.nfo"), "info.NFO"));
+    }
+}
@@ -0,0 +1,107 @@
+//! One running copy per user. A later launch with sftp:// links or --job names
+//! hands its arguments over a loopback socket to the copy already open and
+//! exits, instead of starting a second app on the same queue and journal.
+//!
+//! The running copy writes its port and a random token to instance.port in the
+//! per-user config folder; only someone who can read that file gets heard.
+
+use crate::settings;
+use once_cell::sync::OnceCell;
+use sha2::{Digest, Sha256};
+use std::hash::{BuildHasher, RandomState};
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, Shutdown, TcpListener, TcpStream};
+use std::time::Duration;
+
+const PORT_FILE: &str = "instance.port";
+const TIMEOUT: Duration = Duration::from_secs(2);
+const ACK: &[u8] = b"ok";
+
+static LISTENER: OnceCell<(TcpListener, String)> = OnceCell::new();
+
+/// Give `args` to the copy already running, if there is one. False means this
+/// launch should start the app itself.
+pub fn forward(args: &[String]) -> bool {
+    let Ok(text) = std::fs::read_to_string(settings::data_file(PORT_FILE)) else {
+        return false;
+    };
+    let Some((port, token)) = text.trim().split_once(' ') else {
+        return false;
+    };
+    let Ok(port) = port.parse::<u16>() else {
+        return false;
+    };
+    let address = (Ipv4Addr::LOCALHOST, port).into();
+    let Ok(mut stream) = TcpStream::connect_timeout(&address, TIMEOUT) else {
+        return false; // left over from a copy that has exited
+    };
+    let _ = stream.set_read_timeout(Some(TIMEOUT));
+    let message = std::iter::once(token)
+        .chain(args.iter().map(String::as_str))
+        .collect::<Vec<_>>()
+        .join("\n");
+    if stream.write_all(message.as_bytes()).is_err() || stream.shutdown(Shutdown::Write).is_err() {
+        return false;
+    }
+    // Whatever took the port over since must say it understood
+    let mut reply = Vec::new();
+    let _ = stream.read_to_end(&mut reply);
+    reply == ACK
+}
+
+/// Become the copy later launches forward to
+pub fn listen() {
+    let Ok(listener) = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)) else {
+        return;
+    };
+    let Ok(address) = listener.local_addr() else {
+        return;
+    };
+    if listener.set_nonblocking(true).is_err() {
+        return;
+    }
+    let token = new_token();
+    let line = format!("{} {}", address.port(), token);
+    if std::fs::write(settings::data_file(PORT_FILE), line).is_ok() {
+        let _ = LISTENER.set((listener, token));
+    }
+}
+
+/// The arguments of each launch forwarded since the last call
+pub fn take_forwarded() -> Vec<Vec<String>> {
+    let Some((listener, token)) = LISTENER.get() else {
+        return Vec::new();
+    };
+    let mut launches = Vec::new();
+    while let Ok((mut stream, _)) = listener.accept() {
+        let _ = stream.set_nonblocking(false);
+        let _ = stream.set_read_timeout(Some(TIMEOUT));
+        let mut text = String::new();
+        if stream.read_to_string(&mut text).is_err() {
+            continue;
+        }
+        let mut lines = text.lines();
+        if lines.next() != Some(token.as_str()) {
+            continue;
+        }
+        let _ = stream.write_all(ACK);
+        launches.push(lines.map(str::to_string).collect());
+    }
+    launches
+}
+
+/// Hard to guess for anyone who can't read the port file
+fn new_token() -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(std::process::id().to_le_bytes());
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    hasher.update(now.as_nanos().to_le_bytes());
+    hasher.update(RandomState::new().hash_one(0u8).to_le_bytes()); // seeded by the OS
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
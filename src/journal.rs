@@ -0,0 +1,152 @@
+use crate::settings;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const JOURNAL_FILE: &str = "transfers.journal";
+const COMPACT_AT: u64 = 1024 * 1024; // Rewrite with only live entries past this size
+
+/// Where the app keeps its journal
+pub fn default_path() -> PathBuf {
+    settings::data_file(JOURNAL_FILE)
+}
+
+/// Write-ahead log of how far each in-flight transfer has safely got, appended
+/// at every chunk boundary. queue.json is only saved on status changes, so after
+/// a kill this is what tells the next start where to pick up.
+///
+/// One line per update: `<offset>\t<item>`, or `-\t<item>` once the transfer
+/// is over, where `<item>` is the item's ItemKey::id(). The last line for an
+/// item wins.
+pub struct Journal {
+    path: PathBuf,
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    file: File,
+    live: HashMap<String, u64>,
+    written: u64,
+}
+
+impl Journal {
+    /// Open the journal, rewriting it with just the live entries so a torn last
+    /// line from a previous run can't run into new ones
+    pub fn open(path: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let path = path.into();
+        let live = replay(&path);
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let journal = Self {
+            path,
+            inner: Mutex::new(Inner {
+                file,
+                live,
+                written: 0,
+            }),
+        };
+        journal.compact(&mut journal.inner.lock().unwrap())?;
+        Ok(journal)
+    }
+
+    /// Everything before `offset` of `item` has been handed to the OS (or server)
+    pub fn record(&self, item: &str, offset: u64) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.live.insert(item.to_string(), offset);
+        self.append(&mut inner, &format!("{}\t{}\n", offset, item));
+    }
+
+    /// The transfer finished, failed or was cancelled; nothing to resume
+    pub fn forget(&self, item: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.live.remove(item).is_some() {
+            self.append(&mut inner, &format!("-\t{}\n", item));
+        }
+    }
+
+    fn append(&self, inner: &mut Inner, line: &str) {
+        // One write per line so a kill can at worst leave a torn last line
+        if inner.file.write_all(line.as_bytes()).is_ok() {
+            inner.written += line.len() as u64;
+        }
+        if inner.written > COMPACT_AT {
+            if let Err(e) = self.compact(inner) {
+                println!("DEBUG: Failed to compact transfer journal: {}", e);
+            }
+        }
+    }
+
+    fn compact(&self, inner: &mut Inner) -> std::io::Result<()> {
+        let tmp = self.path.with_extension("journal.tmp");
+        let mut contents = String::new();
+        for (item, offset) in &inner.live {
+            contents.push_str(&format!("{}\t{}\n", offset, item));
+        }
+        std::fs::write(&tmp, &contents)?;
+        std::fs::rename(&tmp, &self.path)?;
+        inner.file = OpenOptions::new().append(true).open(&self.path)?;
+        inner.written = contents.len() as u64;
+        Ok(())
+    }
+}
+
+/// Last recorded offset of every transfer that hadn't finished
+pub fn replay(path: &Path) -> HashMap<String, u64> {
+    let mut live = HashMap::new();
+    let Ok(contents) = std::fs::read_to_string(path) else {
+        return live;
+    };
+    // A line without its newline was cut off mid-write
+    let complete = contents.rfind('\n').map_or("", |end| &contents[..end]);
+    for line in complete.lines() {
+        match line.split_once('\t') {
+            Some(("-", item)) => {
+                live.remove(item);
+            }
+            Some((offset, item)) => {
+                if let Ok(offset) = offset.parse() {
+                    live.insert(item.to_string(), offset);
+                }
+            }
+            None => {}
+        }
+    }
+    live
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay_keeps_last_offset_of_unfinished_transfers() {
+        let path = std::env::temp_dir().join(format!("simplesftp-journal-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let journal = Journal::open(&path).unwrap();
+        journal.record("/r/a.mkv", 65536);
+        journal.record("/r/b.mkv", 100);
+        journal.record("/r/a.mkv", 131072);
+        journal.forget("/r/b.mkv");
+        drop(journal);
+        // A torn write from the moment the process died
+        OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"196608\t/r/a.m")
+            .unwrap();
+
+        let live = replay(&path);
+        assert_eq!(live.len(), 1);
+        assert_eq!(live["/r/a.mkv"], 131072);
+
+        // Reopening keeps the live entries and drops the torn line
+        let journal = Journal::open(&path).unwrap();
+        journal.record("/r/c.mkv", 7);
+        assert_eq!(replay(&path).len(), 2);
+        assert_eq!(replay(&path)["/r/a.mkv"], 131072);
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,147 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+const SERVICE: &str = "simplesftp";
+
+/// Keep `secret` in the desktop's keyring under `account`, through the
+/// system's own tool (secret-tool, security, or PowerShell's credential vault)
+pub fn store(account: &str, secret: &str) -> std::io::Result<()> {
+    store_for(account, secret)
+}
+
+/// The secret stored under `account`, if the keyring has one
+pub fn load(account: &str) -> Option<String> {
+    load_for(account).ok().filter(|s| !s.is_empty())
+}
+
+#[cfg(target_os = "linux")]
+fn store_for(account: &str, secret: &str) -> std::io::Result<()> {
+    let label = format!("SimpleSFTP {}", account);
+    run_with_input(
+        "secret-tool",
+        &[
+            "store", "--label", &label, "service", SERVICE, "account", account,
+        ],
+        secret,
+    )
+}
+
+#[cfg(target_os = "linux")]
+fn load_for(account: &str) -> std::io::Result<String> {
+    output(
+        "secret-tool",
+        &["lookup", "service", SERVICE, "account", account],
+    )
+}
+
+#[cfg(target_os = "macos")]
+fn store_for(account: &str, secret: &str) -> std::io::Result<()> {
+    // `security -i` reads its command from stdin, keeping the secret out of a
+    // process listing like the other systems do
+    let command = format!(
+        "add-generic-password -U -s {} -a {} -w {}\n",
+        security_quote(SERVICE),
+        security_quote(account),
+        security_quote(secret)
+    );
+    run_with_input("security", &["-i"], &command)?;
+    // Interactive mode exits 0 even when the command fails; read it back to know
+    match load_for(account) {
+        Ok(stored) if stored == secret.trim() => Ok(()), // output() trims
+        _ => Err(std::io::Error::other(
+            "security couldn't store the password",
+        )),
+    }
+}
+
+/// A word for `security -i`'s command line, which splits on spaces outside quotes
+#[cfg(target_os = "macos")]
+fn security_quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(target_os = "macos")]
+fn load_for(account: &str) -> std::io::Result<String> {
+    output(
+        "security",
+        &["find-generic-password", "-s", SERVICE, "-a", account, "-w"],
+    )
+}
+
+/// Loads the vault's WinRT types, which PowerShell doesn't know until asked,
+/// and makes any failure end the script with a non-zero exit
+#[cfg(target_os = "windows")]
+const VAULT_PRELUDE: &str = "$ErrorActionPreference = 'Stop'; \
+    [void][Windows.Security.Credentials.PasswordVault,Windows.Security.Credentials,ContentType=WindowsRuntime]; ";
+
+#[cfg(target_os = "windows")]
+fn store_for(account: &str, secret: &str) -> std::io::Result<()> {
+    // The secret goes in on stdin so it never shows in a process listing
+    let script = format!(
+        "{}$v = New-Object Windows.Security.Credentials.PasswordVault; \
+         $v.Add((New-Object Windows.Security.Credentials.PasswordCredential('{}', '{}', [Console]::In.ReadLine())))",
+        VAULT_PRELUDE,
+        SERVICE,
+        account.replace('\'', "''")
+    );
+    run_with_input("powershell", &["-NoProfile", "-Command", &script], secret)
+}
+
+#[cfg(target_os = "windows")]
+fn load_for(account: &str) -> std::io::Result<String> {
+    let script = format!(
+        "{}$v = New-Object Windows.Security.Credentials.PasswordVault; \
+         $c = $v.Retrieve('{}', '{}'); $c.RetrievePassword(); $c.Password",
+        VAULT_PRELUDE,
+        SERVICE,
+        account.replace('\'', "''")
+    );
+    output("powershell", &["-NoProfile", "-Command", &script])
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+fn store_for(_account: &str, _secret: &str) -> std::io::Result<()> {
+    Err(std::io::Error::other("no keyring on this system"))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+fn load_for(_account: &str) -> std::io::Result<String> {
+    Err(std::io::Error::other("no keyring on this system"))
+}
+
+#[allow(dead_code)] // unused where there's no keyring tool
+fn run_with_input(program: &str, args: &[&str], input: &str) -> std::io::Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input.as_bytes())?;
+    }
+    let status = child.wait()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(format!(
+            "{} exited with {}",
+            program, status
+        )))
+    }
+}
+
+#[allow(dead_code)]
+fn output(program: &str, args: &[&str]) -> std::io::Result<String> {
+    let out = Command::new(program)
+        .args(args)
+        .stderr(Stdio::null())
+        .output()?;
+    if out.status.success() {
+        Ok(String::from_utf8_lossy(&out.stdout).trim().to_string())
+    } else {
+        Err(std::io::Error::other(format!(
+            "{} exited with {}",
+            program, out.status
+        )))
+    }
+}
@@ -0,0 +1,226 @@
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
+/// Remote names are raw bytes and don't have to be UTF-8. So they can live in
+/// the Strings used everywhere else (queue.json, events, the UI), each byte that
+/// isn't valid UTF-8 is carried as U+10FF00 + byte, from a private use block no
+/// real filename uses. `to_path` turns them back into the original bytes.
+const ESCAPE_BASE: u32 = 0x10FF00;
+
+/// Lossless String form of a path as the server (or local disk) returned it
+pub fn path_to_string(path: &Path) -> String {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        let mut out = String::new();
+        for chunk in path.as_os_str().as_bytes().utf8_chunks() {
+            out.push_str(chunk.valid());
+            out.extend(
+                chunk
+                    .invalid()
+                    .iter()
+                    .filter_map(|b| char::from_u32(ESCAPE_BASE + *b as u32)),
+            );
+        }
+        out
+    }
+    #[cfg(not(unix))]
+    {
+        path.to_string_lossy().to_string()
+    }
+}
+
+/// The path with its original bytes, for handing to the server or the filesystem
+pub fn to_path(s: &str) -> PathBuf {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStringExt;
+        if !s.chars().any(is_escape) {
+            return PathBuf::from(s);
+        }
+        let mut bytes = Vec::with_capacity(s.len());
+        for c in s.chars() {
+            if is_escape(c) {
+                bytes.push((c as u32 - ESCAPE_BASE) as u8);
+            } else {
+                bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+            }
+        }
+        PathBuf::from(std::ffi::OsString::from_vec(bytes))
+    }
+    #[cfg(not(unix))]
+    {
+        PathBuf::from(s)
+    }
+}
+
+/// `to_path` for a path that may have come straight from a String
+pub fn raw(path: &Path) -> Cow<'_, Path> {
+    match path.to_str() {
+        Some(s) if s.chars().any(is_escape) => Cow::Owned(to_path(s)),
+        _ => Cow::Borrowed(path),
+    }
+}
+
+/// For showing to the user: undecodable bytes become U+FFFD
+pub fn display(s: &str) -> Cow<'_, str> {
+    if s.chars().any(is_escape) {
+        Cow::Owned(
+            s.chars()
+                .map(|c| if is_escape(c) { '\u{FFFD}' } else { c })
+                .collect(),
+        )
+    } else {
+        Cow::Borrowed(s)
+    }
+}
+
+/// Make a remote name safe to create locally, swapping anything this platform
+/// won't accept in a filename for `replacement`
+pub fn local_name(name: &str, replacement: &str) -> String {
+    sanitize(name, replacement, cfg!(windows))
+}
+
+/// `local_name` for each folder in a relative path
+pub fn local_relative(relative: &Path, replacement: &str) -> PathBuf {
+    relative
+        .iter()
+        .map(|part| local_name(&part.to_string_lossy(), replacement))
+        .collect()
+}
+
+const WINDOWS_RESERVED: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+fn sanitize(name: &str, replacement: &str, windows: bool) -> String {
+    let invalid =
+        |c: char| c == '/' || c == '\0' || (windows && (c < ' ' || "<>:\"\\|?*".contains(c)));
+    // The replacement has to be valid itself
+    let replacement: String = replacement.chars().filter(|c| !invalid(*c)).collect();
+    let prefix = if replacement.is_empty() {
+        "_"
+    } else {
+        &replacement
+    };
+    let mut out: String = name
+        .chars()
+        .map(|c| {
+            if invalid(c) {
+                replacement.clone()
+            } else {
+                c.to_string()
+            }
+        })
+        .collect();
+    if windows {
+        // Explorer drops trailing dots and spaces, so "Vol. 1." and "Vol. 1" would collide
+        let trimmed = out.trim_end_matches(['.', ' ']).len();
+        if trimmed < out.len() {
+            out = format!("{}{}", &out[..trimmed], replacement);
+        }
+        let stem = out.split('.').next().unwrap_or_default();
+        if WINDOWS_RESERVED
+            .iter()
+            .any(|r| r.eq_ignore_ascii_case(stem.trim_end()))
+        {
+            out.insert_str(0, prefix);
+        }
+    }
+    if out.is_empty() || out == "." || out == ".." {
+        out.push_str(prefix);
+    }
+    out
+}
+
+/// Windows refuses paths past MAX_PATH (260) unless they use the `\\?\`
+/// extended-length form, which deep remote trees easily reach. Elsewhere the
+/// path is returned as is.
+pub fn long_path(path: &Path) -> Cow<'_, Path> {
+    if !cfg!(windows) || path.as_os_str().len() < LONG_PATH_FROM {
+        return Cow::Borrowed(path);
+    }
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    Cow::Owned(PathBuf::from(verbatim(&absolute.to_string_lossy())))
+}
+
+// Leave some room below 260 for the names Windows appends itself
+const LONG_PATH_FROM: usize = 240;
+
+/// `C:\a/b` -> `\\?\C:\a\b`, `\\server\share\a` -> `\\?\UNC\server\share\a`.
+/// Verbatim paths skip normalization, so separators have to be backslashes.
+fn verbatim(path: &str) -> String {
+    if path.starts_with(r"\\?\") {
+        return path.to_string();
+    }
+    let path = path.replace('/', "\\");
+    match path.strip_prefix(r"\\") {
+        Some(unc) => format!(r"\\?\UNC\{}", unc),
+        None => format!(r"\\?\{}", path),
+    }
+}
+
+fn is_escape(c: char) -> bool {
+    (ESCAPE_BASE..ESCAPE_BASE + 0x100).contains(&(c as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_non_utf8_names_round_trip() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let bytes = b"/r/caf\xe9 \xff\xfe-\xe2\x82\xac.txt"; // Latin-1 é, stray bytes, then a real €
+        let path = Path::new(std::ffi::OsStr::from_bytes(bytes));
+        let s = path_to_string(path);
+        assert_eq!(to_path(&s).as_os_str().as_bytes(), bytes);
+        assert_eq!(raw(Path::new(&s)).as_os_str().as_bytes(), bytes);
+        assert_eq!(display(&s), "/r/caf\u{FFFD} \u{FFFD}\u{FFFD}-€.txt");
+
+        // Plain names pass through untouched
+        let name = "Amélie (2001) [1080p] #1 — ☃.mkv";
+        assert_eq!(path_to_string(Path::new(name)), name);
+        assert_eq!(to_path(name), Path::new(name));
+        assert!(matches!(display(name), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_verbatim_long_paths() {
+        assert_eq!(
+            verbatim(r"C:\Users\me/Downloads/a.mkv"),
+            r"\\?\C:\Users\me\Downloads\a.mkv"
+        );
+        assert_eq!(verbatim(r"\\nas\media\a.mkv"), r"\\?\UNC\nas\media\a.mkv");
+        assert_eq!(verbatim(r"\\?\C:\a"), r"\\?\C:\a");
+        let short = Path::new("Downloads/a.mkv");
+        assert!(matches!(long_path(short), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_sanitizes_names_for_windows() {
+        assert_eq!(
+            sanitize("Who? What: \"This\" <1/2>|*.mkv", "_", true),
+            "Who_ What_ _This_ _1_2___.mkv"
+        );
+        assert_eq!(sanitize("Vol. 1. ", "_", true), "Vol. 1_");
+        assert_eq!(sanitize("con.txt", "_", true), "_con.txt");
+        assert_eq!(sanitize("Console.txt", "_", true), "Console.txt");
+        assert_eq!(sanitize("a:b", "-", true), "a-b");
+        assert_eq!(sanitize("a:b", ":", true), "ab"); // invalid replacement is dropped
+
+        // Elsewhere only the separator matters
+        assert_eq!(sanitize("Who? What: <1/2>", "_", false), "Who? What: <1_2>");
+        assert_eq!(
+            local_relative(Path::new("Show: S1/Ep?"), "_"),
+            Path::new(&sanitize("Show: S1", "_", cfg!(windows))).join(sanitize(
+                "Ep?",
+                "_",
+                cfg!(windows)
+            ))
+        );
+    }
+}
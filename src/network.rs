@@ -0,0 +1,105 @@
+use std::process::Command;
+
+/// What we could find out about the current network connection
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NetworkStatus {
+    pub ssid: Option<String>, // Wi-Fi network name, None on wired or unknown
+    pub metered: bool,        // the OS marks the connection as metered
+}
+
+/// Ask the platform's own tools about the active connection. Anything that
+/// can't be determined comes back as None / not metered.
+pub fn detect() -> NetworkStatus {
+    if cfg!(target_os = "linux") {
+        NetworkStatus {
+            ssid: run("nmcli", &["-t", "-f", "active,ssid", "dev", "wifi"])
+                .and_then(|out| parse_nmcli_ssid(&out)),
+            metered: run(
+                "busctl",
+                &[
+                    "get-property",
+                    "--system",
+                    "org.freedesktop.NetworkManager",
+                    "/org/freedesktop/NetworkManager",
+                    "org.freedesktop.NetworkManager",
+                    "Metered",
+                ],
+            )
+            .is_some_and(|out| parse_nm_metered(&out)),
+        }
+    } else if cfg!(target_os = "macos") {
+        NetworkStatus {
+            ssid: run("networksetup", &["-getairportnetwork", "en0"])
+                .and_then(|out| parse_networksetup_ssid(&out)),
+            metered: false,
+        }
+    } else if cfg!(target_os = "windows") {
+        NetworkStatus {
+            ssid: run("netsh", &["wlan", "show", "interfaces"])
+                .and_then(|out| parse_netsh_ssid(&out)),
+            metered: false,
+        }
+    } else {
+        NetworkStatus::default()
+    }
+}
+
+fn run(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// `yes:Home\:5G` lines from `nmcli -t -f active,ssid dev wifi` (colons in names are escaped)
+fn parse_nmcli_ssid(output: &str) -> Option<String> {
+    output
+        .lines()
+        .find_map(|line| line.strip_prefix("yes:"))
+        .map(|ssid| ssid.replace("\\:", ":"))
+        .filter(|ssid| !ssid.is_empty())
+}
+
+/// NetworkManager's NMMetered: 1 = yes, 3 = guessed yes (e.g. a phone hotspot)
+fn parse_nm_metered(output: &str) -> bool {
+    matches!(output.trim(), "u 1" | "u 3")
+}
+
+fn parse_networksetup_ssid(output: &str) -> Option<String> {
+    output
+        .trim()
+        .strip_prefix("Current Wi-Fi Network: ")
+        .map(|ssid| ssid.to_string())
+}
+
+fn parse_netsh_ssid(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        (key.trim() == "SSID").then(|| value.trim().to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_platform_tool_output() {
+        assert_eq!(
+            parse_nmcli_ssid("no:Neighbour\nyes:Phone\\:Hotspot\n"),
+            Some("Phone:Hotspot".into())
+        );
+        assert_eq!(parse_nmcli_ssid("no:Neighbour\n"), None);
+        assert!(parse_nm_metered("u 3\n"));
+        assert!(!parse_nm_metered("u 4\n"));
+        assert_eq!(
+            parse_networksetup_ssid("Current Wi-Fi Network: Cafe\n"),
+            Some("Cafe".into())
+        );
+        assert_eq!(
+            parse_netsh_ssid("    Name : Wi-Fi\n    SSID : Office\n    BSSID : aa:bb:cc\n"),
+            Some("Office".into())
+        );
+    }
+}
@@ -0,0 +1,32 @@
+use crate::activity;
+use std::process::Command;
+
+/// Show a desktop notification through the platform's own tool. Best effort:
+/// if the tool is missing the status bar message is all the user gets.
+pub fn desktop(title: &str, body: &str) {
+    let result = if cfg!(target_os = "linux") {
+        Command::new("notify-send")
+            .args(["--app-name=SimpleSFTP", title, body])
+            .spawn()
+    } else if cfg!(target_os = "macos") {
+        let script = format!(
+            "display notification {} with title {}",
+            apple_quote(body),
+            apple_quote(title)
+        );
+        Command::new("osascript").args(["-e", &script]).spawn()
+    } else {
+        return;
+    };
+    match result {
+        // Waited on off the UI thread so it doesn't linger as a zombie
+        Ok(mut child) => {
+            std::thread::spawn(move || child.wait());
+        }
+        Err(e) => activity::detail(format!("Notification not shown: {}", e)),
+    }
+}
+
+fn apple_quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -0,0 +1,242 @@
+use crate::types::{TransferItem, TransferStatus};
+use crate::{activity, journal, settings};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+const QUEUE_FILE: &str = "queue.json";
+const QUEUE_BACKUPS: usize = 5; // queue.json.1 (newest) to queue.json.5
+
+/// queue.json couldn't be read: what could still be read from it, and the
+/// earlier good copies it could be restored from instead
+#[derive(Debug, Clone)]
+pub struct QueueProblem {
+    pub error: String,
+    pub salvaged: Vec<TransferItem>,
+    pub backups: Vec<QueueBackup>,
+}
+
+#[derive(Debug, Clone)]
+pub struct QueueBackup {
+    pub modified: String,
+    pub items: Vec<TransferItem>,
+}
+
+pub fn save_queue(queue: &[TransferItem]) {
+    // Tests drive update() on an in-memory queue and must not touch queue.json
+    if cfg!(test) {
+        return;
+    }
+    // Written aside and renamed, so a crash mid-write leaves the old file whole
+    let path = settings::data_file(QUEUE_FILE);
+    let temp = path.with_extension("json.tmp");
+    if let Ok(file) = File::create(&temp) {
+        let writer = BufWriter::new(file);
+        if serde_json::to_writer(writer, queue).is_ok() {
+            let _ = std::fs::rename(&temp, &path);
+        }
+    }
+}
+
+/// Load the queue, keeping a copy of it among the backups when it reads fine.
+/// A file that doesn't is moved aside (so saving can't overwrite it) and
+/// reported with what's left to recover from
+pub fn load_queue_checked() -> Result<Vec<TransferItem>, QueueProblem> {
+    if cfg!(test) {
+        return Ok(Vec::new());
+    }
+    let path = settings::data_file(QUEUE_FILE);
+    let Ok(text) = std::fs::read_to_string(&path) else {
+        return Ok(Vec::new());
+    };
+    match parse_queue(&text) {
+        Ok((mut items, skipped)) => {
+            if skipped > 0 {
+                activity::error(format!(
+                    "Skipped {} unreadable items in {}",
+                    skipped,
+                    path.display()
+                ));
+            }
+            // A file missing items would push a good backup out; an unchanged one
+            // would fill all five slots with the same queue
+            if skipped == 0 {
+                rotate_backups(&path, &text, QUEUE_BACKUPS);
+            }
+            prepare_for_resume(&mut items);
+            Ok(items)
+        }
+        Err((error, mut salvaged)) => {
+            let aside = path.with_extension("json.bad");
+            let _ = std::fs::rename(&path, &aside);
+            activity::error(format!(
+                "{} couldn't be read ({}); kept as {}",
+                path.display(),
+                error,
+                aside.display()
+            ));
+            prepare_for_resume(&mut salvaged);
+            Err(QueueProblem {
+                error,
+                salvaged,
+                backups: load_backups(&path, QUEUE_BACKUPS),
+            })
+        }
+    }
+}
+
+/// The items in a queue file and how many were skipped as unreadable, or why
+/// the file as a whole isn't a queue along with the items read up to the damage
+pub fn parse_queue(text: &str) -> Result<(Vec<TransferItem>, usize), (String, Vec<TransferItem>)> {
+    match serde_json::from_str::<Vec<serde_json::Value>>(text) {
+        Ok(values) => {
+            let total = values.len();
+            let items: Vec<TransferItem> = values
+                .into_iter()
+                .filter_map(|v| serde_json::from_value(v).ok())
+                .collect();
+            let skipped = total - items.len();
+            Ok((items, skipped))
+        }
+        Err(e) => Err((e.to_string(), salvage(text))),
+    }
+}
+
+/// Every whole item before the point a damaged (usually cut short) file stops
+/// making sense
+fn salvage(text: &str) -> Vec<TransferItem> {
+    let mut items = Vec::new();
+    let Some(mut rest) = text.trim_start().strip_prefix('[') else {
+        return items;
+    };
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        let mut values = serde_json::Deserializer::from_str(rest).into_iter::<TransferItem>();
+        match values.next() {
+            Some(Ok(item)) => {
+                items.push(item);
+                rest = &rest[values.byte_offset()..];
+            }
+            _ => return items,
+        }
+    }
+}
+
+/// Shift queue.json.1.. up by one (dropping the oldest) and copy `path`, which
+/// reads as `text`, in as .1. Nothing to do if .1 holds that already
+fn rotate_backups(path: &Path, text: &str, keep: usize) {
+    let backup = |n: usize| PathBuf::from(format!("{}.{}", path.display(), n));
+    if std::fs::read_to_string(backup(1)).is_ok_and(|newest| newest == text) {
+        return;
+    }
+    for n in (1..keep).rev() {
+        let _ = std::fs::rename(backup(n), backup(n + 1));
+    }
+    let _ = std::fs::copy(path, backup(1));
+}
+
+/// The backups of `path` that still read as a queue, newest first
+fn load_backups(path: &Path, keep: usize) -> Vec<QueueBackup> {
+    (1..=keep)
+        .map(|n| PathBuf::from(format!("{}.{}", path.display(), n)))
+        .filter_map(|backup| {
+            let text = std::fs::read_to_string(&backup).ok()?;
+            let (mut items, _) = parse_queue(&text).ok()?;
+            prepare_for_resume(&mut items);
+            let modified = std::fs::metadata(&backup)
+                .and_then(|m| m.modified())
+                .map(|t| {
+                    chrono::DateTime::<chrono::Local>::from(t)
+                        .format("%Y-%m-%d %H:%M")
+                        .to_string()
+                })
+                .unwrap_or_default();
+            Some(QueueBackup { modified, items })
+        })
+        .collect()
+}
+
+fn prepare_for_resume(items: &mut [TransferItem]) {
+    // The journal knows how far in-flight items got after queue.json was written
+    let offsets = journal::replay(&journal::default_path());
+    // Reset any in-flight items to "Queued" so they resume
+    for item in items {
+        if item.status.is_finished() {
+            continue;
+        }
+        if let Some(&offset) = offsets.get(&item.key().id()) {
+            item.bytes_transferred = offset;
+        }
+        if item.status.is_active() {
+            item.status = TransferStatus::Queued;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Direction;
+
+    fn item(remote: &str) -> TransferItem {
+        TransferItem {
+            direction: Direction::Download,
+            local_location: "/tmp".into(),
+            filename: remote.rsplit('/').next().unwrap().into(),
+            remote_file: remote.into(),
+            size_bytes: 10,
+            bytes_transferred: 0,
+            priority: 10,
+            status: TransferStatus::Queued,
+            batch: None,
+            follow: false,
+            speed_limit: 0,
+            profile: String::new(),
+            relay_to: None,
+            resume_check: None,
+            failure: None,
+            queued_at: 0,
+            timeline: Vec::new(),
+            note: String::new(),
+            label: None,
+        }
+    }
+
+    #[test]
+    fn test_damaged_queue_keeps_what_can_be_read() {
+        let text = serde_json::to_string(&[item("/a"), item("/b"), item("/c")]).unwrap();
+
+        // One bad item is skipped, the rest load
+        let mut values: Vec<serde_json::Value> = serde_json::from_str(&text).unwrap();
+        values[1] = serde_json::json!({"remote_file": "/b"});
+        let (items, skipped) = parse_queue(&serde_json::to_string(&values).unwrap()).unwrap();
+        assert_eq!((items.len(), skipped), (2, 1));
+
+        // A file cut off mid-item gives back the whole items before the cut
+        let cut = &text[..text.len() - 20];
+        let (_, salvaged) = parse_queue(cut).unwrap_err();
+        let paths: Vec<_> = salvaged.iter().map(|i| i.remote_file.as_str()).collect();
+        assert_eq!(paths, ["/a", "/b"]);
+        assert!(parse_queue("not json").unwrap_err().1.is_empty());
+    }
+
+    #[test]
+    fn test_backups_rotate_only_on_change() {
+        let dir = std::env::temp_dir().join(format!("simplesftp-backups-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("queue.json");
+        let backup = |n: usize| dir.join(format!("queue.json.{}", n));
+
+        std::fs::write(&path, "one").unwrap();
+        rotate_backups(&path, "one", 3);
+        rotate_backups(&path, "one", 3); // loaded again, unchanged
+        assert!(!backup(2).exists());
+
+        std::fs::write(&path, "two").unwrap();
+        rotate_backups(&path, "two", 3);
+        assert_eq!(std::fs::read_to_string(backup(1)).unwrap(), "two");
+        assert_eq!(std::fs::read_to_string(backup(2)).unwrap(), "one");
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,119 @@
+use std::path::Path;
+use std::process::Command;
+
+/// True when running on battery, false on AC, None if the machine has no
+/// battery or we can't tell
+pub fn on_battery() -> Option<bool> {
+    if cfg!(target_os = "linux") {
+        on_battery_sysfs(Path::new("/sys/class/power_supply"))
+    } else if cfg!(target_os = "macos") {
+        let output = Command::new("pmset").args(["-g", "batt"]).output().ok()?;
+        parse_pmset(&String::from_utf8_lossy(&output.stdout))
+    } else {
+        on_battery_windows()
+    }
+}
+
+#[cfg(windows)]
+fn on_battery_windows() -> Option<bool> {
+    use windows::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    let mut status = SYSTEM_POWER_STATUS::default();
+    // SAFETY: `status` is a plain struct the call fills in
+    unsafe { GetSystemPowerStatus(&mut status) }.ok()?;
+    from_power_status(status.ACLineStatus, status.BatteryFlag)
+}
+
+#[cfg(not(windows))]
+fn on_battery_windows() -> Option<bool> {
+    None
+}
+
+/// SYSTEM_POWER_STATUS: ACLineStatus is 0 offline, 1 online, 255 unknown;
+/// BatteryFlag 128 means there's no battery
+#[cfg(any(windows, test))]
+fn from_power_status(ac_line: u8, battery_flag: u8) -> Option<bool> {
+    match ac_line {
+        1 => Some(false),
+        0 if battery_flag != 128 && battery_flag != 255 => Some(true),
+        _ => None,
+    }
+}
+
+/// Each supply has a `type` file ("Mains", "Battery", "USB"...); adapters report
+/// `online`, batteries their charging `status`
+fn on_battery_sysfs(dir: &Path) -> Option<bool> {
+    let read = |path: &Path| {
+        std::fs::read_to_string(path)
+            .ok()
+            .map(|s| s.trim().to_string())
+    };
+    let mut has_battery = false;
+    let mut discharging = false;
+    for entry in std::fs::read_dir(dir).ok()?.flatten() {
+        let supply = entry.path();
+        match read(&supply.join("type")).as_deref() {
+            Some("Mains") | Some("USB") if read(&supply.join("online")).as_deref() == Some("1") => {
+                return Some(false);
+            }
+            Some("Battery") => {
+                has_battery = true;
+                discharging |= read(&supply.join("status")).as_deref() == Some("Discharging");
+            }
+            _ => {}
+        }
+    }
+    has_battery.then_some(discharging)
+}
+
+/// First line of `pmset -g batt`: "Now drawing from 'Battery Power'"
+fn parse_pmset(output: &str) -> Option<bool> {
+    let first = output.lines().next()?;
+    if first.contains("Battery Power") {
+        Some(true)
+    } else if first.contains("AC Power") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reads_power_supply_state() {
+        let dir = std::env::temp_dir().join(format!("simplesftp-power-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let supply = |name: &str, files: &[(&str, &str)]| {
+            std::fs::create_dir_all(dir.join(name)).unwrap();
+            for (file, contents) in files {
+                std::fs::write(dir.join(name).join(file), contents).unwrap();
+            }
+        };
+
+        supply("AC", &[("type", "Mains\n"), ("online", "0\n")]);
+        assert_eq!(on_battery_sysfs(&dir), None); // desktop without battery
+
+        supply(
+            "BAT0",
+            &[("type", "Battery\n"), ("status", "Discharging\n")],
+        );
+        assert_eq!(on_battery_sysfs(&dir), Some(true));
+
+        supply("AC", &[("online", "1\n")]);
+        assert_eq!(on_battery_sysfs(&dir), Some(false));
+        let _ = std::fs::remove_dir_all(dir);
+
+        assert_eq!(
+            parse_pmset("Now drawing from 'AC Power'\n -InternalBattery-0 100%"),
+            Some(false)
+        );
+
+        assert_eq!(from_power_status(0, 2), Some(true)); // unplugged, battery low
+        assert_eq!(from_power_status(1, 8), Some(false)); // plugged in, charging
+        assert_eq!(from_power_status(1, 128), Some(false)); // desktop
+        assert_eq!(from_power_status(255, 255), None);
+    }
+}
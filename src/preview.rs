@@ -0,0 +1,71 @@
+use crate::error::SftpError;
+use crate::remote_fs::RemoteFs;
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+pub const PREVIEW_LIMIT: u64 = 100 * 1024 * 1024; // bigger files should be queued instead
+const FETCH_CHUNK: usize = 256 * 1024;
+
+/// Copy a remote file into the temp folder for a quick look, replacing any
+/// earlier preview of the same name
+pub fn fetch(fs: &dyn RemoteFs, remote_path: &str, dir: &Path) -> Result<PathBuf, SftpError> {
+    let name = Path::new(remote_path)
+        .file_name()
+        .ok_or_else(|| SftpError::NotFound(remote_path.to_string()))?;
+    std::fs::create_dir_all(dir)
+        .map_err(|e| SftpError::local("Failed to create preview folder", e))?;
+    let local = dir.join(name);
+    let mut file = std::fs::File::create(&local)
+        .map_err(|e| SftpError::local("Failed to create preview file", e))?;
+
+    let mut offset = 0;
+    loop {
+        let chunk = fs.read_chunk(Path::new(remote_path), offset, FETCH_CHUNK)?;
+        if chunk.is_empty() {
+            break;
+        }
+        file.write_all(&chunk)
+            .map_err(|e| SftpError::local("Failed to write preview file", e))?;
+        offset += chunk.len() as u64;
+    }
+    Ok(local)
+}
+
+pub fn preview_dir() -> PathBuf {
+    std::env::temp_dir().join("simplesftp-preview")
+}
+
+/// Open `path` in whatever the desktop uses for its type
+pub fn open(path: &Path) -> std::io::Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(target_os = "windows") {
+        let mut c = Command::new("cmd");
+        c.args(["/C", "start", ""]);
+        c
+    } else {
+        Command::new("xdg-open")
+    };
+    command.arg(path).spawn().map(drop)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::remote_fs::MemoryFs;
+
+    #[test]
+    fn test_fetch_copies_whole_file_into_preview_dir() {
+        let fs = MemoryFs::new();
+        let contents: Vec<u8> = (0..FETCH_CHUNK * 2 + 5).map(|i| i as u8).collect();
+        fs.add_file("/srv/docs/report.pdf", contents.clone());
+        let dir = std::env::temp_dir().join(format!("simplesftp-preview-{}", std::process::id()));
+
+        let local = fetch(&fs, "/srv/docs/report.pdf", &dir).unwrap();
+        assert_eq!(local, dir.join("report.pdf"));
+        assert_eq!(std::fs::read(&local).unwrap(), contents);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,103 @@
+use crate::error::SftpError;
+use crate::settings::SftpConfig;
+use crate::sftp_client::SftpClient;
+use crate::types::FileType;
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+const PINGS: u32 = 5;
+const BURST_BYTES: u64 = 16 * 1024 * 1024; // Stop the sample read after this much...
+const BURST_TIME: Duration = Duration::from_secs(3); // ...or this long
+
+/// Timings from a throwaway session, shown under "Test connection" in Settings
+#[derive(Debug, Clone)]
+pub struct ProbeReport {
+    pub connect: Duration, // TCP connect, handshake and login
+    pub latency: Duration, // average no-op stat round trip
+    pub burst: Option<Burst>,
+}
+
+/// A short download of the largest file in the probed folder, into memory
+#[derive(Debug, Clone)]
+pub struct Burst {
+    pub file: String,
+    pub bytes: u64,
+    pub elapsed: Duration,
+}
+
+impl Burst {
+    pub fn bytes_per_sec(&self) -> u64 {
+        (self.bytes as f64 / self.elapsed.as_secs_f64().max(0.001)) as u64
+    }
+}
+
+impl ProbeReport {
+    /// What the numbers suggest for the transfer settings
+    pub fn hint(&self) -> &'static str {
+        if self.latency > Duration::from_millis(100) {
+            "High latency: parallel transfers help more than a faster line."
+        } else if self.burst.is_none() {
+            "No file in this folder to sample throughput with."
+        } else {
+            "Low latency: a single transfer should get close to the line speed."
+        }
+    }
+}
+
+/// Connect with `config` and measure the link, sampling throughput from the
+/// largest file in `dir`
+pub fn run(config: &SftpConfig, dir: &str) -> Result<ProbeReport, SftpError> {
+    let started = Instant::now();
+    let client = SftpClient::connect(config)?;
+    let connect = started.elapsed();
+
+    let mut total = Duration::ZERO;
+    for _ in 0..PINGS {
+        total += client.ping()?;
+    }
+    let latency = total / PINGS;
+
+    let (_, files) = client.list_dir(Path::new(dir))?;
+    let burst = files
+        .iter()
+        .filter(|f| f.file_type != FileType::Folder && f.size_bytes > 0)
+        .max_by_key(|f| f.size_bytes)
+        .map(|f| {
+            let started = Instant::now();
+            client
+                .read_burst(&f.path, BURST_BYTES, BURST_TIME)
+                .map(|bytes| Burst {
+                    file: f.name.clone(),
+                    bytes,
+                    elapsed: started.elapsed(),
+                })
+        })
+        .transpose()?;
+
+    Ok(ProbeReport {
+        connect,
+        latency,
+        burst,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{pattern, TestServer};
+
+    #[test]
+    fn test_probe_samples_largest_file() {
+        let server = TestServer::start();
+        server.write_file("small.txt", b"hi");
+        server.write_file("big.bin", &pattern(300_000));
+        server.write_file("folder/inner.bin", &pattern(900_000));
+
+        let report = run(&server.config(), &server.root.to_string_lossy()).unwrap();
+        let burst = report.burst.unwrap();
+        assert_eq!(burst.file, "big.bin");
+        assert_eq!(burst.bytes, 300_000);
+        assert!(burst.bytes_per_sec() > 0);
+    }
+}
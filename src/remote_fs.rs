@@ -0,0 +1,239 @@
+use crate::checksum;
+use crate::error::SftpError;
+use crate::settings::SftpConfig;
+use crate::sftp_client::SftpClient;
+
+use std::io::{Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Size and modification time of a remote file, compared during a download to
+/// notice the file being replaced or rewritten
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RemoteStat {
+    pub size: u64,
+    pub mtime: Option<u64>,
+}
+
+/// The remote operations the transfer manager relies on (stat, read and write).
+///
+/// Implemented by SftpClient for real servers and by `MemoryFs` in tests so the
+/// manager's queueing, throttling, pause/resume and retry logic can run without
+/// a network.
+pub trait RemoteFs: Send {
+    fn get_file_size(&self, path: &str) -> Result<u64, SftpError>;
+
+    /// Size and mtime of `path`; without an override only the size is known
+    fn stat(&self, path: &str) -> Result<RemoteStat, SftpError> {
+        Ok(RemoteStat {
+            size: self.get_file_size(path)?,
+            mtime: None,
+        })
+    }
+
+    /// Read up to `chunk_size` bytes starting at `offset`; an empty Vec means EOF
+    fn read_chunk(
+        &self,
+        remote_path: &Path,
+        offset: u64,
+        chunk_size: usize,
+    ) -> Result<Vec<u8>, SftpError>;
+
+    /// SHA-256 of the remote bytes matching `checksum::tail_hash` of a local
+    /// copy cut at `length`, to tell whether the two still agree before resuming
+    fn tail_hash(&self, remote_path: &Path, length: u64) -> Result<String, SftpError> {
+        let mut offset = checksum::tail_start(length);
+        let mut tail = Vec::new();
+        while offset < length {
+            let chunk = self.read_chunk(remote_path, offset, (length - offset) as usize)?;
+            if chunk.is_empty() {
+                break; // shorter than the local copy; the hashes won't match
+            }
+            offset += chunk.len() as u64;
+            tail.extend_from_slice(&chunk);
+        }
+        Ok(checksum::hash_bytes(&tail))
+    }
+
+    /// Write `data` at `offset`, creating the remote file at offset 0
+    fn write_chunk(&self, remote_path: &Path, offset: u64, data: &[u8]) -> Result<(), SftpError>;
+
+    /// Read one chunk and write it to `local`, which the caller keeps open for
+    /// the whole transfer. Returns the number of bytes written, 0 at EOF.
+    fn download_chunk(
+        &self,
+        remote_path: &Path,
+        local: &mut dyn Write,
+        offset: u64,
+        chunk_size: usize,
+    ) -> Result<usize, SftpError> {
+        let buffer = self.read_chunk(remote_path, offset, chunk_size)?;
+        if buffer.is_empty() {
+            return Ok(0); // EOF
+        }
+
+        local
+            .write_all(&buffer)
+            .map_err(|e| SftpError::local("Failed to write to local file", e))?;
+
+        Ok(buffer.len())
+    }
+
+    /// Read the next chunk from `local` and write it to the remote file at `offset`.
+    /// Returns the number of bytes sent, 0 at the end of the local file.
+    fn upload_chunk(
+        &self,
+        local: &mut dyn Read,
+        remote_path: &Path,
+        offset: u64,
+        chunk_size: usize,
+    ) -> Result<usize, SftpError> {
+        let mut buffer = vec![0u8; chunk_size];
+        let bytes_read = local
+            .read(&mut buffer)
+            .map_err(|e| SftpError::local("Failed to read local file", e))?;
+        if bytes_read == 0 {
+            return Ok(0); // EOF
+        }
+
+        self.write_chunk(remote_path, offset, &buffer[..bytes_read])?;
+        Ok(bytes_read)
+    }
+}
+
+/// Opens a fresh remote session; each transfer task calls this once (and again on retry)
+pub type Connector = Arc<dyn Fn() -> Result<Box<dyn RemoteFs>, SftpError> + Send + Sync>;
+
+pub fn sftp_connector(config: SftpConfig) -> Connector {
+    Arc::new(move || SftpClient::connect(&config).map(|c| Box::new(c) as Box<dyn RemoteFs>))
+}
+
+#[cfg(test)]
+pub use memory::MemoryFs;
+
+#[cfg(test)]
+mod memory {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    /// In-memory remote filesystem. Clones share state, so a test can keep a
+    /// handle to inspect or tweak the "server" while the manager uses it.
+    #[derive(Clone, Default)]
+    pub struct MemoryFs {
+        files: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+        versions: Arc<Mutex<HashMap<String, u64>>>, // stands in for mtime
+        failing_reads: Arc<AtomicUsize>,
+        stalled_read: Arc<Mutex<Option<Duration>>>,
+        pub connects: Arc<AtomicUsize>,
+        pub reads: Arc<AtomicUsize>,
+    }
+
+    impl MemoryFs {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        pub fn file(&self, path: &str) -> Option<Vec<u8>> {
+            self.files.lock().unwrap().get(path).cloned()
+        }
+
+        /// Add or replace a file; replacing also moves its mtime on
+        pub fn add_file(&self, path: &str, contents: Vec<u8>) {
+            self.files
+                .lock()
+                .unwrap()
+                .insert(path.to_string(), contents);
+            *self
+                .versions
+                .lock()
+                .unwrap()
+                .entry(path.to_string())
+                .or_default() += 1;
+        }
+
+        /// Make the next `n` reads fail as if the connection dropped
+        pub fn fail_next_reads(&self, n: usize) {
+            self.failing_reads.store(n, Ordering::SeqCst);
+        }
+
+        /// Make the next read hang for `delay` before answering, like a server hiccup
+        pub fn stall_next_read(&self, delay: Duration) {
+            *self.stalled_read.lock().unwrap() = Some(delay);
+        }
+
+        pub fn connector(&self) -> Connector {
+            let fs = self.clone();
+            Arc::new(move || {
+                fs.connects.fetch_add(1, Ordering::SeqCst);
+                Ok(Box::new(fs.clone()) as Box<dyn RemoteFs>)
+            })
+        }
+    }
+
+    impl RemoteFs for MemoryFs {
+        fn get_file_size(&self, path: &str) -> Result<u64, SftpError> {
+            self.files
+                .lock()
+                .unwrap()
+                .get(path)
+                .map(|data| data.len() as u64)
+                .ok_or_else(|| SftpError::NotFound(path.to_string()))
+        }
+
+        fn stat(&self, path: &str) -> Result<RemoteStat, SftpError> {
+            Ok(RemoteStat {
+                size: self.get_file_size(path)?,
+                mtime: self.versions.lock().unwrap().get(path).copied(),
+            })
+        }
+
+        fn read_chunk(
+            &self,
+            remote_path: &Path,
+            offset: u64,
+            chunk_size: usize,
+        ) -> Result<Vec<u8>, SftpError> {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            let stall = self.stalled_read.lock().unwrap().take();
+            if let Some(delay) = stall {
+                std::thread::sleep(delay);
+            }
+            if self
+                .failing_reads
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok()
+            {
+                return Err(SftpError::ConnectionLost("injected failure".into()));
+            }
+
+            let files = self.files.lock().unwrap();
+            let data = files
+                .get(remote_path.to_string_lossy().as_ref())
+                .ok_or_else(|| SftpError::NotFound(remote_path.to_string_lossy().to_string()))?;
+            let start = (offset as usize).min(data.len());
+            let end = (start + chunk_size).min(data.len());
+            Ok(data[start..end].to_vec())
+        }
+
+        fn write_chunk(
+            &self,
+            remote_path: &Path,
+            offset: u64,
+            data: &[u8],
+        ) -> Result<(), SftpError> {
+            let mut files = self.files.lock().unwrap();
+            let file = files
+                .entry(remote_path.to_string_lossy().to_string())
+                .or_default();
+            if offset == 0 {
+                file.clear();
+            }
+            file.truncate(offset as usize);
+            file.extend_from_slice(data);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,315 @@
+use crate::settings::{ReportConfig, ReportPeriod, TransferRecord};
+use crate::sftp_client::format_size;
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate, TimeZone};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// The transfers of one day or week, written out as HTML and CSV
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub key: String, // the day, or the Monday of the week: "2026-10-12"
+    pub title: String,
+    pub records: Vec<TransferRecord>,
+}
+
+impl Report {
+    /// The period `period` that holds `day`, with the transfers that finished in it
+    pub fn for_day(period: ReportPeriod, day: NaiveDate, history: &[TransferRecord]) -> Self {
+        let (first, days, title) = match period {
+            ReportPeriod::Weekly => {
+                let monday = day - Days::new(day.weekday().num_days_from_monday() as u64);
+                (monday, 7, format!("Transfers in the week of {}", monday))
+            }
+            _ => (day, 1, format!("Transfers on {}", day)),
+        };
+        let (start, end) = (midnight(first), midnight(first + Days::new(days)));
+        Self {
+            key: first.to_string(),
+            title,
+            records: history
+                .iter()
+                .filter(|r| (start..end).contains(&r.finished_at))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// The last whole period before `now`, unless it has been sent already
+    pub fn due(
+        config: &ReportConfig,
+        history: &[TransferRecord],
+        now: DateTime<Local>,
+    ) -> Option<Self> {
+        let back = match config.period {
+            ReportPeriod::Off => return None,
+            ReportPeriod::Daily => 1,
+            ReportPeriod::Weekly => 7,
+        };
+        let day = now.date_naive() - Days::new(back);
+        let report = Self::for_day(config.period, day, history);
+        (report.key != config.last_report).then_some(report)
+    }
+
+    pub fn completed(&self) -> usize {
+        self.records.iter().filter(|r| r.error.is_none()).count()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.records.len() - self.completed()
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.records
+            .iter()
+            .filter(|r| r.error.is_none())
+            .map(|r| r.bytes)
+            .sum()
+    }
+
+    /// Bytes per second over the completed transfers' running time
+    pub fn average_speed(&self) -> u64 {
+        let secs: u64 = self
+            .records
+            .iter()
+            .filter(|r| r.error.is_none())
+            .map(|r| r.seconds)
+            .sum();
+        self.bytes() / secs.max(1)
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "{} completed ({}), {} failed, {}/s on average",
+            self.completed(),
+            format_size(self.bytes()),
+            self.failed(),
+            format_size(self.average_speed())
+        )
+    }
+
+    pub fn csv(&self) -> String {
+        let mut out = String::from("finished,server,direction,file,bytes,seconds,result\n");
+        for r in &self.records {
+            out.push_str(&format!(
+                "{},{},{},{},{},{},{}\n",
+                finished(r),
+                csv_field(&r.profile),
+                if r.upload { "upload" } else { "download" },
+                csv_field(&r.name),
+                r.bytes,
+                r.seconds,
+                csv_field(r.error.as_deref().unwrap_or("ok"))
+            ));
+        }
+        out
+    }
+
+    pub fn html(&self) -> String {
+        let rows: String = self
+            .records
+            .iter()
+            .map(|r| {
+                format!(
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                    finished(r),
+                    html_escape(&r.profile),
+                    if r.upload { "Upload" } else { "Download" },
+                    html_escape(&r.name),
+                    format_size(r.bytes),
+                    match &r.error {
+                        None => "OK".to_string(),
+                        Some(e) => format!("<b>Failed:</b> {}", html_escape(e)),
+                    }
+                )
+            })
+            .collect();
+        format!(
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{title}</title></head>\n\
+             <body style=\"font-family: sans-serif\">\n<h2>{title}</h2>\n<p>{summary}</p>\n\
+             <table border=\"1\" cellpadding=\"4\" cellspacing=\"0\">\n\
+             <tr><th>Finished</th><th>Server</th><th>Direction</th><th>File</th><th>Size</th><th>Result</th></tr>\n\
+             {rows}</table>\n</body></html>\n",
+            title = html_escape(&self.title),
+            summary = html_escape(&self.summary()),
+            rows = rows
+        )
+    }
+
+    /// Write transfers-<key>.html and .csv into `folder`, returning the HTML one
+    pub fn save(&self, folder: &Path) -> std::io::Result<PathBuf> {
+        let html = folder.join(format!("transfers-{}.html", self.key));
+        std::fs::write(&html, self.html())?;
+        std::fs::write(
+            folder.join(format!("transfers-{}.csv", self.key)),
+            self.csv(),
+        )?;
+        Ok(html)
+    }
+
+    /// Mail the HTML report through the configured SMTP account. curl does the
+    /// talking (TLS required); the login goes to it on stdin, out of the
+    /// process list
+    pub fn email(&self, config: &ReportConfig) -> std::io::Result<()> {
+        let message = format!(
+            "From: {from}\r\nTo: {to}\r\nSubject: {subject}\r\nMIME-Version: 1.0\r\n\
+             Content-Type: text/html; charset=utf-8\r\n\r\n{body}",
+            from = config.smtp_user,
+            to = config.email_to,
+            subject = self.title,
+            body = self.html()
+        );
+        let file = std::env::temp_dir().join(format!("simplesftp-report-{}.eml", self.key));
+        std::fs::write(&file, message)?;
+        let login = format!(
+            "user = \"{}:{}\"\n",
+            curl_quote(&config.smtp_user),
+            curl_quote(config.smtp_password.as_deref().unwrap_or_default())
+        );
+        let result = run_curl(
+            &[
+                "--silent",
+                "--show-error",
+                "--ssl-reqd",
+                "--config",
+                "-",
+                "--url",
+                &config.smtp_url,
+                "--mail-from",
+                &config.smtp_user,
+                "--mail-rcpt",
+                &config.email_to,
+                "--upload-file",
+                &file.to_string_lossy(),
+            ],
+            &login,
+        );
+        let _ = std::fs::remove_file(&file);
+        result
+    }
+
+    /// Save and/or email the report as configured, describing where it went
+    pub fn deliver(&self, config: &ReportConfig) -> Result<String, String> {
+        let mut done = Vec::new();
+        if !config.folder.trim().is_empty() {
+            let path = self
+                .save(Path::new(config.folder.trim()))
+                .map_err(|e| format!("Couldn't save the report: {}", e))?;
+            done.push(format!("saved as {}", path.display()));
+        }
+        if !config.smtp_url.trim().is_empty() {
+            self.email(config)
+                .map_err(|e| format!("Couldn't email the report: {}", e))?;
+            done.push(format!("emailed to {}", config.email_to));
+        }
+        if done.is_empty() {
+            return Err("Choose a folder or a mail account for reports first".into());
+        }
+        Ok(format!("{} {}", self.title, done.join(" and ")))
+    }
+}
+
+fn midnight(day: NaiveDate) -> i64 {
+    let start = day.and_hms_opt(0, 0, 0).unwrap_or_default();
+    Local
+        .from_local_datetime(&start)
+        .earliest()
+        .map_or_else(|| start.and_utc().timestamp(), |t| t.timestamp())
+}
+
+fn finished(record: &TransferRecord) -> String {
+    Local
+        .timestamp_opt(record.finished_at, 0)
+        .single()
+        .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn curl_quote(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn run_curl(args: &[&str], input: &str) -> std::io::Result<()> {
+    let mut child = Command::new("curl")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input.as_bytes())?;
+    }
+    let out = child.wait_with_output()?;
+    if out.status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(
+            String::from_utf8_lossy(&out.stderr).trim().to_string(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(name: &str, finished_at: i64, error: Option<&str>) -> TransferRecord {
+        TransferRecord {
+            finished_at,
+            name: name.into(),
+            profile: "alice@nas:22".into(),
+            upload: false,
+            bytes: 2048,
+            seconds: 2,
+            error: error.map(Into::into),
+        }
+    }
+
+    #[test]
+    fn test_report_covers_the_last_whole_period() {
+        let day = NaiveDate::from_ymd_opt(2026, 10, 14).unwrap(); // a Wednesday
+        let noon = midnight(day) + 12 * 3600;
+        let history = [
+            record("a, \"b\".mkv", noon, None),
+            record("<c>.iso", noon + 60, Some("Permission denied")),
+            record("tuesday.mkv", noon - 86400, None),
+        ];
+
+        let daily = Report::for_day(ReportPeriod::Daily, day, &history);
+        assert_eq!((daily.completed(), daily.failed()), (1, 1));
+        assert_eq!(daily.average_speed(), 1024);
+        assert!(daily.csv().contains(",\"a, \"\"b\"\".mkv\","));
+        assert!(daily.html().contains("&lt;c&gt;.iso"));
+        let weekly = Report::for_day(ReportPeriod::Weekly, day, &history);
+        assert_eq!(
+            (weekly.key.as_str(), weekly.records.len()),
+            ("2026-10-12", 3)
+        );
+
+        // Due once the day is over, and only until it has gone out
+        let mut config = ReportConfig {
+            period: ReportPeriod::Daily,
+            ..Default::default()
+        };
+        let thursday = Local.timestamp_opt(noon + 86400, 0).unwrap();
+        let due = Report::due(&config, &history, thursday).unwrap();
+        assert_eq!(due.key, "2026-10-14");
+        config.last_report = due.key;
+        assert!(Report::due(&config, &history, thursday).is_none());
+    }
+}
@@ -0,0 +1,128 @@
+use crate::filter::glob_match;
+
+use serde::{Deserialize, Serialize};
+
+/// Priority given to queued items no rule matches
+pub const DEFAULT_PRIORITY: u8 = 10;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RuleMatch {
+    Name(String),     // glob, e.g. "*.srt"
+    LargerThan(u64),  // bytes
+    SmallerThan(u64), // bytes
+}
+
+/// Sets the priority of items as they're queued; the first matching rule wins
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriorityRule {
+    pub matcher: RuleMatch,
+    pub priority: u8,
+}
+
+impl PriorityRule {
+    fn matches(&self, name: &str, size: u64) -> bool {
+        match &self.matcher {
+            RuleMatch::Name(pattern) => glob_match(pattern, name),
+            RuleMatch::LargerThan(bytes) => size > *bytes,
+            RuleMatch::SmallerThan(bytes) => size < *bytes,
+        }
+    }
+}
+
+impl std::fmt::Display for PriorityRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.matcher {
+            RuleMatch::Name(pattern) => write!(f, "{}", pattern)?,
+            RuleMatch::LargerThan(bytes) => write!(f, ">{}", format_size(*bytes))?,
+            RuleMatch::SmallerThan(bytes) => write!(f, "<{}", format_size(*bytes))?,
+        }
+        write!(f, "={}", self.priority)
+    }
+}
+
+pub fn priority_for(rules: &[PriorityRule], name: &str, size: u64) -> u8 {
+    rules
+        .iter()
+        .find(|r| r.matches(name, size))
+        .map_or(DEFAULT_PRIORITY, |r| r.priority)
+}
+
+/// Parse rules as typed in Settings: "*.srt=1, >10GB=20, <1MB=5".
+/// Entries that don't parse are left out.
+pub fn parse_rules(input: &str) -> Vec<PriorityRule> {
+    input
+        .split(',')
+        .filter_map(|entry| {
+            let (lhs, priority) = entry.trim().rsplit_once('=')?;
+            let priority = priority.trim().parse().ok()?;
+            let lhs = lhs.trim();
+            let matcher = if let Some(size) = lhs.strip_prefix('>') {
+                RuleMatch::LargerThan(parse_size(size)?)
+            } else if let Some(size) = lhs.strip_prefix('<') {
+                RuleMatch::SmallerThan(parse_size(size)?)
+            } else if !lhs.is_empty() {
+                RuleMatch::Name(lhs.to_string())
+            } else {
+                return None;
+            };
+            Some(PriorityRule { matcher, priority })
+        })
+        .collect()
+}
+
+pub fn format_rules(rules: &[PriorityRule]) -> String {
+    rules
+        .iter()
+        .map(|r| r.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+const UNITS: [(&str, u64); 4] = [
+    ("TB", 1 << 40),
+    ("GB", 1 << 30),
+    ("MB", 1 << 20),
+    ("KB", 1 << 10),
+];
+
+/// "10GB" / "512 kb" / "2048" (bytes)
+fn parse_size(input: &str) -> Option<u64> {
+    let input = input.trim().to_uppercase();
+    for (unit, factor) in UNITS {
+        if let Some(number) = input.strip_suffix(unit) {
+            return number.trim().parse::<u64>().ok()?.checked_mul(factor);
+        }
+    }
+    input
+        .strip_suffix('B')
+        .unwrap_or(&input)
+        .trim()
+        .parse()
+        .ok()
+}
+
+fn format_size(bytes: u64) -> String {
+    UNITS
+        .iter()
+        .find(|(_, factor)| bytes >= *factor && bytes.is_multiple_of(*factor))
+        .map_or(bytes.to_string(), |(unit, factor)| {
+            format!("{}{}", bytes / factor, unit)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_matching_rule_sets_priority() {
+        let rules = parse_rules("*.srt=1, >10GB=20, <1 mb=5, bogus, =3");
+        assert_eq!(rules.len(), 3);
+        assert_eq!(format_rules(&rules), "*.srt=1, >10GB=20, <1MB=5");
+
+        assert_eq!(priority_for(&rules, "Movie.EN.SRT", 40_000), 1);
+        assert_eq!(priority_for(&rules, "movie.mkv", 12 << 30), 20);
+        assert_eq!(priority_for(&rules, "cover.jpg", 300_000), 5);
+        assert_eq!(priority_for(&rules, "movie.mkv", 4 << 30), DEFAULT_PRIORITY);
+    }
+}
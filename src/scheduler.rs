@@ -1,19 +1,103 @@
 use crate::settings::{ScheduleConfig, ScheduleMode, WeekDays};
-use chrono::{DateTime, Datelike, Duration, Local, Timelike, Weekday};
+use chrono::{DateTime, Datelike, Duration, NaiveDateTime, TimeZone, Timelike, Weekday};
 
 pub struct Scheduler;
 
+pub const WEEK: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
 impl Scheduler {
-    pub fn is_allowed(config: &ScheduleConfig, now: DateTime<Local>) -> bool {
+    pub fn is_allowed<Tz: TimeZone>(config: &ScheduleConfig, now: DateTime<Tz>) -> bool {
+        Self::allowed_at(config, now.weekday(), now.hour() * 60 + now.minute())
+    }
+
+    /// Whether `config` lets transfers run on `weekday`, `minutes` past midnight
+    pub fn allowed_at(config: &ScheduleConfig, weekday: Weekday, minutes: u32) -> bool {
         match config.mode {
             ScheduleMode::None => true,
-            ScheduleMode::Daily => Self::check_time(config, now),
-            ScheduleMode::Weekly => Self::check_weekly(config, now),
+            ScheduleMode::Daily => Self::check_time(config, minutes),
+            ScheduleMode::Weekly => Self::check_weekly(config, weekday, minutes),
+            ScheduleMode::Custom => {
+                config.hours[weekday.num_days_from_monday() as usize] & (1 << (minutes / 60)) != 0
+            }
+        }
+    }
+
+    /// When `config` next flips between allowing and holding transfers, to the
+    /// minute; None when it never does (no schedule, or allowed all week)
+    pub fn next_transition<Tz: TimeZone>(
+        config: &ScheduleConfig,
+        now: DateTime<Tz>,
+    ) -> Option<DateTime<Tz>> {
+        if Self::is_allowed(config, now.clone()) {
+            Self::next_block_start(config, now)
+        } else {
+            Self::next_allowed_start(config, now)
         }
     }
 
-    fn check_time(config: &ScheduleConfig, now: DateTime<Local>) -> bool {
-        let current_minutes = now.hour() as u32 * 60 + now.minute() as u32;
+    /// The next time after `now` that a window opens
+    pub fn next_allowed_start<Tz: TimeZone>(
+        config: &ScheduleConfig,
+        now: DateTime<Tz>,
+    ) -> Option<DateTime<Tz>> {
+        Self::next_edge(config, now, true)
+    }
+
+    /// The next time after `now` that transfers are held again
+    pub fn next_block_start<Tz: TimeZone>(
+        config: &ScheduleConfig,
+        now: DateTime<Tz>,
+    ) -> Option<DateTime<Tz>> {
+        Self::next_edge(config, now, false)
+    }
+
+    /// Walk the wall clock a minute at a time (windows are wall-clock times,
+    /// so a DST change moves them in real time) until the schedule turns
+    /// `to_allowed`. A week and a day covers every weekly pattern.
+    fn next_edge<Tz: TimeZone>(
+        config: &ScheduleConfig,
+        now: DateTime<Tz>,
+        to_allowed: bool,
+    ) -> Option<DateTime<Tz>> {
+        let at = |wall: NaiveDateTime| {
+            Self::allowed_at(config, wall.weekday(), wall.hour() * 60 + wall.minute())
+        };
+        let start = now.naive_local().with_second(0)?.with_nanosecond(0)?;
+        let mut was = at(start);
+        let wall = (1..=8 * 24 * 60)
+            .map(|step| start + Duration::minutes(step))
+            .find(|&wall| {
+                let allowed = at(wall);
+                let edge = allowed == to_allowed && was != to_allowed;
+                was = allowed;
+                edge
+            })?;
+        // A time skipped by the clocks going forward happens when the gap
+        // ends; one repeated by them going back, the first time round
+        (0..=180)
+            .map(|skip| wall + Duration::minutes(skip))
+            .find_map(|wall| now.timezone().from_local_datetime(&wall).earliest())
+    }
+
+    /// The week hour by hour, as the Schedule timeline shows it: an hour counts
+    /// as allowed when its middle is
+    pub fn hour_grid(config: &ScheduleConfig) -> [u32; 7] {
+        WEEK.map(|day| {
+            (0..24)
+                .filter(|hour| Self::allowed_at(config, day, hour * 60 + 30))
+                .fold(0, |bits, hour| bits | 1 << hour)
+        })
+    }
+
+    fn check_time(config: &ScheduleConfig, current_minutes: u32) -> bool {
         let start_minutes = config.start_time.hour as u32 * 60 + config.start_time.minute as u32;
         let end_minutes = config.end_time.hour as u32 * 60 + config.end_time.minute as u32;
 
@@ -30,20 +114,19 @@ impl Scheduler {
         }
     }
 
-    fn check_weekly(config: &ScheduleConfig, now: DateTime<Local>) -> bool {
-        let current_minutes = now.hour() as u32 * 60 + now.minute() as u32;
+    fn check_weekly(config: &ScheduleConfig, weekday: Weekday, current_minutes: u32) -> bool {
         let start_minutes = config.start_time.hour as u32 * 60 + config.start_time.minute as u32;
         let end_minutes = config.end_time.hour as u32 * 60 + config.end_time.minute as u32;
 
         if start_minutes == end_minutes {
             // If full day, just check today
-            return Self::check_day_enabled(&config.days, now.weekday());
+            return Self::check_day_enabled(&config.days, weekday);
         }
 
         if start_minutes < end_minutes {
             // Normal day range: Must be allowed today AND in time range
             if current_minutes >= start_minutes && current_minutes < end_minutes {
-                Self::check_day_enabled(&config.days, now.weekday())
+                Self::check_day_enabled(&config.days, weekday)
             } else {
                 false
             }
@@ -51,11 +134,10 @@ impl Scheduler {
             // Overnight range
             if current_minutes >= start_minutes {
                 // Evening side: use Today's permission
-                Self::check_day_enabled(&config.days, now.weekday())
+                Self::check_day_enabled(&config.days, weekday)
             } else if current_minutes < end_minutes {
                 // Morning side: use Yesterday's permission
-                let yesterday = now - Duration::days(1);
-                Self::check_day_enabled(&config.days, yesterday.weekday())
+                Self::check_day_enabled(&config.days, weekday.pred())
             } else {
                 false
             }
@@ -79,7 +161,75 @@ impl Scheduler {
 mod tests {
     use super::*;
     use crate::settings::{TimeOfDay, WeekDays};
-    use chrono::TimeZone;
+    use chrono::{FixedOffset, Local, LocalResult, NaiveDate};
+
+    /// Europe/London for 2024 only: GMT, BST from 31 March 01:00 UTC until
+    /// 27 October 01:00 UTC, then GMT again
+    #[derive(Debug, Clone, Copy)]
+    struct London;
+
+    fn bst(utc: &NaiveDateTime) -> FixedOffset {
+        let spring = NaiveDate::from_ymd_opt(2024, 3, 31)
+            .unwrap()
+            .and_hms_opt(1, 0, 0)
+            .unwrap();
+        let autumn = NaiveDate::from_ymd_opt(2024, 10, 27)
+            .unwrap()
+            .and_hms_opt(1, 0, 0)
+            .unwrap();
+        let hours = if (spring..autumn).contains(utc) { 1 } else { 0 };
+        FixedOffset::east_opt(hours * 3600).unwrap()
+    }
+
+    impl TimeZone for London {
+        type Offset = FixedOffset;
+
+        fn from_offset(_: &FixedOffset) -> Self {
+            London
+        }
+
+        fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<FixedOffset> {
+            self.offset_from_local_datetime(&local.and_hms_opt(0, 0, 0).unwrap())
+        }
+
+        fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset> {
+            // Earliest instant first: the summer offset
+            let fits: Vec<FixedOffset> = [3600, 0]
+                .map(|secs| FixedOffset::east_opt(secs).unwrap())
+                .into_iter()
+                .filter(|off| {
+                    bst(&(*local - Duration::seconds(off.local_minus_utc() as i64))) == *off
+                })
+                .collect();
+            match fits[..] {
+                [] => LocalResult::None,
+                [one] => LocalResult::Single(one),
+                [early, late, ..] => LocalResult::Ambiguous(early, late),
+            }
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+            bst(&utc.and_hms_opt(0, 0, 0).unwrap())
+        }
+
+        fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> FixedOffset {
+            bst(utc)
+        }
+    }
+
+    fn london(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<London> {
+        London
+            .with_ymd_and_hms(y, mo, d, h, mi, 0)
+            .earliest()
+            .unwrap()
+    }
+
+    fn utc(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, mo, d)
+            .unwrap()
+            .and_hms_opt(h, mi, 0)
+            .unwrap()
+    }
 
     fn make_config(
         mode: ScheduleMode,
@@ -108,6 +258,7 @@ mod tests {
                 sat: false,
                 sun: false,
             }),
+            hours: [0; 7],
         }
     }
 
@@ -184,4 +335,194 @@ mod tests {
         let sat_morning = Local.with_ymd_and_hms(2023, 10, 28, 1, 30, 0).unwrap();
         assert!(!Scheduler::is_allowed(&config, sat_morning));
     }
+
+    #[test]
+    fn test_hour_grid_matches_schedule_and_drives_custom_mode() {
+        let daily = make_config(ScheduleMode::Daily, 22, 0, 5, 0, None);
+        let grid = Scheduler::hour_grid(&daily);
+        // 22:00-05:00 covers hours 22, 23 and 0-4 every day
+        assert!(grid
+            .iter()
+            .all(|&bits| bits == 0b1100_0000_0000_0000_0001_1111));
+
+        let mut custom = make_config(ScheduleMode::Custom, 0, 0, 0, 0, None);
+        custom.hours[5] = 1 << 14; // Saturday 14:00-15:00 only
+        let sat = Local.with_ymd_and_hms(2023, 10, 28, 14, 45, 0).unwrap();
+        assert!(Scheduler::is_allowed(&custom, sat));
+        assert!(!Scheduler::is_allowed(
+            &custom,
+            sat + chrono::Duration::hours(1)
+        ));
+        assert!(!Scheduler::is_allowed(
+            &custom,
+            sat - chrono::Duration::days(1)
+        ));
+    }
+
+    #[test]
+    fn test_next_transition_finds_the_next_window_edge() {
+        // Mon-Thu 23:00-02:00
+        let config = make_config(ScheduleMode::Weekly, 23, 0, 2, 0, None);
+        let thu_noon = Local.with_ymd_and_hms(2023, 10, 26, 12, 0, 30).unwrap();
+        assert_eq!(
+            Scheduler::next_transition(&config, thu_noon),
+            Local.with_ymd_and_hms(2023, 10, 26, 23, 0, 0).single()
+        );
+        // Friday night is off, so Thursday's window is the last until Monday
+        let fri_early = Local.with_ymd_and_hms(2023, 10, 27, 1, 0, 0).unwrap();
+        assert_eq!(
+            Scheduler::next_transition(&config, fri_early),
+            Local.with_ymd_and_hms(2023, 10, 27, 2, 0, 0).single()
+        );
+        let fri_late = Local.with_ymd_and_hms(2023, 10, 27, 3, 0, 0).unwrap();
+        assert_eq!(
+            Scheduler::next_transition(&config, fri_late),
+            Local.with_ymd_and_hms(2023, 10, 30, 23, 0, 0).single()
+        );
+
+        let none = make_config(ScheduleMode::None, 0, 0, 0, 0, None);
+        assert_eq!(Scheduler::next_transition(&none, thu_noon), None);
+    }
+
+    #[test]
+    fn test_next_allowed_and_block_start_around_windows() {
+        let daily = make_config(ScheduleMode::Daily, 22, 0, 5, 0, None);
+        let evening = london(2024, 1, 10, 18, 0);
+        // Outside the window: opens tonight, next held at 05:00 after that
+        assert_eq!(
+            Scheduler::next_allowed_start(&daily, evening)
+                .unwrap()
+                .naive_local(),
+            utc(2024, 1, 10, 22, 0)
+        );
+        assert_eq!(
+            Scheduler::next_block_start(&daily, evening)
+                .unwrap()
+                .naive_local(),
+            utc(2024, 1, 11, 5, 0)
+        );
+        // Exactly at the start is already inside; the next opening is tomorrow
+        let opening = london(2024, 1, 10, 22, 0);
+        assert_eq!(
+            Scheduler::next_allowed_start(&daily, opening)
+                .unwrap()
+                .naive_local(),
+            utc(2024, 1, 11, 22, 0)
+        );
+        // Seconds past the minute don't hide an edge a minute away
+        let almost = London.with_ymd_and_hms(2024, 1, 10, 21, 59, 59).unwrap();
+        assert_eq!(
+            Scheduler::next_transition(&daily, almost)
+                .unwrap()
+                .naive_local(),
+            utc(2024, 1, 10, 22, 0)
+        );
+
+        // Sunday-only overnight window wraps into Monday morning
+        let sunday_only = make_config(
+            ScheduleMode::Weekly,
+            23,
+            0,
+            2,
+            0,
+            Some(WeekDays {
+                mon: false,
+                tue: false,
+                wed: false,
+                thu: false,
+                fri: false,
+                sat: false,
+                sun: true,
+            }),
+        );
+        let monday = london(2024, 1, 8, 1, 0); // inside Sunday night's window
+        assert_eq!(
+            Scheduler::next_block_start(&sunday_only, monday)
+                .unwrap()
+                .naive_local(),
+            utc(2024, 1, 8, 2, 0)
+        );
+        assert_eq!(
+            Scheduler::next_allowed_start(&sunday_only, monday)
+                .unwrap()
+                .naive_local(),
+            utc(2024, 1, 14, 23, 0)
+        );
+
+        // Whole days in weekly mode change over at midnight
+        let weekends = make_config(
+            ScheduleMode::Weekly,
+            0,
+            0,
+            0,
+            0,
+            Some(WeekDays {
+                mon: false,
+                tue: false,
+                wed: false,
+                thu: false,
+                fri: false,
+                sat: true,
+                sun: true,
+            }),
+        );
+        let wednesday = london(2024, 1, 10, 12, 0);
+        assert_eq!(
+            Scheduler::next_allowed_start(&weekends, wednesday)
+                .unwrap()
+                .naive_local(),
+            utc(2024, 1, 13, 0, 0)
+        );
+        assert_eq!(
+            Scheduler::next_block_start(&weekends, wednesday)
+                .unwrap()
+                .naive_local(),
+            utc(2024, 1, 15, 0, 0)
+        );
+
+        // Never held, or never allowed: nothing to wait for
+        let always = make_config(ScheduleMode::Daily, 3, 0, 3, 0, None);
+        assert_eq!(Scheduler::next_block_start(&always, wednesday), None);
+        let mut never = make_config(ScheduleMode::Custom, 0, 0, 0, 0, None);
+        assert_eq!(Scheduler::next_allowed_start(&never, wednesday), None);
+        never.hours[6] = 1 << 23;
+        assert_eq!(
+            Scheduler::next_allowed_start(&never, wednesday)
+                .unwrap()
+                .naive_local(),
+            utc(2024, 1, 14, 23, 0)
+        );
+    }
+
+    #[test]
+    fn test_next_window_follows_the_wall_clock_across_dst() {
+        // The day after clocks go forward, 22:00 comes an hour sooner in real time
+        let daily = make_config(ScheduleMode::Daily, 22, 0, 22, 30, None);
+        let saturday = london(2024, 3, 30, 23, 0);
+        let next = Scheduler::next_allowed_start(&daily, saturday).unwrap();
+        assert_eq!(next.naive_local(), utc(2024, 3, 31, 22, 0));
+        assert_eq!(next.naive_utc(), utc(2024, 3, 31, 21, 0));
+
+        // 01:30 doesn't exist that night; the window opens when the clocks
+        // land on 02:00
+        let skipped = make_config(ScheduleMode::Daily, 1, 30, 4, 0, None);
+        let before = london(2024, 3, 31, 0, 30);
+        let next = Scheduler::next_allowed_start(&skipped, before).unwrap();
+        assert_eq!(next.naive_local(), utc(2024, 3, 31, 2, 0));
+        assert_eq!(next.naive_utc(), utc(2024, 3, 31, 1, 0));
+
+        // 01:30 happens twice when they go back; the first one counts
+        let repeated = make_config(ScheduleMode::Daily, 1, 30, 4, 0, None);
+        let before = london(2024, 10, 27, 0, 30);
+        let next = Scheduler::next_allowed_start(&repeated, before).unwrap();
+        assert_eq!(next.naive_utc(), utc(2024, 10, 27, 0, 30));
+
+        // An overnight window spanning the change ends at 03:00 GMT, 6 hours
+        // of real time after a 22:00 BST start rather than 5
+        let overnight = make_config(ScheduleMode::Daily, 22, 0, 3, 0, None);
+        let start = london(2024, 10, 26, 22, 0);
+        let end = Scheduler::next_block_start(&overnight, start).unwrap();
+        assert_eq!(end.naive_utc(), utc(2024, 10, 27, 3, 0));
+        assert_eq!((end - start).num_hours(), 6);
+    }
 }
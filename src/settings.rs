@@ -1,6 +1,34 @@
+use crate::keyring;
+use crate::rules::PriorityRule;
+use crate::types::QueueOrder;
+use chrono::{FixedOffset, Timelike};
+
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const CONFIG_FILE: &str = "config.json";
+
+/// Where `name` (config.json, queue.json, the transfer journal) is kept: the
+/// per-user config folder, not whatever folder the app was started from. A
+/// browser opening an sftp:// link starts it in a folder of its own choosing.
+/// A copy an older version left in the working folder is carried over once.
+pub fn data_file(name: &str) -> PathBuf {
+    let Some(dirs) = directories::ProjectDirs::from("com", "simplesftp", "simplesftp") else {
+        return PathBuf::from(name);
+    };
+    let dir = dirs.config_dir();
+    if std::fs::create_dir_all(dir).is_err() {
+        return PathBuf::from(name);
+    }
+    let path = dir.join(name);
+    if !path.exists() && Path::new(name).is_file() {
+        let _ = std::fs::copy(name, &path);
+    }
+    path
+}
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AppConfig {
     pub sftp_config: SftpConfig,
     pub download_threshold: u8, // 0-100%
@@ -11,16 +39,338 @@ pub struct AppConfig {
     #[serde(default)]
     pub auto_connect: bool,
     #[serde(default)]
-    pub max_download_speed: u64, // KB/s, 0 = unlimited
+    pub max_download_speed: u64, // KB/s, 0 = unlimited; default for profiles without their own
+    #[serde(default)]
+    pub profile_speed_limits: HashMap<String, u64>, // KB/s, keyed by profile_key()
+    #[serde(default)]
+    pub profile_download_paths: HashMap<String, String>, // keyed by profile_key()
+    #[serde(default)]
+    pub profile_schedules: HashMap<String, ScheduleConfig>, // keyed by profile_key()
+    #[serde(default)]
+    pub fsync_interval_mb: u64, // 0 = only when a transfer pauses or finishes
+    #[serde(default)]
+    pub battery_mode: BatteryMode,
+    #[serde(default = "default_battery_speed")]
+    pub battery_speed_limit: u64, // KB/s while on battery in Throttle mode
+    #[serde(default)]
+    pub pause_on_metered: bool,
+    #[serde(default)]
+    pub metered_ssids: Vec<String>, // Wi-Fi names (globs) treated as metered too
+    #[serde(default)]
+    pub min_free_space_gb: u64, // downloads hold below this much free space, 0 = off
+    #[serde(default = "default_follow_stable")]
+    pub follow_stable_mins: u64, // followed files finish after this long without growing
+    #[serde(default)]
+    pub exclude_patterns: Vec<String>, // e.g. "*.nfo", skipped when queueing folders
+    #[serde(default = "default_filename_replacement")]
+    pub filename_replacement: String, // stands in for characters the local disk rejects
+    #[serde(default)]
+    pub priority_rules: Vec<PriorityRule>, // applied when items are queued
+    #[serde(default)]
+    pub queue_order: QueueOrder, // which waiting item starts next
+    #[serde(default)]
+    pub fair_batches: bool, // batches take turns for transfer slots instead of queue order alone
+    #[serde(default)]
+    pub fast_lane_mb: u64, // files up to this size get an extra slot of their own, 0 = off
+    #[serde(default)]
+    pub color_sizes: bool, // color-grade the Size column
+    #[serde(default)]
+    pub relative_times: bool, // "3 hours ago" instead of dates in the Modified column
+    #[serde(default)]
+    pub server_times: bool, // dates in the profile's server_timezone rather than this computer's
+    #[serde(default)]
+    pub high_contrast: bool, // black and white palette with stronger borders
+    #[serde(default)]
+    pub reduce_motion: bool, // no spinner, hover pop-ins or timed banners
+    #[serde(default)]
+    pub activity_verbose: bool, // Activity Log shows protocol-level commands too
+    #[serde(default)]
+    pub protocol_trace: bool, // advanced: libssh2 trace output into the Activity Log
     #[serde(default)]
     pub download_stats: Vec<DailyStat>,
+    #[serde(default)]
+    pub monthly_usage: Vec<MonthlyUsage>,
+    #[serde(default)]
+    pub sessions: HashMap<String, SessionState>, // keyed by profile_key()
+    #[serde(default)]
+    pub batch_history: Vec<BatchRecord>, // oldest first
+    #[serde(default)]
+    pub double_click: DoubleClick, // what double-clicking a remote file does
+    #[serde(default)]
+    pub existing_copy: ExistingCopy, // what Re-download does with the file already on disk
+    #[serde(default)]
+    pub write_checksums: bool, // SHA256SUMS into a finished folder's download location
+    #[serde(default)]
+    pub dry_run: bool, // deletes, moves, copies, overwrites and extracts are only logged
+    #[serde(default = "default_stale_queue_days")]
+    pub stale_queue_days: u64, // warn about items waiting longer than this, 0 = off
+    #[serde(default)]
+    pub watched_folders: Vec<WatchedFolder>,
+    #[serde(default)]
+    pub downloaded_names: HashMap<String, Vec<String>>, // file names fetched, keyed by profile_key()
+    #[serde(default)]
+    pub transfer_history: Vec<TransferRecord>, // oldest first, for the reports
+    #[serde(default)]
+    pub report: ReportConfig,
+    #[serde(default)]
+    pub jobs: Vec<Job>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+const MAX_BATCH_HISTORY: usize = 100;
+const MAX_JOB_HISTORY: usize = 20;
+const MAX_TRANSFER_HISTORY: usize = 5000;
+
+/// A transfer that completed or failed, summed up in the daily or weekly report
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransferRecord {
+    pub finished_at: i64, // unix seconds
+    pub name: String,
+    pub profile: String,
+    pub upload: bool,
+    pub bytes: u64,
+    pub seconds: u64,          // time spent running
+    pub error: Option<String>, // None when it completed
+}
+
+/// How often a summary of transfers goes out
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReportPeriod {
+    #[default]
+    Off,
+    Daily,
+    Weekly, // Monday to Sunday
+}
+
+/// Transfer reports for running unattended: saved to a folder, emailed, or both
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReportConfig {
+    pub period: ReportPeriod,
+    #[serde(default)]
+    pub folder: String, // HTML and CSV copies go here; empty = not saved
+    #[serde(default)]
+    pub smtp_url: String, // e.g. smtps://smtp.example.com:465; empty = not emailed
+    #[serde(default)]
+    pub smtp_user: String,
+    #[serde(default)]
+    pub smtp_password: Option<String>, // left out of config.json while the keyring holds it
+    #[serde(default)]
+    pub smtp_in_keyring: bool,
+    #[serde(default)]
+    pub email_to: String,
+    #[serde(default)]
+    pub last_report: String, // key of the last period sent, e.g. "2026-10-14"
+}
+
+impl ReportConfig {
+    const KEYRING_ACCOUNT: &'static str = "report/smtp";
+
+    /// Move the SMTP password into the keyring, as for the one-time code secret
+    pub fn store_smtp_password(&mut self) -> std::io::Result<()> {
+        self.smtp_in_keyring = false;
+        let Some(password) = self.smtp_password.as_deref().filter(|p| !p.is_empty()) else {
+            return Ok(());
+        };
+        keyring::store(Self::KEYRING_ACCOUNT, password)?;
+        self.smtp_in_keyring = true;
+        Ok(())
+    }
+}
+
+/// A queued folder that finished, listed under Config > History
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchRecord {
+    pub finished_at: String, // YYYY-MM-DD HH:MM
+    pub name: String,
+    pub files: usize,
+    pub bytes: u64,
+    pub elapsed_secs: u64,
+    pub failures: usize,
+    #[serde(default)]
+    pub manifest: Option<String>, // SHA256SUMS written for the batch, if any
+    #[serde(default)]
+    pub notes: Vec<String>, // its items' notes, each once
+}
+
+/// A remote folder checked every few minutes for new files to queue
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatchedFolder {
+    pub profile: String, // profile_key() of its server
+    pub remote_path: String,
+    #[serde(default)]
+    pub patterns: Vec<String>, // only names matching one of these; empty = every file
+    #[serde(default)]
+    pub skip_downloaded: bool, // leave out names in downloaded_names, even if since deleted
+}
+
+/// A named, reusable download: run from the Jobs dialog, daily by the
+/// scheduler, or with `--job <name>` on the command line
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Job {
+    pub name: String,
+    pub profile: String, // profile_key() of its server
+    pub remote_path: String,
+    #[serde(default)]
+    pub patterns: Vec<String>, // only names matching one of these; empty = every file
+    #[serde(default)]
+    pub destination: String, // local folder; empty = the server's download folder
+    #[serde(default)]
+    pub run_at: Option<TimeOfDay>, // daily; None = only when started by hand
+    #[serde(default)]
+    pub last_scheduled: String, // day of the last scheduled run, "2026-10-14"
+    #[serde(default)]
+    pub history: Vec<JobRun>, // oldest first, at most MAX_JOB_HISTORY
+}
+
+impl Job {
+    /// Whether the scheduler should run it: its time has come today and it
+    /// hasn't had today's run yet
+    pub fn due(&self, now: chrono::DateTime<chrono::Local>) -> bool {
+        let Some(at) = self.run_at else {
+            return false;
+        };
+        (now.hour(), now.minute()) >= (at.hour as u32, at.minute as u32)
+            && self.last_scheduled != now.format("%Y-%m-%d").to_string()
+    }
+
+    pub fn record(&mut self, run: JobRun) {
+        self.history.push(run);
+        let excess = self.history.len().saturating_sub(MAX_JOB_HISTORY);
+        self.history.drain(..excess);
+    }
+}
+
+/// One run of a job, for its history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobRun {
+    pub at: i64, // unix seconds
+    pub trigger: JobTrigger,
+    pub queued: usize,
+    pub error: Option<String>, // why nothing could be queued
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JobTrigger {
+    Manual,
+    Schedule,
+    CommandLine,
+}
+
+/// What transfers do while the laptop runs on battery
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BatteryMode {
+    #[default]
+    Ignore,
+    Pause,
+    Throttle,
+}
+
+/// What double-clicking a file in the remote listing does; folders always open
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DoubleClick {
+    #[default]
+    Nothing,
+    Queue,
+    Download,
+    Preview,
+}
+
+/// What re-downloading an item does with the local copy it replaces
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExistingCopy {
+    #[default]
+    Rename, // kept beside the new one as name.old.ext
+    Delete,
+}
+
+fn default_battery_speed() -> u64 {
+    500
+}
+
+fn default_follow_stable() -> u64 {
+    2
+}
+
+fn default_stale_queue_days() -> u64 {
+    3
+}
+
+fn default_filename_replacement() -> String {
+    "_".to_string()
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SortColumn {
+    #[default]
+    Name,
+    Size,
+    Type,
+    Modified,
+}
+
+/// Remote pane layout and browsing state, remembered per connection profile
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionState {
+    pub remote_path: String,
+    pub sort_column: SortColumn,
+    pub sort_ascending: bool,
+    pub filter: String,
+    pub show_hidden: bool,
+    pub show_owner: bool, // Owner and Mode columns
+    pub show_tree: bool,
+    pub grid_view: bool,
+    pub pane_ratio: f32,                 // Queue share of the window width
+    pub scroll_offset: f32,              // Remote listing, in pixels
+    pub visits: HashMap<String, String>, // folder -> last listed, UTC "%Y-%m-%d %H:%M:%S"
+}
+
+impl Default for SessionState {
+    fn default() -> Self {
+        Self {
+            remote_path: ".".to_string(),
+            sort_column: SortColumn::Name,
+            sort_ascending: true,
+            filter: String::new(),
+            show_hidden: true,
+            show_owner: false,
+            show_tree: false,
+            grid_view: false,
+            pane_ratio: 0.4,
+            scroll_offset: 0.0,
+            visits: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DailyStat {
     pub date: String, // YYYY-MM-DD
     pub bytes_downloaded: u64,
     pub seconds_active: u64,
+    #[serde(default)]
+    pub hourly: Vec<(u64, u64)>, // bytes and seconds per local hour, 0-23
+}
+
+/// A daily download window picked from the per-hour speeds in the stats history
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduleSuggestion {
+    pub start_hour: u8,
+    pub end_hour: u8, // exclusive; below start_hour when the window runs past midnight
+    pub window_speed: u64, // bytes/s over the window
+    pub other_speed: u64, // bytes/s over the other hours with transfers
+    pub limit_kb: u64, // most of the window's speed, leaving some for everything else
+}
+
+const SUGGEST_DAYS: usize = 30;
+const SUGGEST_MIN_SECS: u64 = 120; // transfer time an hour needs before its speed counts
+
+/// Bytes transferred with one server in one calendar month, for providers
+/// that cap traffic per server
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MonthlyUsage {
+    pub month: String,   // YYYY-MM
+    pub profile: String, // profile_key() of the server
+    pub bytes: u64,
 }
 
 impl Default for AppConfig {
@@ -37,18 +387,132 @@ impl Default for AppConfig {
             last_remote_path: ".".to_string(),
             auto_connect: false,
             max_download_speed: 0,
+            profile_speed_limits: HashMap::new(),
+            profile_download_paths: HashMap::new(),
+            profile_schedules: HashMap::new(),
+            fsync_interval_mb: 0,
+            battery_mode: BatteryMode::Ignore,
+            battery_speed_limit: default_battery_speed(),
+            pause_on_metered: false,
+            metered_ssids: Vec::new(),
+            min_free_space_gb: 0,
+            follow_stable_mins: default_follow_stable(),
+            exclude_patterns: Vec::new(),
+            filename_replacement: default_filename_replacement(),
+            priority_rules: Vec::new(),
+            queue_order: QueueOrder::default(),
+            fair_batches: false,
+            fast_lane_mb: 0,
+            color_sizes: false,
+            relative_times: false,
+            server_times: false,
+            high_contrast: false,
+            reduce_motion: false,
+            activity_verbose: false,
+            protocol_trace: false,
             download_stats: Vec::new(),
+            monthly_usage: Vec::new(),
+            sessions: HashMap::new(),
+            batch_history: Vec::new(),
+            transfer_history: Vec::new(),
+            report: ReportConfig::default(),
+            jobs: Vec::new(),
+            double_click: DoubleClick::Nothing,
+            existing_copy: ExistingCopy::Rename,
+            write_checksums: false,
+            dry_run: false,
+            stale_queue_days: default_stale_queue_days(),
+            watched_folders: Vec::new(),
+            downloaded_names: HashMap::new(),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SftpConfig {
     pub host: String,
     pub port: u16,
     pub username: String,
     pub password: Option<String>,
     pub private_key_path: Option<String>,
+    #[serde(default)]
+    pub pre_connect_command: Option<String>, // e.g. a port knock, run before every connect
+    #[serde(default)]
+    pub start_directory: Option<String>, // opened on connect instead of the last folder
+    #[serde(default)]
+    pub trash_folder: Option<String>, // remote deletes move here instead of unlinking
+    #[serde(default)]
+    pub compression: bool, // ask for zlib transport compression (ssh -C)
+    #[serde(default)]
+    pub algorithms: AlgorithmPrefs,
+    #[serde(default)]
+    pub read_only: bool, // refuse deletes, renames, copies and uploads on this server
+    #[serde(default)]
+    pub allow_shell: bool, // the Console may run commands here
+    #[serde(default)]
+    pub totp_secret: Option<String>, // base32; left out of config.json while the keyring holds it
+    #[serde(default)]
+    pub totp_in_keyring: bool,
+    #[serde(default)]
+    pub server_timezone: Option<String>, // UTC offset like "+02:00", for showing times as the server does
+}
+
+/// Preferred SSH algorithms, comma-separated in order of preference.
+/// Blank lists leave libssh2's defaults in place.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlgorithmPrefs {
+    pub strict: bool, // only the modern preset below, ignoring the lists
+    pub kex: String,
+    pub ciphers: String,
+    pub macs: String,
+}
+
+const STRICT_KEX: &str = "curve25519-sha256,curve25519-sha256@libssh.org,ecdh-sha2-nistp521,ecdh-sha2-nistp384,ecdh-sha2-nistp256";
+const STRICT_CIPHERS: &str =
+    "aes256-gcm@openssh.com,aes128-gcm@openssh.com,aes256-ctr,aes192-ctr,aes128-ctr";
+const STRICT_MACS: &str =
+    "hmac-sha2-512-etm@openssh.com,hmac-sha2-256-etm@openssh.com,hmac-sha2-512,hmac-sha2-256";
+
+impl AlgorithmPrefs {
+    /// (kex, ciphers, macs) to hand to the session, None where the default stays
+    pub fn effective(&self) -> [Option<String>; 3] {
+        if self.strict {
+            return [STRICT_KEX, STRICT_CIPHERS, STRICT_MACS].map(|s| Some(s.to_string()));
+        }
+        [&self.kex, &self.ciphers, &self.macs].map(|list| {
+            let list: String = list.chars().filter(|c| !c.is_whitespace()).collect();
+            (!list.is_empty()).then_some(list)
+        })
+    }
+}
+
+impl SftpConfig {
+    /// "user@host:port", the key per-server settings and queue items are stored under
+    pub fn profile_key(&self) -> String {
+        format!("{}@{}:{}", self.username, self.host, self.port)
+    }
+
+    /// The server's timezone, when the profile names a valid one
+    pub fn server_offset(&self) -> Option<FixedOffset> {
+        self.server_timezone.as_deref().and_then(parse_utc_offset)
+    }
+
+    fn totp_account(&self) -> String {
+        format!("{}/totp", self.profile_key())
+    }
+
+    /// Move the one-time code secret into the keyring, leaving it in the config
+    /// file only where there's no keyring to hold it
+    pub fn store_totp_secret(&mut self) -> std::io::Result<()> {
+        self.totp_in_keyring = false;
+        let Some(secret) = self.totp_secret.as_deref().filter(|s| !s.trim().is_empty()) else {
+            return Ok(());
+        };
+        keyring::store(&self.totp_account(), secret)?;
+        self.totp_in_keyring = true;
+        Ok(())
+    }
 }
 
 impl Default for SftpConfig {
@@ -59,6 +523,16 @@ impl Default for SftpConfig {
             username: String::new(),
             password: None,
             private_key_path: None,
+            pre_connect_command: None,
+            start_directory: None,
+            trash_folder: None,
+            compression: false,
+            read_only: false,
+            allow_shell: false,
+            totp_secret: None,
+            totp_in_keyring: false,
+            server_timezone: None,
+            algorithms: AlgorithmPrefs::default(),
         }
     }
 }
@@ -68,15 +542,46 @@ pub enum ScheduleMode {
     None,
     Daily,
     Weekly,
+    Custom, // hour by hour, painted on the Schedule timeline
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct TimeOfDay {
     pub hour: u8,
     pub minute: u8,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl TimeOfDay {
+    /// Read a typed time: "22:30", "2230", "7", "7:05 pm" or "12am"
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().to_ascii_lowercase();
+        let (digits, pm) = match text.strip_suffix("pm").or(text.strip_suffix("p")) {
+            Some(rest) => (rest.trim(), Some(true)),
+            None => match text.strip_suffix("am").or(text.strip_suffix("a")) {
+                Some(rest) => (rest.trim(), Some(false)),
+                None => (text.as_str(), None),
+            },
+        };
+        let (hour, minute) = match digits.split_once(':') {
+            Some((h, m)) if m.len() == 2 => (h.parse::<u8>().ok()?, m.parse::<u8>().ok()?),
+            Some(_) => return None,
+            None if digits.len() > 2 => {
+                let (h, m) = digits.split_at(digits.len() - 2);
+                (h.parse().ok()?, m.parse().ok()?)
+            }
+            None => (digits.parse().ok()?, 0),
+        };
+        let hour = match pm {
+            Some(_) if !(1..=12).contains(&hour) => return None,
+            Some(true) => hour % 12 + 12,
+            Some(false) => hour % 12,
+            None => hour,
+        };
+        (hour < 24 && minute < 60).then_some(Self { hour, minute })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct WeekDays {
     pub mon: bool,
     pub tue: bool,
@@ -87,12 +592,14 @@ pub struct WeekDays {
     pub sun: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ScheduleConfig {
     pub mode: ScheduleMode,
     pub start_time: TimeOfDay,
     pub end_time: TimeOfDay,
     pub days: WeekDays,
+    #[serde(default)]
+    pub hours: [u32; 7], // Custom mode: Mon..Sun, bit n set = hour n allowed
 }
 
 impl Default for ScheduleConfig {
@@ -110,22 +617,128 @@ impl Default for ScheduleConfig {
                 sat: false,
                 sun: false,
             },
+            hours: [0; 7],
         }
     }
 }
 
 impl AppConfig {
+    /// Identifies the current connection profile, e.g. "pi@192.168.1.10:22"
+    pub fn profile_key(&self) -> String {
+        self.sftp_config.profile_key()
+    }
+
+    /// Note a finished download's file name for watched-folder dedup
+    pub fn remember_download(&mut self, profile: &str, name: &str) {
+        let names = self
+            .downloaded_names
+            .entry(profile.to_string())
+            .or_default();
+        if !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    }
+
+    /// Where the current profile's downloads go: its own folder if set, else the default
+    pub fn download_path(&self) -> &str {
+        self.profile_download_paths
+            .get(&self.profile_key())
+            .unwrap_or(&self.local_download_path)
+    }
+
+    /// When the current profile may transfer: its own schedule if set, else the default
+    pub fn active_schedule(&self) -> &ScheduleConfig {
+        self.profile_schedules
+            .get(&self.profile_key())
+            .unwrap_or(&self.schedule)
+    }
+
+    /// The schedule the Schedule dialog edits, the same one `active_schedule` returns
+    pub fn active_schedule_mut(&mut self) -> &mut ScheduleConfig {
+        let key = self.profile_key();
+        match self.profile_schedules.get_mut(&key) {
+            Some(schedule) => schedule,
+            None => &mut self.schedule,
+        }
+    }
+
     pub fn load() -> Self {
-        if let Ok(content) = std::fs::read_to_string("config.json") {
-            serde_json::from_str(&content).unwrap_or_default()
-        } else {
-            Self::default()
+        // Unit tests drive the app on defaults, never the developer's own config
+        if cfg!(test) {
+            return Self::default();
         }
+        let mut config: Self = match std::fs::read_to_string(data_file(CONFIG_FILE)) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
+            Err(_) => Self::default(),
+        };
+        let sftp = &mut config.sftp_config;
+        if sftp.totp_in_keyring {
+            sftp.totp_secret = keyring::load(&sftp.totp_account());
+        }
+        if config.report.smtp_in_keyring {
+            config.report.smtp_password = keyring::load(ReportConfig::KEYRING_ACCOUNT);
+        }
+        config
     }
 
     pub fn save(&self) -> std::io::Result<()> {
-        let content = serde_json::to_string_pretty(self)?;
-        std::fs::write("config.json", content)
+        if cfg!(test) {
+            return Ok(());
+        }
+        let content = if self.sftp_config.totp_in_keyring || self.report.smtp_in_keyring {
+            let mut stored = self.clone();
+            if self.sftp_config.totp_in_keyring {
+                stored.sftp_config.totp_secret = None;
+            }
+            if self.report.smtp_in_keyring {
+                stored.report.smtp_password = None;
+            }
+            serde_json::to_string_pretty(&stored)?
+        } else {
+            serde_json::to_string_pretty(self)?
+        };
+        std::fs::write(data_file(CONFIG_FILE), content)
+    }
+
+    /// `saved` with this config's running state (stats, history, remembered
+    /// sessions) carried over, for discarding a dialog's edits without losing
+    /// what happened in the background meanwhile
+    pub fn reverted_to(&self, saved: &AppConfig) -> AppConfig {
+        AppConfig {
+            last_remote_path: self.last_remote_path.clone(),
+            auto_connect: self.auto_connect,
+            download_stats: self.download_stats.clone(),
+            monthly_usage: self.monthly_usage.clone(),
+            sessions: self.sessions.clone(),
+            batch_history: self.batch_history.clone(),
+            transfer_history: self.transfer_history.clone(),
+            jobs: self.jobs.clone(), // edited in their own dialog, saved as they change
+            report: ReportConfig {
+                last_report: self.report.last_report.clone(),
+                ..saved.report.clone()
+            },
+            ..saved.clone()
+        }
+    }
+
+    /// True if anything the user edits differs from `saved`
+    pub fn edited_since(&self, saved: &AppConfig) -> bool {
+        self.reverted_to(saved) != *self
+    }
+
+    pub fn record_batch(&mut self, record: BatchRecord) {
+        self.batch_history.push(record);
+        let excess = self.batch_history.len().saturating_sub(MAX_BATCH_HISTORY);
+        self.batch_history.drain(..excess);
+    }
+
+    pub fn record_transfer(&mut self, record: TransferRecord) {
+        self.transfer_history.push(record);
+        let excess = self
+            .transfer_history
+            .len()
+            .saturating_sub(MAX_TRANSFER_HISTORY);
+        self.transfer_history.drain(..excess);
     }
 
     pub fn get_today_stat(&mut self) -> &mut DailyStat {
@@ -135,15 +748,121 @@ impl AppConfig {
                 date: today,
                 bytes_downloaded: 0,
                 seconds_active: 0,
+                hourly: Vec::new(),
             });
         }
         self.download_stats.last_mut().unwrap()
     }
 
     pub fn add_daily_stat(&mut self, bytes: u64, seconds: u64) {
+        let hour = chrono::Local::now().hour() as usize;
         let stat = self.get_today_stat();
         stat.bytes_downloaded += bytes;
         stat.seconds_active += seconds;
+        stat.hourly.resize(stat.hourly.len().max(24), (0, 0));
+        stat.hourly[hour].0 += bytes;
+        stat.hourly[hour].1 += seconds;
+    }
+
+    /// Put `suggestion` into the schedule being edited. The limit goes with it:
+    /// to this server alone when it has its own schedule, else the default
+    pub fn use_schedule_suggestion(&mut self, suggestion: &ScheduleSuggestion) {
+        let schedule = self.active_schedule_mut();
+        schedule.mode = ScheduleMode::Daily;
+        schedule.start_time = TimeOfDay {
+            hour: suggestion.start_hour,
+            minute: 0,
+        };
+        schedule.end_time = TimeOfDay {
+            hour: suggestion.end_hour,
+            minute: 0,
+        };
+        if self.has_own_schedule() {
+            self.profile_speed_limits
+                .insert(self.profile_key(), suggestion.limit_kb);
+        } else {
+            self.max_download_speed = suggestion.limit_kb;
+        }
+    }
+
+    pub fn has_own_schedule(&self) -> bool {
+        self.profile_schedules.contains_key(&self.profile_key())
+    }
+
+    /// The stretch of hours that has been clearly fastest over the last month,
+    /// grown out from the fastest hour while neighbours keep within 80% of it.
+    /// None without enough history, or when no hours stand out
+    pub fn suggest_schedule(&self) -> Option<ScheduleSuggestion> {
+        let start_idx = self.download_stats.len().saturating_sub(SUGGEST_DAYS);
+        let mut totals = [(0u64, 0u64); 24];
+        for stat in &self.download_stats[start_idx..] {
+            for (total, (bytes, secs)) in totals.iter_mut().zip(&stat.hourly) {
+                total.0 += bytes;
+                total.1 += secs;
+            }
+        }
+        let speed = |hour: usize| {
+            let (bytes, secs) = totals[hour % 24];
+            (secs >= SUGGEST_MIN_SECS).then(|| bytes / secs)
+        };
+        if (0..24).filter(|&h| speed(h).is_some()).count() < 3 {
+            return None;
+        }
+        let best = (0..24).max_by_key(|&h| speed(h).unwrap_or(0))?;
+        let threshold = speed(best)? * 4 / 5;
+        let fast = |hour: usize| speed(hour).is_some_and(|s| s >= threshold);
+        let (mut start, mut end) = (best + 24, best + 25); // kept above 0 while growing
+        while end - start < 23 && fast(end) {
+            end += 1;
+        }
+        while end - start < 23 && fast(start - 1) {
+            start -= 1;
+        }
+        let sum = |hours: &mut dyn Iterator<Item = usize>| {
+            hours.fold((0, 0), |(b, s), h| {
+                let (bytes, secs) = totals[h % 24];
+                (b + bytes, s + secs)
+            })
+        };
+        let (window_bytes, window_secs) = sum(&mut (start..end));
+        let (other_bytes, other_secs) = sum(&mut (end..start + 24));
+        let window_speed = window_bytes / window_secs.max(1);
+        let other_speed = other_bytes / other_secs.max(1);
+        // Without a real difference a window only holds downloads back
+        if other_secs < SUGGEST_MIN_SECS || window_speed < other_speed * 6 / 5 {
+            return None;
+        }
+        Some(ScheduleSuggestion {
+            start_hour: (start % 24) as u8,
+            end_hour: (end % 24) as u8,
+            window_speed,
+            other_speed,
+            limit_kb: window_speed * 9 / 10 / 1024,
+        })
+    }
+
+    /// Count `bytes` against `profile` for the current month
+    pub fn add_usage(&mut self, profile: &str, bytes: u64) {
+        let month = chrono::Local::now().format("%Y-%m").to_string();
+        match self
+            .monthly_usage
+            .iter_mut()
+            .find(|u| u.month == month && u.profile == profile)
+        {
+            Some(usage) => usage.bytes += bytes,
+            None => self.monthly_usage.push(MonthlyUsage {
+                month,
+                profile: profile.to_string(),
+                bytes,
+            }),
+        }
+    }
+
+    /// Usage per server and month, newest month first, busiest server first within it
+    pub fn usage_by_month(&self) -> Vec<&MonthlyUsage> {
+        let mut usage: Vec<&MonthlyUsage> = self.monthly_usage.iter().collect();
+        usage.sort_by(|a, b| b.month.cmp(&a.month).then(b.bytes.cmp(&a.bytes)));
+        usage
     }
 
     pub fn get_weekly_average(&self) -> u64 {
@@ -171,3 +890,289 @@ impl AppConfig {
         }
     }
 }
+
+pub const BACKUP_FORMAT: u32 = 1;
+
+/// A settings backup file: the whole config (server, per-profile settings,
+/// rules, schedules, watched folders) for setting up another machine
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Backup {
+    pub format: u32,
+    pub created: String, // YYYY-MM-DD HH:MM, local time
+    pub config: AppConfig,
+}
+
+impl AppConfig {
+    /// This config as a backup file; the saved passwords and one-time code
+    /// secret are never written out
+    pub fn backup(&self, created: &str) -> String {
+        let mut config = self.clone();
+        config.sftp_config.password = None;
+        config.sftp_config.totp_secret = None;
+        config.sftp_config.totp_in_keyring = false;
+        config.report.smtp_password = None;
+        config.report.smtp_in_keyring = false;
+        let backup = Backup {
+            format: BACKUP_FORMAT,
+            created: created.to_string(),
+            config,
+        };
+        serde_json::to_string_pretty(&backup).unwrap_or_default()
+    }
+
+    /// The config a backup file restores, keeping this config's password and
+    /// code secret when the backup is for the same server, and its SMTP
+    /// password for the same mail account
+    pub fn restored_from(&self, text: &str) -> Result<AppConfig, String> {
+        let backup: Backup =
+            serde_json::from_str(text).map_err(|e| format!("Not a settings backup: {}", e))?;
+        if backup.format > BACKUP_FORMAT {
+            return Err("This backup is from a newer version of SimpleSFTP".into());
+        }
+        let mut config = backup.config;
+        if config.profile_key() == self.profile_key() {
+            config.sftp_config.password = self.sftp_config.password.clone();
+            config.sftp_config.totp_secret = self.sftp_config.totp_secret.clone();
+            config.sftp_config.totp_in_keyring = self.sftp_config.totp_in_keyring;
+        }
+        if config.report.smtp_user == self.report.smtp_user {
+            config.report.smtp_password = self.report.smtp_password.clone();
+            config.report.smtp_in_keyring = self.report.smtp_in_keyring;
+        }
+        Ok(config)
+    }
+}
+
+/// Problems that keep the Settings form from being saved, each shown under its field
+#[derive(Debug, Default, PartialEq)]
+pub struct FormErrors {
+    pub host: Option<&'static str>,
+    pub port: Option<&'static str>,
+    pub download_path: Option<&'static str>,
+    pub timezone: Option<&'static str>,
+}
+
+impl FormErrors {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Check the Settings form; `port_input` is the port field as typed
+pub fn validate(config: &AppConfig, port_input: &str) -> FormErrors {
+    let host = config.sftp_config.host.trim();
+    FormErrors {
+        host: if host.is_empty() {
+            Some("Host is required")
+        } else if host.contains(char::is_whitespace) {
+            Some("Host can't contain spaces")
+        } else {
+            None
+        },
+        port: match port_input.trim().parse::<u16>() {
+            Ok(1..) => None,
+            _ => Some("Port must be a number from 1 to 65535"),
+        },
+        download_path: [&config.local_download_path, config.download_path()]
+            .iter()
+            .any(|path| !std::path::Path::new(path).is_dir())
+            .then_some("Download folder doesn't exist"),
+        timezone: match &config.sftp_config.server_timezone {
+            Some(zone) if parse_utc_offset(zone).is_none() => {
+                Some("Server timezone must be a UTC offset like +02:00")
+            }
+            _ => None,
+        },
+    }
+}
+
+/// "+02:00", "-0530", "UTC+2" or "Z" as a fixed offset from UTC
+pub fn parse_utc_offset(text: &str) -> Option<FixedOffset> {
+    let text = text.trim();
+    let text = text
+        .strip_prefix("UTC")
+        .or_else(|| text.strip_prefix("GMT"))
+        .unwrap_or(text);
+    if text.is_empty() || text == "Z" {
+        return FixedOffset::east_opt(0);
+    }
+    let (sign, rest) = match text.split_at(1) {
+        ("+", rest) => (1, rest),
+        ("-", rest) => (-1, rest),
+        _ => return None,
+    };
+    let (hours, mins) = match rest.split_once(':') {
+        Some((h, m)) => (h, m),
+        None if rest.len() > 2 => rest.split_at(rest.len() - 2),
+        None => (rest, "0"),
+    };
+    let hours: i32 = hours.parse().ok()?;
+    let mins: i32 = mins.parse().ok()?;
+    if hours > 14 || mins > 59 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + mins * 60))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backup_leaves_out_password_and_restores_settings() {
+        let mut config = AppConfig::default();
+        config.sftp_config.host = "nas.local".into();
+        config.sftp_config.password = Some("hunter2".into());
+        config.exclude_patterns = vec!["*.nfo".into()];
+        let text = config.backup("2026-10-15 09:00");
+        assert!(!text.contains("hunter2"));
+
+        // Same server here: the password typed on this machine is kept
+        let restored = config.restored_from(&text).unwrap();
+        assert_eq!(restored, config);
+        let fresh = AppConfig::default().restored_from(&text).unwrap();
+        assert_eq!(fresh.sftp_config.password, None);
+        assert_eq!(fresh.exclude_patterns, ["*.nfo"]);
+
+        let newer = text.replace("\"format\": 1", "\"format\": 2");
+        assert!(config.restored_from(&newer).is_err());
+        assert!(config.restored_from("{}").is_err());
+    }
+
+    #[test]
+    fn test_time_of_day_parses_24h_and_12h() {
+        let at = |hour, minute| Some(TimeOfDay { hour, minute });
+        assert_eq!(TimeOfDay::parse("22:30"), at(22, 30));
+        assert_eq!(TimeOfDay::parse("0630"), at(6, 30));
+        assert_eq!(TimeOfDay::parse("7"), at(7, 0));
+        assert_eq!(TimeOfDay::parse("7:05 PM"), at(19, 5));
+        assert_eq!(TimeOfDay::parse("12am"), at(0, 0));
+        assert_eq!(TimeOfDay::parse("12:15p"), at(12, 15));
+        assert_eq!(TimeOfDay::parse("24:00"), None);
+        assert_eq!(TimeOfDay::parse("13pm"), None);
+        assert_eq!(TimeOfDay::parse("9:5"), None);
+        assert_eq!(TimeOfDay::parse("soon"), None);
+    }
+
+    #[test]
+    fn test_profile_download_path_and_schedule_override_default() {
+        let mut config = AppConfig::default();
+        config.sftp_config.host = "seedbox".into();
+        let night = ScheduleConfig {
+            mode: ScheduleMode::Daily,
+            ..ScheduleConfig::default()
+        };
+        config
+            .profile_download_paths
+            .insert(config.profile_key(), "/mnt/nas".into());
+        config
+            .profile_schedules
+            .insert(config.profile_key(), night.clone());
+        assert_eq!(config.download_path(), "/mnt/nas");
+        assert_eq!(config.active_schedule(), &night);
+
+        // Edits go to the profile's own schedule, leaving the default alone
+        config.active_schedule_mut().start_time.hour = 1;
+        assert_eq!(config.schedule, ScheduleConfig::default());
+
+        config.sftp_config.host = "work".into();
+        assert_eq!(config.download_path(), config.local_download_path);
+        assert_eq!(config.active_schedule(), &config.schedule);
+    }
+
+    #[test]
+    fn test_validate_and_revert_edits() {
+        let mut config = AppConfig {
+            local_download_path: std::env::temp_dir().to_string_lossy().to_string(),
+            ..AppConfig::default()
+        };
+        config.sftp_config.host = "seedbox".into();
+        assert!(validate(&config, "22").is_empty());
+        assert_eq!(
+            validate(&config, "0").port,
+            Some("Port must be a number from 1 to 65535")
+        );
+        config.sftp_config.host = "seed box".into();
+        config.local_download_path = "/nonexistent/downloads".into();
+        let errors = validate(&config, "70000");
+        assert!(errors.host.is_some() && errors.port.is_some() && errors.download_path.is_some());
+
+        // Stats gathered while the dialog was open survive a discard; edits don't
+        let saved = config.clone();
+        config.add_daily_stat(1024, 1);
+        assert!(!config.edited_since(&saved));
+        config.max_download_speed = 300;
+        assert!(config.edited_since(&saved));
+        let reverted = config.reverted_to(&saved);
+        assert_eq!(reverted.max_download_speed, saved.max_download_speed);
+        assert_eq!(reverted.download_stats, config.download_stats);
+    }
+
+    #[test]
+    fn test_suggests_the_fastest_hours() {
+        let mb = 1024 * 1024;
+        // 01:00-05:00 run at 4 MB/s, 23:00 at 3.5 MB/s, the evening at 1 MB/s
+        let mut hourly = vec![(0, 0); 24];
+        hourly[1..5].fill((4 * mb * 600, 600));
+        hourly[23] = (7 * mb / 2 * 600, 600);
+        hourly[18..22].fill((mb * 600, 600));
+        let mut config = AppConfig::default();
+        config.download_stats.push(DailyStat {
+            date: "2026-10-01".into(),
+            bytes_downloaded: 0,
+            seconds_active: 0,
+            hourly,
+        });
+        let suggestion = config.suggest_schedule().unwrap();
+        // 00:00 has no transfers, so the 23:00 hour isn't joined on
+        assert_eq!((suggestion.start_hour, suggestion.end_hour), (1, 5));
+        assert_eq!(suggestion.window_speed, 4 * mb);
+        assert!(suggestion.other_speed < 2 * mb);
+        assert_eq!(suggestion.limit_kb, 4 * 1024 * 9 / 10);
+
+        // A server with its own schedule gets the limit to itself
+        let key = config.profile_key();
+        config
+            .profile_schedules
+            .insert(key.clone(), ScheduleConfig::default());
+        config.use_schedule_suggestion(&suggestion);
+        assert_eq!(config.profile_schedules[&key].start_time.hour, 1);
+        assert_eq!(config.profile_speed_limits[&key], suggestion.limit_kb);
+        assert_eq!(config.max_download_speed, 0);
+        config.profile_schedules.clear();
+        config.use_schedule_suggestion(&suggestion);
+        assert_eq!(config.max_download_speed, suggestion.limit_kb);
+
+        // The same speed at every hour suggests nothing
+        config.download_stats[0].hourly = vec![(mb * 600, 600); 24];
+        assert!(config.suggest_schedule().is_none());
+        assert!(AppConfig::default().suggest_schedule().is_none());
+    }
+
+    #[test]
+    fn test_usage_is_kept_per_server_and_month() {
+        let mut config = AppConfig::default();
+        config.add_usage("pi@seedbox:22", 100);
+        config.add_usage("me@nas:22", 50);
+        config.add_usage("pi@seedbox:22", 400);
+        config.monthly_usage.push(MonthlyUsage {
+            month: "2001-01".into(),
+            profile: "me@nas:22".into(),
+            bytes: 9999,
+        });
+
+        let usage: Vec<(&str, u64)> = config
+            .usage_by_month()
+            .iter()
+            .map(|u| (u.profile.as_str(), u.bytes))
+            .collect();
+        assert_eq!(
+            usage,
+            [
+                ("pi@seedbox:22", 500),
+                ("me@nas:22", 50),
+                ("me@nas:22", 9999)
+            ]
+        );
+    }
+}
@@ -1,12 +1,18 @@
+use crate::activity;
+use crate::error::SftpError;
+use crate::names;
+use crate::remote_fs::{RemoteFs, RemoteStat};
 use crate::settings::SftpConfig;
-use crate::types::{FileType, RemoteFile};
+use crate::totp;
+use crate::types::{FileType, RemoteFile, ScanProgress};
 
 const KB: u64 = 1024;
 const MB: u64 = KB * 1024;
 const GB: u64 = MB * 1024;
 const TB: u64 = GB * 1024;
 
-fn format_size(size: u64) -> String {
+/// "1.50 GB": the size shown in listings, the queue and reports
+pub fn format_size(size: u64) -> String {
     if size >= TB {
         format!("{:.2} TB", size as f64 / TB as f64)
     } else if size >= GB {
@@ -20,14 +26,358 @@ fn format_size(size: u64) -> String {
     }
 }
 
-use ssh2::{Session, Sftp};
+/// Single-quote `s` for a POSIX shell
+pub fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// The server command that unpacks archive `name` into its own folder, or None
+/// for files that aren't archives or aren't the first part of a set
+pub fn extract_command(name: &str) -> Option<String> {
+    let lower = name.to_lowercase();
+    let quoted = shell_quote(name);
+    if let Some(stem) = lower.strip_suffix(".rar") {
+        // name.part01.rar ... only the first part starts the set
+        let part = stem
+            .rsplit_once(".part")
+            .map(|(_, n)| n)
+            .filter(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()));
+        if part.is_some_and(|n| n.trim_start_matches('0') != "1") {
+            return None;
+        }
+        return Some(format!("unrar x -o+ -- {}", quoted));
+    }
+    [".7z", ".zip", ".7z.001", ".zip.001"]
+        .iter()
+        .any(|ext| lower.ends_with(ext))
+        .then(|| format!("7z x -y -- {}", quoted))
+}
+
+/// Hidden files next to an archive that an extraction writes its exit status
+/// and output to
+fn extract_markers(archive: &Path) -> (String, String) {
+    let dir = archive.parent().unwrap_or(Path::new("/"));
+    let name = archive.file_name().unwrap_or_default().to_string_lossy();
+    let marker = |ext: &str| {
+        names::path_to_string(&dir.join(format!(".simplesftp-extract-{}.{}", name, ext)))
+    };
+    (marker("done"), marker("log"))
+}
+
+/// Run the profile's pre-connect command (e.g. `knock host 7000 8000 9000`)
+/// through the shell and log its output to the Activity Log. A non-zero exit,
+/// or still running after PRE_CONNECT_TIMEOUT, aborts the connect.
+fn run_pre_connect(command: &str) -> Result<(), SftpError> {
+    use std::io::Read;
+    use std::process::{Command, Stdio};
+
+    let command = command.trim();
+    if command.is_empty() {
+        return Ok(());
+    }
+    activity::info(format!("Running pre-connect command: {}", command));
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let failed = |e: String| {
+        activity::error(format!("Pre-connect command: {}", e));
+        SftpError::Connection(format!("Pre-connect command: {}", e))
+    };
+    let mut child = Command::new(shell)
+        .args([flag, command])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| failed(e.to_string()))?;
+    // Drained on their own threads so a chatty command can't fill a pipe and stall
+    let (output_tx, output_rx) = std::sync::mpsc::channel();
+    let pipes: [Option<Box<dyn Read + Send>>; 2] = [
+        child.stdout.take().map(|p| Box::new(p) as _),
+        child.stderr.take().map(|p| Box::new(p) as _),
+    ];
+    for mut pipe in pipes.into_iter().flatten() {
+        let output_tx = output_tx.clone();
+        std::thread::spawn(move || {
+            let mut text = String::new();
+            let _ = pipe.read_to_string(&mut text);
+            let _ = output_tx.send(text);
+        });
+    }
+
+    let deadline = std::time::Instant::now() + PRE_CONNECT_TIMEOUT;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Some(status),
+            Ok(None) if std::time::Instant::now() < deadline => {
+                std::thread::sleep(std::time::Duration::from_millis(50))
+            }
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                break None;
+            }
+        }
+    };
+    // Something it left running in the background may hold a pipe open, so
+    // don't wait long for the last of the output
+    for _ in 0..2 {
+        let Ok(text) = output_rx.recv_timeout(std::time::Duration::from_secs(1)) else {
+            break;
+        };
+        for line in text.lines() {
+            activity::detail(format!("pre-connect: {}", line));
+        }
+    }
+    match status {
+        Some(status) if status.success() => Ok(()),
+        Some(status) => Err(failed(format!("failed ({})", status))),
+        None => Err(failed(format!(
+            "still running after {} seconds, stopped",
+            PRE_CONNECT_TIMEOUT.as_secs()
+        ))),
+    }
+}
+
+/// OpenSSH-style fingerprint: "SHA256:" + unpadded base64 of the key hash
+fn sha256_fingerprint(hash: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::from("SHA256:");
+    for chunk in hash.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+        }
+    }
+    out
+}
+
+/// FIFOs, devices and sockets: reading one blocks or never ends, so they're
+/// neither listed nor queued. Symlinks are kept and followed on transfer.
+fn is_special(stat: &ssh2::FileStat) -> bool {
+    !(stat.is_dir() || stat.is_file() || stat.file_type() == ssh2::FileType::Symlink)
+}
+
+/// Advanced setting: feed libssh2's own protocol trace into the Activity Log.
+/// libssh2 only emits it when built with LIBSSH2DEBUG, which libssh2-sys does
+/// for debug builds alone, so release builds don't offer it.
+pub const PROTOCOL_TRACE_AVAILABLE: bool = cfg!(debug_assertions);
+
+static PROTOCOL_TRACE: AtomicBool = AtomicBool::new(false);
+
+pub fn set_protocol_trace(on: bool) {
+    PROTOCOL_TRACE.store(on && PROTOCOL_TRACE_AVAILABLE, Ordering::Relaxed);
+}
+
+// libssh2 has had this since 1.2.3, but libssh2-sys doesn't bind it
+type TraceHandler = extern "C" fn(*mut c_void, *mut c_void, *const c_char, usize);
+extern "C" {
+    fn libssh2_trace_sethandler(
+        session: *mut c_void,
+        context: *mut c_void,
+        callback: TraceHandler,
+    ) -> c_int;
+}
+
+extern "C" fn trace_to_log(_: *mut c_void, _: *mut c_void, data: *const c_char, len: usize) {
+    if data.is_null() {
+        return;
+    }
+    // SAFETY: libssh2 passes a buffer of `len` bytes valid for this call
+    let bytes = unsafe { std::slice::from_raw_parts(data as *const u8, len) };
+    activity::detail(format!(
+        "[libssh2] {}",
+        String::from_utf8_lossy(bytes).trim_end()
+    ));
+}
+
+fn enable_trace(session: &Session) {
+    {
+        let mut raw = session.raw();
+        // SAFETY: the session pointer is valid while the guard is held, and the
+        // handler is a plain function that needs no context
+        unsafe {
+            libssh2_trace_sethandler(
+                &mut *raw as *mut _ as *mut c_void,
+                std::ptr::null_mut(),
+                trace_to_log,
+            );
+        }
+    }
+    session.trace(TraceFlags::all());
+    activity::detail("libssh2 protocol trace enabled");
+}
+
+use ssh2::{
+    HashType, HostKeyType, KeyboardInteractivePrompt, MethodType, Prompt, Session, Sftp, TraceFlags,
+};
+use std::collections::HashMap;
+use std::ffi::{c_char, c_int, c_void};
 use std::fmt;
 use std::net::TcpStream;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+const COPY_CHUNK_SIZE: usize = 256 * 1024;
+const PRE_CONNECT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+const EXEC_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(600);
+const EXEC_POLL_MS: u32 = 250; // how often a running command checks for cancel
+
+/// Answers keyboard-interactive prompts: the current one-time code for prompts
+/// asking for one, the saved password for anything else
+struct CodePrompter<'a> {
+    password: Option<&'a str>,
+    secret: &'a str,
+}
+
+impl KeyboardInteractivePrompt for CodePrompter<'_> {
+    fn prompt<'p>(
+        &mut self,
+        _username: &str,
+        _instructions: &str,
+        prompts: &[Prompt<'p>],
+    ) -> Vec<String> {
+        prompts
+            .iter()
+            .map(|p| {
+                if is_code_prompt(&p.text) {
+                    totp::now_code(self.secret).unwrap_or_else(|e| {
+                        activity::error(format!("Couldn't make a one-time code: {}", e));
+                        String::new()
+                    })
+                } else {
+                    self.password.unwrap_or_default().to_string()
+                }
+            })
+            .collect()
+    }
+}
+
+fn is_code_prompt(text: &str) -> bool {
+    let text = text.to_lowercase();
+    [
+        "code",
+        "verification",
+        "token",
+        "otp",
+        "one-time",
+        "authenticator",
+    ]
+    .iter()
+    .any(|word| text.contains(word))
+}
+
+/// Names for the uid and gid numbers in a listing
+#[derive(Debug, Clone, Default)]
+pub struct OwnerNames {
+    pub users: HashMap<u32, String>,
+    pub groups: HashMap<u32, String>,
+}
+
+impl OwnerNames {
+    /// "user:group", falling back to the numbers where there's no name
+    pub fn owner(&self, file: &RemoteFile) -> String {
+        let name = |id: Option<u32>, names: &HashMap<u32, String>| match id {
+            Some(id) => names.get(&id).cloned().unwrap_or_else(|| id.to_string()),
+            None => "?".to_string(),
+        };
+        if file.uid.is_none() && file.gid.is_none() {
+            return String::new();
+        }
+        format!(
+            "{}:{}",
+            name(file.uid, &self.users),
+            name(file.gid, &self.groups)
+        )
+    }
+}
+
+/// Id to name from passwd/group lines ("name:x:id:...")
+pub fn parse_id_names(text: &str) -> HashMap<u32, String> {
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let id = fields.nth(1)?.parse().ok()?;
+            Some((id, name.to_string()))
+        })
+        .collect()
+}
+
+/// Listings in progress; background checks hold off while there are any
+static INTERACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+struct Interactive;
+
+impl Interactive {
+    fn begin() -> Self {
+        INTERACTIVE.fetch_add(1, Ordering::SeqCst);
+        Interactive
+    }
+}
+
+impl Drop for Interactive {
+    fn drop(&mut self) {
+        INTERACTIVE.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+fn file_size(sftp: &Sftp, path: &str) -> Result<u64, SftpError> {
+    let canonical_path = sftp
+        .realpath(&names::to_path(path))
+        .map_err(|e| SftpError::from_ssh("Canonicalization failed", e))?;
+
+    let stat = sftp
+        .stat(&canonical_path)
+        .map_err(|e| SftpError::from_ssh("Stat failed", e))?;
+    activity::detail(format!(
+        "stat {}: {} bytes",
+        canonical_path.display(),
+        stat.size.unwrap_or(0)
+    ));
+
+    Ok(stat.size.unwrap_or(0))
+}
+
+/// Its own SFTP channel on a connected session, for checks that run alongside
+/// browsing; each request waits for any listing in progress to finish first
+pub struct BackgroundChannel {
+    sftp: Sftp,
+}
+
+impl BackgroundChannel {
+    pub fn get_file_size(&self, path: &str) -> Result<u64, SftpError> {
+        while INTERACTIVE.load(Ordering::SeqCst) > 0 {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        file_size(&self.sftp, path)
+    }
+}
 
 pub struct SftpClient {
-    _session: Session, // Keep session alive
+    session: Session, // Keep session alive, also used for exec channels
     sftp: Sftp,
+    info: SessionInfo,
+    read_only: bool,
+}
+
+/// What the server told us about itself and the algorithms we settled on
+/// during the handshake
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionInfo {
+    pub banner: String, // e.g. "SSH-2.0-OpenSSH_9.6"
+    pub host_key_type: String,
+    pub fingerprint: String, // SHA256:...
+    pub kex: String,
+    pub host_key: String,
+    pub cipher: String, // client to server; servers use the same one back in practice
+    pub mac: String,
+    pub compression: String,
 }
 
 impl fmt::Debug for SftpClient {
@@ -37,68 +387,226 @@ impl fmt::Debug for SftpClient {
 }
 
 impl SftpClient {
-    pub fn connect(config: &SftpConfig) -> Result<Self, String> {
+    pub fn connect(config: &SftpConfig) -> Result<Self, SftpError> {
+        if let Some(command) = config.pre_connect_command.as_deref() {
+            run_pre_connect(command)?;
+        }
+
         let tcp = TcpStream::connect(format!("{}:{}", config.host, config.port))
-            .map_err(|e| format!("Failed to connect to host: {}", e))?;
+            .map_err(|e| SftpError::Connection(e.to_string()))?;
 
-        let mut session = Session::new().map_err(|e| format!("Session error: {}", e))?;
+        let mut session = Session::new().map_err(|e| SftpError::from_ssh("Session error", e))?;
         session.set_tcp_stream(tcp);
+        if PROTOCOL_TRACE.load(Ordering::Relaxed) {
+            enable_trace(&session);
+        }
+        session.set_compress(config.compression);
+        let [kex, ciphers, macs] = config.algorithms.effective();
+        let prefs = [
+            (MethodType::Kex, kex),
+            (MethodType::CryptCs, ciphers.clone()),
+            (MethodType::CryptSc, ciphers),
+            (MethodType::MacCs, macs.clone()),
+            (MethodType::MacSc, macs),
+        ];
+        for (method, list) in prefs {
+            if let Some(list) = list {
+                session
+                    .method_pref(method, &list)
+                    .map_err(|e| SftpError::Handshake(format!("{}: {}", list, e.message())))?;
+            }
+        }
         session
             .handshake()
-            .map_err(|e| format!("Handshake failed: {}", e))?;
+            .map_err(|e| SftpError::Handshake(e.message().to_string()))?;
+
+        let method = |m| session.methods(m).unwrap_or("none").to_string();
+        let host_key_type = match session.host_key().map(|(_, kind)| kind) {
+            Some(HostKeyType::Rsa) => "RSA",
+            Some(HostKeyType::Dss) => "DSA",
+            Some(HostKeyType::Ecdsa256) => "ECDSA P-256",
+            Some(HostKeyType::Ecdsa384) => "ECDSA P-384",
+            Some(HostKeyType::Ecdsa521) => "ECDSA P-521",
+            Some(HostKeyType::Ed25519) => "Ed25519",
+            _ => "Unknown",
+        };
+        let info = SessionInfo {
+            banner: session.banner().unwrap_or_default().to_string(),
+            host_key_type: host_key_type.to_string(),
+            fingerprint: session
+                .host_key_hash(HashType::Sha256)
+                .map(sha256_fingerprint)
+                .unwrap_or_default(),
+            kex: method(MethodType::Kex),
+            host_key: method(MethodType::HostKey),
+            cipher: method(MethodType::CryptCs),
+            mac: method(MethodType::MacCs),
+            // The server may decline compression, so log what was actually negotiated
+            compression: method(MethodType::CompCs),
+        };
+        println!(
+            "DEBUG: Session to {}:{} kex: {}, cipher: {}, mac: {}, compression: {}",
+            config.host, config.port, info.kex, info.cipher, info.mac, info.compression
+        );
+        activity::detail(format!("Server: {}", info.banner));
+        activity::detail(format!(
+            "Host key {} {}; kex {}, cipher {}, mac {}, compression {}",
+            info.host_key_type, info.fingerprint, info.kex, info.cipher, info.mac, info.compression
+        ));
 
-        if let Some(password) = &config.password {
-            session
-                .userauth_password(&config.username, password)
-                .map_err(|e| format!("Authentication failed: {}", e))?;
+        if let Some(secret) = &config.totp_secret {
+            // Two-factor servers usually take the password first, then ask for the code
+            if let Some(password) = &config.password {
+                let _ = session.userauth_password(&config.username, password);
+            }
+            if !session.authenticated() {
+                let mut prompter = CodePrompter {
+                    password: config.password.as_deref(),
+                    secret,
+                };
+                session
+                    .userauth_keyboard_interactive(&config.username, &mut prompter)
+                    .map_err(SftpError::from_auth)?;
+            }
         } else {
-            // TODO: Key auth support later
-            return Err("Password required for now".into());
+            // The key first, as ssh does, then the password
+            if let Some(key) = &config.private_key_path {
+                let tried = session.userauth_pubkey_file(
+                    &config.username,
+                    None,
+                    Path::new(key),
+                    config.password.as_deref(), // unlocks a passphrase-protected key
+                );
+                match tried {
+                    Err(e) if config.password.is_none() => return Err(SftpError::from_auth(e)),
+                    Err(e) => activity::detail(format!("Key {} not accepted: {}", key, e)),
+                    Ok(()) => {}
+                }
+            }
+            if !session.authenticated() {
+                let Some(password) = &config.password else {
+                    return Err(SftpError::Auth("No password or private key set".into()));
+                };
+                session
+                    .userauth_password(&config.username, password)
+                    .map_err(SftpError::from_auth)?;
+            }
         }
 
         if !session.authenticated() {
-            return Err("Authentication failed".into());
+            return Err(SftpError::Auth("Server rejected credentials".into()));
         }
 
-        let sftp = session.sftp().map_err(|e| format!("SFTP error: {}", e))?;
+        let sftp = session
+            .sftp()
+            .map_err(|e| SftpError::from_ssh("SFTP subsystem", e))?;
+        activity::detail(format!(
+            "Logged in to {}:{} as {}",
+            config.host, config.port, config.username
+        ));
 
         Ok(Self {
-            _session: session,
+            session,
             sftp,
+            info,
+            read_only: config.read_only,
         })
     }
 
-    pub fn get_file_size(&self, path: &str) -> Result<u64, String> {
-        let canonical_path = self
-            .sftp
-            .realpath(Path::new(path))
-            .map_err(|e| format!("Canonicalization failed: {}", e))?;
+    /// Refuse `action` on a read-only profile, before anything reaches the server
+    fn check_writable(&self, action: &str) -> Result<(), SftpError> {
+        if self.read_only {
+            activity::error(format!("Blocked {}: the profile is read-only", action));
+            return Err(SftpError::PermissionDenied(format!(
+                "{}: this server is read-only in SimpleSFTP",
+                action
+            )));
+        }
+        Ok(())
+    }
 
-        let stat = self
+    pub fn info(&self) -> &SessionInfo {
+        &self.info
+    }
+
+    /// Round trip of a no-op stat, to show the link's latency
+    pub fn ping(&self) -> Result<std::time::Duration, SftpError> {
+        let started = std::time::Instant::now();
+        self.sftp
+            .stat(Path::new("."))
+            .map_err(|e| SftpError::from_ssh("Stat failed", e))?;
+        Ok(started.elapsed())
+    }
+
+    /// Read `path` into a scratch buffer until `max_bytes`, EOF or `max_time`,
+    /// returning how many bytes arrived
+    pub fn read_burst(
+        &self,
+        path: &str,
+        max_bytes: u64,
+        max_time: std::time::Duration,
+    ) -> Result<u64, SftpError> {
+        use std::io::Read;
+
+        let mut remote_file = self
             .sftp
-            .stat(&canonical_path)
-            .map_err(|e| format!("Stat failed: {}", e))?;
+            .open(names::to_path(path))
+            .map_err(|e| SftpError::from_ssh("Failed to open remote file", e))?;
+        let started = std::time::Instant::now();
+        let mut buf = vec![0u8; COPY_CHUNK_SIZE];
+        let mut total = 0;
+        while total < max_bytes && started.elapsed() < max_time {
+            let n = remote_file
+                .read(&mut buf)
+                .map_err(|e| SftpError::from_remote_io("Failed to read remote file", e))?;
+            if n == 0 {
+                break;
+            }
+            total += n as u64;
+        }
+        Ok(total)
+    }
+
+    pub fn get_file_size(&self, path: &str) -> Result<u64, SftpError> {
+        file_size(&self.sftp, path)
+    }
 
-        Ok(stat.size.unwrap_or(0))
+    /// A second SFTP channel over this session for background checks, usable
+    /// without holding the client's lock so browsing isn't stuck behind them
+    pub fn background_channel(&self) -> Result<BackgroundChannel, SftpError> {
+        let sftp = self
+            .session
+            .sftp()
+            .map_err(|e| SftpError::from_ssh("SFTP subsystem", e))?;
+        Ok(BackgroundChannel { sftp })
     }
 
-    pub fn list_dir(&self, path: &Path) -> Result<(String, Vec<RemoteFile>), String> {
+    pub fn list_dir(&self, path: &Path) -> Result<(String, Vec<RemoteFile>), SftpError> {
+        let _interactive = Interactive::begin();
         println!("DEBUG: Listing directory: {:?}", path);
 
         let canonical_path = self
             .sftp
-            .realpath(path)
-            .map_err(|e| format!("Canonicalization failed: {}", e))?;
+            .realpath(&names::raw(path))
+            .map_err(|e| SftpError::from_ssh("Canonicalization failed", e))?;
 
-        let path_str = canonical_path.to_str().unwrap_or(".").to_string();
+        let path_str = names::path_to_string(&canonical_path);
         println!("DEBUG: Resolved to: {}", path_str);
+        activity::detail(format!(
+            "realpath {} -> {}",
+            path.display(),
+            canonical_path.display()
+        ));
 
         match self.sftp.readdir(&canonical_path) {
             Ok(files) => {
                 let mut remote_files = Vec::new();
                 for (path_buf, stat) in files {
-                    let filename = path_buf.file_name().unwrap().to_str().unwrap().to_string();
-                    if filename == "." {
+                    let Some(filename) = path_buf.file_name() else {
+                        continue;
+                    };
+                    let filename = names::path_to_string(Path::new(filename));
+                    if filename == "." || is_special(&stat) {
                         continue;
                     }
 
@@ -114,18 +622,12 @@ impl SftpClient {
                         FileType::File
                     };
 
-                    let modified = if let Some(mtime) = stat.mtime {
-                        if let Some(dt) = chrono::DateTime::from_timestamp(mtime as i64, 0) {
-                            dt.format("%Y-%m-%d %H:%M:%S").to_string()
-                        } else {
-                            "".to_string()
-                        }
-                    } else {
-                        "".to_string()
-                    };
+                    let modified = stat
+                        .mtime
+                        .and_then(|mtime| chrono::DateTime::from_timestamp(mtime as i64, 0));
 
                     let full_path = canonical_path.join(&filename);
-                    let full_path_str = full_path.to_string_lossy().to_string();
+                    let full_path_str = names::path_to_string(&full_path);
 
                     remote_files.push(RemoteFile {
                         name: filename,
@@ -134,6 +636,9 @@ impl SftpClient {
                         size_bytes: raw_size,
                         file_type,
                         modified,
+                        permissions: stat.perm,
+                        uid: stat.uid,
+                        gid: stat.gid,
                     });
                 }
 
@@ -149,32 +654,56 @@ impl SftpClient {
                     }
                 });
 
+                activity::info(format!(
+                    "Listed {} ({} entries)",
+                    names::display(&path_str),
+                    remote_files.len()
+                ));
                 Ok((path_str, remote_files))
             }
-            Err(e) => Err(format!("SFTP Error: {}", e)),
+            Err(e) => Err(SftpError::from_ssh("Listing failed", e)),
         }
     }
 
-    pub fn recursive_scan(&self, path: &Path) -> Result<Vec<RemoteFile>, String> {
+    pub fn recursive_scan(&self, path: &Path) -> Result<Vec<RemoteFile>, SftpError> {
+        self.recursive_scan_with(path, &AtomicBool::new(false), &mut |_| {})
+    }
+
+    /// `recursive_scan` that reports progress after every directory and stops with
+    /// `SftpError::Cancelled` once `cancel` is set
+    pub fn recursive_scan_with(
+        &self,
+        path: &Path,
+        cancel: &AtomicBool,
+        progress: &mut dyn FnMut(&ScanProgress),
+    ) -> Result<Vec<RemoteFile>, SftpError> {
         let mut all_files = Vec::new();
+        let mut dirs_visited = 0;
+        let mut special_skipped = 0;
         let canonical_path = self
             .sftp
-            .realpath(path)
-            .map_err(|e| format!("Canonicalization failed: {}", e))?;
+            .realpath(&names::raw(path))
+            .map_err(|e| SftpError::from_ssh("Canonicalization failed", e))?;
 
         let mut stack = vec![canonical_path];
 
         while let Some(current_path) = stack.pop() {
+            if cancel.load(Ordering::Relaxed) {
+                return Err(SftpError::Cancelled);
+            }
+            dirs_visited += 1;
             if let Ok(entries) = self.sftp.readdir(&current_path) {
                 for (path, stat) in entries {
-                    let filename = path
-                        .file_name()
-                        .unwrap_or_default()
-                        .to_string_lossy()
-                        .to_string();
+                    let filename =
+                        names::path_to_string(Path::new(path.file_name().unwrap_or_default()));
                     if filename == "." || filename == ".." {
                         continue;
                     }
+                    if is_special(&stat) {
+                        println!("WARNING: Skipping special file {}", path.display());
+                        special_skipped += 1;
+                        continue;
+                    }
 
                     let raw_size = stat.size.unwrap_or(0);
                     let size = if stat.is_dir() {
@@ -188,23 +717,20 @@ impl SftpClient {
                         FileType::File
                     };
 
-                    let modified = if let Some(mtime) = stat.mtime {
-                        if let Some(dt) = chrono::DateTime::from_timestamp(mtime as i64, 0) {
-                            dt.format("%Y-%m-%d %H:%M:%S").to_string()
-                        } else {
-                            "".to_string()
-                        }
-                    } else {
-                        "".to_string()
-                    };
+                    let modified = stat
+                        .mtime
+                        .and_then(|mtime| chrono::DateTime::from_timestamp(mtime as i64, 0));
 
                     let remote_file = RemoteFile {
                         name: filename,
-                        path: path.to_string_lossy().to_string(),
+                        path: names::path_to_string(&path),
                         size,
                         size_bytes: raw_size,
                         file_type: file_type.clone(),
                         modified,
+                        permissions: stat.perm,
+                        uid: stat.uid,
+                        gid: stat.gid,
                     };
 
                     if file_type == FileType::Folder {
@@ -214,77 +740,870 @@ impl SftpClient {
                     }
                 }
             }
+            progress(&ScanProgress {
+                dirs_visited,
+                files_found: all_files.len(),
+                special_skipped,
+                current_path: names::path_to_string(&current_path),
+            });
         }
         Ok(all_files)
     }
 
-    pub fn download_chunk(
+    /// Move or rename a file or folder, also across directories
+    pub fn rename(&self, from: &Path, to: &Path) -> Result<(), SftpError> {
+        self.check_writable("Rename")?;
+        self.sftp
+            .rename(&names::raw(from), &names::raw(to), None)
+            .map_err(|e| SftpError::from_ssh("Failed to move", e))?;
+        activity::info(format!("Renamed {} to {}", from.display(), to.display()));
+        Ok(())
+    }
+
+    /// Run a shell command on the server and return its exit status.
+    /// Fails on SFTP-only accounts that don't allow exec channels.
+    pub fn exec(&self, command: &str) -> Result<i32, SftpError> {
+        self.exec_output(command).map(|(status, _)| status)
+    }
+
+    /// Like `exec`, also returning what the command printed, stdout and stderr
+    /// as they came
+    pub fn exec_output(&self, command: &str) -> Result<(i32, String), SftpError> {
+        self.exec_output_with(command, &AtomicBool::new(false))
+    }
+
+    /// `exec_output` that stops the command with `SftpError::Cancelled` once
+    /// `cancel` is set, and gives up on it after EXEC_TIMEOUT
+    pub fn exec_output_with(
+        &self,
+        command: &str,
+        cancel: &AtomicBool,
+    ) -> Result<(i32, String), SftpError> {
+        use std::io::Read;
+
+        let mut channel = self
+            .session
+            .channel_session()
+            .map_err(|e| SftpError::from_ssh("Failed to open channel", e))?;
+        // One stream, so a command writing lots to stderr can't stall on a full window
+        channel
+            .handle_extended_data(ssh2::ExtendedData::Merge)
+            .map_err(|e| SftpError::from_ssh("Failed to open channel", e))?;
+        channel
+            .exec(command)
+            .map_err(|e| SftpError::from_ssh("Exec rejected", e))?;
+        // Short reads so cancel and the deadline get looked at; the session
+        // gets its own timeout back afterwards
+        let saved_timeout = self.session.timeout();
+        self.session.set_timeout(EXEC_POLL_MS);
+        let deadline = std::time::Instant::now() + EXEC_TIMEOUT;
+        let mut output = Vec::new();
+        let mut buf = [0; 8192];
+        let stopped = loop {
+            if cancel.load(Ordering::Relaxed) {
+                break Some(SftpError::Cancelled);
+            }
+            if std::time::Instant::now() > deadline {
+                break Some(SftpError::Protocol(format!(
+                    "{} still running after {} minutes, stopped",
+                    command,
+                    EXEC_TIMEOUT.as_secs() / 60
+                )));
+            }
+            match channel.read(&mut buf) {
+                Ok(0) => break None,
+                Ok(n) => output.extend_from_slice(&buf[..n]),
+                Err(e) if e.kind() == std::io::ErrorKind::TimedOut => {}
+                Err(_) => break None,
+            }
+        };
+        self.session.set_timeout(saved_timeout);
+        if let Some(e) = stopped {
+            let _ = channel.close();
+            activity::detail(format!("exec {}: {}", command, e));
+            return Err(e);
+        }
+        channel
+            .wait_close()
+            .map_err(|e| SftpError::from_ssh("Exec failed", e))?;
+        let status = channel
+            .exit_status()
+            .map_err(|e| SftpError::from_ssh("Exec failed", e))?;
+        activity::detail(format!("exec {}: exit {}", command, status));
+        Ok((status, String::from_utf8_lossy(&output).into_owned()))
+    }
+
+    /// User and group names by id from the server's account database, for the
+    /// Owner column; empty where the account can't run commands
+    pub fn owner_names(&self) -> OwnerNames {
+        let names = |database: &str| {
+            self.exec_output(&format!("getent {0} 2>/dev/null || cat /etc/{0}", database))
+                .map(|(_, output)| parse_id_names(&output))
+                .unwrap_or_default()
+        };
+        OwnerNames {
+            users: names("passwd"),
+            groups: names("group"),
+        }
+    }
+
+    /// Start unpacking `archive` in its folder and return at once; the command
+    /// keeps running on the server after the channel closes. See `extract_status`.
+    pub fn start_extract(&self, archive: &Path) -> Result<(), SftpError> {
+        self.check_writable("Extract")?;
+        let name = archive.file_name().unwrap_or_default().to_string_lossy();
+        let command = extract_command(&name)
+            .ok_or_else(|| SftpError::Protocol(format!("{} is not an archive", name)))?;
+        let dir = archive.parent().unwrap_or(Path::new("/"));
+        let (done, log) = extract_markers(archive);
+        let job = format!(
+            "{} > {} 2>&1; echo $? > {}",
+            command,
+            shell_quote(&log),
+            shell_quote(&done)
+        );
+        let line = format!(
+            "cd {} && rm -f {} && nohup sh -c {} > /dev/null 2>&1 &",
+            shell_quote(&names::path_to_string(dir)),
+            shell_quote(&done),
+            shell_quote(&job)
+        );
+        match self.exec(&line)? {
+            0 => Ok(()),
+            status => Err(SftpError::Protocol(format!(
+                "Could not start extraction (exit {})",
+                status
+            ))),
+        }
+    }
+
+    /// None while the extraction of `archive` is still running; afterwards its
+    /// exit status and the last lines it printed. The markers are removed.
+    pub fn extract_status(&self, archive: &Path) -> Result<Option<(i32, String)>, SftpError> {
+        let (done, log) = extract_markers(archive);
+        let (done, log) = (shell_quote(&done), shell_quote(&log));
+        let (_, output) = self.exec_output(&format!(
+            "if [ -e {d} ]; then cat {d}; tail -n 5 {l}; rm -f {d} {l}; fi 2>/dev/null",
+            d = done,
+            l = log
+        ))?;
+        let mut lines = output.lines();
+        let Some(status) = lines.next().and_then(|s| s.trim().parse().ok()) else {
+            return Ok(None);
+        };
+        Ok(Some((status, lines.collect::<Vec<_>>().join("\n"))))
+    }
+
+    /// Copy a file or folder to `to` on the server.
+    ///
+    /// Tries a server-side `cp` first; if the account can't run commands the data
+    /// is streamed through this client instead. `progress` gets (bytes done, total).
+    pub fn copy(
+        &self,
+        from: &Path,
+        to: &Path,
+        progress: &mut dyn FnMut(u64, u64),
+    ) -> Result<(), SftpError> {
+        self.check_writable("Copy")?;
+        let command = format!(
+            "cp -Rp -- {} {}",
+            shell_quote(&from.to_string_lossy()),
+            shell_quote(&to.to_string_lossy())
+        );
+        if let Ok(0) = self.exec(&command) {
+            progress(1, 1);
+            return Ok(());
+        }
+
+        let stat = self
+            .sftp
+            .stat(&names::raw(from))
+            .map_err(|e| SftpError::from_ssh("Failed to stat source", e))?;
+        if !stat.is_dir() {
+            let total = stat.size.unwrap_or(0);
+            return self.copy_file(from, to, 0, total, progress);
+        }
+
+        let files = self.recursive_scan(from)?;
+        let total = files.iter().map(|f| f.size_bytes).sum();
+        let from = self
+            .sftp
+            .realpath(&names::raw(from))
+            .map(|p| PathBuf::from(names::path_to_string(&p)))
+            .map_err(|e| SftpError::from_ssh("Canonicalization failed", e))?;
+        self.mkdir_all(to)?;
+        let mut done = 0;
+        for file in files {
+            let source = Path::new(&file.path);
+            let Ok(relative) = source.strip_prefix(&from) else {
+                continue;
+            };
+            let target = to.join(relative);
+            if let Some(parent) = target.parent() {
+                self.mkdir_all(parent)?;
+            }
+            self.copy_file(source, &target, done, total, progress)?;
+            done += file.size_bytes;
+        }
+        Ok(())
+    }
+
+    fn copy_file(
+        &self,
+        from: &Path,
+        to: &Path,
+        done: u64,
+        total: u64,
+        progress: &mut dyn FnMut(u64, u64),
+    ) -> Result<(), SftpError> {
+        use ssh2::{OpenFlags, OpenType};
+        use std::io::{Read, Write};
+
+        let mut source = self
+            .sftp
+            .open(names::raw(from))
+            .map_err(|e| SftpError::from_ssh("Failed to open source", e))?;
+        let mut target = self
+            .sftp
+            .open_mode(
+                names::raw(to),
+                OpenFlags::WRITE | OpenFlags::CREATE | OpenFlags::TRUNCATE,
+                0o644,
+                OpenType::File,
+            )
+            .map_err(|e| SftpError::from_ssh("Failed to create target", e))?;
+
+        let mut buffer = vec![0u8; COPY_CHUNK_SIZE];
+        let mut copied = 0;
+        loop {
+            let n = source
+                .read(&mut buffer)
+                .map_err(|e| SftpError::from_remote_io("Failed to read source", e))?;
+            if n == 0 {
+                break;
+            }
+            target
+                .write_all(&buffer[..n])
+                .map_err(|e| SftpError::from_remote_io("Failed to write target", e))?;
+            copied += n as u64;
+            progress(done + copied, total);
+        }
+        Ok(())
+    }
+
+    /// Create `path` and any missing parents
+    fn mkdir_all(&self, path: &Path) -> Result<(), SftpError> {
+        if self.sftp.stat(&names::raw(path)).is_ok() {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                self.mkdir_all(parent)?;
+            }
+        }
+        self.sftp
+            .mkdir(&names::raw(path), 0o755)
+            .map_err(|e| SftpError::from_ssh("Failed to create directory", e))?;
+        activity::detail(format!("mkdir {}", path.display()));
+        Ok(())
+    }
+
+    /// Delete a file, or a folder with everything in it
+    pub fn remove(&self, path: &Path) -> Result<(), SftpError> {
+        self.check_writable("Delete")?;
+        // lstat, so a link to a folder goes rather than what it points at
+        let stat = self
+            .sftp
+            .lstat(&names::raw(path))
+            .map_err(|e| SftpError::from_ssh("Failed to stat path", e))?;
+
+        if stat.is_dir() {
+            let entries = self
+                .sftp
+                .readdir(names::raw(path))
+                .map_err(|e| SftpError::from_ssh("Failed to list directory", e))?;
+            for (entry, _) in entries {
+                self.remove(&entry)?;
+            }
+            self.sftp
+                .rmdir(&names::raw(path))
+                .map_err(|e| SftpError::from_ssh("Failed to remove directory", e))?;
+        } else {
+            self.sftp
+                .unlink(&names::raw(path))
+                .map_err(|e| SftpError::from_ssh("Failed to remove file", e))?;
+        }
+        activity::info(format!("Deleted {}", path.display()));
+        Ok(())
+    }
+
+    /// Move `path` into the `trash` folder instead of deleting it. A name that's
+    /// already in the trash is kept; the newcomer gets a timestamp appended.
+    pub fn move_to_trash(&self, path: &Path, trash: &Path) -> Result<PathBuf, SftpError> {
+        self.check_writable("Delete")?;
+        let name = path
+            .file_name()
+            .map(|n| names::path_to_string(Path::new(n)))
+            .ok_or_else(|| SftpError::Internal(format!("Nothing to trash at {:?}", path)))?;
+        self.mkdir_all(trash)?;
+        let mut target = trash.join(&name);
+        if self.sftp.lstat(&names::raw(&target)).is_ok() {
+            let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+            target = trash.join(format!("{}.{}", name, stamp));
+        }
+        self.rename(path, &target)?;
+        Ok(target)
+    }
+}
+
+impl RemoteFs for SftpClient {
+    fn get_file_size(&self, path: &str) -> Result<u64, SftpError> {
+        SftpClient::get_file_size(self, path)
+    }
+
+    fn stat(&self, path: &str) -> Result<RemoteStat, SftpError> {
+        let stat = self
+            .sftp
+            .stat(&names::to_path(path))
+            .map_err(|e| SftpError::from_ssh("Stat failed", e))?;
+        activity::detail(format!(
+            "stat {}: {} bytes, mtime {:?}",
+            names::display(path),
+            stat.size.unwrap_or(0),
+            stat.mtime
+        ));
+        Ok(RemoteStat {
+            size: stat.size.unwrap_or(0),
+            mtime: stat.mtime,
+        })
+    }
+
+    fn read_chunk(
         &self,
         remote_path: &Path,
-        local_path: &Path,
         offset: u64,
         chunk_size: usize,
-    ) -> Result<usize, String> {
-        use std::fs::{File, OpenOptions};
-        use std::io::{Read, Seek, SeekFrom, Write};
+    ) -> Result<Vec<u8>, SftpError> {
+        use std::io::{Read, Seek, SeekFrom};
 
-        // Open remote file
         let mut remote_file = self
             .sftp
-            .open(remote_path)
-            .map_err(|e| format!("Failed to open remote file: {}", e))?;
+            .open(names::raw(remote_path))
+            .map_err(|e| SftpError::from_ssh("Failed to open remote file", e))?;
 
-        // Seek to offset
         remote_file
             .seek(SeekFrom::Start(offset))
-            .map_err(|e| format!("Failed to seek in remote file: {}", e))?;
+            .map_err(|e| SftpError::from_remote_io("Failed to seek in remote file", e))?;
 
-        // Read chunk
         let mut buffer = vec![0u8; chunk_size];
         let bytes_read = remote_file
             .read(&mut buffer)
-            .map_err(|e| format!("Failed to read from remote file: {}", e))?;
+            .map_err(|e| SftpError::from_remote_io("Failed to read from remote file", e))?;
+        buffer.truncate(bytes_read);
+        activity::detail(format!(
+            "read {} bytes at {} from {}",
+            bytes_read,
+            offset,
+            remote_path.display()
+        ));
 
-        if bytes_read == 0 {
-            return Ok(0); // EOF
-        }
+        Ok(buffer)
+    }
+
+    fn write_chunk(&self, remote_path: &Path, offset: u64, data: &[u8]) -> Result<(), SftpError> {
+        use ssh2::{OpenFlags, OpenType};
+        use std::io::{Seek, SeekFrom, Write};
 
-        // Open/create local file
-        let mut local_file = if offset == 0 {
-            File::create(local_path).map_err(|e| format!("Failed to create local file: {}", e))?
+        self.check_writable("Upload")?;
+        let flags = if offset == 0 {
+            OpenFlags::WRITE | OpenFlags::CREATE | OpenFlags::TRUNCATE
         } else {
-            OpenOptions::new()
-                .write(true)
-                .append(true)
-                .open(local_path)
-                .map_err(|e| format!("Failed to open local file for append: {}", e))?
+            OpenFlags::WRITE | OpenFlags::CREATE
         };
+        let mut remote_file = self
+            .sftp
+            .open_mode(names::raw(remote_path), flags, 0o644, OpenType::File)
+            .map_err(|e| SftpError::from_ssh("Failed to open remote file for writing", e))?;
 
-        // Write chunk
-        local_file
-            .write_all(&buffer[..bytes_read])
-            .map_err(|e| format!("Failed to write to local file: {}", e))?;
+        remote_file
+            .seek(SeekFrom::Start(offset))
+            .map_err(|e| SftpError::from_remote_io("Failed to seek in remote file", e))?;
+        remote_file
+            .write_all(data)
+            .map_err(|e| SftpError::from_remote_io("Failed to write to remote file", e))?;
+        activity::detail(format!(
+            "wrote {} bytes at {} to {}",
+            data.len(),
+            offset,
+            remote_path.display()
+        ));
 
-        Ok(bytes_read)
+        Ok(())
     }
+}
 
-    #[allow(dead_code)]
-    pub fn remove(&self, path: &Path) -> Result<(), String> {
-        // Try to remove as file first, then as directory
-        // Alternatively check stat first
-        let stat = self
-            .sftp
-            .stat(path)
-            .map_err(|e| format!("Failed to stat path: {}", e))?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{pattern, TestServer};
 
-        if stat.is_dir() {
-            self.sftp
-                .rmdir(path)
-                .map_err(|e| format!("Failed to remove directory: {}", e))
-        } else {
-            self.sftp
-                .unlink(path)
-                .map_err(|e| format!("Failed to remove file: {}", e))
+    #[test]
+    fn test_extract_command_picks_tool_and_first_part() {
+        assert_eq!(
+            extract_command("Show.S01.rar").unwrap(),
+            "unrar x -o+ -- 'Show.S01.rar'"
+        );
+        assert!(extract_command("Show.part01.rar").is_some());
+        assert!(extract_command("Show.part02.rar").is_none());
+        assert!(extract_command("Show.part10.rar").is_none());
+        assert_eq!(
+            extract_command("it's.7z.001").unwrap(),
+            "7z x -y -- 'it'\\''s.7z.001'"
+        );
+        assert!(extract_command("Show.mkv").is_none());
+    }
+
+    #[test]
+    fn test_connect_and_reject_bad_password() {
+        let server = TestServer::start();
+        assert!(SftpClient::connect(&server.config()).is_ok());
+
+        let mut config = server.config();
+        config.password = Some("wrong".into());
+        let err = SftpClient::connect(&config).unwrap_err();
+        assert!(err.is_auth(), "expected auth error, got {:?}", err);
+
+        // A key instead of the password; a key file that isn't there is refused
+        config.password = None;
+        config.private_key_path = Some(server.write_client_key());
+        assert!(SftpClient::connect(&config).is_ok());
+        config.private_key_path = Some(server.path("missing.key"));
+        let err = SftpClient::connect(&config).unwrap_err();
+        assert!(err.is_auth(), "expected auth error, got {:?}", err);
+
+        // A connection dropped mid-login is worth retrying, not a refusal
+        let dropped = ssh2::Error::new(ssh2::ErrorCode::Session(-13), "socket disconnect");
+        let err = SftpError::from_auth(dropped);
+        assert!(!err.is_auth() && err.is_retryable(), "{:?}", err);
+    }
+
+    #[test]
+    fn test_read_only_profile_blocks_changes() {
+        let server = TestServer::start();
+        server.write_file("keep.txt", b"precious");
+        let mut config = server.config();
+        config.read_only = true;
+        let client = SftpClient::connect(&config).unwrap();
+        let path = server.root.join("keep.txt");
+
+        let err = client.remove(&path).unwrap_err();
+        assert!(matches!(err, SftpError::PermissionDenied(_)), "{:?}", err);
+        assert!(client
+            .rename(&path, &server.root.join("moved.txt"))
+            .is_err());
+        assert!(client
+            .write_chunk(&server.root.join("new.txt"), 0, b"x")
+            .is_err());
+        let (_, files) = client.list_dir(&server.root).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(client.read_chunk(&path, 0, 64).unwrap(), b"precious");
+    }
+
+    #[test]
+    fn test_connect_with_compression() {
+        let server = TestServer::start();
+        server.write_file("notes.txt", &[b'a'; 4096]);
+        let mut config = server.config();
+        config.compression = true;
+        let client = SftpClient::connect(&config).unwrap();
+        let (_, files) = client.list_dir(&server.root).unwrap();
+        assert_eq!(files[0].size_bytes, 4096);
+    }
+
+    #[test]
+    fn test_session_info_and_ping() {
+        let server = TestServer::start();
+        let client = SftpClient::connect(&server.config()).unwrap();
+        let info = client.info();
+        assert!(
+            info.banner.starts_with("SSH-2.0-"),
+            "banner {}",
+            info.banner
+        );
+        assert_eq!(info.host_key_type, "Ed25519");
+        assert_eq!(info.fingerprint.len(), "SHA256:".len() + 43);
+        assert!(client.ping().is_ok());
+
+        assert_eq!(sha256_fingerprint(b"ab"), "SHA256:YWI");
+    }
+
+    #[test]
+    fn test_strict_algorithms_are_negotiated() {
+        let server = TestServer::start();
+        let mut config = server.config();
+        config.algorithms.strict = true;
+        let client = SftpClient::connect(&config).unwrap();
+        let [kex, ciphers, macs] = config.algorithms.effective().map(Option::unwrap);
+        let info = client.info();
+        assert!(kex.split(',').any(|k| k == info.kex), "kex {}", info.kex);
+        assert!(
+            ciphers.split(',').any(|c| c == info.cipher),
+            "cipher {}",
+            info.cipher
+        );
+        // AEAD ciphers carry their own integrity check
+        assert!(info.cipher.contains("gcm") || macs.split(',').any(|m| m == info.mac));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_pre_connect_command_runs_first() {
+        let server = TestServer::start();
+        let marker = server.root.join("knocked");
+        let mut config = server.config();
+        config.pre_connect_command = Some(format!(
+            "touch {} && echo knocked-{}",
+            shell_quote(&marker.to_string_lossy()),
+            server.port
+        ));
+        assert!(SftpClient::connect(&config).is_ok());
+        assert!(marker.exists());
+        // Its output goes to the Activity Log
+        let logged = format!("pre-connect: knocked-{}", server.port);
+        assert!(activity::entries(true).iter().any(|e| e.text == logged));
+
+        config.pre_connect_command = Some("exit 3".into());
+        let err = SftpClient::connect(&config).unwrap_err();
+        assert!(matches!(err, SftpError::Connection(_)), "got {:?}", err);
+    }
+
+    #[test]
+    fn test_list_dir_sorts_folders_first() {
+        let server = TestServer::start();
+        server.write_file("b.txt", b"hello");
+        server.write_file("a-folder/inner.txt", b"x");
+        let client = SftpClient::connect(&server.config()).unwrap();
+
+        let (resolved, files) = client.list_dir(&server.root).unwrap();
+        assert_eq!(Path::new(&resolved), server.root);
+        let names: Vec<_> = files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["a-folder", "b.txt"]);
+        assert_eq!(files[1].size_bytes, 5);
+        assert_eq!(files[0].file_type, FileType::Folder);
+    }
+
+    #[test]
+    fn test_tricky_names_list_and_read() {
+        let server = TestServer::start();
+        let names = [
+            "-leading dash.txt",
+            "it's \"quoted\" $HOME.txt",
+            "Ame\u{301}lie — 日本語 🎬.mkv",
+            "tab\there.txt",
+        ];
+        for (i, name) in names.iter().enumerate() {
+            server.write_file(&format!("odd/{}", name), &pattern(i + 1));
         }
+        let client = SftpClient::connect(&server.config()).unwrap();
+
+        let (_, files) = client.list_dir(Path::new(&server.path("odd"))).unwrap();
+        assert_eq!(files.len(), names.len());
+        for file in &files {
+            let i = names.iter().position(|n| *n == file.name).unwrap();
+            let data = client.read_chunk(Path::new(&file.path), 0, 64).unwrap();
+            assert_eq!(data, pattern(i + 1), "{}", file.name);
+        }
+    }
+
+    #[test]
+    fn test_list_dir_missing_path_is_not_found() {
+        let server = TestServer::start();
+        let client = SftpClient::connect(&server.config()).unwrap();
+        let err = client
+            .list_dir(Path::new(&server.path("nope")))
+            .unwrap_err();
+        assert!(matches!(err, SftpError::NotFound(_)), "{:?}", err);
+    }
+
+    #[test]
+    fn test_recursive_scan_returns_only_files() {
+        let server = TestServer::start();
+        server.write_file("show/s01/e01.mkv", b"1");
+        server.write_file("show/s01/e02.mkv", b"22");
+        server.write_file("show/notes.txt", b"333");
+        let client = SftpClient::connect(&server.config()).unwrap();
+
+        let mut files = client
+            .recursive_scan(Path::new(&server.path("show")))
+            .unwrap();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        let names: Vec<_> = files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["notes.txt", "e01.mkv", "e02.mkv"]);
+        assert!(files.iter().all(|f| f.file_type == FileType::File));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_scan_skips_special_files() {
+        let server = TestServer::start();
+        server.write_file("box/empty.txt", b"");
+        let fifo = std::ffi::CString::new(server.path("box/pipe")).unwrap();
+        assert_eq!(unsafe { libc::mkfifo(fifo.as_ptr(), 0o644) }, 0);
+        let client = SftpClient::connect(&server.config()).unwrap();
+
+        let mut last = ScanProgress::default();
+        let files = client
+            .recursive_scan_with(
+                Path::new(&server.path("box")),
+                &AtomicBool::new(false),
+                &mut |p| last = p.clone(),
+            )
+            .unwrap();
+        let names: Vec<_> = files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["empty.txt"]);
+        assert_eq!(last.special_skipped, 1);
+
+        let (_, listed) = client.list_dir(Path::new(&server.path("box"))).unwrap();
+        assert_eq!(listed.len(), 1);
+    }
+
+    #[test]
+    fn test_recursive_scan_reports_progress_and_cancels() {
+        let server = TestServer::start();
+        for dir in ["a", "b", "c"] {
+            server.write_file(&format!("tree/{}/file.bin", dir), b"x");
+        }
+        let client = SftpClient::connect(&server.config()).unwrap();
+        let root = server.path("tree");
+
+        let mut updates = Vec::new();
+        let files = client
+            .recursive_scan_with(Path::new(&root), &AtomicBool::new(false), &mut |p| {
+                updates.push(p.clone())
+            })
+            .unwrap();
+        assert_eq!(files.len(), 3);
+        let last = updates.last().unwrap();
+        assert_eq!((last.dirs_visited, last.files_found), (4, 3));
+
+        // Cancelling from the progress callback stops before the next directory
+        let cancel = AtomicBool::new(false);
+        let err = client
+            .recursive_scan_with(Path::new(&root), &cancel, &mut |_| {
+                cancel.store(true, Ordering::Relaxed)
+            })
+            .unwrap_err();
+        assert_eq!(err, SftpError::Cancelled);
+    }
+
+    #[test]
+    fn test_owner_names_come_from_passwd_lines() {
+        let users =
+            parse_id_names("root:x:0:0:root:/root:/bin/bash\nmedia:x:1001:1001::/srv:/bin/sh\n");
+        let names = OwnerNames {
+            users,
+            groups: parse_id_names("users:x:100:\n"),
+        };
+        let file = RemoteFile {
+            name: "a".into(),
+            path: "/srv/a".into(),
+            size: String::new(),
+            size_bytes: 0,
+            file_type: FileType::File,
+            modified: None,
+            permissions: Some(0o100640),
+            uid: Some(1001),
+            gid: Some(1001),
+        };
+        assert_eq!(names.owner(&file), "media:1001");
+    }
+
+    #[test]
+    fn test_exec_reads_stderr_alongside_and_stops_on_cancel() {
+        let server = TestServer::start();
+        let client = SftpClient::connect(&server.config()).unwrap();
+        let (status, output) = client.exec_output("noisy").unwrap();
+        assert_eq!(status, 0);
+        assert_eq!(output.len(), 1024 * 1024 + 5);
+        assert!(output.ends_with("done\n"));
+
+        // A command that never ends gives the session back when cancelled
+        let cancel = AtomicBool::new(false);
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                std::thread::sleep(std::time::Duration::from_millis(300));
+                cancel.store(true, Ordering::Relaxed);
+            });
+            assert!(matches!(
+                client.exec_output_with("hang", &cancel),
+                Err(SftpError::Cancelled)
+            ));
+        });
+        assert!(client.list_dir(Path::new(&server.path(""))).is_ok());
+    }
+
+    #[test]
+    fn test_background_channel_checks_while_client_is_busy() {
+        let server = TestServer::start();
+        let remote = server.write_file("data.bin", &pattern(1234));
+        let client = std::sync::Mutex::new(SftpClient::connect(&server.config()).unwrap());
+        let channel = client.lock().unwrap().background_channel().unwrap();
+
+        // The client stays locked, as it would be by a listing, and the check still runs
+        let busy = client.lock().unwrap();
+        assert_eq!(channel.get_file_size(&remote).unwrap(), 1234);
+        let (_, files) = busy.list_dir(Path::new(&server.path(""))).unwrap();
+        assert!(files.iter().any(|f| f.name == "data.bin"));
+    }
+
+    #[test]
+    fn test_chunked_download_and_resume() {
+        let server = TestServer::start();
+        let contents = pattern(10_000);
+        let remote = server.write_file("data.bin", &contents);
+        let local = server.local_dir().join("data.bin");
+        let client = SftpClient::connect(&server.config()).unwrap();
+
+        assert_eq!(client.get_file_size(&remote).unwrap(), 10_000);
+
+        let mut offset = 0;
+        let mut file = std::fs::File::create(&local).unwrap();
+        let first = client
+            .download_chunk(Path::new(&remote), &mut file, offset, 4096)
+            .unwrap();
+        assert_eq!(first, 4096);
+        offset += first as u64;
+
+        // Reconnect and reopen to simulate resuming in a new session
+        let client = SftpClient::connect(&server.config()).unwrap();
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&local)
+            .unwrap();
+        loop {
+            let n = client
+                .download_chunk(Path::new(&remote), &mut file, offset, 4096)
+                .unwrap();
+            if n == 0 {
+                break;
+            }
+            offset += n as u64;
+        }
+        assert_eq!(std::fs::read(&local).unwrap(), contents);
+    }
+
+    #[test]
+    fn test_chunked_upload() {
+        let server = TestServer::start();
+        let contents = pattern(10_000);
+        let local = server.local_dir().join("upload.bin");
+        std::fs::write(&local, &contents).unwrap();
+        let remote = server.path("upload.bin");
+        let client = SftpClient::connect(&server.config()).unwrap();
+
+        let mut file = std::fs::File::open(&local).unwrap();
+        let mut offset = 0;
+        loop {
+            let n = client
+                .upload_chunk(&mut file, Path::new(&remote), offset, 4096)
+                .unwrap();
+            if n == 0 {
+                break;
+            }
+            offset += n as u64;
+        }
+        assert_eq!(std::fs::read(&remote).unwrap(), contents);
+    }
+
+    #[test]
+    fn test_rename_moves_across_directories() {
+        let server = TestServer::start();
+        let from = server.write_file("incoming/show.mkv", b"episode");
+        std::fs::create_dir_all(server.path("archive")).unwrap();
+        let client = SftpClient::connect(&server.config()).unwrap();
+
+        let to = server.path("archive/show.mkv");
+        client.rename(Path::new(&from), Path::new(&to)).unwrap();
+
+        assert!(!Path::new(&from).exists());
+        assert_eq!(std::fs::read(&to).unwrap(), b"episode");
+    }
+
+    #[test]
+    fn test_copy_folder_without_exec_streams_files() {
+        let server = TestServer::start();
+        let big = pattern(COPY_CHUNK_SIZE * 2 + 1);
+        server.write_file("album/a.flac", &big);
+        server.write_file("album/disc2/b.flac", b"short");
+        let client = SftpClient::connect(&server.config()).unwrap();
+
+        // The test server refuses exec, so this takes the streaming path
+        let mut last = (0, 0);
+        client
+            .copy(
+                Path::new(&server.path("album")),
+                Path::new(&server.path("backup/album")),
+                &mut |done, total| last = (done, total),
+            )
+            .unwrap();
+
+        assert_eq!(
+            std::fs::read(server.path("backup/album/a.flac")).unwrap(),
+            big
+        );
+        assert_eq!(
+            std::fs::read(server.path("backup/album/disc2/b.flac")).unwrap(),
+            b"short"
+        );
+        assert_eq!(last, (big.len() as u64 + 5, big.len() as u64 + 5));
+        // The source is left alone
+        assert!(Path::new(&server.path("album/a.flac")).exists());
+    }
+
+    #[test]
+    fn test_remove_and_trash() {
+        let server = TestServer::start();
+        server.write_file("old/a.txt", b"a");
+        server.write_file("old/nested/b.txt", b"b");
+        let first = server.write_file("show.mkv", b"one");
+        let client = SftpClient::connect(&server.config()).unwrap();
+
+        client.remove(Path::new(&server.path("old"))).unwrap();
+        assert!(!Path::new(&server.path("old")).exists());
+
+        let trash = server.path(".trash");
+        let moved = client
+            .move_to_trash(Path::new(&first), Path::new(&trash))
+            .unwrap();
+        assert_eq!(moved, Path::new(&trash).join("show.mkv"));
+        assert_eq!(std::fs::read(&moved).unwrap(), b"one");
+
+        // A second file with the same name doesn't replace the first
+        let second = server.write_file("show.mkv", b"two");
+        let moved_again = client
+            .move_to_trash(Path::new(&second), Path::new(&trash))
+            .unwrap();
+        assert_ne!(moved_again, moved);
+        assert_eq!(std::fs::read(&moved).unwrap(), b"one");
+        assert_eq!(std::fs::read(&moved_again).unwrap(), b"two");
+    }
+
+    #[test]
+    fn test_connect_with_protocol_trace() {
+        let server = TestServer::start();
+        server.write_file("a.txt", b"hello");
+        set_protocol_trace(true);
+        let client = SftpClient::connect(&server.config());
+        set_protocol_trace(false);
+
+        let traced = activity::entries(true)
+            .iter()
+            .any(|e| e.line().contains("[libssh2]"));
+        assert_eq!(traced, PROTOCOL_TRACE_AVAILABLE);
+        let (_, files) = client
+            .unwrap()
+            .list_dir(Path::new(&server.path("")))
+            .unwrap();
+        assert_eq!(files.len(), 1);
     }
 }
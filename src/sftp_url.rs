@@ -0,0 +1,127 @@
+use crate::settings::SftpConfig;
+
+/// A `sftp://[user@]host[:port]/path` link, from the command line or pasted in
+#[derive(Debug, Clone, PartialEq)]
+pub struct SftpUrl {
+    pub username: Option<String>,
+    pub host: String,
+    pub port: u16,
+    pub path: String, // percent-decoded, "/" when the link has none
+}
+
+impl SftpUrl {
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let (scheme, rest) = text.split_once("://")?;
+        if !scheme.eq_ignore_ascii_case("sftp") {
+            return None;
+        }
+        let (authority, path) = match rest.find('/') {
+            Some(i) => rest.split_at(i),
+            None => (rest, "/"),
+        };
+        let (username, host_port) = match authority.rsplit_once('@') {
+            // Drop connection parameters like ";fingerprint=..." from the user part
+            Some((user, host)) => {
+                let user = user.split(';').next().unwrap_or_default();
+                (Some(percent_decode(user)).filter(|u| !u.is_empty()), host)
+            }
+            None => (None, authority),
+        };
+        // IPv6 addresses come in brackets: [::1]:2222
+        let (host, port) = match host_port.strip_prefix('[') {
+            Some(bracketed) => {
+                let (host, after) = bracketed.split_once(']')?;
+                (host, after.strip_prefix(':'))
+            }
+            None => match host_port.rsplit_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (host_port, None),
+            },
+        };
+        let port = match port {
+            Some(port) => port.parse().ok()?,
+            None => 22,
+        };
+        if host.is_empty() {
+            return None;
+        }
+        Some(Self {
+            username,
+            host: host.to_string(),
+            port,
+            path: percent_decode(path),
+        })
+    }
+
+    /// Whether `config` is the server this link points at, and the same login
+    /// when the link names one
+    pub fn matches(&self, config: &SftpConfig) -> bool {
+        config.host.eq_ignore_ascii_case(&self.host)
+            && config.port == self.port
+            && self
+                .username
+                .as_ref()
+                .is_none_or(|user| *user == config.username)
+    }
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_sftp_links() {
+        let url = SftpUrl::parse(
+            "sftp://alice;fingerprint=ssh-ed25519-abc@nas.local:2222/srv/TV%20Shows/",
+        )
+        .unwrap();
+        assert_eq!(url.username.as_deref(), Some("alice"));
+        assert_eq!(url.host, "nas.local");
+        assert_eq!(url.port, 2222);
+        assert_eq!(url.path, "/srv/TV Shows/");
+
+        let bare = SftpUrl::parse("SFTP://example.com").unwrap();
+        assert_eq!(
+            (bare.username, bare.port, bare.path.as_str()),
+            (None, 22, "/")
+        );
+        assert_eq!(SftpUrl::parse("sftp://[::1]:22/x").unwrap().host, "::1");
+        assert!(SftpUrl::parse("https://example.com/x").is_none());
+        assert!(SftpUrl::parse("sftp://host:port/x").is_none());
+
+        let config = SftpConfig {
+            host: "NAS.local".into(),
+            port: 2222,
+            username: "alice".into(),
+            ..Default::default()
+        };
+        assert!(url.matches(&config));
+        assert!(!SftpUrl::parse("sftp://bob@nas.local:2222/")
+            .unwrap()
+            .matches(&config));
+    }
+}
@@ -1,14 +1,50 @@
-use iced::widget::container;
-use iced::{Background, Border, Theme};
+use crate::types::TransferStatus;
+use iced::theme::Palette;
+use iced::widget::{button, container};
+use iced::{Background, Border, Color, Theme};
+
+const HIGH_CONTRAST: &str = "High Contrast";
+
+/// The app's theme: the usual dark one, or pure black and white with a yellow
+/// accent, which every palette-based style (including iced's buttons) picks up
+pub fn theme(high_contrast: bool) -> Theme {
+    if !high_contrast {
+        return Theme::Dark;
+    }
+    Theme::custom(
+        HIGH_CONTRAST.to_string(),
+        Palette {
+            background: Color::BLACK,
+            text: Color::WHITE,
+            primary: Color::from_rgb(1.0, 0.85, 0.0),
+            success: Color::from_rgb(0.3, 1.0, 0.3),
+            danger: Color::from_rgb(1.0, 0.3, 0.3),
+        },
+    )
+}
+
+fn is_high_contrast(theme: &Theme) -> bool {
+    matches!(theme, Theme::Custom(custom) if custom.to_string() == HIGH_CONTRAST)
+}
+
+/// Outline for boxes: a hairline normally, a solid white line in high contrast
+fn outline(theme: &Theme) -> (f32, Color) {
+    if is_high_contrast(theme) {
+        (2.0, Color::WHITE)
+    } else {
+        (1.0, theme.extended_palette().background.strong.color)
+    }
+}
 
 pub fn header_style(theme: &Theme) -> container::Style {
     let palette = theme.extended_palette();
+    let (width, color) = outline(theme);
     container::Style {
         background: Some(Background::Color(palette.background.weak.color)),
         text_color: Some(palette.background.weak.text),
         border: Border {
-            width: 1.0,
-            color: palette.background.strong.color,
+            width,
+            color,
             radius: 0.0.into(),
         },
         ..Default::default()
@@ -17,13 +53,81 @@ pub fn header_style(theme: &Theme) -> container::Style {
 
 pub fn pane_style(theme: &Theme) -> container::Style {
     let palette = theme.extended_palette();
+    let (width, color) = outline(theme);
     container::Style {
         background: Some(Background::Color(palette.background.base.color)),
         border: Border {
-            width: 1.0,
-            color: palette.background.strong.color,
+            width,
+            color,
             radius: 0.0.into(),
         },
         ..Default::default()
     }
 }
+
+pub fn status_badge_style(theme: &Theme) -> container::Style {
+    let palette = theme.extended_palette();
+    let width = if is_high_contrast(theme) { 1.0 } else { 0.0 };
+    container::Style {
+        background: Some(Background::Color(palette.background.strong.color)),
+        border: Border {
+            width,
+            color: Color::WHITE,
+            radius: 8.0.into(),
+        },
+        ..Default::default()
+    }
+}
+
+/// Borderless text buttons (tabs, tree toggles, dismiss ×) get an outline in
+/// high contrast so they read as buttons
+pub fn text_button(theme: &Theme, status: button::Status) -> button::Style {
+    let style = button::text(theme, status);
+    if !is_high_contrast(theme) {
+        return style;
+    }
+    button::Style {
+        border: Border {
+            width: 1.0,
+            color: Color::WHITE,
+            radius: 2.0.into(),
+        },
+        ..style
+    }
+}
+
+pub fn status_color(status: &TransferStatus) -> Color {
+    match status {
+        TransferStatus::Queued => Color::from_rgb(0.7, 0.7, 0.7),
+        TransferStatus::Connecting | TransferStatus::Verifying => Color::from_rgb(0.4, 0.7, 1.0),
+        TransferStatus::Downloading => Color::from_rgb(0.3, 0.6, 1.0),
+        TransferStatus::Retrying(_) | TransferStatus::Stalled(_) => Color::from_rgb(1.0, 0.7, 0.2),
+        TransferStatus::Paused => Color::from_rgb(0.9, 0.8, 0.3),
+        TransferStatus::Completed => Color::from_rgb(0.3, 0.8, 0.3),
+        TransferStatus::Skipped(_) => Color::from_rgb(0.5, 0.7, 0.5),
+        TransferStatus::Cancelled => Color::from_rgb(0.5, 0.5, 0.5),
+        TransferStatus::Failed(_) => Color::from_rgb(1.0, 0.4, 0.4),
+    }
+}
+
+/// Highlight big files in listings: plain below 100 MB, then warmer up to red past 4 GB
+pub fn size_color(bytes: u64) -> Option<Color> {
+    const MB: u64 = 1024 * 1024;
+    const GB: u64 = MB * 1024;
+    match bytes {
+        b if b >= 4 * GB => Some(Color::from_rgb(1.0, 0.4, 0.4)),
+        b if b >= GB => Some(Color::from_rgb(1.0, 0.6, 0.3)),
+        b if b >= 100 * MB => Some(Color::from_rgb(0.95, 0.85, 0.4)),
+        _ => None,
+    }
+}
+
+/// Listing entries that changed on the server since the folder was last visited
+pub fn new_entry_color() -> Color {
+    Color::from_rgb(0.4, 0.85, 1.0)
+}
+
+/// Things worth a look but not failures, like items stuck in the queue for days
+pub fn warning_color() -> Color {
+    Color::from_rgb(1.0, 0.7, 0.2)
+}
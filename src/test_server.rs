@@ -0,0 +1,458 @@
+//! In-process SFTP server for tests.
+//!
+//! Serves a throwaway directory on 127.0.0.1 over russh/russh-sftp so SftpClient
+//! and DownloadManager can be exercised against a real SSH handshake. Remote
+//! paths are plain local paths; relative paths resolve against the served root.
+
+use crate::settings::SftpConfig;
+
+use russh::keys::ssh_key::private::Ed25519Keypair;
+use russh::keys::ssh_key::LineEnding;
+use russh::keys::PrivateKey;
+use russh::server::{Auth, Msg, Server as _, Session};
+use russh::{Channel, ChannelId};
+use russh_sftp::protocol::{
+    Attrs, Data, File, FileAttributes, Handle, Name, OpenFlags, Status, StatusCode,
+};
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+pub const TEST_USER: &str = "tester";
+pub const TEST_PASSWORD: &str = "secret";
+
+/// The key the server accepts for TEST_USER, as well as the password
+fn client_key() -> PrivateKey {
+    PrivateKey::from(Ed25519Keypair::from_seed(&[9u8; 32]))
+}
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+pub struct TestServer {
+    pub root: PathBuf,
+    pub port: u16,
+}
+
+impl TestServer {
+    /// Start a server on a random port, serving a fresh empty directory
+    pub fn start() -> Self {
+        let root = std::env::temp_dir().join(format!(
+            "simplesftp-test-{}-{}",
+            std::process::id(),
+            NEXT_ID.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).expect("create test root");
+        let root = root.canonicalize().expect("canonicalize test root");
+
+        // The server gets its own runtime thread so blocking ssh2 tests and
+        // #[tokio::test] tests can both use it
+        let (port_tx, port_rx) = std::sync::mpsc::channel();
+        let served_root = root.clone();
+        std::thread::spawn(move || {
+            let runtime = tokio::runtime::Runtime::new().expect("test server runtime");
+            runtime.block_on(async move {
+                let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+                    .await
+                    .expect("bind test server");
+                port_tx.send(listener.local_addr().unwrap().port()).unwrap();
+
+                let config = russh::server::Config {
+                    auth_rejection_time: std::time::Duration::from_millis(10),
+                    auth_rejection_time_initial: Some(std::time::Duration::from_secs(0)),
+                    keys: vec![PrivateKey::from(Ed25519Keypair::from_seed(&[7u8; 32]))],
+                    ..Default::default()
+                };
+                let mut server = TestSshServer { root: served_root };
+                let _ = server.run_on_socket(Arc::new(config), &listener).await;
+            });
+        });
+        let port = port_rx.recv().expect("test server port");
+
+        Self { root, port }
+    }
+
+    pub fn config(&self) -> SftpConfig {
+        SftpConfig {
+            host: "127.0.0.1".into(),
+            port: self.port,
+            username: TEST_USER.into(),
+            password: Some(TEST_PASSWORD.into()),
+            private_key_path: None,
+            pre_connect_command: None,
+            start_directory: None,
+            trash_folder: None,
+            compression: false,
+            read_only: false,
+            allow_shell: false,
+            totp_secret: None,
+            totp_in_keyring: false,
+            server_timezone: None,
+            algorithms: Default::default(),
+        }
+    }
+
+    /// Write the accepted client key next to the served root, for key logins
+    pub fn write_client_key(&self) -> String {
+        let path = self.root.with_extension("key");
+        let pem = client_key().to_openssh(LineEnding::LF).unwrap();
+        std::fs::write(&path, pem.as_bytes()).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    /// Create a file (and its parent folders) under the served root
+    pub fn write_file(&self, relative: &str, contents: &[u8]) -> String {
+        let path = self.root.join(relative);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, contents).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    pub fn path(&self, relative: &str) -> String {
+        self.root.join(relative).to_string_lossy().to_string()
+    }
+
+    /// A sibling scratch directory for the local side of a transfer
+    pub fn local_dir(&self) -> PathBuf {
+        let dir = self.root.with_extension("local");
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.root);
+        let _ = std::fs::remove_dir_all(self.root.with_extension("local"));
+    }
+}
+
+/// Deterministic file contents so downloads can be compared byte for byte
+pub fn pattern(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+#[derive(Clone)]
+struct TestSshServer {
+    root: PathBuf,
+}
+
+impl russh::server::Server for TestSshServer {
+    type Handler = TestSshSession;
+
+    fn new_client(&mut self, _: Option<std::net::SocketAddr>) -> Self::Handler {
+        TestSshSession {
+            root: self.root.clone(),
+            channels: HashMap::new(),
+        }
+    }
+}
+
+struct TestSshSession {
+    root: PathBuf,
+    channels: HashMap<ChannelId, Channel<Msg>>,
+}
+
+impl russh::server::Handler for TestSshSession {
+    type Error = russh::Error;
+
+    async fn auth_password(&mut self, user: &str, password: &str) -> Result<Auth, Self::Error> {
+        if user == TEST_USER && password == TEST_PASSWORD {
+            Ok(Auth::Accept)
+        } else {
+            Ok(Auth::reject())
+        }
+    }
+
+    async fn auth_publickey(
+        &mut self,
+        user: &str,
+        public_key: &russh::keys::PublicKey,
+    ) -> Result<Auth, Self::Error> {
+        if user == TEST_USER && public_key == client_key().public_key() {
+            Ok(Auth::Accept)
+        } else {
+            Ok(Auth::reject())
+        }
+    }
+
+    async fn channel_open_session(
+        &mut self,
+        channel: Channel<Msg>,
+        reply: russh::server::ChannelOpenHandle,
+        _session: &mut Session,
+    ) -> Result<(), Self::Error> {
+        self.channels.insert(channel.id(), channel);
+        reply.accept().await;
+        Ok(())
+    }
+
+    async fn channel_eof(
+        &mut self,
+        channel: ChannelId,
+        session: &mut Session,
+    ) -> Result<(), Self::Error> {
+        session.close(channel)?;
+        Ok(())
+    }
+
+    /// Behave like an SFTP-only (internal-sftp) server: no shell commands,
+    /// apart from two canned ones for exec tests. "noisy" writes 1 MiB to
+    /// stderr then "done" to stdout; "hang" never finishes
+    async fn exec_request(
+        &mut self,
+        channel: ChannelId,
+        data: &[u8],
+        session: &mut Session,
+    ) -> Result<(), Self::Error> {
+        match data {
+            b"noisy" => {
+                session.channel_success(channel)?;
+                let handle = session.handle();
+                tokio::spawn(async move {
+                    for _ in 0..32 {
+                        let _ = handle
+                            .extended_data(channel, 1, vec![b'e'; 32 * 1024])
+                            .await;
+                    }
+                    let _ = handle.data(channel, b"done\n".to_vec()).await;
+                    let _ = handle.exit_status_request(channel, 0).await;
+                    let _ = handle.eof(channel).await;
+                    let _ = handle.close(channel).await;
+                });
+            }
+            b"hang" => session.channel_success(channel)?,
+            _ => session.channel_failure(channel)?,
+        }
+        Ok(())
+    }
+
+    async fn subsystem_request(
+        &mut self,
+        channel_id: ChannelId,
+        name: &str,
+        session: &mut Session,
+    ) -> Result<(), Self::Error> {
+        match (name, self.channels.remove(&channel_id)) {
+            ("sftp", Some(channel)) => {
+                session.channel_success(channel_id)?;
+                let sftp = TestSftpSession {
+                    root: self.root.clone(),
+                    handles: HashMap::new(),
+                    next_handle: 0,
+                };
+                russh_sftp::server::run(channel.into_stream(), sftp).await;
+            }
+            _ => session.channel_failure(channel_id)?,
+        }
+        Ok(())
+    }
+}
+
+enum OpenHandle {
+    File(std::fs::File),
+    Dir(Option<Vec<File>>),
+}
+
+struct TestSftpSession {
+    root: PathBuf,
+    handles: HashMap<String, OpenHandle>,
+    next_handle: u64,
+}
+
+fn status_of(e: std::io::Error) -> StatusCode {
+    match e.kind() {
+        std::io::ErrorKind::NotFound => StatusCode::NoSuchFile,
+        std::io::ErrorKind::PermissionDenied => StatusCode::PermissionDenied,
+        _ => StatusCode::Failure,
+    }
+}
+
+fn ok(id: u32) -> Status {
+    Status {
+        id,
+        status_code: StatusCode::Ok,
+        error_message: "Ok".into(),
+        language_tag: "en-US".into(),
+    }
+}
+
+impl TestSftpSession {
+    fn resolve(&self, path: &str) -> PathBuf {
+        let path = Path::new(path);
+        if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            self.root.join(path)
+        }
+    }
+
+    fn add_handle(&mut self, handle: OpenHandle) -> String {
+        self.next_handle += 1;
+        let key = self.next_handle.to_string();
+        self.handles.insert(key.clone(), handle);
+        key
+    }
+}
+
+impl russh_sftp::server::Handler for TestSftpSession {
+    type Error = StatusCode;
+
+    fn unimplemented(&self) -> Self::Error {
+        StatusCode::OpUnsupported
+    }
+
+    async fn realpath(&mut self, id: u32, path: String) -> Result<Name, Self::Error> {
+        let resolved = self.resolve(&path).canonicalize().map_err(status_of)?;
+        Ok(Name {
+            id,
+            files: vec![File::dummy(resolved.to_string_lossy())],
+        })
+    }
+
+    async fn stat(&mut self, id: u32, path: String) -> Result<Attrs, Self::Error> {
+        let metadata = std::fs::metadata(self.resolve(&path)).map_err(status_of)?;
+        Ok(Attrs {
+            id,
+            attrs: FileAttributes::from(&metadata),
+        })
+    }
+
+    async fn lstat(&mut self, id: u32, path: String) -> Result<Attrs, Self::Error> {
+        let metadata = std::fs::symlink_metadata(self.resolve(&path)).map_err(status_of)?;
+        Ok(Attrs {
+            id,
+            attrs: FileAttributes::from(&metadata),
+        })
+    }
+
+    async fn fstat(&mut self, id: u32, handle: String) -> Result<Attrs, Self::Error> {
+        match self.handles.get(&handle) {
+            Some(OpenHandle::File(file)) => Ok(Attrs {
+                id,
+                attrs: FileAttributes::from(&file.metadata().map_err(status_of)?),
+            }),
+            _ => Err(StatusCode::Failure),
+        }
+    }
+
+    async fn opendir(&mut self, id: u32, path: String) -> Result<Handle, Self::Error> {
+        let dir = self.resolve(&path);
+        let mut files = Vec::new();
+        for name in [".", ".."] {
+            let metadata = std::fs::metadata(dir.join(name)).map_err(status_of)?;
+            files.push(File::new(name, FileAttributes::from(&metadata)));
+        }
+        for entry in std::fs::read_dir(&dir).map_err(status_of)? {
+            let entry = entry.map_err(status_of)?;
+            let metadata = entry.metadata().map_err(status_of)?;
+            files.push(File::new(
+                entry.file_name().to_string_lossy(),
+                FileAttributes::from(&metadata),
+            ));
+        }
+        let handle = self.add_handle(OpenHandle::Dir(Some(files)));
+        Ok(Handle { id, handle })
+    }
+
+    async fn readdir(&mut self, id: u32, handle: String) -> Result<Name, Self::Error> {
+        match self.handles.get_mut(&handle) {
+            Some(OpenHandle::Dir(entries)) => match entries.take() {
+                Some(files) => Ok(Name { id, files }),
+                None => Err(StatusCode::Eof),
+            },
+            _ => Err(StatusCode::Failure),
+        }
+    }
+
+    async fn open(
+        &mut self,
+        id: u32,
+        filename: String,
+        pflags: OpenFlags,
+        _attrs: FileAttributes,
+    ) -> Result<Handle, Self::Error> {
+        let file = std::fs::OpenOptions::new()
+            .read(pflags.contains(OpenFlags::READ))
+            .write(pflags.contains(OpenFlags::WRITE))
+            .append(pflags.contains(OpenFlags::APPEND))
+            .create(pflags.contains(OpenFlags::CREATE))
+            .truncate(pflags.contains(OpenFlags::TRUNCATE))
+            .open(self.resolve(&filename))
+            .map_err(status_of)?;
+        let handle = self.add_handle(OpenHandle::File(file));
+        Ok(Handle { id, handle })
+    }
+
+    async fn read(
+        &mut self,
+        id: u32,
+        handle: String,
+        offset: u64,
+        len: u32,
+    ) -> Result<Data, Self::Error> {
+        let Some(OpenHandle::File(file)) = self.handles.get_mut(&handle) else {
+            return Err(StatusCode::Failure);
+        };
+        file.seek(SeekFrom::Start(offset)).map_err(status_of)?;
+        let mut data = vec![0u8; len as usize];
+        let n = file.read(&mut data).map_err(status_of)?;
+        if n == 0 {
+            return Err(StatusCode::Eof);
+        }
+        data.truncate(n);
+        Ok(Data { id, data })
+    }
+
+    async fn write(
+        &mut self,
+        id: u32,
+        handle: String,
+        offset: u64,
+        data: Vec<u8>,
+    ) -> Result<Status, Self::Error> {
+        let Some(OpenHandle::File(file)) = self.handles.get_mut(&handle) else {
+            return Err(StatusCode::Failure);
+        };
+        file.seek(SeekFrom::Start(offset)).map_err(status_of)?;
+        file.write_all(&data).map_err(status_of)?;
+        Ok(ok(id))
+    }
+
+    async fn close(&mut self, id: u32, handle: String) -> Result<Status, Self::Error> {
+        self.handles.remove(&handle);
+        Ok(ok(id))
+    }
+
+    async fn mkdir(
+        &mut self,
+        id: u32,
+        path: String,
+        _attrs: FileAttributes,
+    ) -> Result<Status, Self::Error> {
+        std::fs::create_dir(self.resolve(&path)).map_err(status_of)?;
+        Ok(ok(id))
+    }
+
+    async fn rmdir(&mut self, id: u32, path: String) -> Result<Status, Self::Error> {
+        std::fs::remove_dir(self.resolve(&path)).map_err(status_of)?;
+        Ok(ok(id))
+    }
+
+    async fn remove(&mut self, id: u32, filename: String) -> Result<Status, Self::Error> {
+        std::fs::remove_file(self.resolve(&filename)).map_err(status_of)?;
+        Ok(ok(id))
+    }
+
+    async fn rename(
+        &mut self,
+        id: u32,
+        oldpath: String,
+        newpath: String,
+    ) -> Result<Status, Self::Error> {
+        std::fs::rename(self.resolve(&oldpath), self.resolve(&newpath)).map_err(status_of)?;
+        Ok(ok(id))
+    }
+}
@@ -0,0 +1,188 @@
+use crate::error::SftpError;
+use crate::remote_fs::RemoteFs;
+
+use crate::checksum;
+use std::path::{Path, PathBuf};
+
+const THUMB_SIZE: u32 = 128;
+const FULL_FETCH_LIMIT: u64 = 8 * 1024 * 1024; // Smaller images are fetched whole
+const HEAD_BYTES: usize = 64 * 1024; // Bigger JPEGs: look for the EXIF thumbnail in here
+const FETCH_CHUNK: usize = 256 * 1024;
+const CACHE_MAX_ENTRIES: usize = 500;
+
+/// Fetch enough of a remote image to build a thumbnail and return it as PNG bytes.
+/// `Ok(None)` means the file couldn't be turned into one (too big without an
+/// embedded preview, or not decodable).
+pub fn fetch_thumbnail(
+    fs: &dyn RemoteFs,
+    remote_path: &str,
+    size: u64,
+) -> Result<Option<Vec<u8>>, SftpError> {
+    let path = Path::new(remote_path);
+    if size > FULL_FETCH_LIMIT {
+        let head = fs.read_chunk(path, 0, HEAD_BYTES)?;
+        return Ok(embedded_jpeg_thumbnail(&head).and_then(make_thumbnail));
+    }
+
+    let mut data = Vec::with_capacity(size as usize);
+    loop {
+        let chunk = fs.read_chunk(path, data.len() as u64, FETCH_CHUNK)?;
+        if chunk.is_empty() {
+            break;
+        }
+        data.extend_from_slice(&chunk);
+    }
+    Ok(make_thumbnail(&data))
+}
+
+/// Decode an image and scale it down to fit THUMB_SIZE, encoded as PNG
+pub fn make_thumbnail(data: &[u8]) -> Option<Vec<u8>> {
+    let thumb = image::load_from_memory(data)
+        .ok()?
+        .thumbnail(THUMB_SIZE, THUMB_SIZE);
+    let mut png = Vec::new();
+    thumb
+        .write_to(
+            &mut std::io::Cursor::new(&mut png),
+            image::ImageOutputFormat::Png,
+        )
+        .ok()?;
+    Some(png)
+}
+
+/// Cameras store a small JPEG preview in the EXIF block near the start of the
+/// file; find the second SOI marker and its matching EOI
+pub fn embedded_jpeg_thumbnail(head: &[u8]) -> Option<&[u8]> {
+    if !head.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let start = head[2..].windows(2).position(|w| w == [0xFF, 0xD8])? + 2;
+    let len = head[start..].windows(2).position(|w| w == [0xFF, 0xD9])? + 2;
+    Some(&head[start..start + len])
+}
+
+/// Thumbnails on disk for one server, keyed by remote path + modification time
+/// so an edited image gets a fresh one. Least recently used entries are dropped
+/// past the limit.
+pub struct ThumbnailCache {
+    dir: PathBuf,
+    max_entries: usize,
+    profile: String, // profile_key() of the server, so servers don't share entries
+}
+
+impl ThumbnailCache {
+    pub fn new(profile: &str) -> Self {
+        let dir = directories::ProjectDirs::from("com", "simplesftp", "simplesftp")
+            .map(|d| d.cache_dir().join("thumbnails"))
+            .unwrap_or_else(|| PathBuf::from("thumbnails"));
+        Self::with_dir(dir, CACHE_MAX_ENTRIES, profile)
+    }
+
+    pub fn with_dir(dir: PathBuf, max_entries: usize, profile: &str) -> Self {
+        let _ = std::fs::create_dir_all(&dir);
+        Self {
+            dir,
+            max_entries,
+            profile: profile.to_string(),
+        }
+    }
+
+    /// SHA-256 rather than std's hasher, whose output can change between Rust
+    /// releases and would orphan the cache
+    fn entry_path(&self, remote_path: &str, modified: &str) -> PathBuf {
+        let key = format!("{}\0{}\0{}", self.profile, remote_path, modified);
+        let hash = checksum::hash_bytes(key.as_bytes());
+        self.dir.join(format!("{}.png", &hash[..32]))
+    }
+
+    pub fn get(&self, remote_path: &str, modified: &str) -> Option<Vec<u8>> {
+        let path = self.entry_path(remote_path, modified);
+        let data = std::fs::read(&path).ok()?;
+        // Reading counts as a use for LRU purposes
+        if let Ok(file) = std::fs::File::options().append(true).open(&path) {
+            let _ = file.set_modified(std::time::SystemTime::now());
+        }
+        Some(data)
+    }
+
+    pub fn put(&self, remote_path: &str, modified: &str, png: &[u8]) {
+        let _ = std::fs::write(self.entry_path(remote_path, modified), png);
+        self.evict();
+    }
+
+    fn evict(&self) {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return;
+        };
+        let mut files: Vec<_> = entries
+            .flatten()
+            .filter_map(|e| Some((e.metadata().ok()?.modified().ok()?, e.path())))
+            .collect();
+        if files.len() <= self.max_entries {
+            return;
+        }
+        files.sort();
+        for (_, path) in &files[..files.len() - self.max_entries] {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::remote_fs::MemoryFs;
+
+    fn png_bytes(width: u32, height: u32) -> Vec<u8> {
+        let img = image::RgbImage::from_pixel(width, height, image::Rgb([200, 30, 30]));
+        let mut out = Vec::new();
+        image::DynamicImage::ImageRgb8(img)
+            .write_to(
+                &mut std::io::Cursor::new(&mut out),
+                image::ImageOutputFormat::Png,
+            )
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn test_fetches_and_scales_small_image() {
+        let fs = MemoryFs::new();
+        let data = png_bytes(640, 320);
+        fs.add_file("/r/photo.png", data.clone());
+
+        let thumb = fetch_thumbnail(&fs, "/r/photo.png", data.len() as u64)
+            .unwrap()
+            .unwrap();
+        let decoded = image::load_from_memory(&thumb).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (128, 64));
+
+        fs.add_file("/r/notes.png", b"not an image".to_vec());
+        assert_eq!(fetch_thumbnail(&fs, "/r/notes.png", 12).unwrap(), None);
+    }
+
+    #[test]
+    fn test_cache_evicts_least_recently_used() {
+        let dir = std::env::temp_dir().join(format!("simplesftp-thumbs-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let cache = ThumbnailCache::with_dir(dir.clone(), 2, "alice@nas:22");
+
+        cache.put("/a.jpg", "2024-01-01", b"a");
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        cache.put("/b.jpg", "2024-01-01", b"b");
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        assert_eq!(cache.get("/a.jpg", "2024-01-01"), Some(b"a".to_vec()));
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        cache.put("/c.jpg", "2024-01-01", b"c");
+
+        // "b" was the least recently used; a changed mtime is a different entry
+        assert_eq!(cache.get("/b.jpg", "2024-01-01"), None);
+        assert!(cache.get("/a.jpg", "2024-01-01").is_some());
+        assert!(cache.get("/c.jpg", "2024-01-01").is_some());
+        assert_eq!(cache.get("/a.jpg", "2024-06-01"), None);
+        // Another server's file at the same path is its own entry
+        let other = ThumbnailCache::with_dir(dir.clone(), 2, "bob@backup:22");
+        assert_eq!(other.get("/a.jpg", "2024-01-01"), None);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -2,12 +2,13 @@
 use gtk;
 use tray_icon::{
     menu::{Menu, MenuEvent, MenuId, MenuItem},
-    Icon, TrayIcon, TrayIconBuilder,
+    Icon, MouseButton, MouseButtonState, TrayIcon, TrayIconBuilder, TrayIconEvent,
 };
 
 pub struct TrayManager {
     _tray_icon: TrayIcon,
     menu_event_receiver: tray_icon::menu::MenuEventReceiver,
+    icon_event_receiver: tray_icon::TrayIconEventReceiver,
     show_item_id: MenuId,
     exit_item_id: MenuId,
 }
@@ -42,21 +43,29 @@ impl TrayManager {
         let icon = Self::generate_icon()?;
         let tray_icon = TrayIconBuilder::new()
             .with_menu(Box::new(tray_menu))
+            // Left click toggles the window; the menu stays on right click
+            .with_menu_on_left_click(false)
             .with_tooltip("SimpleSFTP")
             .with_icon(icon)
             .build()?;
         println!("Tray icon built successfully.");
 
         let menu_event_receiver = MenuEvent::receiver().clone();
+        let icon_event_receiver = TrayIconEvent::receiver().clone();
 
         Ok(Self {
             _tray_icon: tray_icon,
             menu_event_receiver,
+            icon_event_receiver,
             show_item_id,
             exit_item_id,
         })
     }
 
+    /// Let the tray run. Only Linux needs this: there the icon lives in GTK,
+    /// which has no loop of its own in an iced app. On Windows and macOS the
+    /// icon's messages arrive through the window's own event loop on the main
+    /// thread, and the receivers just need polling
     pub fn update(&self) {
         #[cfg(target_os = "linux")]
         {
@@ -66,7 +75,18 @@ impl TrayManager {
         }
     }
 
+    /// The app icon at tray size, or a plain blue square if it won't decode
     fn generate_icon() -> Result<Icon, Box<dyn std::error::Error>> {
+        if let Ok(image) = image::load_from_memory(include_bytes!("../assets/icon.png")) {
+            let rgba = image
+                .resize(32, 32, image::imageops::FilterType::Lanczos3)
+                .into_rgba8();
+            let (width, height) = rgba.dimensions();
+            if let Ok(icon) = Icon::from_rgba(rgba.into_raw(), width, height) {
+                return Ok(icon);
+            }
+        }
+
         let width = 32;
         let height = 32;
         let mut rgba = Vec::with_capacity((width * height * 4) as usize);
@@ -83,7 +103,8 @@ impl TrayManager {
             .map_err(|e| format!("Failed to create icon: {}", e).into())
     }
 
-    /// Check for tray menu events and return the action
+    /// Check for tray menu events and clicks on the icon and return the action.
+    /// Icon clicks aren't reported on Linux, where the menu is the only way in
     pub fn poll_events(&self) -> Option<TrayAction> {
         if let Ok(event) = self.menu_event_receiver.try_recv() {
             if event.id == self.show_item_id {
@@ -92,6 +113,16 @@ impl TrayManager {
                 return Some(TrayAction::Exit);
             }
         }
+        while let Ok(event) = self.icon_event_receiver.try_recv() {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                return Some(TrayAction::Toggle);
+            }
+        }
         None
     }
 }
@@ -99,5 +130,6 @@ impl TrayManager {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TrayAction {
     Show,
+    Toggle, // left click on the icon: show the window if hidden, else hide it
     Exit,
 }
@@ -1,35 +1,485 @@
+use crate::checksum::ResumeCheck;
+use crate::error::FailureKind;
+use crate::names;
+use chrono::{DateTime, Utc};
+
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TransferStatus {
-    Pending,
+    #[serde(alias = "Pending")]
+    Queued,
+    Connecting,
     Downloading,
+    Verifying,
+    Retrying(u32), // attempt number
+    Stalled(u32),  // no data for a while, reconnecting; attempt number
     Paused,
     Completed,
+    Skipped(String),
+    Cancelled,
     Failed(String),
 }
 
+impl TransferStatus {
+    /// True while a transfer task owns the item (connecting, moving bytes or checking them)
+    pub fn is_active(&self) -> bool {
+        matches!(
+            self,
+            TransferStatus::Connecting
+                | TransferStatus::Downloading
+                | TransferStatus::Verifying
+                | TransferStatus::Retrying(_)
+                | TransferStatus::Stalled(_)
+        )
+    }
+
+    /// True once the item will not be picked up again without user action
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            TransferStatus::Completed
+                | TransferStatus::Skipped(_)
+                | TransferStatus::Cancelled
+                | TransferStatus::Failed(_)
+        )
+    }
+}
+
 impl std::fmt::Display for TransferStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            TransferStatus::Pending => write!(f, "Pending"),
+            TransferStatus::Queued => write!(f, "Queued"),
+            TransferStatus::Connecting => write!(f, "Connecting"),
             TransferStatus::Downloading => write!(f, "Downloading"),
+            TransferStatus::Verifying => write!(f, "Verifying"),
+            TransferStatus::Retrying(attempt) => write!(f, "Retrying ({})", attempt),
+            TransferStatus::Stalled(attempt) => write!(f, "Stalled, restarting ({})", attempt),
             TransferStatus::Paused => write!(f, "Paused"),
             TransferStatus::Completed => write!(f, "Completed"),
+            TransferStatus::Skipped(reason) => write!(f, "Skipped: {}", reason),
+            TransferStatus::Cancelled => write!(f, "Cancelled"),
             TransferStatus::Failed(e) => write!(f, "Failed: {}", e),
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Direction {
+    #[default]
+    Download,
+    Upload, // also server-to-server relays: no local disk, resumed from the destination's size
+}
+
+/// Where a server-to-server copy goes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RelayTarget {
+    pub profile: String,     // profile_key() of the destination server
+    pub remote_file: String, // full path on that server
+}
+
+/// What tells queue items apart, here and in the download manager: the same
+/// path can be queued from two servers, or to two places
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ItemKey {
+    pub profile: String, // as in TransferItem::profile
+    pub remote_file: String,
+    pub local_file: String, // the local copy's path; for a relay, the server and path it goes to
+}
+
+impl ItemKey {
+    /// The key as one line of text, for the transfer journal
+    pub fn id(&self) -> String {
+        format!(
+            "{}\t{}\t{}",
+            self.profile, self.remote_file, self.local_file
+        )
+    }
+}
+
+/// One entry in the transfer queue. Uploads and downloads share the queue,
+/// its concurrency slots and the speed limit.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct QueueItem {
+pub struct TransferItem {
+    #[serde(default)]
+    pub direction: Direction,
     pub local_location: String,
     pub filename: String,
     pub remote_file: String,
     pub size_bytes: u64,
-    pub bytes_downloaded: u64,
-    pub priority: u8,
+    #[serde(alias = "bytes_downloaded")]
+    pub bytes_transferred: u64,
+    pub priority: u8, // lower runs first
     pub status: TransferStatus,
+    #[serde(default)]
+    pub batch: Option<Batch>,
+    #[serde(default)]
+    pub follow: bool, // keep reading as the remote file grows, until it settles
+    #[serde(default)]
+    pub speed_limit: u64, // KB/s cap for this item alone, on top of the global one; 0 = none
+    #[serde(default)]
+    pub profile: String, // profile_key() of the server it's from; empty = the manager's own
+    #[serde(default)]
+    pub relay_to: Option<RelayTarget>, // copy to another server instead of a local file
+    #[serde(default)]
+    pub resume_check: Option<ResumeCheck>, // the partial file as it was left at the last pause
+    #[serde(default)]
+    pub failure: Option<FailureKind>, // set along with TransferStatus::Failed
+    #[serde(default)]
+    pub queued_at: i64, // unix seconds; 0 = queued before this was recorded
+    #[serde(default)]
+    pub timeline: Vec<TimelineEntry>, // oldest first, at most TIMELINE_LIMIT
+    #[serde(default)]
+    pub note: String, // free text from the detail panel, e.g. "for Alice"
+    #[serde(default)]
+    pub label: Option<ItemLabel>,
+}
+
+/// A colored tag for a queue item, picked in the detail panel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ItemLabel {
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+}
+
+impl ItemLabel {
+    pub const ALL: [ItemLabel; 6] = [
+        ItemLabel::Red,
+        ItemLabel::Orange,
+        ItemLabel::Yellow,
+        ItemLabel::Green,
+        ItemLabel::Blue,
+        ItemLabel::Purple,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ItemLabel::Red => "Red label",
+            ItemLabel::Orange => "Orange label",
+            ItemLabel::Yellow => "Yellow label",
+            ItemLabel::Green => "Green label",
+            ItemLabel::Blue => "Blue label",
+            ItemLabel::Purple => "Purple label",
+        }
+    }
+
+    pub fn rgb(self) -> (f32, f32, f32) {
+        match self {
+            ItemLabel::Red => (0.9, 0.3, 0.3),
+            ItemLabel::Orange => (0.95, 0.6, 0.2),
+            ItemLabel::Yellow => (0.95, 0.85, 0.3),
+            ItemLabel::Green => (0.4, 0.8, 0.4),
+            ItemLabel::Blue => (0.35, 0.6, 0.95),
+            ItemLabel::Purple => (0.7, 0.45, 0.9),
+        }
+    }
+}
+
+pub const TIMELINE_LIMIT: usize = 100;
+
+/// Something that happened to a transfer, kept for its timeline in the detail panel
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimelineEntry {
+    pub at: i64, // unix seconds
+    pub event: TimelineEvent,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TimelineEvent {
+    Started,
+    Paused(String), // why, e.g. "schedule" or "by you"
+    Retried,        // a chunk failed or stalled and the transfer reconnected
+    Finished(TransferStatus),
+}
+
+/// A stretch of an item's timeline between two events
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimelineSpan {
+    Running(u64), // seconds
+    Paused(String, u64),
+}
+
+impl TransferItem {
+    pub fn key(&self) -> ItemKey {
+        ItemKey {
+            profile: self.profile.clone(),
+            remote_file: self.remote_file.clone(),
+            local_file: match &self.relay_to {
+                Some(target) => format!("{}:{}", target.profile, target.remote_file),
+                None => names::path_to_string(&self.local_path()),
+            },
+        }
+    }
+
+    pub fn local_path(&self) -> PathBuf {
+        names::to_path(&self.local_location).join(names::to_path(&self.filename))
+    }
+
+    /// How long an unfinished item has been in the queue, as of `now` (unix seconds)
+    pub fn waiting_secs(&self, now: i64) -> Option<u64> {
+        if self.status.is_finished() || self.queued_at == 0 {
+            return None;
+        }
+        Some(now.saturating_sub(self.queued_at).max(0) as u64)
+    }
+
+    /// What the item takes turns with when the queue is fair: its batch, or
+    /// else the other single items from the same server
+    pub fn fairness_group(&self) -> String {
+        match &self.batch {
+            Some(batch) => format!("batch {}", batch.id),
+            None => format!("server {}", self.profile),
+        }
+    }
+
+    /// Record `event` for the timeline, dropping the oldest past the limit
+    pub fn note(&mut self, at: i64, event: TimelineEvent) {
+        self.timeline.push(TimelineEntry { at, event });
+        if self.timeline.len() > TIMELINE_LIMIT {
+            self.timeline.remove(0);
+        }
+    }
+
+    /// Running and paused stretches from the first start on, up to `now` while
+    /// unfinished; retries don't break a running stretch
+    pub fn timeline_spans(&self, now: i64) -> Vec<TimelineSpan> {
+        let mut spans: Vec<TimelineSpan> = Vec::new();
+        for (i, entry) in self.timeline.iter().enumerate() {
+            let end = self.timeline.get(i + 1).map_or(now, |next| next.at);
+            let secs = end.saturating_sub(entry.at).max(0) as u64;
+            let span = match &entry.event {
+                TimelineEvent::Started | TimelineEvent::Retried => TimelineSpan::Running(secs),
+                TimelineEvent::Paused(why) => TimelineSpan::Paused(why.clone(), secs),
+                TimelineEvent::Finished(_) => continue,
+            };
+            match (spans.last_mut(), span) {
+                (Some(TimelineSpan::Running(total)), TimelineSpan::Running(secs)) => *total += secs,
+                (_, span) => spans.push(span),
+            }
+        }
+        spans
+    }
+
+    pub fn retries(&self) -> usize {
+        self.timeline
+            .iter()
+            .filter(|e| e.event == TimelineEvent::Retried)
+            .count()
+    }
+}
+
+/// Files queued together from one folder scan
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Batch {
+    pub id: u64,
+    pub name: String, // the scanned folder's name
+}
+
+/// Which waiting item the manager starts next, picked in the queue toolbar
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QueueOrder {
+    #[default]
+    Priority,
+    Fifo,
+    SmallestFirst,
+    LargestFirst,
+    ByFolder,
+}
+
+impl QueueOrder {
+    pub const ALL: [QueueOrder; 5] = [
+        QueueOrder::Priority,
+        QueueOrder::Fifo,
+        QueueOrder::SmallestFirst,
+        QueueOrder::LargestFirst,
+        QueueOrder::ByFolder,
+    ];
+
+    /// Whether `a` should start before `b`. Equal keeps queue order, so
+    /// callers must use a stable sort or take the first of equals.
+    pub fn compare(self, a: &TransferItem, b: &TransferItem) -> std::cmp::Ordering {
+        match self {
+            QueueOrder::Priority => a.priority.cmp(&b.priority),
+            QueueOrder::Fifo => std::cmp::Ordering::Equal,
+            QueueOrder::SmallestFirst => a.size_bytes.cmp(&b.size_bytes),
+            QueueOrder::LargestFirst => b.size_bytes.cmp(&a.size_bytes),
+            // Finish one folder before moving to the next
+            QueueOrder::ByFolder => {
+                let folder =
+                    |i: &TransferItem| PathBuf::from(&i.remote_file).parent().map(PathBuf::from);
+                folder(a)
+                    .cmp(&folder(b))
+                    .then_with(|| a.remote_file.cmp(&b.remote_file))
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for QueueOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            QueueOrder::Priority => "Priority",
+            QueueOrder::Fifo => "First in, first out",
+            QueueOrder::SmallestFirst => "Smallest first",
+            QueueOrder::LargestFirst => "Largest first",
+            QueueOrder::ByFolder => "By folder",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// Which rows the queue list shows, picked with the chips above it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QueueFilter {
+    #[default]
+    All,
+    Active,
+    Pending,
+    Paused,
+    Failed,
+    Completed,
+}
+
+impl QueueFilter {
+    pub const ALL: [QueueFilter; 6] = [
+        QueueFilter::All,
+        QueueFilter::Active,
+        QueueFilter::Pending,
+        QueueFilter::Paused,
+        QueueFilter::Failed,
+        QueueFilter::Completed,
+    ];
+
+    pub fn matches(self, status: &TransferStatus) -> bool {
+        match self {
+            QueueFilter::All => true,
+            QueueFilter::Active => status.is_active(),
+            QueueFilter::Pending => *status == TransferStatus::Queued,
+            QueueFilter::Paused => *status == TransferStatus::Paused,
+            QueueFilter::Failed => matches!(status, TransferStatus::Failed(_)),
+            QueueFilter::Completed => {
+                matches!(
+                    status,
+                    TransferStatus::Completed | TransferStatus::Skipped(_)
+                )
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for QueueFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            QueueFilter::All => "All",
+            QueueFilter::Active => "Active",
+            QueueFilter::Pending => "Pending",
+            QueueFilter::Paused => "Paused",
+            QueueFilter::Failed => "Failed",
+            QueueFilter::Completed => "Completed",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// Reorder the items still waiting (queued or paused) by `order`, in the slots
+/// they already occupy. Running and finished rows stay where they are.
+pub fn sort_pending(items: &mut [TransferItem], order: QueueOrder) {
+    let is_pending =
+        |i: &TransferItem| matches!(i.status, TransferStatus::Queued | TransferStatus::Paused);
+    let slots: Vec<usize> = (0..items.len())
+        .filter(|&n| is_pending(&items[n]))
+        .collect();
+    let mut pending: Vec<TransferItem> = slots.iter().map(|&n| items[n].clone()).collect();
+    pending.sort_by(|a, b| order.compare(a, b));
+    for (slot, item) in slots.into_iter().zip(pending) {
+        items[slot] = item;
+    }
+}
+
+/// How a new item relates to what is already queued for the same remote file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Collision {
+    None,
+    Duplicate, // same file to the same place, still to do
+    Conflict,  // same file to the same place, finished already
+}
+
+/// The queue holds one item per remote file and destination, so queueing the
+/// same again needs the user's say-so unless it's still to do and can just be
+/// dropped. The same file headed somewhere else is a separate item
+pub fn collision(items: &[TransferItem], item: &TransferItem) -> Collision {
+    let key = item.key();
+    match items.iter().find(|i| i.key() == key) {
+        None => Collision::None,
+        Some(existing) if !existing.status.is_finished() => Collision::Duplicate,
+        Some(_) => Collision::Conflict,
+    }
+}
+
+/// How a batch turned out once none of its items are pending any more
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchOutcome {
+    pub files: usize, // completed or already present
+    pub bytes: u64,
+    pub failures: usize,
+}
+
+/// Tally batch `id`, or None while any of its items still has work left
+pub fn batch_outcome(items: &[TransferItem], id: u64) -> Option<BatchOutcome> {
+    let mut members = items
+        .iter()
+        .filter(|i| i.batch.as_ref().is_some_and(|b| b.id == id))
+        .peekable();
+    members.peek()?;
+
+    let mut outcome = BatchOutcome {
+        files: 0,
+        bytes: 0,
+        failures: 0,
+    };
+    for item in members {
+        match &item.status {
+            TransferStatus::Completed | TransferStatus::Skipped(_) => {
+                outcome.files += 1;
+                outcome.bytes += item.size_bytes;
+            }
+            TransferStatus::Failed(_) => outcome.failures += 1,
+            TransferStatus::Cancelled => {}
+            _ => return None,
+        }
+    }
+    Some(outcome)
+}
+
+/// A queue row: a lone item, or all items of one batch under a group header
+pub enum QueueGroup<'a> {
+    Single(&'a TransferItem),
+    Batch(&'a Batch, Vec<&'a TransferItem>),
+}
+
+/// Gather batch members behind the position of the first one, keeping queue order otherwise
+pub fn group_by_batch(items: &[TransferItem]) -> Vec<QueueGroup<'_>> {
+    let mut groups: Vec<QueueGroup> = Vec::new();
+    for item in items {
+        let Some(batch) = &item.batch else {
+            groups.push(QueueGroup::Single(item));
+            continue;
+        };
+        let existing = groups.iter_mut().find_map(|g| match g {
+            QueueGroup::Batch(b, members) if b.id == batch.id => Some(members),
+            _ => None,
+        });
+        match existing {
+            Some(members) => members.push(item),
+            None => groups.push(QueueGroup::Batch(batch, vec![item])),
+        }
+    }
+    groups
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -45,5 +495,345 @@ pub struct RemoteFile {
     pub size: String,
     pub size_bytes: u64,
     pub file_type: FileType,
-    pub modified: String,
+    pub modified: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub permissions: Option<u32>, // st_mode, file type bits included
+    #[serde(default)]
+    pub uid: Option<u32>,
+    #[serde(default)]
+    pub gid: Option<u32>,
+}
+
+/// What a remote entry looks like it holds, judged by folder flag and extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    Folder,
+    Video,
+    Audio,
+    Image,
+    Archive,
+    Text,
+    Executable,
+    Other,
+}
+
+impl FileKind {
+    pub fn icon(self) -> &'static str {
+        match self {
+            FileKind::Folder => "📁",
+            FileKind::Video => "🎬",
+            FileKind::Audio => "🎵",
+            FileKind::Image => "🖼",
+            FileKind::Archive => "📦",
+            FileKind::Text => "📝",
+            FileKind::Executable => "⚙",
+            FileKind::Other => "📄",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FileKind::Folder => "Folder",
+            FileKind::Video => "Video",
+            FileKind::Audio => "Audio",
+            FileKind::Image => "Image",
+            FileKind::Archive => "Archive",
+            FileKind::Text => "Text",
+            FileKind::Executable => "Executable",
+            FileKind::Other => "File",
+        }
+    }
+}
+
+impl RemoteFile {
+    pub fn kind(&self) -> FileKind {
+        if self.file_type == FileType::Folder {
+            return FileKind::Folder;
+        }
+        let ext = std::path::Path::new(&self.name)
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match ext.as_str() {
+            "mkv" | "mp4" | "avi" | "mov" | "wmv" | "m4v" | "webm" | "ts" | "mpg" | "mpeg" => {
+                FileKind::Video
+            }
+            "mp3" | "flac" | "wav" | "ogg" | "m4a" | "aac" | "opus" | "wma" => FileKind::Audio,
+            "jpg" | "jpeg" | "png" | "gif" | "bmp" | "webp" | "tif" | "tiff" | "heic" => {
+                FileKind::Image
+            }
+            "zip" | "rar" | "7z" | "tar" | "gz" | "tgz" | "bz2" | "xz" | "zst" | "iso" => {
+                FileKind::Archive
+            }
+            "txt" | "nfo" | "md" | "log" | "srt" | "sub" | "json" | "xml" | "csv" | "ini"
+            | "conf" | "yaml" | "yml" => FileKind::Text,
+            "sh" | "exe" | "bin" | "run" | "appimage" | "deb" | "rpm" | "msi" | "bat" => {
+                FileKind::Executable
+            }
+            _ => FileKind::Other,
+        }
+    }
+
+    /// Permissions the way `ls -l` shows them, e.g. "drwxr-x---"
+    pub fn mode(&self) -> String {
+        let Some(mode) = self.permissions else {
+            return String::new();
+        };
+        let kind = match mode & 0o170000 {
+            0o040000 => 'd',
+            0o120000 => 'l',
+            0o010000 => 'p',
+            0o140000 => 's',
+            0o020000 => 'c',
+            0o060000 => 'b',
+            _ => '-',
+        };
+        let mut out = String::from(kind);
+        // Owner, group, other; each with its special bit shown over x
+        for (shift, special, marks) in [(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')] {
+            let bits = (mode >> shift) & 7;
+            out.push(if bits & 4 != 0 { 'r' } else { '-' });
+            out.push(if bits & 2 != 0 { 'w' } else { '-' });
+            out.push(match (bits & 1 != 0, mode & special != 0) {
+                (true, true) => marks,
+                (false, true) => marks.to_ascii_uppercase(),
+                (true, false) => 'x',
+                (false, false) => '-',
+            });
+        }
+        out
+    }
+}
+
+/// Running totals while a folder tree is being scanned
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScanProgress {
+    pub dirs_visited: usize,
+    pub files_found: usize,
+    pub special_skipped: usize, // FIFOs, devices and sockets left out
+    pub current_path: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A queued download of `remote` into /dl
+    fn item(remote: &str) -> TransferItem {
+        TransferItem {
+            direction: Direction::Download,
+            local_location: "/dl".into(),
+            filename: remote.rsplit('/').next().unwrap().into(),
+            remote_file: remote.into(),
+            size_bytes: 1,
+            bytes_transferred: 0,
+            priority: 10,
+            status: TransferStatus::Queued,
+            batch: None,
+            follow: false,
+            speed_limit: 0,
+            profile: String::new(),
+            relay_to: None,
+            resume_check: None,
+            failure: None,
+            queued_at: 0,
+            timeline: Vec::new(),
+            note: String::new(),
+            label: None,
+        }
+    }
+
+    #[test]
+    fn test_timeline_spans_merge_retries_and_keep_pause_reasons() {
+        let mut item = item("/a");
+        item.note(100, TimelineEvent::Started);
+        item.note(160, TimelineEvent::Retried);
+        item.note(200, TimelineEvent::Paused("schedule".into()));
+        item.note(3800, TimelineEvent::Started);
+        assert_eq!(
+            item.timeline_spans(3900),
+            [
+                TimelineSpan::Running(100),
+                TimelineSpan::Paused("schedule".into(), 3600),
+                TimelineSpan::Running(100),
+            ]
+        );
+        item.note(4000, TimelineEvent::Finished(TransferStatus::Completed));
+        assert_eq!(
+            item.timeline_spans(9999).last(),
+            Some(&TimelineSpan::Running(200))
+        );
+        assert_eq!(item.retries(), 1);
+    }
+
+    #[test]
+    fn test_legacy_pending_status_loads_as_queued() {
+        let status: TransferStatus = serde_json::from_str("\"Pending\"").unwrap();
+        assert_eq!(status, TransferStatus::Queued);
+        assert!(!status.is_active());
+        assert!(!status.is_finished());
+    }
+
+    #[test]
+    fn test_mode_reads_like_ls() {
+        let file = |permissions| RemoteFile {
+            name: "a".into(),
+            path: "/r/a".into(),
+            size: String::new(),
+            size_bytes: 0,
+            file_type: FileType::File,
+            modified: None,
+            permissions,
+            uid: Some(1000),
+            gid: Some(1000),
+        };
+        assert_eq!(file(Some(0o040750)).mode(), "drwxr-x---");
+        assert_eq!(file(Some(0o100644)).mode(), "-rw-r--r--");
+        assert_eq!(file(Some(0o104755)).mode(), "-rwsr-xr-x");
+        assert_eq!(file(Some(0o041777)).mode(), "drwxrwxrwt");
+        assert_eq!(file(Some(0o102640)).mode(), "-rw-r-S---");
+        assert_eq!(file(None).mode(), "");
+    }
+
+    #[test]
+    fn test_file_kind_from_extension() {
+        let file = |name: &str, file_type: FileType| RemoteFile {
+            name: name.into(),
+            path: format!("/r/{}", name),
+            size: String::new(),
+            size_bytes: 0,
+            file_type,
+            modified: None,
+            permissions: None,
+            uid: None,
+            gid: None,
+        };
+        assert_eq!(file("Movie.MKV", FileType::File).kind(), FileKind::Video);
+        assert_eq!(file("album.flac", FileType::File).kind(), FileKind::Audio);
+        assert_eq!(
+            file("backup.tar.gz", FileType::File).kind(),
+            FileKind::Archive
+        );
+        assert_eq!(file("README", FileType::File).kind(), FileKind::Other);
+        assert_eq!(
+            file("photos.jpg", FileType::Folder).kind(),
+            FileKind::Folder
+        );
+    }
+
+    #[test]
+    fn test_legacy_queue_item_loads_as_download() {
+        let json = r#"{"local_location":"/dl","filename":"a.mkv","remote_file":"/r/a.mkv","size_bytes":10,"bytes_downloaded":4,"priority":10,"status":"Paused"}"#;
+        let item: TransferItem = serde_json::from_str(json).unwrap();
+        assert_eq!(item.direction, Direction::Download);
+        assert_eq!(item.bytes_transferred, 4);
+        assert_eq!(item.batch, None);
+    }
+
+    #[test]
+    fn test_group_by_batch_keeps_first_position() {
+        let batched = |path: &str, batch: Option<u64>| TransferItem {
+            batch: batch.map(|id| Batch {
+                id,
+                name: format!("folder{}", id),
+            }),
+            ..item(path)
+        };
+        let items = vec![
+            batched("/r/1", Some(7)),
+            batched("/r/2", None),
+            batched("/r/3", Some(8)),
+            batched("/r/4", Some(7)),
+        ];
+
+        let shape: Vec<String> = group_by_batch(&items)
+            .iter()
+            .map(|g| match g {
+                QueueGroup::Single(i) => i.remote_file.clone(),
+                QueueGroup::Batch(b, members) => format!("{}:{}", b.name, members.len()),
+            })
+            .collect();
+        assert_eq!(shape, ["folder7:2", "/r/2", "folder8:1"]);
+    }
+
+    #[test]
+    fn test_sort_pending_leaves_other_rows_in_place() {
+        let ranked = |path: &str, priority: u8, status: TransferStatus| TransferItem {
+            priority,
+            status,
+            ..item(path)
+        };
+        let mut items = vec![
+            ranked("/r/done", 1, TransferStatus::Completed),
+            ranked("/r/first", 5, TransferStatus::Queued),
+            ranked("/r/running", 1, TransferStatus::Downloading),
+            ranked("/r/last", 30, TransferStatus::Paused),
+            ranked("/r/mid", 10, TransferStatus::Queued),
+        ];
+        sort_pending(&mut items, QueueOrder::Priority);
+        let order: Vec<&str> = items.iter().map(|i| i.remote_file.as_str()).collect();
+        assert_eq!(
+            order,
+            ["/r/done", "/r/first", "/r/running", "/r/mid", "/r/last"]
+        );
+
+        items[1].size_bytes = 50;
+        items[4].size_bytes = 5;
+        sort_pending(&mut items, QueueOrder::SmallestFirst);
+        let order: Vec<&str> = items.iter().map(|i| i.remote_file.as_str()).collect();
+        assert_eq!(
+            order,
+            ["/r/done", "/r/mid", "/r/running", "/r/last", "/r/first"]
+        );
+    }
+
+    #[test]
+    fn test_batch_outcome_waits_for_every_item() {
+        let mut items: Vec<TransferItem> = ["/r/a", "/r/b", "/r/c"]
+            .map(|path| TransferItem {
+                size_bytes: 100,
+                batch: Some(Batch {
+                    id: 7,
+                    name: "Show".into(),
+                }),
+                ..item(path)
+            })
+            .into();
+        items[0].status = TransferStatus::Completed;
+        items[1].status = TransferStatus::Failed("gone".into());
+        assert_eq!(batch_outcome(&items, 7), None);
+        assert_eq!(batch_outcome(&items, 8), None);
+
+        items[2].status = TransferStatus::Skipped("Already downloaded".into());
+        assert_eq!(
+            batch_outcome(&items, 7),
+            Some(BatchOutcome {
+                files: 2,
+                bytes: 200,
+                failures: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_collision_looks_at_destination() {
+        let queued = item("/r/a");
+        let mut items = vec![queued.clone()];
+        assert_eq!(collision(&items, &queued), Collision::Duplicate);
+
+        let elsewhere = TransferItem {
+            local_location: "/other".into(),
+            ..queued.clone()
+        };
+        assert_eq!(collision(&items, &elsewhere), Collision::None); // a second copy, not a clash
+
+        items[0].status = TransferStatus::Completed;
+        assert_eq!(collision(&items, &queued), Collision::Conflict);
+
+        let new = TransferItem {
+            remote_file: "/r/b".into(),
+            ..queued
+        };
+        assert_eq!(collision(&items, &new), Collision::None);
+    }
 }
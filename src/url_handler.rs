@@ -0,0 +1,108 @@
+use std::path::Path;
+use std::process::Command;
+
+#[cfg(target_os = "linux")]
+const DESKTOP_FILE: &str = "simplesftp-url.desktop";
+#[cfg(target_os = "macos")]
+const BUNDLE_ID: &str = "com.simplesftp.app"; // matches package.metadata.bundle
+
+/// Make this executable the handler for sftp:// links for the current user, so
+/// browsers and other apps hand links to it (they arrive as a command-line argument)
+pub fn register() -> std::io::Result<()> {
+    let exe = std::env::current_exe()?;
+    register_for(&exe)
+}
+
+#[cfg(target_os = "linux")]
+fn register_for(exe: &Path) -> std::io::Result<()> {
+    let dir = std::env::var_os("XDG_DATA_HOME")
+        .map(std::path::PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| Path::new(&h).join(".local/share")))
+        .ok_or_else(|| std::io::Error::other("no home folder"))?
+        .join("applications");
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join(DESKTOP_FILE), desktop_entry(exe))?;
+    run(
+        "xdg-mime",
+        &["default", DESKTOP_FILE, "x-scheme-handler/sftp"],
+    )?;
+    // Only refreshes the menu cache; the handler works without it
+    let _ = run("update-desktop-database", &[&dir.to_string_lossy()]);
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn register_for(exe: &Path) -> std::io::Result<()> {
+    let key = r"HKCU\Software\Classes\sftp";
+    let command = format!("\"{}\" \"%1\"", exe.display());
+    run("reg", &["add", key, "/ve", "/d", "URL:SFTP Protocol", "/f"])?;
+    run("reg", &["add", key, "/v", "URL Protocol", "/d", "", "/f"])?;
+    run(
+        "reg",
+        &[
+            "add",
+            &format!(r"{}\shell\open\command", key),
+            "/ve",
+            "/d",
+            &command,
+            "/f",
+        ],
+    )
+}
+
+/// The bundle's Info.plist declares the scheme (osx_url_schemes); this makes
+/// it the default over other apps claiming sftp://
+#[cfg(target_os = "macos")]
+fn register_for(_exe: &Path) -> std::io::Result<()> {
+    let script = format!(
+        "ObjC.import('CoreServices'); $.LSSetDefaultHandlerForURLScheme($('sftp'), $('{}'))",
+        BUNDLE_ID
+    );
+    run("osascript", &["-l", "JavaScript", "-e", &script])
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+fn register_for(_exe: &Path) -> std::io::Result<()> {
+    Err(std::io::Error::other("not supported on this system"))
+}
+
+/// Hidden launcher entry that only claims the sftp:// scheme
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub fn desktop_entry(exe: &Path) -> String {
+    format!(
+        "[Desktop Entry]\n\
+         Name=SimpleSFTP\n\
+         Exec=\"{}\" %u\n\
+         Terminal=false\n\
+         Type=Application\n\
+         NoDisplay=true\n\
+         MimeType=x-scheme-handler/sftp;\n\
+         Icon=simplesftp\n",
+        exe.display()
+    )
+}
+
+#[allow(dead_code)] // unused where registration isn't supported
+fn run(program: &str, args: &[&str]) -> std::io::Result<()> {
+    let status = Command::new(program).args(args).status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(format!(
+            "{} exited with {}",
+            program, status
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_desktop_entry_claims_sftp_links() {
+        let entry = desktop_entry(Path::new("/opt/simple sftp/simplesftp"));
+        assert!(entry.contains("Exec=\"/opt/simple sftp/simplesftp\" %u\n"));
+        assert!(entry.contains("MimeType=x-scheme-handler/sftp;\n"));
+    }
+}
@@ -0,0 +1,530 @@
+use crate::app::{
+    console_input_id, format_count, format_duration, Message, QueueRecovery, SftpApp,
+};
+use crate::error::FailureKind;
+use crate::types::{TransferItem, TransferStatus};
+use crate::{activity, names, style};
+use iced::widget::{
+    button, checkbox, column, container, horizontal_rule, horizontal_space, pick_list, row,
+    scrollable, text, text_input,
+};
+use iced::{Element, Length, Theme};
+
+impl SftpApp {
+    pub fn view_history(&self) -> Element<'_, Message> {
+        let title = text("Finished Folders").size(24);
+
+        let shown = self.history_shown();
+        let entries: Vec<Element<Message>> = if self.config.batch_history.is_empty() {
+            vec![text("Nothing finished yet.").size(14).into()]
+        } else if shown.is_empty() {
+            vec![text("Nothing matches.").size(14).into()]
+        } else {
+            shown
+                .into_iter()
+                .map(|record| {
+                    let mut line = format!(
+                        "{}  {} — {} files, {} in {}",
+                        record.finished_at,
+                        record.name,
+                        format_count(record.files),
+                        self.format_bytes(&record.bytes.to_string()),
+                        format_duration(record.elapsed_secs)
+                    );
+                    if record.failures > 0 {
+                        line.push_str(&format!(", {} failed", record.failures));
+                    }
+                    if !record.notes.is_empty() {
+                        line.push_str(&format!("  ({})", record.notes.join("; ")));
+                    }
+                    let line = text(line).size(14).color_maybe(
+                        (record.failures > 0).then_some(iced::Color::from_rgb(1.0, 0.5, 0.5)),
+                    );
+                    let Some(manifest) = &record.manifest else {
+                        return line.into();
+                    };
+                    let check = match self.manifest_checks.get(manifest) {
+                        Some(Ok(summary)) => summary.clone(),
+                        Some(Err(e)) => e.to_string(),
+                        None => String::new(),
+                    };
+                    row![
+                        line.width(Length::Fill),
+                        text(check).size(12),
+                        button(text("Verify").size(12))
+                            .on_press(Message::VerifyManifest(manifest.clone()))
+                            .padding(3)
+                    ]
+                    .spacing(10)
+                    .align_y(iced::Alignment::Center)
+                    .into()
+                })
+                .collect()
+        };
+
+        let content = column![
+            title,
+            text_input("Search folders and notes", &self.history_search)
+                .on_input(Message::HistorySearchChanged),
+            scrollable(column(entries).spacing(5)).height(300),
+            horizontal_rule(1),
+            button("Close").on_press(Message::CloseHistory),
+        ]
+        .spacing(20)
+        .padding(20)
+        .max_width(600);
+
+        container(container(content).padding(20).style(style::header_style))
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .center_x(Length::Fill)
+            .center_y(Length::Fill)
+            .style(|_t: &Theme| container::Style {
+                background: Some(iced::Color::from_rgba(0.0, 0.0, 0.0, 0.5).into()),
+                ..Default::default()
+            })
+            .into()
+    }
+
+    pub fn view_failure_report(&self) -> Element<'_, Message> {
+        let Some(batch) = &self.failure_report else {
+            return column![].into();
+        };
+        let failed: Vec<&TransferItem> = self
+            .queue_items
+            .iter()
+            .filter(|i| {
+                i.batch.as_ref().map(|b| b.id) == Some(batch.id)
+                    && matches!(i.status, TransferStatus::Failed(_))
+            })
+            .collect();
+        let title = text(format!(
+            "{}: {} failed",
+            batch.name,
+            format_count(failed.len())
+        ))
+        .size(24);
+
+        let entries: Vec<Element<Message>> = failed
+            .iter()
+            .map(|item| {
+                let kind = item.failure.unwrap_or(FailureKind::Other);
+                let error = match &item.status {
+                    TransferStatus::Failed(e) => e.as_str(),
+                    _ => "",
+                };
+                column![
+                    row![
+                        text(names::display(&item.filename).to_string())
+                            .size(14)
+                            .width(Length::Fill),
+                        text(kind.label())
+                            .size(12)
+                            .color(iced::Color::from_rgb(1.0, 0.5, 0.5)),
+                    ]
+                    .spacing(10),
+                    text(error).size(12),
+                    text(format!("→ {}", kind.suggestion())).size(12),
+                ]
+                .spacing(2)
+                .into()
+            })
+            .collect();
+
+        let content = column![
+            title,
+            scrollable(column(entries).spacing(10)).height(300),
+            horizontal_rule(1),
+            row![
+                button("Retry these").on_press_maybe(
+                    (!failed.is_empty()).then_some(Message::RetryBatchFailed(batch.id))
+                ),
+                button("Export...")
+                    .on_press(Message::ExportFailureReport)
+                    .style(button::secondary),
+                horizontal_space(),
+                button("Close").on_press(Message::CloseFailureReport),
+            ]
+            .spacing(10),
+        ]
+        .spacing(20)
+        .padding(20)
+        .max_width(700);
+
+        container(container(content).padding(20).style(style::header_style))
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .center_x(Length::Fill)
+            .center_y(Length::Fill)
+            .style(|_t: &Theme| container::Style {
+                background: Some(iced::Color::from_rgba(0.0, 0.0, 0.0, 0.5).into()),
+                ..Default::default()
+            })
+            .into()
+    }
+
+    pub fn view_connection_info(&self) -> Element<'_, Message> {
+        let title = text("Connection Info").size(24);
+
+        let details: Element<Message> = match &self.session_info {
+            Some(info) => {
+                let line = |label: &str, value: &str| {
+                    row![
+                        text(format!("{}:", label)).width(140),
+                        text(value.to_string())
+                    ]
+                    .spacing(10)
+                };
+                let uptime = self
+                    .connected_at
+                    .map(|t| format_duration(t.elapsed().as_secs()))
+                    .unwrap_or_default();
+                let latency = self
+                    .latency
+                    .map(|d| format!("{} ms", d.as_millis()))
+                    .unwrap_or_else(|| "Measuring...".into());
+                column![
+                    line("Server", &self.config.profile_key()),
+                    line("Software", &info.banner),
+                    line(
+                        "Host key",
+                        &format!("{} {}", info.host_key_type, info.fingerprint)
+                    ),
+                    line("Uptime", &uptime),
+                    line("Latency", &latency),
+                    line("Key exchange", &info.kex),
+                    line("Host key algorithm", &info.host_key),
+                    line("Cipher", &info.cipher),
+                    line("MAC", &info.mac),
+                    line("Compression", &info.compression),
+                ]
+                .spacing(5)
+                .into()
+            }
+            None => text("Not connected.").size(14).into(),
+        };
+
+        let content = column![
+            title,
+            details,
+            horizontal_rule(1),
+            button("Close").on_press(Message::CloseConnectionInfo),
+        ]
+        .spacing(20)
+        .padding(20)
+        .max_width(600);
+
+        container(container(content).padding(20).style(style::header_style))
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .center_x(Length::Fill)
+            .center_y(Length::Fill)
+            .style(|_t: &Theme| container::Style {
+                background: Some(iced::Color::from_rgba(0.0, 0.0, 0.0, 0.5).into()),
+                ..Default::default()
+            })
+            .into()
+    }
+
+    pub fn view_activity_log(&self) -> Element<'_, Message> {
+        let title = text("Activity Log").size(24);
+
+        let entries = activity::entries(self.config.activity_verbose);
+        let lines = column(entries.iter().map(|entry| {
+            let line = text(entry.line())
+                .size(12)
+                .font(iced::font::Font::MONOSPACE);
+            match entry.level {
+                activity::Level::Error => line.color(iced::Color::from_rgb(1.0, 0.5, 0.5)),
+                activity::Level::Detail => line.color(iced::Color::from_rgb(0.6, 0.6, 0.6)),
+                activity::Level::Info => line,
+            }
+            .into()
+        }))
+        .spacing(2);
+
+        let content = column![
+            title,
+            checkbox(
+                "Verbose (show protocol commands)",
+                self.config.activity_verbose
+            )
+            .on_toggle(Message::ActivityVerboseToggled),
+            container(scrollable(lines).anchor_bottom().width(Length::Fill)).height(400),
+            horizontal_rule(1),
+            button("Close").on_press(Message::CloseActivityLog),
+        ]
+        .spacing(20)
+        .padding(20)
+        .max_width(900);
+
+        container(container(content).padding(20).style(style::header_style))
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .center_x(Length::Fill)
+            .center_y(Length::Fill)
+            .style(|_t: &Theme| container::Style {
+                background: Some(iced::Color::from_rgba(0.0, 0.0, 0.0, 0.5).into()),
+                ..Default::default()
+            })
+            .into()
+    }
+
+    pub fn view_console(&self) -> Element<'_, Message> {
+        let title = text("Console").size(24);
+        let note = match self.console_blocked() {
+            Some(reason) => text(reason).size(12).style(text::danger),
+            None => text(format!("Commands run in {}", self.current_remote_path)).size(12),
+        };
+
+        let output = text(&self.console_output)
+            .size(12)
+            .font(iced::font::Font::MONOSPACE);
+        let mut input = text_input("Command", &self.console_input)
+            .id(console_input_id())
+            .font(iced::font::Font::MONOSPACE);
+        if !self.console_running {
+            input = input
+                .on_input(Message::ConsoleInputChanged)
+                .on_submit(Message::ConsoleSubmit);
+        }
+        let history: Vec<String> = self.console_history.iter().rev().cloned().collect();
+        let prompt = row![
+            input,
+            if self.console_running {
+                button("Stop")
+                    .on_press(Message::StopConsoleCommand)
+                    .style(button::danger)
+            } else {
+                button("Run").on_press(Message::ConsoleSubmit)
+            },
+            pick_list(history, None::<String>, Message::ConsoleHistoryPicked)
+                .placeholder("History")
+                .width(140),
+        ]
+        .spacing(10);
+
+        let content = column![
+            title,
+            note,
+            container(scrollable(output).anchor_bottom().width(Length::Fill)).height(400),
+            prompt,
+            horizontal_rule(1),
+            row![
+                button("Clear").on_press(Message::ClearConsole),
+                horizontal_space(),
+                button("Close").on_press(Message::CloseConsole),
+            ],
+        ]
+        .spacing(20)
+        .padding(20)
+        .max_width(900);
+
+        container(container(content).padding(20).style(style::header_style))
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .center_x(Length::Fill)
+            .center_y(Length::Fill)
+            .style(|_t: &Theme| container::Style {
+                background: Some(iced::Color::from_rgba(0.0, 0.0, 0.0, 0.5).into()),
+                ..Default::default()
+            })
+            .into()
+    }
+
+    pub fn view_reauth(&self) -> Element<'_, Message> {
+        let Some(reauth) = &self.reauth else {
+            return column![].into();
+        };
+        let title = text("Sign in again").size(24);
+        let submit = (!reauth.checking).then_some(Message::SubmitReauth);
+
+        let content = column![
+            title,
+            text(format!(
+                "{} refused the login for a transfer, so the queue is paused. \
+                 Enter the new password or key to carry on.",
+                reauth.profile
+            ))
+            .size(14),
+            text(&reauth.error)
+                .size(12)
+                .color(iced::Color::from_rgb(1.0, 0.5, 0.5)),
+            text_input("New password (blank keeps the saved one)", &reauth.password)
+                .on_input(Message::ReauthPasswordChanged)
+                .on_submit_maybe(submit.clone())
+                .secure(true)
+                .padding(10),
+            text_input("Private key file (optional)", &reauth.key_path)
+                .on_input(Message::ReauthKeyChanged)
+                .on_submit_maybe(submit.clone())
+                .padding(10),
+            horizontal_rule(1),
+            row![
+                button(if reauth.checking {
+                    "Signing in..."
+                } else {
+                    "Sign in and resume"
+                })
+                .on_press_maybe(submit),
+                horizontal_space(),
+                button("Not now")
+                    .on_press(Message::CancelReauth)
+                    .style(button::secondary),
+            ],
+        ]
+        .spacing(20)
+        .padding(20)
+        .max_width(600);
+
+        container(container(content).padding(20).style(style::header_style))
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .center_x(Length::Fill)
+            .center_y(Length::Fill)
+            .style(|_t: &Theme| container::Style {
+                background: Some(iced::Color::from_rgba(0.0, 0.0, 0.0, 0.5).into()),
+                ..Default::default()
+            })
+            .into()
+    }
+
+    pub fn view_queue_recovery(&self) -> Element<'_, Message> {
+        let Some(problem) = &self.queue_problem else {
+            return column![].into();
+        };
+        let title = text("The saved queue is damaged").size(24);
+
+        let mut choices = column![row![
+            text(format!(
+                "Keep the {} items that could still be read",
+                format_count(problem.salvaged.len())
+            ))
+            .size(14),
+            horizontal_space(),
+            button("Keep these").on_press(Message::RecoverQueue(QueueRecovery::Salvaged)),
+        ]
+        .align_y(iced::Alignment::Center)]
+        .spacing(10);
+        for (index, backup) in problem.backups.iter().enumerate() {
+            choices = choices.push(
+                row![
+                    text(format!(
+                        "Backup from {}: {} items",
+                        backup.modified,
+                        format_count(backup.items.len())
+                    ))
+                    .size(14),
+                    horizontal_space(),
+                    button("Restore")
+                        .on_press(Message::RecoverQueue(QueueRecovery::Backup(index)))
+                        .style(button::secondary),
+                ]
+                .align_y(iced::Alignment::Center),
+            );
+        }
+        if problem.backups.is_empty() {
+            choices = choices.push(text("There are no earlier copies to restore.").size(12));
+        }
+
+        let content = column![
+            title,
+            text(
+                "queue.json couldn't be read, so nothing has been loaded from it. \
+                 The damaged file is kept as queue.json.bad."
+            )
+            .size(14),
+            text(&problem.error)
+                .size(12)
+                .color(iced::Color::from_rgb(1.0, 0.5, 0.5)),
+            horizontal_rule(1),
+            scrollable(choices).height(Length::Shrink),
+            horizontal_rule(1),
+            row![
+                horizontal_space(),
+                button("Start with an empty queue")
+                    .on_press(Message::RecoverQueue(QueueRecovery::Empty))
+                    .style(button::danger),
+            ],
+        ]
+        .spacing(20)
+        .padding(20)
+        .max_width(600);
+
+        container(container(content).padding(20).style(style::header_style))
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .center_x(Length::Fill)
+            .center_y(Length::Fill)
+            .style(|_t: &Theme| container::Style {
+                background: Some(iced::Color::from_rgba(0.0, 0.0, 0.0, 0.5).into()),
+                ..Default::default()
+            })
+            .into()
+    }
+
+    pub fn view_missing_folders(&self) -> Element<'_, Message> {
+        let title = text("Download folders are missing").size(24);
+
+        let mut folders = column![].spacing(10);
+        for folder in &self.missing_folders {
+            let under = std::path::Path::new(folder);
+            let waiting = self
+                .queue_items
+                .iter()
+                .filter(|i| !i.status.is_finished())
+                .filter(|i| names::to_path(&i.local_location).starts_with(under))
+                .count();
+            folders = folders.push(
+                column![
+                    text(names::display(folder)).size(14),
+                    text(format!("{} downloads waiting", format_count(waiting))).size(12),
+                    row![
+                        button(text("Pick new folder").size(12))
+                            .on_press(Message::PickMissingFolderTarget(folder.clone())),
+                        button(text("Create it").size(12))
+                            .on_press(Message::CreateMissingFolder(folder.clone()))
+                            .style(button::secondary),
+                        button(text("Pause its downloads").size(12))
+                            .on_press(Message::PauseMissingFolder(folder.clone()))
+                            .style(button::secondary),
+                    ]
+                    .spacing(5),
+                ]
+                .spacing(4),
+            );
+        }
+
+        let content = column![
+            title,
+            text(
+                "These folders were moved, renamed or are on a drive that isn't \
+                 connected. Choose what to do before their downloads start."
+            )
+            .size(14),
+            horizontal_rule(1),
+            scrollable(folders).height(Length::Shrink),
+            horizontal_rule(1),
+            row![
+                horizontal_space(),
+                button("Ask again later")
+                    .on_press(Message::DismissMissingFolders)
+                    .style(button::secondary),
+            ],
+        ]
+        .spacing(20)
+        .padding(20)
+        .max_width(600);
+
+        container(container(content).padding(20).style(style::header_style))
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .center_x(Length::Fill)
+            .center_y(Length::Fill)
+            .style(|_t: &Theme| container::Style {
+                background: Some(iced::Color::from_rgba(0.0, 0.0, 0.0, 0.5).into()),
+                ..Default::default()
+            })
+            .into()
+    }
+}
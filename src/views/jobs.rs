@@ -0,0 +1,118 @@
+use crate::app::{Message, SftpApp};
+use crate::settings::{Job, JobTrigger};
+use crate::style;
+use chrono::{Local, TimeZone};
+use iced::widget::{button, column, container, horizontal_rule, row, scrollable, text, text_input};
+use iced::{Element, Length, Theme};
+
+impl SftpApp {
+    /// Saved jobs, each with its settings, a Run button and its recent runs
+    pub fn view_jobs(&self) -> Element<'_, Message> {
+        let title = text("Jobs").size(24);
+
+        let entries: Vec<Element<Message>> = if self.config.jobs.is_empty() {
+            vec![text("No jobs yet: use Save as Job on a remote folder")
+                .size(14)
+                .into()]
+        } else {
+            self.config
+                .jobs
+                .iter()
+                .enumerate()
+                .map(|(index, job)| self.view_job(index, job))
+                .collect()
+        };
+
+        let content = column![
+            title,
+            text("Run a job from here, daily at its time, or with simplesftp --job \"<name>\"")
+                .size(12),
+            scrollable(column(entries).spacing(15)).height(400),
+            horizontal_rule(1),
+            button("Close").on_press(Message::CloseJobs),
+        ]
+        .spacing(20)
+        .padding(20)
+        .max_width(700);
+
+        container(container(content).padding(20).style(style::header_style))
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .center_x(Length::Fill)
+            .center_y(Length::Fill)
+            .style(|_t: &Theme| container::Style {
+                background: Some(iced::Color::from_rgba(0.0, 0.0, 0.0, 0.5).into()),
+                ..Default::default()
+            })
+            .into()
+    }
+
+    fn view_job<'a>(&'a self, index: usize, job: &'a Job) -> Element<'a, Message> {
+        let (patterns, run_at) = &self.job_inputs[index];
+        let mut runs = column![].spacing(2);
+        for run in job.history.iter().rev().take(5) {
+            let at = Local
+                .timestamp_opt(run.at, 0)
+                .single()
+                .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_default();
+            let how = match run.trigger {
+                JobTrigger::Manual => "by hand",
+                JobTrigger::Schedule => "scheduled",
+                JobTrigger::CommandLine => "command line",
+            };
+            let line = match &run.error {
+                Some(e) => format!("{}  {}: failed, {}", at, how, e),
+                None => format!("{}  {}: queued {} files", at, how, run.queued),
+            };
+            runs = runs.push(
+                text(line).size(12).color_maybe(
+                    run.error
+                        .is_some()
+                        .then_some(iced::Color::from_rgb(1.0, 0.5, 0.5)),
+                ),
+            );
+        }
+
+        column![
+            row![
+                text_input("Name", &job.name)
+                    .on_input(move |val| Message::JobNameChanged(index, val))
+                    .size(14)
+                    .padding(4),
+                button(text("Run").size(12)).on_press(Message::RunJob(index)),
+                button(text("Delete").size(12))
+                    .on_press(Message::DeleteJob(index))
+                    .style(button::danger),
+            ]
+            .spacing(8)
+            .align_y(iced::Alignment::Center),
+            text(format!("{} on {}", job.remote_path, job.profile)).size(12),
+            row![
+                text_input("*.mkv, *S01E*", patterns)
+                    .on_input(move |val| Message::JobPatternsChanged(index, val))
+                    .size(12)
+                    .padding(3)
+                    .width(160),
+                text_input("Download folder", &job.destination)
+                    .on_input(move |val| Message::JobDestinationChanged(index, val))
+                    .size(12)
+                    .padding(3),
+                button(text("Browse").size(12))
+                    .on_press(Message::PickJobDestination(index))
+                    .style(button::secondary)
+                    .padding(3),
+                text_input("Daily at, e.g. 02:00", run_at)
+                    .on_input(move |val| Message::JobRunAtChanged(index, val))
+                    .size(12)
+                    .padding(3)
+                    .width(130),
+            ]
+            .spacing(8)
+            .align_y(iced::Alignment::Center),
+            runs,
+        ]
+        .spacing(6)
+        .into()
+    }
+}
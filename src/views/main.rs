@@ -0,0 +1,270 @@
+use crate::app::{format_count, format_duration, ConfigOption, Message, PaneState, SftpApp};
+use crate::{settings, style};
+use iced::widget::{
+    button, column, container, horizontal_space, mouse_area, pane_grid, row, stack, text,
+};
+use iced::{Element, Length};
+
+impl SftpApp {
+    pub fn view_main(&self) -> Element<'_, Message> {
+        // Menu Bar
+        let config_btn = button("Config").on_press(Message::ToggleConfigMenu);
+        let menu_bar = row![config_btn, button("Help").on_press(Message::NoOp)]
+            .padding(5)
+            .spacing(10);
+
+        // Status Indicator
+        let status_color = if self.is_connected {
+            iced::Color::from_rgb(0.0, 0.8, 0.0) // Green
+        } else {
+            iced::Color::from_rgb(0.8, 0.0, 0.0) // Red
+        };
+
+        // Toolbar / Breadcrumbs
+        let breadcrumb_bar = container(
+            row![
+                text("Current Folder").size(14),
+                text(&self.current_remote_path)
+                    .size(14)
+                    .color(iced::Color::from_rgb(0.2, 0.4, 1.0)),
+                horizontal_space(),
+                mouse_area(
+                    container(container(horizontal_space()).width(10).height(10).style(
+                        move |_| container::Style {
+                            background: Some(status_color.into()),
+                            border: iced::Border {
+                                radius: 5.0.into(),
+                                ..Default::default()
+                            },
+                            ..Default::default()
+                        }
+                    ))
+                    .padding(5)
+                )
+                .on_press(Message::ConfigOptionSelected(ConfigOption::ConnectionInfo))
+            ]
+            .align_y(iced::Alignment::Center)
+            .spacing(10),
+        )
+        .padding(5)
+        .style(style::header_style);
+
+        // Panes
+        let pane_grid = pane_grid::PaneGrid::new(&self.panes, |_id, _pane_state, _max_size| {
+            let content = match _pane_state {
+                PaneState::Queue => self.view_queue(),
+                PaneState::Remote => self.view_remote(),
+            };
+            pane_grid::Content::new(content).style(style::pane_style)
+        })
+        .on_resize(10, Message::PaneResized);
+
+        // Status Bar
+        let total_queued = self.queue_items.len();
+        let total_bytes: u64 = self
+            .queue_items
+            .iter()
+            .map(|i| i.size_bytes - i.bytes_transferred)
+            .sum();
+        let total_size_str = self.format_bytes(&total_bytes.to_string());
+
+        let scanning_text = match &self.scan_progress {
+            Some(p) if self.is_scanning_queue => format!(
+                " | Scanning: {} folders, {} files — {}",
+                format_count(p.dirs_visited),
+                format_count(p.files_found),
+                p.current_path
+            ),
+            _ if self.is_scanning_queue => " | Scanning...".to_string(),
+            _ => String::new(),
+        };
+
+        let verify_text = match self.verify_progress {
+            Some((checked, total)) => format!(
+                " | Verifying {}/{}",
+                format_count(checked),
+                format_count(total)
+            ),
+            None => String::new(),
+        };
+
+        let schedule_text = match self.schedule_resumes_at {
+            _ if self.config.active_schedule().mode == settings::ScheduleMode::None => {
+                String::new()
+            }
+            _ if self.schedule_override => " | Schedule: Ignored, forcing downloads ⏩".to_string(),
+            _ if self.last_schedule_allowed => " | Schedule: Running".to_string(),
+            Some(at) => {
+                let now = chrono::Local::now();
+                let secs = (at - now).num_seconds().max(0) as u64;
+                let when = if at.date_naive() == now.date_naive() {
+                    at.format("%H:%M")
+                } else {
+                    at.format("%a %H:%M")
+                };
+                format!(
+                    " | Downloads resume in {} (at {})",
+                    format_duration(secs),
+                    when
+                )
+            }
+            None => " | Schedule: Paused ⏸".to_string(),
+        };
+
+        let metered_text = match &self.network.ssid {
+            _ if !self.config.pause_on_metered || !self.on_metered => String::new(),
+            _ if self.metered_override => " | Metered network: Downloading anyway".to_string(),
+            Some(ssid) => format!(" | Metered network ({}): Paused ⏸", ssid),
+            None => " | Metered network: Paused ⏸".to_string(),
+        };
+
+        let battery_text = match self.config.battery_mode {
+            _ if !self.on_battery => "",
+            settings::BatteryMode::Ignore => "",
+            settings::BatteryMode::Pause => " | On battery: Paused ⏸",
+            settings::BatteryMode::Throttle => " | On battery: Limited",
+        };
+
+        let stats = &self.session_stats;
+        let speed_text = if self.is_downloading {
+            format!(
+                " | Speed: {}/s | This session: {} (avg {}/s), {} active, {} waiting",
+                self.format_bytes(&stats.bytes_per_sec.to_string()),
+                self.format_bytes(&stats.bytes.to_string()),
+                self.format_bytes(&stats.average_speed().to_string()),
+                stats.active,
+                stats.pending
+            )
+        } else {
+            "".to_string()
+        };
+
+        let status_text = format!(
+            "{}Total Queued: {} ({}){}{}{}{}{}{}",
+            if self.status_message.is_empty() {
+                String::new()
+            } else {
+                format!("{} | ", self.status_message)
+            },
+            total_queued,
+            total_size_str,
+            scanning_text,
+            verify_text,
+            schedule_text,
+            metered_text,
+            battery_text,
+            speed_text
+        );
+
+        let status_row = row![text(status_text).size(12), horizontal_space()]
+            .push_maybe(self.is_checking_connection.then(|| {
+                button(text("Cancel connect").size(12))
+                    .on_press(Message::CancelConnect)
+                    .padding([1, 6])
+                    .style(button::secondary)
+            }))
+            .push_maybe((self.config.pause_on_metered && self.on_metered).then(|| {
+                let (label, on) = if self.metered_override {
+                    ("Pause on metered", false)
+                } else {
+                    ("Download anyway", true)
+                };
+                button(text(label).size(12))
+                    .on_press(Message::MeteredOverride(on))
+                    .padding([1, 6])
+                    .style(button::secondary)
+            }))
+            .push_maybe(self.verify_cancel.as_ref().map(|_| {
+                button(text("Skip verification").size(12))
+                    .on_press(Message::SkipVerification)
+                    .padding([1, 6])
+                    .style(button::secondary)
+            }))
+            .push_maybe(self.scan_cancel.as_ref().map(|_| {
+                button(text("Cancel scan").size(12))
+                    .on_press(Message::CancelScan)
+                    .padding([1, 6])
+                    .style(button::secondary)
+            }))
+            .align_y(iced::Alignment::Center);
+        let status_bar = container(status_row).padding(5).style(style::header_style);
+
+        let disk_banner = self.disk_warning.as_ref().map(|warning| {
+            container(text(warning).size(16).color(iced::Color::WHITE))
+                .padding(8)
+                .width(Length::Fill)
+                .style(|_| container::Style {
+                    background: Some(iced::Color::from_rgb(0.7, 0.1, 0.1).into()),
+                    ..Default::default()
+                })
+        });
+
+        let base_content = column![
+            container(menu_bar).style(style::header_style),
+            breadcrumb_bar,
+        ]
+        .push_maybe(disk_banner)
+        .push(
+            container(pane_grid)
+                .height(Length::Fill)
+                .width(Length::Fill),
+        )
+        .push(status_bar);
+
+        let mut base_content: Element<Message> = base_content.into();
+
+        if self.is_config_menu_open {
+            let menu_options = column![
+                button("Settings")
+                    .on_press(Message::ConfigOptionSelected(ConfigOption::Settings))
+                    .width(Length::Fill),
+                button("Connect")
+                    .on_press(Message::ConfigOptionSelected(ConfigOption::Connect))
+                    .width(Length::Fill),
+                button("Schedule")
+                    .on_press(Message::ConfigOptionSelected(ConfigOption::Schedule))
+                    .width(Length::Fill),
+                button("Jobs")
+                    .on_press(Message::ConfigOptionSelected(ConfigOption::Jobs))
+                    .width(Length::Fill),
+                button("History")
+                    .on_press(Message::ConfigOptionSelected(ConfigOption::History))
+                    .width(Length::Fill),
+                button("Connection Info")
+                    .on_press(Message::ConfigOptionSelected(ConfigOption::ConnectionInfo))
+                    .width(Length::Fill),
+                button("Activity Log")
+                    .on_press(Message::ConfigOptionSelected(ConfigOption::ActivityLog))
+                    .width(Length::Fill),
+                button("Console")
+                    .on_press(Message::ConfigOptionSelected(ConfigOption::Console))
+                    .width(Length::Fill),
+                button("Minimize")
+                    .on_press(Message::ConfigOptionSelected(ConfigOption::Minimize))
+                    .width(Length::Fill),
+                button("Disconnect")
+                    .on_press(Message::ConfigOptionSelected(ConfigOption::Disconnect))
+                    .width(Length::Fill),
+                button("Exit")
+                    .on_press(Message::ConfigOptionSelected(ConfigOption::Exit))
+                    .width(Length::Fill),
+            ]
+            .width(150)
+            .padding(5)
+            .spacing(5);
+
+            let menu_overlay = container(container(menu_options).style(style::header_style))
+                .padding(iced::Padding {
+                    top: 45.0,
+                    left: 5.0,
+                    bottom: 0.0,
+                    right: 0.0,
+                });
+
+            // stack![base_content, menu_overlay].into()
+            base_content = stack![base_content, menu_overlay].into();
+        }
+
+        base_content
+    }
+}
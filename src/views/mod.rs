@@ -0,0 +1,112 @@
+mod info;
+mod jobs;
+mod main;
+mod queue;
+mod remote;
+mod schedule;
+mod settings;
+
+use crate::app::{AppState, Message, SftpApp};
+use iced::widget::{button, container, horizontal_space, row, stack, text, tooltip};
+use iced::{Color, Element};
+
+const WARNING: Color = Color::from_rgb(1.0, 0.6, 0.2);
+
+/// Red note under a form field, if the field has a problem
+pub fn field_error(problem: Option<&str>) -> Option<Element<'_, Message>> {
+    problem.map(|p| {
+        text(p)
+            .size(12)
+            .color(Color::from_rgb(1.0, 0.3, 0.3))
+            .into()
+    })
+}
+
+/// An icon-only control with its meaning in words, shown on hover
+pub fn described<'a>(
+    control: impl Into<Element<'a, Message>>,
+    label: &'a str,
+) -> Element<'a, Message> {
+    tooltip(
+        control,
+        container(text(label).size(12))
+            .padding(4)
+            .style(container::rounded_box),
+        tooltip::Position::Bottom,
+    )
+    .into()
+}
+
+impl SftpApp {
+    pub fn view(&self) -> Element<'_, Message> {
+        match self.state {
+            AppState::SettingsView => return self.view_settings(),
+            AppState::ScheduleView => return self.view_schedule(),
+            AppState::JobsView => return self.view_jobs(),
+            AppState::HistoryView => return self.view_history(),
+            AppState::FailureReportView => return self.view_failure_report(),
+            AppState::ConnectionInfoView => return self.view_connection_info(),
+            AppState::ActivityLogView => return self.view_activity_log(),
+            AppState::ConsoleView => return self.view_console(),
+            AppState::ReauthView => return self.view_reauth(),
+            AppState::QueueRecoveryView => return self.view_queue_recovery(),
+            AppState::MissingFoldersView => return self.view_missing_folders(),
+            _ => {}
+        }
+
+        let main_view = self.view_main();
+
+        // This logic was for overlay, but SettingsView is now full screen or we can keep it overlay.
+        // The original code used a stack/overlay approach for Settings.
+        // Let's migrate to a clearer state matching, consistent with my proposed changes.
+        // Actually, looking at lines 647-651, settings was overlay.
+        // User requested "dialog".
+        // Let's stick to full view switching for Schedule as per my plan implementation,
+        // unless I want to overlay it. Let's overlay it like settings if settings was overlay.
+        // Line 647 says: if self.state == AppState::SettingsView { stack![main_view, self.view_settings()] }
+        // So I should follow that pattern.
+
+        let root = match self.state {
+            AppState::SettingsView => stack![main_view, self.view_settings()].into(),
+            AppState::ScheduleView => stack![main_view, self.view_schedule()].into(),
+            AppState::JobsView => stack![main_view, self.view_jobs()].into(),
+            AppState::HistoryView => stack![main_view, self.view_history()].into(),
+            AppState::FailureReportView => stack![main_view, self.view_failure_report()].into(),
+            AppState::ConnectionInfoView => stack![main_view, self.view_connection_info()].into(),
+            AppState::ActivityLogView => stack![main_view, self.view_activity_log()].into(),
+            AppState::ConsoleView => stack![main_view, self.view_console()].into(),
+            AppState::ReauthView => stack![main_view, self.view_reauth()].into(),
+            AppState::QueueRecoveryView => stack![main_view, self.view_queue_recovery()].into(),
+            AppState::MissingFoldersView => stack![main_view, self.view_missing_folders()].into(),
+            _ => main_view,
+        };
+
+        root
+    }
+
+    /// Dialog heading, marked while it has edits that haven't been saved
+    pub fn view_dialog_title(&self, title: &'static str) -> Element<'_, Message> {
+        row![text(title).size(24), horizontal_space()]
+            .push_maybe(
+                self.has_unsaved_changes()
+                    .then(|| text("Unsaved changes").size(14).color(WARNING)),
+            )
+            .align_y(iced::Alignment::Center)
+            .into()
+    }
+
+    /// Takes the place of a dialog's buttons after Cancel with unsaved edits
+    pub fn view_discard_prompt(&self) -> Element<'_, Message> {
+        row![
+            text("Discard unsaved changes?").color(WARNING),
+            horizontal_space(),
+            button("Discard")
+                .on_press(Message::DiscardChanges)
+                .style(button::danger),
+            button("Keep editing").on_press(Message::KeepEditing),
+        ]
+        .spacing(10)
+        .align_y(iced::Alignment::Center)
+        .into()
+    }
+}
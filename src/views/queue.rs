@@ -0,0 +1,808 @@
+use super::described;
+use crate::app::{format_count, format_duration, ConfigOption, Message, PendingBatch, SftpApp};
+use crate::types::{
+    Batch, Direction, ItemLabel, QueueFilter, QueueGroup, QueueOrder, TimelineEntry, TimelineEvent,
+    TimelineSpan, TransferItem, TransferStatus,
+};
+use crate::{filter, names, settings, style, types};
+use iced::widget::{
+    button, checkbox, column, container, horizontal_space, mouse_area, pick_list, row, scrollable,
+    text, text_input,
+};
+use iced::{Element, Length};
+
+impl SftpApp {
+    pub fn view_queue(&self) -> Element<'_, Message> {
+        // A server with its own folder changes that one, not the default
+        let change = if self
+            .config
+            .profile_download_paths
+            .contains_key(&self.config.profile_key())
+        {
+            Message::SelectProfileDownloadPath
+        } else {
+            Message::SelectDownloadPath
+        };
+        let path_row = row![
+            text(format!("Download to: {}", self.config.download_path())).size(14),
+            horizontal_space(),
+            button("Change")
+                .on_press(change)
+                .padding(3)
+                .style(button::secondary),
+            button("Relocate pending")
+                .on_press(Message::ShowRelocate)
+                .padding(3)
+                .style(button::secondary)
+        ]
+        .spacing(5)
+        .padding(5)
+        .align_y(iced::Alignment::Center);
+
+        // Determine button actions based on selected queue item
+        let selected = self.selected_queue_item.clone();
+        let selected_status = selected.as_ref().and_then(|key| {
+            self.queue_items
+                .iter()
+                .find(|i| &i.key() == key)
+                .map(|i| i.status.clone())
+        });
+
+        let start_btn = if self.is_downloading {
+            button(text("Downloading...").size(12)).style(button::secondary)
+        } else {
+            button(text("Start").size(12))
+                .on_press(Message::StartDownloads)
+                .style(button::primary)
+        };
+
+        let pause_resume_btn = match &selected_status {
+            Some(status) if status.is_active() => button(text("Pause").size(12))
+                .on_press(Message::PauseDownload(selected.clone().unwrap())),
+            Some(TransferStatus::Paused) => button(text("Resume").size(12))
+                .on_press(Message::ResumeDownload(selected.clone().unwrap())),
+            _ => button(text("Pause").size(12)),
+        };
+
+        let remove_label = match &selected_status {
+            Some(status) if !status.is_finished() => "Cancel",
+            _ => "Remove",
+        };
+        let following = selected
+            .as_ref()
+            .and_then(|key| self.queue_items.iter().find(|i| &i.key() == key))
+            .filter(|i| i.direction == Direction::Download && !i.status.is_finished())
+            .map(|i| i.follow);
+        let follow_btn = button(
+            text(if following == Some(true) {
+                "Unfollow"
+            } else {
+                "Follow"
+            })
+            .size(12),
+        )
+        .on_press_maybe(
+            following
+                .is_some()
+                .then(|| Message::ToggleFollow(selected.clone().unwrap())),
+        );
+
+        let remove_btn = if selected.is_some() {
+            button(text(remove_label).size(12))
+                .on_press(Message::CancelDownload(selected.clone().unwrap()))
+        } else {
+            button(text(remove_label).size(12))
+        };
+
+        let failed: Vec<&TransferItem> = self
+            .queue_items
+            .iter()
+            .filter(|i| matches!(i.status, TransferStatus::Failed(_)))
+            .collect();
+        let transient = failed
+            .iter()
+            .filter(|i| i.failure.is_some_and(|k| k.is_transient()))
+            .count();
+        let retry_btn = (!failed.is_empty()).then(|| {
+            button(text(format!("Retry all failed ({})", format_count(failed.len()))).size(12))
+                .on_press(Message::RetryFailed(false))
+                .style(button::secondary)
+        });
+        // Only worth offering when it would leave something out
+        let retry_transient_btn = (transient > 0 && transient < failed.len()).then(|| {
+            button(text(format!("Network errors only ({})", format_count(transient))).size(12))
+                .on_press(Message::RetryFailed(true))
+                .style(button::secondary)
+        });
+
+        // Only offered while a schedule is set and something is left to run
+        let waiting = self.queue_items.iter().any(|i| !i.status.is_finished());
+        let force_btn =
+            (self.config.active_schedule().mode != settings::ScheduleMode::None).then(|| {
+                button(text("Force downloads").size(12))
+                    .on_press_maybe(
+                        (waiting || self.schedule_override)
+                            .then_some(Message::ForceDownloads(!self.schedule_override)),
+                    )
+                    .style(if self.schedule_override {
+                        button::primary
+                    } else {
+                        button::secondary
+                    })
+            });
+
+        let toolbar = row![
+            text("Queue").size(18),
+            horizontal_space(),
+            pick_list(
+                QueueOrder::ALL,
+                Some(self.config.queue_order),
+                Message::QueueOrderChanged
+            )
+            .text_size(12),
+            checkbox("Batches take turns", self.config.fair_batches)
+                .on_toggle(Message::FairBatchesToggled)
+                .text_size(12),
+            button(text("Refresh").size(12))
+                .on_press(Message::RefreshQueue)
+                .style(button::secondary),
+            start_btn,
+            pause_resume_btn,
+            follow_btn,
+            remove_btn,
+        ]
+        .push_maybe(force_btn)
+        .push_maybe(retry_btn)
+        .push_maybe(retry_transient_btn)
+        .spacing(5)
+        .padding(5);
+
+        // Manual header with portions to match content
+        let headers = container(
+            row![
+                container(
+                    text("Local Location")
+                        .size(12)
+                        .font(iced::font::Font::MONOSPACE)
+                )
+                .width(Length::FillPortion(2))
+                .padding(5)
+                .style(style::header_style),
+                container(text("File name").size(12).font(iced::font::Font::MONOSPACE))
+                    .width(Length::FillPortion(2))
+                    .padding(5)
+                    .style(style::header_style),
+                container(
+                    text("Remote file")
+                        .size(12)
+                        .font(iced::font::Font::MONOSPACE)
+                )
+                .width(Length::FillPortion(2))
+                .padding(5)
+                .style(style::header_style),
+                container(
+                    text("Transferred")
+                        .size(12)
+                        .font(iced::font::Font::MONOSPACE)
+                )
+                .width(Length::FillPortion(1))
+                .padding(5)
+                .style(style::header_style),
+                container(text("Remaining").size(12).font(iced::font::Font::MONOSPACE))
+                    .width(Length::FillPortion(1))
+                    .padding(5)
+                    .style(style::header_style),
+                container(text("Priority").size(12).font(iced::font::Font::MONOSPACE))
+                    .width(Length::FillPortion(1))
+                    .padding(5)
+                    .style(style::header_style),
+                container(text("Progress").size(12).font(iced::font::Font::MONOSPACE))
+                    .width(Length::FillPortion(1))
+                    .padding(5)
+                    .style(style::header_style),
+            ]
+            .spacing(1),
+        )
+        .padding(5)
+        .style(style::header_style);
+
+        let mut chips = row![].spacing(5).align_y(iced::Alignment::Center);
+        for filter in QueueFilter::ALL {
+            let count = self
+                .queue_items
+                .iter()
+                .filter(|i| filter.matches(&i.status))
+                .count();
+            chips = chips.push(
+                button(text(format!("{} ({})", filter, format_count(count))).size(12))
+                    .on_press(Message::QueueFilterChanged(filter))
+                    .padding(3)
+                    .style(if filter == self.queue_filter {
+                        button::primary
+                    } else {
+                        button::secondary
+                    }),
+            );
+        }
+        let filter_row = chips
+            .push(horizontal_space())
+            .push(
+                text_input("Filter by file name or note", &self.queue_search)
+                    .on_input(Message::QueueSearchChanged)
+                    .size(12)
+                    .padding(3)
+                    .width(200),
+            )
+            .padding(5);
+
+        // Batch headers keep counting every member; only the rows are filtered
+        let mut rows: Vec<Element<Message>> = Vec::new();
+        for group in types::group_by_batch(&self.queue_items) {
+            match group {
+                QueueGroup::Single(item) if self.queue_shows(item) => {
+                    rows.push(self.view_queue_row(item))
+                }
+                QueueGroup::Single(_) => {}
+                QueueGroup::Batch(batch, members) => {
+                    let visible: Vec<&TransferItem> = members
+                        .iter()
+                        .copied()
+                        .filter(|i| self.queue_shows(i))
+                        .collect();
+                    if visible.is_empty() {
+                        continue;
+                    }
+                    let collapsed = self.collapsed_batches.contains(&batch.id);
+                    rows.push(self.view_batch_header(batch, &members, collapsed));
+                    if !collapsed {
+                        rows.extend(visible.into_iter().map(|item| {
+                            container(self.view_queue_row(item))
+                                .padding(iced::Padding::ZERO.left(20))
+                                .into()
+                        }));
+                    }
+                }
+            }
+        }
+        let items = column(rows).spacing(2);
+
+        let undo = self.removed.as_ref().map(|removed| {
+            container(
+                row![
+                    text(format!("{} —", removed.label)).size(14),
+                    button(text("Undo").size(12))
+                        .on_press(Message::UndoRemove)
+                        .style(button::primary),
+                    horizontal_space(),
+                    described(
+                        button(text("×").size(12))
+                            .on_press(Message::DismissUndo)
+                            .style(style::text_button),
+                        "Dismiss"
+                    ),
+                ]
+                .spacing(5)
+                .align_y(iced::Alignment::Center),
+            )
+            .padding(8)
+            .style(style::header_style)
+        });
+
+        let stale = self.stale_items();
+        let stale_alert = (stale > self.stale_dismissed).then(|| {
+            container(
+                row![
+                    text(format!(
+                        "⚠ {} waiting more than {} days. Is the schedule letting downloads run?",
+                        if stale == 1 {
+                            "1 item has been".to_string()
+                        } else {
+                            format!("{} items have been", format_count(stale))
+                        },
+                        self.config.stale_queue_days
+                    ))
+                    .size(14)
+                    .color(style::warning_color()),
+                    button(text("Review schedule").size(12))
+                        .on_press(Message::ConfigOptionSelected(ConfigOption::Schedule))
+                        .style(button::primary),
+                    horizontal_space(),
+                    described(
+                        button(text("×").size(12))
+                            .on_press(Message::DismissStaleAlert)
+                            .style(style::text_button),
+                        "Dismiss"
+                    ),
+                ]
+                .spacing(5)
+                .align_y(iced::Alignment::Center),
+            )
+            .padding(8)
+            .style(style::header_style)
+        });
+
+        column![path_row]
+            .push_maybe(self.view_relocate())
+            .push(toolbar)
+            .push_maybe(stale_alert)
+            .push_maybe(self.view_item_details())
+            .push_maybe(undo)
+            .push(filter_row)
+            .push(headers)
+            .push(scrollable(items))
+            .into()
+    }
+
+    /// The selected item's timeline, and its settings while unfinished, shown
+    /// under the toolbar
+    fn view_item_details(&self) -> Option<Element<'_, Message>> {
+        let key = self.selected_queue_item.as_ref()?;
+        let item = self.queue_items.iter().find(|i| &i.key() == key)?;
+        let cap = match item.speed_limit {
+            0 => String::new(),
+            kb => kb.to_string(),
+        };
+        let speed_cap = (!item.status.is_finished()).then(|| {
+            row![
+                text("Speed cap for this file (KB/s):").size(12),
+                text_input("None", &cap)
+                    .on_input(Message::ItemSpeedLimitChanged)
+                    .size(12)
+                    .padding(3)
+                    .width(80),
+            ]
+            .spacing(10)
+            .align_y(iced::Alignment::Center)
+        });
+        let header = row![
+            text(names::display(&item.filename).to_string()).size(12),
+            text(if item.profile.is_empty() {
+                String::new()
+            } else {
+                format!("from {}", item.profile)
+            })
+            .size(12),
+            horizontal_space(),
+        ]
+        .push_maybe(speed_cap)
+        .push_maybe(
+            (item.direction == Direction::Download
+                && !item.status.is_active()
+                && item.status != TransferStatus::Paused)
+                .then(|| {
+                    described(
+                        button(text("Re-download").size(12))
+                            .on_press(Message::Redownload(item.key()))
+                            .padding([2, 6])
+                            .style(button::secondary),
+                        "Set the local copy aside and download it again from the start",
+                    )
+                }),
+        )
+        .spacing(10)
+        .align_y(iced::Alignment::Center);
+        let swatches = ItemLabel::ALL.iter().map(|&label| {
+            let (r, g, b) = label.rgb();
+            let picked = item.label == Some(label);
+            described(
+                button(text(if picked { "●" } else { "○" }).size(14))
+                    .on_press(Message::ItemLabelPicked((!picked).then_some(label)))
+                    .padding([0, 3])
+                    .style(move |theme, status| button::Style {
+                        text_color: iced::Color::from_rgb(r, g, b),
+                        ..button::text(theme, status)
+                    }),
+                label.name(),
+            )
+        });
+        let note = row![
+            text("Note:").size(12),
+            text_input("e.g. for Alice, verify before deleting", &item.note)
+                .on_input(Message::ItemNoteChanged)
+                .size(12)
+                .padding(3),
+            row(swatches).spacing(2),
+        ]
+        .spacing(10)
+        .align_y(iced::Alignment::Center);
+        Some(
+            container(
+                column![header, note]
+                    .push_maybe(self.view_item_timeline(item))
+                    .spacing(4),
+            )
+            .padding(5)
+            .style(style::header_style)
+            .into(),
+        )
+    }
+
+    /// A bar of running and paused stretches, with a one-line account of the
+    /// time spent in each, from the first start to finishing (or now)
+    fn view_item_timeline<'a>(&self, item: &'a TransferItem) -> Option<Element<'a, Message>> {
+        let first = item.timeline.first()?;
+        let spans = item.timeline_spans(chrono::Local::now().timestamp());
+        let secs = |span: &TimelineSpan| match span {
+            TimelineSpan::Running(secs) | TimelineSpan::Paused(_, secs) => *secs,
+        };
+        let total = spans.iter().map(secs).sum::<u64>().max(1);
+        let bar = spans.iter().fold(row![].height(8), |bar, span| {
+            let color = match span {
+                TimelineSpan::Running(_) => style::status_color(&TransferStatus::Downloading),
+                TimelineSpan::Paused(..) => style::status_color(&TransferStatus::Paused),
+            };
+            let portion = (secs(span) * 1000 / total).max(1) as u16;
+            bar.push(
+                container(horizontal_space())
+                    .width(Length::FillPortion(portion))
+                    .height(Length::Fill)
+                    .style(move |_| container::Style {
+                        background: Some(color.into()),
+                        ..Default::default()
+                    }),
+            )
+        });
+
+        let running: u64 = spans
+            .iter()
+            .filter(|s| matches!(s, TimelineSpan::Running(_)))
+            .map(secs)
+            .sum();
+        let mut paused: Vec<(&str, u64)> = Vec::new();
+        for span in &spans {
+            if let TimelineSpan::Paused(why, secs) = span {
+                match paused.iter_mut().find(|(w, _)| w == why) {
+                    Some((_, total)) => *total += secs,
+                    None => paused.push((why, *secs)),
+                }
+            }
+        }
+        let at = |secs: i64| {
+            chrono::DateTime::from_timestamp(secs, 0)
+                .map(|t| {
+                    t.with_timezone(&chrono::Local)
+                        .format("%a %H:%M")
+                        .to_string()
+                })
+                .unwrap_or_default()
+        };
+        let mut summary = format!(
+            "Started {}, running {}",
+            at(first.at),
+            format_duration(running)
+        );
+        for (why, secs) in paused {
+            summary.push_str(&format!(", paused {} ({})", format_duration(secs), why));
+        }
+        match item.retries() {
+            0 => {}
+            1 => summary.push_str(", 1 retry"),
+            n => summary.push_str(&format!(", {} retries", n)),
+        }
+        if let Some(TimelineEntry {
+            at: end,
+            event: TimelineEvent::Finished(status),
+        }) = item.timeline.last()
+        {
+            summary.push_str(&format!(", {} {}", status, at(*end)));
+        }
+        Some(column![bar, text(summary).size(12)].spacing(4).into())
+    }
+
+    pub fn view_queue_row<'a>(&'a self, item: &'a TransferItem) -> Element<'a, Message> {
+        let key = item.key();
+        let is_selected = self.selected_queue_item.as_ref() == Some(&key);
+
+        let (arrow, direction) = match item.direction {
+            _ if item.relay_to.is_some() => ("⇄", "Server to server"),
+            Direction::Download => ("↓", "Download"),
+            Direction::Upload => ("↑", "Upload"),
+        };
+
+        let row_content = row![
+            container(
+                row![
+                    described(text(arrow).size(12), direction),
+                    text(names::display(&item.local_location)).size(12),
+                ]
+                .spacing(4)
+            )
+            .width(Length::FillPortion(2)),
+            container(
+                row![]
+                    .push_maybe(item.label.map(|label| {
+                        let (r, g, b) = label.rgb();
+                        described(
+                            text("●").size(12).color(iced::Color::from_rgb(r, g, b)),
+                            label.name(),
+                        )
+                    }))
+                    .push(
+                        text(if item.follow {
+                            format!("{} (following)", names::display(&item.filename))
+                        } else {
+                            names::display(&item.filename).to_string()
+                        })
+                        .size(12)
+                    )
+                    .push_maybe((!item.note.is_empty()).then(|| {
+                        text(&item.note)
+                            .size(11)
+                            .color(iced::Color::from_rgb(0.6, 0.6, 0.6))
+                    }))
+                    .spacing(4)
+            )
+            .width(Length::FillPortion(2)),
+            container(text(names::display(&item.remote_file)).size(12))
+                .width(Length::FillPortion(2)),
+            container(text(self.format_bytes(&item.bytes_transferred.to_string())).size(12))
+                .width(Length::FillPortion(1)),
+            container(
+                text(self.format_bytes(&(item.size_bytes - item.bytes_transferred).to_string()))
+                    .size(12)
+            )
+            .width(Length::FillPortion(1)),
+            container(
+                row![
+                    described(
+                        button(text("-").size(12))
+                            .on_press(Message::ChangePriority(key.clone(), -1))
+                            .padding([0, 5])
+                            .style(button::secondary),
+                        "Run sooner"
+                    ),
+                    text(item.priority.to_string()).size(12),
+                    described(
+                        button(text("+").size(12))
+                            .on_press(Message::ChangePriority(key.clone(), 1))
+                            .padding([0, 5])
+                            .style(button::secondary),
+                        "Run later"
+                    ),
+                ]
+                .spacing(4)
+                .align_y(iced::Alignment::Center)
+            )
+            .width(Length::FillPortion(1)),
+            container(
+                row![container(
+                    text(item.status.to_string())
+                        .size(12)
+                        .color(style::status_color(&item.status))
+                )
+                .padding([1, 6])
+                .style(style::status_badge_style)]
+                .push_maybe(self.view_item_age(item))
+                .spacing(4)
+                .align_y(iced::Alignment::Center)
+            )
+            .width(Length::FillPortion(1)),
+        ]
+        .spacing(5);
+
+        let btn = button(container(row_content).padding(3))
+            .on_press(Message::TransferItemClicked(key.clone()))
+            .width(Length::Fill)
+            .style(move |_theme, _status| {
+                if is_selected {
+                    button::Style {
+                        background: Some(iced::Color::from_rgb(0.2, 0.4, 0.7).into()),
+                        text_color: iced::Color::WHITE,
+                        ..Default::default()
+                    }
+                } else {
+                    button::Style {
+                        text_color: iced::Color::WHITE,
+                        ..button::text(_theme, _status)
+                    }
+                }
+            });
+
+        // Middle-click removes, like closing a browser tab
+        mouse_area(btn)
+            .on_middle_press(Message::RemoveQueueItem(key))
+            .into()
+    }
+
+    /// How long an unfinished item has been queued, amber once it counts as stale
+    fn view_item_age(&self, item: &TransferItem) -> Option<Element<'_, Message>> {
+        let secs = item.waiting_secs(chrono::Local::now().timestamp())?;
+        let label = match secs {
+            0..=59 => return None,
+            60..=86_399 => format_duration(secs),
+            _ => format!("{}d", secs / 86_400),
+        };
+        let days = self.config.stale_queue_days;
+        let stale = days > 0 && secs > days * 86_400;
+        Some(
+            text(label)
+                .size(11)
+                .color_maybe(stale.then(style::warning_color))
+                .into(),
+        )
+    }
+
+    /// Group header for a queued folder: totals, progress and group-wide controls
+    pub fn view_batch_header<'a>(
+        &'a self,
+        batch: &'a Batch,
+        members: &[&TransferItem],
+        collapsed: bool,
+    ) -> Element<'a, Message> {
+        let total: u64 = members.iter().map(|i| i.size_bytes).sum();
+        let done: u64 = members.iter().map(|i| i.bytes_transferred).sum();
+        let finished = members.iter().filter(|i| i.status.is_finished()).count();
+        let percent = (done * 100).checked_div(total).unwrap_or(100);
+        let any_paused = members.iter().any(|i| i.status == TransferStatus::Paused);
+        let any_failed = members
+            .iter()
+            .any(|i| matches!(i.status, TransferStatus::Failed(_)));
+        let any_running = members
+            .iter()
+            .any(|i| !i.status.is_finished() && i.status != TransferStatus::Paused);
+
+        let summary = format!(
+            "{} 📁 {} — {}/{} files, {} of {} ({}%)",
+            if collapsed { "▸" } else { "▾" },
+            batch.name,
+            finished,
+            members.len(),
+            self.format_bytes(&done.to_string()),
+            self.format_bytes(&total.to_string()),
+            percent
+        );
+
+        container(
+            row![
+                button(text(summary).size(12))
+                    .on_press(Message::ToggleBatchCollapsed(batch.id))
+                    .style(style::text_button)
+                    .width(Length::Fill),
+                button(text("Pause").size(12))
+                    .on_press_maybe(any_running.then_some(Message::PauseBatch(batch.id)))
+                    .style(button::secondary),
+                button(text("Resume").size(12))
+                    .on_press_maybe(any_paused.then_some(Message::ResumeBatch(batch.id)))
+                    .style(button::secondary),
+                button(text("Failures").size(12))
+                    .on_press_maybe(any_failed.then(|| Message::ShowFailureReport(batch.clone())))
+                    .style(button::secondary),
+                button(text("Remove").size(12))
+                    .on_press(Message::RemoveBatch(batch.id))
+                    .style(button::secondary),
+            ]
+            .spacing(5)
+            .align_y(iced::Alignment::Center),
+        )
+        .padding(3)
+        .style(style::header_style)
+        .into()
+    }
+
+    pub fn view_batch_summary(&self, batch: &PendingBatch) -> Element<'_, Message> {
+        let patterns = &self.config.exclude_patterns;
+        let (count, bytes) = batch
+            .files
+            .iter()
+            .filter(|f| !filter::is_excluded(patterns, &f.name))
+            .fold((0usize, 0u64), |(c, b), f| (c + 1, b + f.size_bytes));
+        let filters = if patterns.is_empty() {
+            "none".to_string()
+        } else {
+            format!("exclude {}", patterns.join(", "))
+        };
+        let summary = format!(
+            "{} files, {} — filters applied: {} — destination: {}",
+            format_count(count),
+            self.format_bytes(&bytes.to_string()),
+            filters,
+            self.config.download_path()
+        );
+
+        container(
+            column![
+                text(summary).size(14),
+                row![
+                    text("Exclude:").size(14),
+                    text_input("*.nfo, sample*", &self.exclude_input)
+                        .on_input(Message::ExcludePatternsChanged)
+                        .padding(3),
+                    button(text("Change destination").size(12))
+                        .on_press(Message::SelectDownloadPath)
+                        .style(button::secondary),
+                ]
+                .spacing(5)
+                .align_y(iced::Alignment::Center),
+                row![
+                    button(text("Add to queue").size(12))
+                        .on_press(Message::ConfirmBatch)
+                        .style(button::primary),
+                    button(text("Cancel").size(12))
+                        .on_press(Message::CancelBatch)
+                        .style(button::secondary),
+                ]
+                .spacing(5),
+            ]
+            .spacing(5),
+        )
+        .padding(8)
+        .style(style::header_style)
+        .into()
+    }
+
+    /// Moving pending downloads to another folder, e.g. after a drive's mount
+    /// point changed
+    fn view_relocate(&self) -> Option<Element<'_, Message>> {
+        let (from, to) = self.relocate.as_ref()?;
+        let apply =
+            (!from.trim().is_empty() && !to.trim().is_empty()).then_some(Message::ApplyRelocate);
+        Some(
+            container(
+                column![
+                    text(
+                        "Pending downloads saving under the first folder will save under \
+                         the second; move partial files there too to keep their progress."
+                    )
+                    .size(12),
+                    row![
+                        text_input("Old folder", from)
+                            .on_input(Message::RelocateFromChanged)
+                            .size(12)
+                            .padding(3),
+                        text("→").size(14),
+                        text_input("New folder", to)
+                            .on_input(Message::RelocateToChanged)
+                            .on_submit_maybe(apply.clone())
+                            .size(12)
+                            .padding(3),
+                        button(text("Browse").size(12))
+                            .on_press(Message::PickRelocateTarget)
+                            .style(button::secondary),
+                        button(text("Relocate").size(12))
+                            .on_press_maybe(apply)
+                            .style(button::primary),
+                        button(text("Cancel").size(12))
+                            .on_press(Message::CancelRelocate)
+                            .style(button::secondary),
+                    ]
+                    .spacing(5)
+                    .align_y(iced::Alignment::Center),
+                ]
+                .spacing(5),
+            )
+            .padding(8)
+            .style(style::header_style)
+            .into(),
+        )
+    }
+
+    pub fn view_requeue_prompt(&self) -> Element<'_, Message> {
+        let label = match self.pending_requeue.as_slice() {
+            [item] => format!(
+                "{} is already in the queue — requeue it to {}?",
+                names::display(&item.filename),
+                names::display(&item.local_location)
+            ),
+            items => format!(
+                "{} files are already in the queue — requeue them anyway?",
+                format_count(items.len())
+            ),
+        };
+        container(
+            row![
+                text(label).size(14).width(Length::Fill),
+                button(text("Requeue anyway").size(12))
+                    .on_press(Message::ConfirmRequeue)
+                    .style(button::primary),
+                button(text("Skip").size(12))
+                    .on_press(Message::SkipRequeue)
+                    .style(button::secondary),
+            ]
+            .spacing(5)
+            .align_y(iced::Alignment::Center),
+        )
+        .padding(8)
+        .style(style::header_style)
+        .into()
+    }
+}
@@ -0,0 +1,699 @@
+use super::described;
+use crate::app::{
+    format_modified, remote_scroll_id, FolderSize, Message, RemoteOp, SftpApp, Thumbnail, SPINNER,
+};
+use crate::error::SftpError;
+use crate::settings::SortColumn;
+use crate::types::{FileType, RemoteFile};
+use crate::{names, sftp_client, style};
+use iced::widget::{
+    button, checkbox, column, container, horizontal_space, mouse_area, pick_list, row, scrollable,
+    text, text_input,
+};
+use iced::{Element, Length};
+
+const GRID_THUMB_SIZE: f32 = 128.0;
+
+const GRID_COLUMNS: usize = 5;
+
+impl SftpApp {
+    /// One tab per connected server, the on-screen one highlighted, and "+"
+    /// to connect another without dropping this one
+    fn view_server_tabs(&self) -> Option<Element<'_, Message>> {
+        if !self.is_connected && self.server_tabs.is_empty() {
+            return None;
+        }
+        let mut tabs = row![].spacing(5).align_y(iced::Alignment::Center);
+        if self.is_connected {
+            tabs = tabs.push(
+                button(text(self.config.profile_key()).size(12))
+                    .on_press(Message::RefreshRemote)
+                    .style(button::primary),
+            );
+        }
+        for (index, tab) in self.server_tabs.iter().enumerate() {
+            tabs = tabs.push(
+                row![
+                    button(text(tab.sftp_config.profile_key()).size(12))
+                        .on_press(Message::SwitchServerTab(index))
+                        .style(button::secondary),
+                    described(
+                        button(text("×").size(12))
+                            .on_press(Message::CloseServerTab(index))
+                            .style(style::text_button),
+                        "Close tab"
+                    ),
+                ]
+                .align_y(iced::Alignment::Center),
+            );
+        }
+        tabs = tabs.push(described(
+            button(text("+").size(12))
+                .on_press(Message::NewServerTab)
+                .style(button::secondary),
+            "New server tab",
+        ));
+        Some(container(tabs).padding(5).into())
+    }
+
+    pub fn view_remote(&self) -> Element<'_, Message> {
+        let now = chrono::Utc::now();
+        let zone = self
+            .config
+            .server_times
+            .then(|| self.config.sftp_config.server_offset())
+            .flatten();
+        let read_only = self.config.sftp_config.read_only;
+        let toolbar = row![
+            text(format!(
+                "Remote: {}, Folder: {}",
+                self.config.sftp_config.host, self.current_remote_path
+            ))
+            .size(16),
+            text(self.new_since_summary())
+                .size(12)
+                .color(style::new_entry_color()),
+            text(if read_only { "Read-only" } else { "" })
+                .size(12)
+                .color(style::warning_color()),
+            horizontal_space(),
+            button(text("Refresh").size(12))
+                .on_press(Message::RefreshRemote)
+                .style(button::secondary),
+            button(text("Up").size(12))
+                .on_press(Message::GoToParent)
+                .style(button::secondary),
+            button(text("Open link...").size(12))
+                .on_press(Message::ShowUrlPrompt)
+                .style(button::secondary),
+            button(text("Go to copied path").size(12))
+                .on_press_maybe(self.is_connected.then_some(Message::GoToClipboardPath))
+                .style(button::secondary),
+            button(text("Tree").size(12))
+                .on_press(Message::ToggleTree)
+                .style(if self.session.show_tree {
+                    button::primary
+                } else {
+                    button::secondary
+                }),
+            button(text("Grid").size(12))
+                .on_press(Message::ToggleGridView)
+                .style(if self.session.grid_view {
+                    button::primary
+                } else {
+                    button::secondary
+                }),
+            button(text("Upload").size(12))
+                .on_press_maybe(
+                    (self.is_connected && !read_only).then_some(Message::SelectUploadFiles)
+                )
+                .style(button::primary)
+        ]
+        .padding(5)
+        .align_y(iced::Alignment::Center)
+        .spacing(5);
+
+        let options = row![
+            text_input("Filter", &self.session.filter)
+                .on_input(Message::RemoteFilterChanged)
+                .padding(3)
+                .width(200),
+            checkbox("Show hidden", self.session.show_hidden).on_toggle(Message::ShowHiddenToggled),
+            checkbox("Owner & mode", self.session.show_owner).on_toggle(Message::ShowOwnerToggled),
+            checkbox("Color sizes", self.config.color_sizes).on_toggle(Message::ColorSizesToggled),
+            checkbox("Relative times", self.config.relative_times)
+                .on_toggle(Message::RelativeTimesToggled),
+        ]
+        .push_maybe(self.config.sftp_config.server_offset().map(|_| {
+            checkbox("Server time", self.config.server_times).on_toggle(Message::ServerTimesToggled)
+        }))
+        .spacing(10)
+        .padding([0, 5])
+        .align_y(iced::Alignment::Center);
+
+        // Clicking a header sorts by it, clicking again flips the direction
+        let header = |label: &str, column: SortColumn, portion: u16| {
+            let arrow = match (
+                self.session.sort_column == column,
+                self.session.sort_ascending,
+            ) {
+                (true, true) => " ▲",
+                (true, false) => " ▼",
+                _ => "",
+            };
+            button(
+                text(format!("{}{}", label, arrow))
+                    .size(14)
+                    .font(iced::Font {
+                        weight: iced::font::Weight::Bold,
+                        ..Default::default()
+                    }),
+            )
+            .on_press(Message::SortBy(column))
+            .padding(0)
+            .style(style::text_button)
+            .width(Length::FillPortion(portion))
+        };
+
+        let headers = container(
+            row![
+                header("Name", SortColumn::Name, 2),
+                header("Size", SortColumn::Size, 1),
+                header("Type", SortColumn::Type, 1),
+                header("Modified", SortColumn::Modified, 1),
+            ]
+            .push_maybe(self.session.show_owner.then(|| {
+                let bold = iced::Font {
+                    weight: iced::font::Weight::Bold,
+                    ..Default::default()
+                };
+                row![
+                    text("Owner")
+                        .size(14)
+                        .font(bold)
+                        .width(Length::FillPortion(1)),
+                    text("Mode")
+                        .size(14)
+                        .font(bold)
+                        .width(Length::FillPortion(1)),
+                ]
+                .spacing(5)
+                .width(Length::FillPortion(2))
+            }))
+            .spacing(5),
+        )
+        .padding(5)
+        .style(style::header_style);
+
+        let items = column(
+            self.visible_remote_files()
+                .into_iter()
+                .map(|file| {
+                    let is_folder = file.file_type == FileType::Folder;
+                    let kind = file.kind();
+                    let name_text = format!("{} {}", kind.icon(), names::display(&file.name));
+                    let new_color = self.is_new_since_visit(file).then(style::new_entry_color);
+
+                    // Name is just text now, whole row is clickable
+                    let name_widget: Element<Message> =
+                        text(name_text).size(14).color_maybe(new_color).into();
+
+                    let type_str = kind.label();
+
+                    let is_selected = self.selected_file.as_ref() == Some(&file.name);
+                    let is_hovered = self.hovered_file.as_ref() == Some(&file.name);
+
+                    let size_color = match self.folder_sizes.get(&file.path) {
+                        _ if !self.config.color_sizes => None,
+                        Some(FolderSize::Done { bytes, .. }) if is_folder => {
+                            style::size_color(*bytes)
+                        }
+                        _ if is_folder => None,
+                        _ => style::size_color(file.size_bytes),
+                    };
+                    let size_str = match self.folder_sizes.get(&file.path) {
+                        Some(FolderSize::Scanning) if is_folder => {
+                            if self.config.reduce_motion {
+                                "Scanning...".to_string()
+                            } else {
+                                format!("{} Scanning...", SPINNER[self.spinner_frame])
+                            }
+                        }
+                        Some(FolderSize::Done { bytes, files }) if is_folder => format!(
+                            "{} ({} files)",
+                            self.format_bytes(&bytes.to_string()),
+                            files
+                        ),
+                        _ => file.size.clone(),
+                    };
+
+                    let row_content = row![
+                        container(name_widget).width(Length::FillPortion(2)),
+                        container(text(size_str).size(14).color_maybe(size_color))
+                            .width(Length::FillPortion(1)),
+                        container(text(type_str).size(14)).width(Length::FillPortion(1)),
+                        container(
+                            text(format_modified(
+                                file.modified,
+                                self.config.relative_times,
+                                zone,
+                                now
+                            ))
+                            .size(14)
+                            .color_maybe(new_color)
+                        )
+                        .width(Length::FillPortion(1)),
+                    ]
+                    .push_maybe(self.session.show_owner.then(|| {
+                        let owner = self
+                            .owner_names
+                            .as_ref()
+                            .map(|names| names.owner(file))
+                            .unwrap_or_default();
+                        row![
+                            text(owner).size(14).width(Length::FillPortion(1)),
+                            text(file.mode())
+                                .size(14)
+                                .font(iced::Font::MONOSPACE)
+                                .width(Length::FillPortion(1)),
+                        ]
+                        .spacing(5)
+                        .width(Length::FillPortion(2))
+                    }))
+                    .spacing(5);
+
+                    let main_btn = button(container(row_content).padding(5))
+                        .on_press(Message::RemoteFileClicked(file.clone()))
+                        .width(Length::Fill)
+                        .style(move |_thread, _status| {
+                            if is_selected {
+                                button::Style {
+                                    background: Some(iced::Color::from_rgb(0.2, 0.4, 0.7).into()),
+                                    text_color: iced::Color::WHITE,
+                                    ..Default::default()
+                                }
+                            } else {
+                                button::Style {
+                                    text_color: iced::Color::WHITE,
+                                    ..button::text(_thread, _status)
+                                }
+                            }
+                        });
+
+                    // The selected row keeps its actions, for keyboard users; with
+                    // reduced motion they don't pop in on hover either
+                    let actions = if is_selected || (is_hovered && !self.config.reduce_motion) {
+                        row![
+                            button(text("Queue").size(12))
+                                .on_press(Message::QueueFile(file.clone()))
+                                .style(button::secondary)
+                                .padding(5),
+                            button(text("Download").size(12))
+                                .on_press(Message::DownloadFile(file.clone()))
+                                .style(button::primary)
+                                .padding(5),
+                        ]
+                        .push_maybe((!read_only).then(|| {
+                            row![
+                                button(text("Move to...").size(12))
+                                    .on_press(Message::StartRemoteOp(RemoteOp::Move, file.clone()))
+                                    .style(button::secondary)
+                                    .padding(5),
+                                button(text("Copy to...").size(12))
+                                    .on_press(Message::StartRemoteOp(RemoteOp::Copy, file.clone()))
+                                    .style(button::secondary)
+                                    .padding(5),
+                                button(text("Delete").size(12))
+                                    .on_press(Message::DeleteRemote(file.clone()))
+                                    .style(button::danger)
+                                    .padding(5),
+                            ]
+                            .spacing(5)
+                        }))
+                        .push(
+                            button(text("Copy path").size(12))
+                                .on_press(Message::CopyRemotePath(file.path.clone()))
+                                .style(button::secondary)
+                                .padding(5),
+                        )
+                        .push_maybe((!is_folder && !self.server_tabs.is_empty()).then(|| {
+                            button(text("Copy to server...").size(12))
+                                .on_press(Message::StartRemoteOp(RemoteOp::Relay, file.clone()))
+                                .style(button::secondary)
+                                .padding(5)
+                        }))
+                        .push_maybe((is_folder && file.name != "..").then(|| {
+                            button(text("Calculate size").size(12))
+                                .on_press(Message::CalculateFolderSize(file.clone()))
+                                .style(button::secondary)
+                                .padding(5)
+                        }))
+                        .push_maybe(
+                            (!is_folder
+                                && self.console_blocked().is_none()
+                                && sftp_client::extract_command(&file.name).is_some())
+                            .then(|| {
+                                let busy = self.extracting.contains(&file.path);
+                                button(
+                                    text(if busy {
+                                        "Extracting..."
+                                    } else {
+                                        "Extract on server"
+                                    })
+                                    .size(12),
+                                )
+                                .on_press_maybe(
+                                    (!busy).then(|| Message::ExtractOnServer(file.clone())),
+                                )
+                                .style(button::secondary)
+                                .padding(5)
+                            }),
+                        )
+                        .push_maybe((is_folder && file.name != "..").then(|| {
+                            button(text("Watch").size(12))
+                                .on_press(Message::WatchFolder(file.clone()))
+                                .style(button::secondary)
+                                .padding(5)
+                        }))
+                        .push_maybe((is_folder && file.name != "..").then(|| {
+                            button(text("Save as Job").size(12))
+                                .on_press(Message::SaveAsJob(file.clone()))
+                                .style(button::secondary)
+                                .padding(5)
+                        }))
+                        .spacing(5)
+                        .padding(2)
+                    } else {
+                        row![].padding(2)
+                    };
+
+                    let container_row = row![main_btn, actions].align_y(iced::Alignment::Center);
+
+                    mouse_area(container_row)
+                        .on_enter(Message::HoverFile(file.name.clone()))
+                        .on_exit(Message::UnhoverFile)
+                        .into()
+                })
+                .collect::<Vec<_>>(),
+        )
+        .spacing(2);
+
+        let mut content = column![].push_maybe(self.view_server_tabs()).push(toolbar);
+        if let Some(batch) = &self.pending_batch {
+            content = content.push(self.view_batch_summary(batch));
+        }
+        if !self.pending_requeue.is_empty() {
+            content = content.push(self.view_requeue_prompt());
+        }
+        if let Some(input) = &self.url_input {
+            content = content.push(
+                row![
+                    text("Queue from link:").size(14),
+                    text_input("sftp://user@host:port/path", input)
+                        .on_input(Message::UrlInputChanged)
+                        .on_submit(Message::SubmitUrl)
+                        .size(12)
+                        .padding(3),
+                    button(text("Open").size(12))
+                        .on_press(Message::SubmitUrl)
+                        .style(button::primary),
+                    button(text("Cancel").size(12))
+                        .on_press(Message::CancelUrlPrompt)
+                        .style(button::secondary),
+                ]
+                .spacing(5)
+                .padding(5)
+                .align_y(iced::Alignment::Center),
+            );
+        }
+        if let Some(file) = &self.pending_delete {
+            let label = match &self.config.sftp_config.trash_folder {
+                Some(trash) => format!("Move {} to {}?", file.name, trash),
+                None => format!(
+                    "Delete {} from the server? This can't be undone.",
+                    file.name
+                ),
+            };
+            let confirm = if self.config.dry_run {
+                "Log dry run"
+            } else {
+                "Delete"
+            };
+            content = content.push(
+                row![
+                    text(label).size(14),
+                    // Only once the full list is there to review
+                    button(text(confirm).size(12))
+                        .on_press_maybe(
+                            self.delete_preview
+                                .is_some()
+                                .then_some(Message::ConfirmDelete)
+                        )
+                        .style(button::danger),
+                    button(text("Cancel").size(12))
+                        .on_press(Message::CancelDelete)
+                        .style(button::secondary),
+                ]
+                .spacing(5)
+                .padding(5)
+                .align_y(iced::Alignment::Center),
+            );
+            let preview: Element<Message> = match &self.delete_preview {
+                None => text("Listing what would be deleted...").size(12).into(),
+                Some(plan) => container(scrollable(
+                    iced::widget::Column::with_children(
+                        plan.iter().map(|line| text(line).size(12).into()),
+                    )
+                    .spacing(2),
+                ))
+                .max_height(150)
+                .into(),
+            };
+            content = content.push(container(preview).padding([0, 10]));
+        }
+        if let Some((op, file)) = &self.remote_op {
+            let label = match op {
+                RemoteOp::Move => format!("Move {} to:", file.name),
+                RemoteOp::Copy => format!("Copy {} to:", file.name),
+                RemoteOp::Relay => format!("Copy {} to server:", file.name),
+            };
+            let servers = (*op == RemoteOp::Relay).then(|| {
+                let profiles: Vec<String> = self
+                    .server_tabs
+                    .iter()
+                    .map(|tab| tab.sftp_config.profile_key())
+                    .collect();
+                pick_list(
+                    profiles,
+                    Some(self.relay_server.clone()),
+                    Message::RelayServerPicked,
+                )
+                .text_size(12)
+            });
+            content = content.push(
+                row![text(label).size(14)]
+                    .push_maybe(servers)
+                    .push(
+                        text_input("/remote/folder", &self.remote_op_destination)
+                            .on_input(Message::RemoteOpDestinationChanged)
+                            .on_submit(Message::ConfirmRemoteOp)
+                            .padding(3),
+                    )
+                    .push(
+                        button(text("OK").size(12))
+                            .on_press(Message::ConfirmRemoteOp)
+                            .style(button::primary),
+                    )
+                    .push(
+                        button(text("Cancel").size(12))
+                            .on_press(Message::CancelRemoteOp)
+                            .style(button::secondary),
+                    )
+                    .spacing(5)
+                    .padding(5)
+                    .align_y(iced::Alignment::Center),
+            );
+        }
+        if let Some(err) = &self.app_error {
+            content = content.push(
+                container(
+                    text(format!("Error: {}", err))
+                        .size(14)
+                        .color(iced::Color::from_rgb(1.0, 0.5, 0.5)),
+                )
+                .padding(5)
+                .style(|_| container::Style {
+                    background: Some(iced::Color::from_rgb(0.2, 0.0, 0.0).into()),
+                    ..Default::default()
+                }),
+            );
+        }
+        if let Some((path, error)) = &self.listing_error {
+            // The last good listing stays below; offer a way out instead of a dead end
+            let retry_as_user = matches!(error, SftpError::PermissionDenied(_)).then(|| {
+                button(text("Retry as different user").size(12))
+                    .on_press(Message::ListingRetryAsUser)
+                    .style(button::secondary)
+            });
+            content = content.push(
+                container(
+                    row![
+                        text(format!(
+                            "Can't open {}: {}",
+                            names::display(path),
+                            error.folder_message()
+                        ))
+                        .size(14)
+                        .color(iced::Color::from_rgb(1.0, 0.5, 0.5)),
+                        horizontal_space(),
+                        button(text("Go up").size(12))
+                            .on_press(Message::ListingGoUp)
+                            .style(button::secondary),
+                    ]
+                    .push_maybe(retry_as_user)
+                    .spacing(5)
+                    .align_y(iced::Alignment::Center),
+                )
+                .padding(5)
+                .style(|_| container::Style {
+                    background: Some(iced::Color::from_rgb(0.2, 0.0, 0.0).into()),
+                    ..Default::default()
+                }),
+            );
+        }
+        let listing = if self.session.grid_view {
+            column![
+                options,
+                scrollable(self.view_grid())
+                    .id(remote_scroll_id())
+                    .on_scroll(Message::RemoteScrolled),
+            ]
+        } else {
+            column![
+                options,
+                headers,
+                scrollable(items)
+                    .id(remote_scroll_id())
+                    .on_scroll(Message::RemoteScrolled),
+            ]
+        };
+        if !self.session.show_tree {
+            return content.push(listing).into();
+        }
+
+        let mut tree = Vec::new();
+        self.tree_rows("/", 0, &mut tree);
+        let sidebar = container(scrollable(column(tree).spacing(1).padding(5)))
+            .width(220)
+            .height(Length::Fill)
+            .style(style::header_style);
+        content.push(row![sidebar, listing].spacing(5)).into()
+    }
+
+    /// Remote listing as tiles, images showing their thumbnail once it has loaded
+    pub fn view_grid(&self) -> Element<'_, Message> {
+        let tiles: Vec<Element<Message>> = self
+            .visible_remote_files()
+            .into_iter()
+            .map(|file| {
+                let preview: Element<Message> = match self.thumbnails.get(&file.path) {
+                    Some(Thumbnail::Ready(handle)) => iced::widget::image(handle.clone())
+                        .width(GRID_THUMB_SIZE)
+                        .height(GRID_THUMB_SIZE)
+                        .into(),
+                    _ => container(text(file.kind().icon()).size(48))
+                        .center(GRID_THUMB_SIZE)
+                        .into(),
+                };
+                let is_selected = self.selected_file.as_ref() == Some(&file.name);
+                button(
+                    column![
+                        preview,
+                        text(names::display(&file.name)).size(12).color_maybe(
+                            self.is_new_since_visit(file).then(style::new_entry_color)
+                        )
+                    ]
+                    .spacing(4)
+                    .align_x(iced::Alignment::Center),
+                )
+                .on_press(Message::RemoteFileClicked(file.clone()))
+                .width(GRID_THUMB_SIZE + 16.0)
+                .padding(8)
+                .style(if is_selected {
+                    button::primary
+                } else {
+                    button::text
+                })
+                .into()
+            })
+            .collect();
+
+        let mut rows = column![].spacing(5).padding(5);
+        let mut tiles = tiles.into_iter().peekable();
+        while tiles.peek().is_some() {
+            rows = rows.push(row(tiles.by_ref().take(GRID_COLUMNS)).spacing(5));
+        }
+        rows.into()
+    }
+
+    /// Rows for `path` and its expanded descendants, indented by depth
+    pub fn tree_rows(&self, path: &str, depth: u16, rows: &mut Vec<Element<'_, Message>>) {
+        let expanded = self.tree_expanded.contains(path);
+        let name = match std::path::Path::new(path).file_name() {
+            Some(n) => n.to_string_lossy().to_string(),
+            None => path.to_string(),
+        };
+        let toggle = match self.tree_children.get(path) {
+            Some(children) if children.is_empty() => " ",
+            _ if expanded => "▾",
+            _ => "▸",
+        };
+        let is_current = path == self.current_remote_path;
+
+        rows.push(
+            row![
+                described(
+                    button(text(toggle).size(12))
+                        .on_press(Message::TreeToggleExpand(path.to_string()))
+                        .padding([0, 4])
+                        .style(style::text_button),
+                    if expanded { "Collapse" } else { "Expand" }
+                ),
+                button(text(format!("📁 {}", name)).size(13))
+                    .on_press(Message::TreeSelect(path.to_string()))
+                    .padding([1, 4])
+                    .style(move |theme, status| {
+                        if is_current {
+                            button::primary(theme, status)
+                        } else {
+                            button::text(theme, status)
+                        }
+                    }),
+            ]
+            .padding(iced::Padding::ZERO.left(depth * 12))
+            .align_y(iced::Alignment::Center)
+            .into(),
+        );
+
+        if expanded {
+            if let Some(children) = self.tree_children.get(path) {
+                for child in children {
+                    self.tree_rows(child, depth + 1, rows);
+                }
+            }
+        }
+    }
+
+    /// Remote files after the session's filter, hidden-file and sort settings
+    pub fn visible_remote_files(&self) -> Vec<&RemoteFile> {
+        let filter = self.session.filter.to_lowercase();
+        let mut files: Vec<&RemoteFile> = self
+            .remote_files
+            .iter()
+            .filter(|f| f.name == ".." || self.session.show_hidden || !f.name.starts_with('.'))
+            .filter(|f| f.name == ".." || f.name.to_lowercase().contains(&filter))
+            .collect();
+
+        files.sort_by(|a, b| {
+            // ".." stays on top and folders stay before files whatever the column
+            let rank = |f: &RemoteFile| match (f.name.as_str(), &f.file_type) {
+                ("..", _) => 0,
+                (_, FileType::Folder) => 1,
+                _ => 2,
+            };
+            let by_column = match self.session.sort_column {
+                SortColumn::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+                SortColumn::Size => a.size_bytes.cmp(&b.size_bytes),
+                SortColumn::Type => a.kind().label().cmp(b.kind().label()),
+                SortColumn::Modified => a.modified.cmp(&b.modified),
+            };
+            let by_column = if self.session.sort_ascending {
+                by_column
+            } else {
+                by_column.reverse()
+            };
+            rank(a).cmp(&rank(b)).then(by_column)
+        });
+        files
+    }
+}
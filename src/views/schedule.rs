@@ -0,0 +1,412 @@
+use crate::app::{Message, SftpApp};
+use crate::scheduler::Scheduler;
+use crate::settings::ScheduleConfig;
+use crate::{settings, style};
+use iced::widget::canvas::{self, event, Frame, Geometry};
+use iced::widget::{
+    button, checkbox, column, container, horizontal_rule, pick_list, radio, row, text, text_input,
+};
+use iced::{mouse, Color, Element, Length, Point, Rectangle, Renderer, Size, Theme};
+
+const LABEL_WIDTH: f32 = 36.0;
+const HEADER_HEIGHT: f32 = 16.0;
+const ROW_HEIGHT: f32 = 20.0;
+const DAY_NAMES: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+impl SftpApp {
+    pub fn view_schedule(&self) -> Element<'_, Message> {
+        let title = self.view_dialog_title("Download Schedule");
+
+        let mode_section = column![
+            text("Schedule Mode:").size(16),
+            radio(
+                "None",
+                settings::ScheduleMode::None,
+                Some(self.config.active_schedule().mode),
+                Message::ScheduleModeChanged
+            ),
+            radio(
+                "Daily",
+                settings::ScheduleMode::Daily,
+                Some(self.config.active_schedule().mode),
+                Message::ScheduleModeChanged
+            ),
+            radio(
+                "Weekly",
+                settings::ScheduleMode::Weekly,
+                Some(self.config.active_schedule().mode),
+                Message::ScheduleModeChanged
+            ),
+            radio(
+                "Custom (paint hours on the timeline)",
+                settings::ScheduleMode::Custom,
+                Some(self.config.active_schedule().mode),
+                Message::ScheduleModeChanged
+            ),
+        ]
+        .spacing(10);
+
+        let own_schedule = checkbox(
+            format!("Only for this server ({})", self.config.profile_key()),
+            self.config.has_own_schedule(),
+        )
+        .on_toggle(Message::ProfileScheduleToggled);
+
+        let timeline = column![
+            canvas::Canvas::new(Timeline {
+                schedule: self.config.active_schedule(),
+            })
+            .width(Length::Fill)
+            .height(HEADER_HEIGHT + 7.0 * ROW_HEIGHT),
+            text("Click or drag across hours to allow or block them")
+                .size(12)
+                .color(Color::from_rgb(0.6, 0.6, 0.6)),
+        ]
+        .spacing(5);
+
+        let mut content = column![
+            title,
+            own_schedule,
+            self.view_schedule_suggestion(),
+            mode_section,
+            timeline
+        ]
+        .spacing(20)
+        .padding(20);
+
+        if matches!(
+            self.config.active_schedule().mode,
+            settings::ScheduleMode::Daily | settings::ScheduleMode::Weekly
+        ) {
+            let schedule = self.config.active_schedule();
+            let start_val =
+                schedule.start_time.hour as u16 * 60 + schedule.start_time.minute as u16;
+            let end_val = schedule.end_time.hour as u16 * 60 + schedule.end_time.minute as u16;
+            let start_time_row = self.view_time_picker("Start Time:", schedule.start_time, true);
+            let end_time_row = row![self.view_time_picker("End Time:", schedule.end_time, false)]
+                .push_maybe((end_val < start_val).then(|| {
+                    text("(Next Day)")
+                        .size(12)
+                        .color(iced::Color::from_rgb(0.6, 0.6, 0.6))
+                }))
+                .spacing(10)
+                .align_y(iced::Alignment::Center);
+
+            content = content.push(column![start_time_row, end_time_row].spacing(10));
+        }
+
+        if self.config.active_schedule().mode == settings::ScheduleMode::Weekly {
+            let days = &self.config.active_schedule().days;
+            let days_row = row![
+                checkbox("Mon", days.mon).on_toggle(|_| Message::ScheduleDayToggled(0)),
+                checkbox("Tue", days.tue).on_toggle(|_| Message::ScheduleDayToggled(1)),
+                checkbox("Wed", days.wed).on_toggle(|_| Message::ScheduleDayToggled(2)),
+                checkbox("Thu", days.thu).on_toggle(|_| Message::ScheduleDayToggled(3)),
+                checkbox("Fri", days.fri).on_toggle(|_| Message::ScheduleDayToggled(4)),
+                checkbox("Sat", days.sat).on_toggle(|_| Message::ScheduleDayToggled(5)),
+                checkbox("Sun", days.sun).on_toggle(|_| Message::ScheduleDayToggled(6)),
+            ]
+            .spacing(15);
+
+            content = content.push(text("Active Days:")).push(days_row);
+        }
+
+        let battery_section = column![
+            text("On Battery:").size(16),
+            row![
+                radio(
+                    "Keep going",
+                    settings::BatteryMode::Ignore,
+                    Some(self.config.battery_mode),
+                    Message::BatteryModeChanged
+                ),
+                radio(
+                    "Pause",
+                    settings::BatteryMode::Pause,
+                    Some(self.config.battery_mode),
+                    Message::BatteryModeChanged
+                ),
+                radio(
+                    "Limit to (KB/s):",
+                    settings::BatteryMode::Throttle,
+                    Some(self.config.battery_mode),
+                    Message::BatteryModeChanged
+                ),
+                text_input("500", &self.config.battery_speed_limit.to_string())
+                    .on_input(Message::BatterySpeedChanged)
+                    .width(80)
+                    .padding(5),
+            ]
+            .spacing(15)
+            .align_y(iced::Alignment::Center),
+        ]
+        .spacing(10);
+        content = content.push(battery_section);
+
+        let buttons: Element<'_, Message> = if self.confirm_discard {
+            self.view_discard_prompt()
+        } else {
+            row![
+                button("Save").on_press(Message::SaveSchedule),
+                button("Cancel")
+                    .on_press(Message::CancelSchedule)
+                    .style(button::secondary),
+            ]
+            .spacing(10)
+            .into()
+        };
+
+        content = content.push(horizontal_rule(1)).push(buttons);
+
+        container(
+            container(content.spacing(20).max_width(600))
+                .padding(20)
+                .style(style::header_style),
+        )
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .center_x(Length::Fill)
+        .center_y(Length::Fill)
+        .style(|_t: &Theme| container::Style {
+            background: Some(iced::Color::from_rgba(0.0, 0.0, 0.0, 0.5).into()),
+            ..Default::default()
+        })
+        .into()
+    }
+
+    /// The "Suggest from history" button, or the window it came up with and
+    /// what filling it in would set
+    fn view_schedule_suggestion(&self) -> Element<'_, Message> {
+        let Some(suggestion) = &self.schedule_suggestion else {
+            return row![
+                button("Suggest from history")
+                    .on_press(Message::SuggestSchedule)
+                    .style(button::secondary),
+                text("Picks a window from the speeds seen at each hour over the last month")
+                    .size(12)
+                    .color(Color::from_rgb(0.6, 0.6, 0.6)),
+            ]
+            .spacing(10)
+            .align_y(iced::Alignment::Center)
+            .into();
+        };
+        let speed = |bytes: u64| format!("{}/s", self.format_bytes(&bytes.to_string()));
+        container(
+            column![
+                text(format!(
+                    "Transfers have been fastest {:02}:00–{:02}:00: {} on average, against {} at other times.",
+                    suggestion.start_hour,
+                    suggestion.end_hour,
+                    speed(suggestion.window_speed),
+                    speed(suggestion.other_speed)
+                )),
+                text(format!(
+                    "Suggested: download daily in that window, limited to {} KB/s {}.",
+                    suggestion.limit_kb,
+                    if self.config.has_own_schedule() {
+                        "for this server"
+                    } else {
+                        "as the default for servers without their own limit"
+                    }
+                ))
+                .size(12),
+                row![
+                    button("Use this").on_press(Message::UseScheduleSuggestion),
+                    button("Dismiss")
+                        .on_press(Message::DismissScheduleSuggestion)
+                        .style(button::secondary),
+                ]
+                .spacing(10),
+            ]
+            .spacing(8),
+        )
+        .padding(10)
+        .style(style::header_style)
+        .into()
+    }
+
+    /// Typed HH:MM (24h or 12h) plus hour and minute dropdowns for one end of
+    /// the window; dropdown picks go through the usual time-changed messages
+    fn view_time_picker(
+        &self,
+        label: &str,
+        time: settings::TimeOfDay,
+        start: bool,
+    ) -> Element<'_, Message> {
+        let changed = move |hour: u8, minute: u8| {
+            if start {
+                Message::ScheduleStartTimeChanged(hour, minute)
+            } else {
+                Message::ScheduleEndTimeChanged(hour, minute)
+            }
+        };
+        let typed = self
+            .schedule_time_input
+            .as_ref()
+            .filter(|(which, _)| *which == start)
+            .map(|(_, text)| text.clone());
+        let invalid = typed
+            .as_ref()
+            .is_some_and(|t| settings::TimeOfDay::parse(t).is_none());
+        let hours: Vec<String> = (0..24).map(|h| format!("{:02}", h)).collect();
+        let minutes: Vec<String> = (0..60).step_by(5).map(|m| format!("{:02}", m)).collect();
+        let period = if time.hour >= 12 { "PM" } else { "AM" };
+        let h12 = if time.hour.is_multiple_of(12) {
+            12
+        } else {
+            time.hour % 12
+        };
+
+        row![
+            text(label.to_string()).width(100),
+            text_input(
+                "HH:MM",
+                &typed.unwrap_or_else(|| format!("{:02}:{:02}", time.hour, time.minute))
+            )
+            .on_input(move |text| Message::ScheduleTimeTyped(start, text))
+            .on_submit(Message::ScheduleTimeTypingDone)
+            .width(90)
+            .padding(5),
+            pick_list(hours, Some(format!("{:02}", time.hour)), move |h| {
+                changed(h.parse().unwrap_or(0), time.minute)
+            })
+            .width(70),
+            text(":"),
+            pick_list(minutes, Some(format!("{:02}", time.minute)), move |m| {
+                changed(time.hour, m.parse().unwrap_or(0))
+            })
+            .width(70),
+            if invalid {
+                text("Use HH:MM or e.g. 10:30 pm")
+                    .size(12)
+                    .color(iced::Color::from_rgb(1.0, 0.4, 0.4))
+            } else {
+                text(format!("{}:{:02} {}", h12, time.minute, period))
+                    .size(12)
+                    .color(iced::Color::from_rgb(0.6, 0.6, 0.6))
+            },
+        ]
+        .spacing(10)
+        .align_y(iced::Alignment::Center)
+        .into()
+    }
+}
+
+/// The week at a glance, one cell per hour with allowed ones lit. Clicking or
+/// dragging paints cells, which turns the schedule into a Custom one.
+struct Timeline<'a> {
+    schedule: &'a ScheduleConfig,
+}
+
+impl Timeline<'_> {
+    fn cell_at(bounds: Rectangle, position: Point) -> Option<(usize, u32)> {
+        let cell_width = (bounds.width - LABEL_WIDTH) / 24.0;
+        let x = position.x - LABEL_WIDTH;
+        let y = position.y - HEADER_HEIGHT;
+        if x < 0.0 || y < 0.0 {
+            return None;
+        }
+        let (day, hour) = ((y / ROW_HEIGHT) as usize, (x / cell_width) as u32);
+        (day < 7 && hour < 24).then_some((day, hour))
+    }
+}
+
+impl canvas::Program<Message> for Timeline<'_> {
+    type State = Option<(bool, (usize, u32))>; // value being painted, last cell painted
+
+    fn update(
+        &self,
+        state: &mut Self::State,
+        event: canvas::Event,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> (event::Status, Option<Message>) {
+        let cell = cursor
+            .position_in(bounds)
+            .and_then(|p| Self::cell_at(bounds, p));
+        match event {
+            canvas::Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
+                let Some((day, hour)) = cell else {
+                    return (event::Status::Ignored, None);
+                };
+                let on = Scheduler::hour_grid(self.schedule)[day] & (1 << hour) == 0;
+                *state = Some((on, (day, hour)));
+                return (
+                    event::Status::Captured,
+                    Some(Message::ScheduleCellSet(day, hour, on)),
+                );
+            }
+            canvas::Event::Mouse(mouse::Event::CursorMoved { .. }) => {
+                if let (Some((on, last)), Some(cell)) = (state.as_mut(), cell) {
+                    if *last != cell {
+                        *last = cell;
+                        return (
+                            event::Status::Captured,
+                            Some(Message::ScheduleCellSet(cell.0, cell.1, *on)),
+                        );
+                    }
+                }
+            }
+            canvas::Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                *state = None;
+            }
+            _ => {}
+        }
+        (event::Status::Ignored, None)
+    }
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let palette = theme.extended_palette();
+        let mut frame = Frame::new(renderer, bounds.size());
+        let cell_width = (bounds.width - LABEL_WIDTH) / 24.0;
+        let label = |content: String, position: Point| canvas::Text {
+            content,
+            position,
+            color: palette.background.base.text,
+            size: 11.0.into(),
+            ..Default::default()
+        };
+
+        for hour in (0..24).step_by(3) {
+            let x = LABEL_WIDTH + hour as f32 * cell_width;
+            frame.fill_text(label(format!("{:02}", hour), Point::new(x, 0.0)));
+        }
+        let grid = Scheduler::hour_grid(self.schedule);
+        for (day, bits) in grid.iter().enumerate() {
+            let y = HEADER_HEIGHT + day as f32 * ROW_HEIGHT;
+            frame.fill_text(label(DAY_NAMES[day].to_string(), Point::new(0.0, y + 3.0)));
+            for hour in 0..24 {
+                let color = if bits & (1 << hour) != 0 {
+                    palette.success.base.color
+                } else {
+                    palette.background.weak.color
+                };
+                frame.fill_rectangle(
+                    Point::new(LABEL_WIDTH + hour as f32 * cell_width, y),
+                    Size::new(cell_width - 1.0, ROW_HEIGHT - 1.0),
+                    color,
+                );
+            }
+        }
+        vec![frame.into_geometry()]
+    }
+
+    fn mouse_interaction(
+        &self,
+        _state: &Self::State,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> mouse::Interaction {
+        match cursor.position_in(bounds) {
+            Some(p) if Self::cell_at(bounds, p).is_some() => mouse::Interaction::Pointer,
+            _ => mouse::Interaction::default(),
+        }
+    }
+}
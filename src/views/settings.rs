@@ -0,0 +1,659 @@
+use super::field_error;
+use crate::app::{Message, SftpApp};
+use crate::{settings, sftp_client, style, totp};
+use iced::widget::{
+    button, checkbox, column, container, horizontal_space, radio, row, scrollable, text,
+    text_input, vertical_space,
+};
+use iced::{Element, Length, Theme};
+
+/// Months-by-server lines listed under Statistics, newest first
+const MAX_USAGE_ROWS: usize = 12;
+
+impl SftpApp {
+    pub fn view_settings(&self) -> Element<'_, Message> {
+        let title = self.view_dialog_title("Settings");
+        let errors = settings::validate(&self.config, &self.port_input);
+
+        let content = if self.is_checking_connection {
+            column![
+                title,
+                vertical_space().height(20),
+                text("Checking connection...").size(18),
+                button("Cancel").on_press(Message::CancelConnect),
+            ]
+        } else {
+            let host_input = text_input("Host", &self.config.sftp_config.host)
+                .on_input(Message::HostChanged)
+                .padding(10);
+
+            let port_input = text_input("Port", &self.port_input)
+                .on_input(Message::PortChanged)
+                .padding(10)
+                .width(80);
+
+            let host_row = column![row![host_input, port_input].spacing(10)]
+                .push_maybe(field_error(errors.host))
+                .push_maybe(field_error(errors.port))
+                .spacing(5);
+
+            let own_path = self
+                .config
+                .profile_download_paths
+                .get(&self.config.profile_key());
+            let download_row = column![
+                row![
+                    text("Download to:"),
+                    text(&self.config.local_download_path).width(Length::Fill),
+                    button("Browse...").on_press(Message::SelectDownloadPath),
+                ]
+                .spacing(10)
+                .align_y(iced::Alignment::Center),
+                row![
+                    text("This server's folder:"),
+                    text(own_path.map_or("Default", |p| p.as_str())).width(Length::Fill),
+                    button("Browse...").on_press(Message::SelectProfileDownloadPath),
+                ]
+                .push_maybe(own_path.map(|_| {
+                    button("Use default")
+                        .on_press(Message::ClearProfileDownloadPath)
+                        .style(button::secondary)
+                }))
+                .spacing(10)
+                .align_y(iced::Alignment::Center),
+            ]
+            .push_maybe(field_error(errors.download_path))
+            .spacing(5);
+
+            let user_input = text_input("Username", &self.config.sftp_config.username)
+                .on_input(Message::UsernameChanged)
+                .padding(10);
+
+            let password_val = self.config.sftp_config.password.clone().unwrap_or_default();
+            let pass_input = text_input("Password", &password_val)
+                .on_input(Message::PasswordChanged)
+                .secure(true)
+                .padding(10);
+
+            let start_dir_val = self
+                .config
+                .sftp_config
+                .start_directory
+                .clone()
+                .unwrap_or_default();
+            let start_dir_input = text_input(
+                "Start directory, e.g. /downloads/complete (optional)",
+                &start_dir_val,
+            )
+            .on_input(Message::StartDirectoryChanged)
+            .padding(10);
+
+            let timezone_val = self
+                .config
+                .sftp_config
+                .server_timezone
+                .clone()
+                .unwrap_or_default();
+            let timezone_input = column![text_input(
+                "Server timezone as a UTC offset, e.g. +02:00 (optional)",
+                &timezone_val,
+            )
+            .on_input(Message::ServerTimezoneChanged)
+            .padding(10)]
+            .push_maybe(field_error(errors.timezone));
+
+            let trash_val = self
+                .config
+                .sftp_config
+                .trash_folder
+                .clone()
+                .unwrap_or_default();
+            let trash_input = text_input(
+                "Trash folder for remote deletes, e.g. /home/me/.trash (optional)",
+                &trash_val,
+            )
+            .on_input(Message::TrashFolderChanged)
+            .padding(10);
+
+            let pre_connect_val = self
+                .config
+                .sftp_config
+                .pre_connect_command
+                .clone()
+                .unwrap_or_default();
+            let pre_connect_input = text_input(
+                "Pre-connect command, e.g. knock host 7000 8000 9000 (optional)",
+                &pre_connect_val,
+            )
+            .on_input(Message::PreConnectChanged)
+            .padding(10);
+
+            let totp_val = self
+                .config
+                .sftp_config
+                .totp_secret
+                .clone()
+                .unwrap_or_default();
+            let totp_input = text_input("One-time code secret (TOTP, optional)", &totp_val)
+                .on_input(Message::TotpSecretChanged)
+                .secure(true)
+                .padding(10);
+            let totp_note = match totp::now_code(&totp_val) {
+                _ if totp_val.is_empty() => None,
+                Ok(code) if self.config.sftp_config.totp_in_keyring => Some(text(format!(
+                    "Current code {}; the secret is kept in the system keyring",
+                    code
+                ))),
+                Ok(code) => Some(text(format!("Current code {}", code))),
+                Err(e) => Some(text(e).style(text::danger)),
+            };
+
+            let valid = errors.is_empty();
+            let controls: Element<'_, Message> = if self.confirm_discard {
+                self.view_discard_prompt()
+            } else {
+                row![
+                    button("Save").on_press_maybe(valid.then_some(Message::SaveSettings)),
+                    button(if self.is_probing {
+                        "Testing..."
+                    } else {
+                        "Test connection"
+                    })
+                    .on_press_maybe((valid && !self.is_probing).then_some(Message::TestConnection)),
+                    button("Cancel").on_press(Message::CancelSettings),
+                    checkbox("Connect after saving", self.connect_after_save)
+                        .on_toggle(Message::ConnectAfterSaveToggled),
+                ]
+                .spacing(20)
+                .align_y(iced::Alignment::Center)
+                .into()
+            };
+
+            let weekly_avg = self.config.get_weekly_average();
+            let monthly_avg = self.config.get_monthly_average();
+            let weekly_str = self.format_bytes(&weekly_avg.to_string());
+            let monthly_str = self.format_bytes(&monthly_avg.to_string());
+
+            let mut col = column![
+                title,
+                text("SFTP Connection Details").size(18),
+                host_row,
+                user_input,
+                pass_input,
+                start_dir_input,
+                timezone_input,
+                trash_input,
+                pre_connect_input,
+                column![totp_input]
+                    .push_maybe(totp_note.map(|t| t.size(12)))
+                    .spacing(4),
+                checkbox(
+                    "Compress SSH traffic (slow links, compressible files)",
+                    self.config.sftp_config.compression
+                )
+                .on_toggle(Message::CompressionToggled),
+                checkbox(
+                    "Read-only: no deletes, moves, copies or uploads on this server",
+                    self.config.sftp_config.read_only
+                )
+                .on_toggle(Message::ReadOnlyToggled),
+                checkbox(
+                    "Allow console commands on this server",
+                    self.config.sftp_config.allow_shell
+                )
+                .on_toggle(Message::AllowShellToggled),
+                self.view_algorithm_settings(),
+                vertical_space().height(10),
+                text("Download Settings").size(18),
+                download_row,
+                row![
+                    text("Max Speed (KB/s, 0=Unlimited):"),
+                    text_input("0", &self.config.max_download_speed.to_string())
+                        .on_input(Message::SpeedLimitChanged)
+                        .width(100)
+                        .padding(5)
+                ]
+                .spacing(10)
+                .align_y(iced::Alignment::Center),
+                row![
+                    text("This server's Max Speed (KB/s, blank=Default):"),
+                    text_input(
+                        "Default",
+                        &self
+                            .config
+                            .profile_speed_limits
+                            .get(&self.config.profile_key())
+                            .map(|s| s.to_string())
+                            .unwrap_or_default()
+                    )
+                    .on_input(Message::ProfileSpeedLimitChanged)
+                    .width(100)
+                    .padding(5)
+                ]
+                .spacing(10)
+                .align_y(iced::Alignment::Center),
+                row![
+                    text("Sync to disk every (MB, 0=On pause/finish):"),
+                    text_input("0", &self.config.fsync_interval_mb.to_string())
+                        .on_input(Message::FsyncIntervalChanged)
+                        .width(100)
+                        .padding(5)
+                ]
+                .spacing(10)
+                .align_y(iced::Alignment::Center),
+                row![
+                    text("Priority rules (lower runs first):"),
+                    text_input("*.srt=1, >10GB=20", &self.rules_input)
+                        .on_input(Message::PriorityRulesChanged)
+                        .padding(5)
+                ]
+                .spacing(10)
+                .align_y(iced::Alignment::Center),
+                checkbox(
+                    "Pause downloads on metered networks",
+                    self.config.pause_on_metered
+                )
+                .on_toggle(Message::PauseOnMeteredToggled),
+                row![
+                    text("Double-click a file:"),
+                    radio(
+                        "Do nothing",
+                        settings::DoubleClick::Nothing,
+                        Some(self.config.double_click),
+                        Message::DoubleClickChanged
+                    ),
+                    radio(
+                        "Queue",
+                        settings::DoubleClick::Queue,
+                        Some(self.config.double_click),
+                        Message::DoubleClickChanged
+                    ),
+                    radio(
+                        "Download now",
+                        settings::DoubleClick::Download,
+                        Some(self.config.double_click),
+                        Message::DoubleClickChanged
+                    ),
+                    radio(
+                        "Preview",
+                        settings::DoubleClick::Preview,
+                        Some(self.config.double_click),
+                        Message::DoubleClickChanged
+                    ),
+                ]
+                .spacing(10)
+                .align_y(iced::Alignment::Center),
+                row![
+                    text("Re-downloading replaces the local copy by:"),
+                    radio(
+                        "Renaming it to .old",
+                        settings::ExistingCopy::Rename,
+                        Some(self.config.existing_copy),
+                        Message::ExistingCopyChanged
+                    ),
+                    radio(
+                        "Deleting it",
+                        settings::ExistingCopy::Delete,
+                        Some(self.config.existing_copy),
+                        Message::ExistingCopyChanged
+                    ),
+                ]
+                .spacing(10)
+                .align_y(iced::Alignment::Center),
+                row![
+                    text("Open sftp:// links from browsers and other apps here:"),
+                    button("Register")
+                        .on_press(Message::RegisterUrlHandler)
+                        .style(button::secondary),
+                ]
+                .spacing(10)
+                .align_y(iced::Alignment::Center),
+                row![
+                    text("Move these settings to another machine:"),
+                    button("Back up...")
+                        .on_press(Message::BackupSettings)
+                        .style(button::secondary),
+                    button("Restore...")
+                        .on_press(Message::RestoreSettings)
+                        .style(button::secondary),
+                ]
+                .spacing(10)
+                .align_y(iced::Alignment::Center),
+                checkbox(
+                    "Write SHA256SUMS into finished folders",
+                    self.config.write_checksums
+                )
+                .on_toggle(Message::WriteChecksumsToggled),
+                checkbox(
+                    "Dry run: only log deletes, moves, copies, overwrites and extracts",
+                    self.config.dry_run
+                )
+                .on_toggle(Message::DryRunToggled),
+                row![
+                    checkbox("High contrast", self.config.high_contrast)
+                        .on_toggle(Message::HighContrastToggled),
+                    checkbox(
+                        "Reduce motion (no spinners, pop-ins or timed banners)",
+                        self.config.reduce_motion
+                    )
+                    .on_toggle(Message::ReduceMotionToggled),
+                ]
+                .spacing(20),
+                row![
+                    text("Also treat these Wi-Fi networks as metered:"),
+                    text_input("Phone*, Cafe WiFi", &self.metered_ssids_input)
+                        .on_input(Message::MeteredSsidsChanged)
+                        .padding(5)
+                ]
+                .spacing(10)
+                .align_y(iced::Alignment::Center),
+                row![
+                    text("Replace characters not allowed in local names with:"),
+                    text_input("_", &self.config.filename_replacement)
+                        .on_input(Message::FilenameReplacementChanged)
+                        .width(60)
+                        .padding(5)
+                ]
+                .spacing(10)
+                .align_y(iced::Alignment::Center),
+                row![
+                    text("Keep at least (GB free, 0=Off):"),
+                    text_input("0", &self.config.min_free_space_gb.to_string())
+                        .on_input(Message::MinFreeSpaceChanged)
+                        .width(100)
+                        .padding(5)
+                ]
+                .spacing(10)
+                .align_y(iced::Alignment::Center),
+                row![
+                    text("Warn about items queued longer than (days, 0=Off):"),
+                    text_input("3", &self.config.stale_queue_days.to_string())
+                        .on_input(Message::StaleQueueDaysChanged)
+                        .width(100)
+                        .padding(5)
+                ]
+                .spacing(10)
+                .align_y(iced::Alignment::Center),
+                row![
+                    text("Extra transfer slot for files up to (MB, 0=Off):"),
+                    text_input("0", &self.config.fast_lane_mb.to_string())
+                        .on_input(Message::FastLaneChanged)
+                        .width(100)
+                        .padding(5)
+                ]
+                .spacing(10)
+                .align_y(iced::Alignment::Center),
+                row![
+                    text("Followed files finish after no growth for (min):"),
+                    text_input("2", &self.config.follow_stable_mins.to_string())
+                        .on_input(Message::FollowStableChanged)
+                        .width(100)
+                        .padding(5)
+                ]
+                .spacing(10)
+                .align_y(iced::Alignment::Center),
+                self.view_watched_folders(),
+                vertical_space().height(10),
+                text("Statistics").size(18),
+                text(format!("Weekly Average: {}/s", weekly_str)),
+                text(format!("Monthly Average: {}/s", monthly_str)),
+                self.view_monthly_usage(),
+                self.view_reports(),
+            ];
+
+            // The advanced section only holds the protocol trace
+            if sftp_client::PROTOCOL_TRACE_AVAILABLE {
+                col = col.push(
+                    checkbox("Show advanced settings", self.show_advanced)
+                        .on_toggle(Message::ShowAdvancedToggled),
+                );
+            }
+            if self.show_advanced && sftp_client::PROTOCOL_TRACE_AVAILABLE {
+                col = col.push(
+                    column![
+                        text("Advanced").size(18),
+                        checkbox(
+                            "Trace SFTP protocol (libssh2) into the Activity Log",
+                            self.config.protocol_trace
+                        )
+                        .on_toggle(Message::ProtocolTraceToggled),
+                        text(
+                            "For diagnosing odd servers. Applies to new connections and shows \
+                             with Verbose on."
+                        )
+                        .size(12),
+                    ]
+                    .spacing(10),
+                );
+            }
+
+            if let Some(err) = &self.settings_error {
+                col = col.push(
+                    text(format!("Error: {}", err)).color(iced::Color::from_rgb(1.0, 0.0, 0.0)),
+                );
+            }
+
+            if let Some(report) = &self.probe_result {
+                col = col.push(text(report).size(14));
+            }
+
+            col.push(vertical_space().height(20)).push(controls)
+        };
+
+        container(
+            container(content.spacing(20).max_width(400))
+                .padding(20)
+                .style(style::header_style),
+        )
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .center_x(Length::Fill)
+        .center_y(Length::Fill)
+        .style(|_t: &Theme| container::Style {
+            background: Some(iced::Color::from_rgba(0.0, 0.0, 0.0, 0.5).into()),
+            ..Default::default()
+        })
+        .into()
+    }
+
+    /// Traffic per server and month, for providers with per-server quotas
+    fn view_monthly_usage(&self) -> Element<'_, Message> {
+        let usage = self.config.usage_by_month();
+        let mut rows = column![text("Traffic by server and month").size(14)].spacing(4);
+        if usage.is_empty() {
+            rows = rows.push(text("Nothing transferred yet").size(12));
+        }
+        for entry in usage.into_iter().take(MAX_USAGE_ROWS) {
+            rows = rows.push(
+                row![
+                    text(&entry.month).size(12).width(70),
+                    text(&entry.profile).size(12).width(Length::Fill),
+                    text(self.format_bytes(&entry.bytes.to_string())).size(12),
+                ]
+                .spacing(10),
+            );
+        }
+        rows.into()
+    }
+
+    /// When transfer reports go out, and to which folder and mail account
+    fn view_reports(&self) -> Element<'_, Message> {
+        let report = &self.config.report;
+        let period = |label, value| {
+            radio(
+                label,
+                value,
+                Some(report.period),
+                Message::ReportPeriodChanged,
+            )
+            .size(14)
+            .text_size(12)
+        };
+        column![
+            text("Transfer reports").size(14),
+            row![
+                period("Off", settings::ReportPeriod::Off),
+                period("Daily", settings::ReportPeriod::Daily),
+                period("Weekly", settings::ReportPeriod::Weekly),
+                horizontal_space(),
+                button(text("Send now").size(12))
+                    .on_press(Message::SendReportNow)
+                    .style(button::secondary),
+            ]
+            .spacing(10)
+            .align_y(iced::Alignment::Center),
+            row![
+                text_input("Save HTML and CSV to folder", &report.folder)
+                    .on_input(Message::ReportFolderChanged)
+                    .size(12)
+                    .padding(3),
+                button(text("Browse").size(12))
+                    .on_press(Message::PickReportFolder)
+                    .style(button::secondary),
+            ]
+            .spacing(8)
+            .align_y(iced::Alignment::Center),
+            text_input(
+                "SMTP server, e.g. smtps://smtp.example.com:465",
+                &report.smtp_url
+            )
+            .on_input(Message::ReportSmtpUrlChanged)
+            .size(12)
+            .padding(3),
+            row![
+                text_input("Mail account", &report.smtp_user)
+                    .on_input(Message::ReportSmtpUserChanged)
+                    .size(12)
+                    .padding(3),
+                text_input(
+                    "Password",
+                    report.smtp_password.as_deref().unwrap_or_default()
+                )
+                .on_input(Message::ReportSmtpPasswordChanged)
+                .secure(true)
+                .size(12)
+                .padding(3),
+            ]
+            .spacing(8),
+            text_input("Send to", &report.email_to)
+                .on_input(Message::ReportEmailToChanged)
+                .size(12)
+                .padding(3),
+        ]
+        .spacing(6)
+        .into()
+    }
+
+    /// Folders queued from automatically, and the names this server won't fetch twice
+    fn view_watched_folders(&self) -> Element<'_, Message> {
+        let mut rows = column![text("Watched folders").size(14)].spacing(6);
+        if self.config.watched_folders.is_empty() {
+            rows = rows.push(text("None yet: use Watch on a remote folder").size(12));
+        }
+        for (index, watch) in self.config.watched_folders.iter().enumerate() {
+            rows = rows.push(
+                row![
+                    text(format!("{} on {}", watch.remote_path, watch.profile))
+                        .size(12)
+                        .width(Length::Fill),
+                    text_input("*.mkv, *S01E*", &self.watch_inputs[index])
+                        .on_input(move |val| Message::WatchPatternsChanged(index, val))
+                        .size(12)
+                        .padding(3)
+                        .width(160),
+                    checkbox("Skip already downloaded", watch.skip_downloaded)
+                        .on_toggle(move |on| Message::WatchSkipDownloadedToggled(index, on))
+                        .size(14)
+                        .text_size(12),
+                    button(text("Remove").size(12))
+                        .on_press(Message::UnwatchFolder(index))
+                        .style(button::secondary),
+                ]
+                .spacing(8)
+                .align_y(iced::Alignment::Center),
+            );
+        }
+
+        let names = self
+            .config
+            .downloaded_names
+            .get(&self.config.profile_key())
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let mut skipped = column![].spacing(2);
+        for name in names.iter().rev() {
+            skipped = skipped.push(
+                row![
+                    text(name).size(12).width(Length::Fill),
+                    button(text("Forget").size(12))
+                        .on_press(Message::ForgetDownloaded(name.clone()))
+                        .padding([0, 6])
+                        .style(style::text_button),
+                ]
+                .align_y(iced::Alignment::Center),
+            );
+        }
+        rows.push(
+            text(format!(
+                "Already downloaded from this server ({})",
+                names.len()
+            ))
+            .size(12),
+        )
+        .push(container(scrollable(skipped)).max_height(120))
+        .push(
+            row![
+                text_input("Never fetch this name", &self.ignore_input)
+                    .on_input(Message::IgnoreInputChanged)
+                    .on_submit(Message::AddIgnored)
+                    .size(12)
+                    .padding(3),
+                button(text("Add").size(12))
+                    .on_press(Message::AddIgnored)
+                    .style(button::secondary),
+            ]
+            .spacing(8)
+            .align_y(iced::Alignment::Center),
+        )
+        .into()
+    }
+
+    /// Advanced: preferred SSH algorithms for this profile
+    pub fn view_algorithm_settings(&self) -> Element<'_, Message> {
+        let prefs = &self.config.sftp_config.algorithms;
+        let list = |label, placeholder, value, on_input: fn(String) -> Message| {
+            row![
+                text(label).width(120),
+                text_input(placeholder, value)
+                    .on_input_maybe((!prefs.strict).then_some(on_input))
+                    .padding(5)
+            ]
+            .spacing(10)
+            .align_y(iced::Alignment::Center)
+        };
+        column![
+            checkbox("Strict modern algorithms only", prefs.strict)
+                .on_toggle(Message::StrictAlgorithmsToggled),
+            list(
+                "Key exchange:",
+                "Default, e.g. curve25519-sha256",
+                &prefs.kex,
+                Message::KexChanged
+            ),
+            list(
+                "Ciphers:",
+                "Default, e.g. aes256-gcm@openssh.com,aes256-ctr",
+                &prefs.ciphers,
+                Message::CiphersChanged
+            ),
+            list(
+                "MACs:",
+                "Default, e.g. hmac-sha2-256",
+                &prefs.macs,
+                Message::MacsChanged
+            ),
+        ]
+        .spacing(5)
+        .into()
+    }
+}